@@ -0,0 +1,113 @@
+// Benchmarks of the hot paths of a render, to measure changes to them against: the escape-time
+// kernel for single points and for a whole view, turning escape data into palette positions
+// and colors, and encoding colored cells for the terminal. Run with `cargo bench`; criterion
+// keeps the last run in target/criterion and reports how far each benchmark moved from it.
+//
+// The kernel is scalar f64 throughout, one point at a time on each thread; a vectorized one
+// would get its own group next to `kernel`.
+
+use std::hint::black_box;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use mandelbrot::colorscheme::smooth_iteration;
+use mandelbrot::{ColorScheme, ColoringMode, ComputeOptions, DEFAULT_BAILOUT, Fractal, Gradient, Interpolation, Job, OutputFormat, Renderer, renderer};
+
+// a view mixing the inside of the set, its boundary and the flat outside, as most views do
+const REGION: (f64, f64, f64, f64) = (-2.0, 1.0, -1.0, 1.0);
+const MAX_ITERS: usize = 500;
+
+fn view(width: usize, height: usize) -> Job {
+    Job {
+        fractal: Fractal::Mandelbrot,
+        max_iters: MAX_ITERS,
+        region: REGION,
+        width,
+        height,
+        options: ComputeOptions::default(),
+    }
+}
+
+fn kernel(c: &mut Criterion) {
+    let mut group = c.benchmark_group("kernel");
+    // points that escape at once, after a while near the boundary, and never
+    for (name, (cx, cy)) in [("outside", (1.0, 1.0)), ("boundary", (-0.7436, 0.1318)), ("inside", (-0.1, 0.1))] {
+        group.bench_with_input(BenchmarkId::new("mandelbrot_at_point", name), &(cx, cy), |b, &(cx, cy)| {
+            b.iter(|| mandelbrot::mandelbrot_at_point(black_box(cx), black_box(cy), MAX_ITERS, DEFAULT_BAILOUT))
+        });
+    }
+    for fractal in [Fractal::Mandelbrot, Fractal::BurningShip] {
+        group.bench_with_input(BenchmarkId::new("at_point", fractal.name()), &fractal, |b, fractal| {
+            b.iter(|| fractal.at_point(black_box(-0.7436), black_box(0.1318), MAX_ITERS))
+        });
+    }
+
+    let job = view(160, 100);
+    group.throughput(Throughput::Elements((job.width * job.height) as u64));
+    group.bench_function("view", |b| b.iter(|| mandelbrot::compute(black_box(&job))));
+    let no_shortcuts = Job {
+        options: ComputeOptions {
+            early_bailout: false,
+            symmetry: false,
+            ..job.options
+        },
+        ..job.clone()
+    };
+    group.bench_function("view without shortcuts", |b| b.iter(|| mandelbrot::compute(black_box(&no_shortcuts))));
+    group.finish();
+}
+
+fn coloring(c: &mut Criterion) {
+    let mut group = c.benchmark_group("coloring");
+    group.bench_function("smooth_iteration", |b| b.iter(|| smooth_iteration(black_box(37), black_box(3.7), DEFAULT_BAILOUT, 2.0)));
+
+    let data = mandelbrot::compute(&view(160, 100));
+    group.throughput(Throughput::Elements((data.width() * data.height()) as u64));
+    for (name, coloring) in [("linear", ColoringMode::Linear), ("smooth", ColoringMode::Smooth)] {
+        let renderer = Renderer::new(ColorScheme::Classic, OutputFormat::AnsiTrueColor).with_coloring(coloring);
+        group.bench_function(BenchmarkId::new("to_rgb_buffer", name), |b| b.iter(|| renderer.to_rgb_buffer(black_box(&data))));
+    }
+    group.finish();
+}
+
+fn gradient_lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("gradient lookup");
+    let stops = "0.0:#000764,0.16:#206bcb,0.42:#edffff,0.64:#ffaa00,0.86:#000200,1.0:#000764";
+    let schemes = [
+        ("classic", ColorScheme::Classic),
+        ("turbo", ColorScheme::Turbo),
+        ("custom rgb", ColorScheme::Custom(Gradient::from_spec(stops).expect("the stops parse"))),
+        ("custom oklab", ColorScheme::Custom(Gradient::from_spec(stops).expect("the stops parse").with_interpolation(Interpolation::Oklab))),
+    ];
+    for (name, scheme) in &schemes {
+        group.bench_with_input(BenchmarkId::new("color_at", name), scheme, |b, scheme| b.iter(|| scheme.color_at(black_box(0.37))));
+    }
+    group.finish();
+}
+
+fn ansi_encoding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("ansi encoding");
+    group.bench_function("rgb_to_ansi256", |b| b.iter(|| renderer::rgb_to_ansi256(black_box(200), black_box(120), black_box(40))));
+
+    // a terminal's worth of cells
+    let data = mandelbrot::compute(&view(160, 48));
+    group.throughput(Throughput::Elements((data.width() * data.height()) as u64));
+    let formats = [
+        ("truecolor", OutputFormat::AnsiTrueColor),
+        ("256 colors", OutputFormat::Ansi256),
+        ("half blocks", OutputFormat::HalfBlock { truecolor: true }),
+    ];
+    for (name, format) in formats {
+        let renderer = Renderer::new(ColorScheme::Classic, format);
+        group.bench_function(BenchmarkId::new("render_to", name), |b| {
+            let mut out = Vec::new();
+            b.iter(|| {
+                out.clear();
+                renderer.render_to(black_box(&data), &mut out)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, kernel, coloring, gradient_lookup, ansi_encoding);
+criterion_main!(benches);
@@ -0,0 +1,47 @@
+/* C API of the mandelbrot library, built with the ffi feature (see src/ffi.rs). */
+
+#ifndef MANDELBROT_H
+#define MANDELBROT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define MANDELBROT_OK 0
+/* a pointer argument is null, or a name isn't UTF-8 */
+#define MANDELBROT_ERROR_ARGUMENT 1
+#define MANDELBROT_ERROR_UNKNOWN_FRACTAL 2
+#define MANDELBROT_ERROR_UNKNOWN_PALETTE 3
+/* the buffer holds fewer than width * height * 3 bytes */
+#define MANDELBROT_ERROR_BUFFER_TOO_SMALL 4
+#define MANDELBROT_ERROR_PANIC 5
+
+/* the region to render and how finely */
+struct mandelbrot_view {
+    double x_min;
+    double x_max;
+    double y_min;
+    double y_max;
+    size_t width;
+    size_t height;
+    size_t max_iterations;
+};
+
+/* Renders `view` of the fractal named `fractal` ("mandelbrot", "julia", "burning-ship", ...) in
+ * the palette named `palette` ("classic", "ocean", "viridis", ...; "-r" reverses one) into
+ * `buffer` as packed RGB, row by row from y_min. Returns MANDELBROT_OK or a MANDELBROT_ERROR_
+ * code, leaving the buffer untouched on error. */
+int mandelbrot_render_region(const char *fractal, const char *palette, const struct mandelbrot_view *view,
+                             uint8_t *buffer, size_t buffer_len);
+
+/* 1 if `palette` names a palette mandelbrot_render_region takes, 0 if not. */
+int mandelbrot_palette_exists(const char *palette);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,364 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+use crate::renderer::{self, FrameWriter, ImageFormat, RenderData, Renderer};
+
+/// Exponential zoom from a starting region towards a target point.
+pub struct ZoomAnimation {
+    start_region: (f64, f64, f64, f64),
+    target: (f64, f64),
+    final_zoom: f64,
+    frames: usize,
+    base_iterations: usize,
+}
+
+impl ZoomAnimation {
+    pub fn new(
+        start_region: (f64, f64, f64, f64),
+        target: (f64, f64),
+        final_zoom: f64,
+        frames: usize,
+        base_iterations: usize,
+    ) -> Self {
+        Self {
+            start_region,
+            target,
+            final_zoom: final_zoom.max(1.0),
+            frames: frames.max(1),
+            base_iterations,
+        }
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    fn progress(&self, frame: usize) -> f64 {
+        if self.frames <= 1 {
+            1.0
+        } else {
+            frame as f64 / (self.frames - 1) as f64
+        }
+    }
+
+    /// Zoom factor relative to the start region; grows by the same ratio every frame.
+    pub fn zoom(&self, frame: usize) -> f64 {
+        self.zoom_at(self.progress(frame))
+    }
+
+    /// Zoom factor `t` of the way (0.0 - 1.0) through the animation, for playback by time
+    /// rather than by frame.
+    pub fn zoom_at(&self, t: f64) -> f64 {
+        self.final_zoom.powf(t)
+    }
+
+    /// Region shown in `frame`. The target drifts linearly (in screen space) from wherever it
+    /// starts to the center of the view, so the last frame is centered on it.
+    pub fn region(&self, frame: usize) -> (f64, f64, f64, f64) {
+        self.region_at(self.progress(frame))
+    }
+
+    /// Region shown `t` of the way (0.0 - 1.0) through the animation.
+    pub fn region_at(&self, t: f64) -> (f64, f64, f64, f64) {
+        let (x_min, x_max, y_min, y_max) = self.start_region;
+        let (target_x, target_y) = self.target;
+        let zoom = self.zoom_at(t);
+
+        let center_x = target_x + ((x_min + x_max) / 2.0 - target_x) * (1.0 - t) / zoom;
+        let center_y = target_y + ((y_min + y_max) / 2.0 - target_y) * (1.0 - t) / zoom;
+        let half_width = (x_max - x_min) / (2.0 * zoom);
+        let half_height = (y_max - y_min) / (2.0 * zoom);
+
+        (
+            center_x - half_width,
+            center_x + half_width,
+            center_y - half_height,
+            center_y + half_height,
+        )
+    }
+
+    /// Max iterations for `frame`, raised with zoom depth so deep frames keep their detail.
+    pub fn iterations(&self, frame: usize) -> usize {
+        iterations_at(self.base_iterations, self.zoom(frame))
+    }
+}
+
+/// `base_iterations` raised for a view `zoom` times deeper: half as many again per factor of 10.
+pub fn iterations_at(base_iterations: usize, zoom: f64) -> usize {
+    (base_iterations as f64 * (1.0 + zoom.log10() / 2.0)) as usize
+}
+
+/// Center of the most detailed of `grid`×`grid` tiles over `data`, which covers `region`: the
+/// tile whose iteration counts vary the most, which is one straddling the boundary rather than
+/// the flat outside or the inside of the set. `None` when every tile is flat.
+pub fn most_detailed(data: &RenderData, region: (f64, f64, f64, f64), grid: usize) -> Option<(f64, f64)> {
+    let (x_min, x_max, y_min, y_max) = region;
+    let (width, height) = (data.width(), data.height());
+    let grid = grid.max(1);
+    let mut best = None;
+    for ty in 0..grid {
+        for tx in 0..grid {
+            let (x0, x1) = (tx * width / grid, (tx + 1) * width / grid);
+            let (y0, y1) = (ty * height / grid, (ty + 1) * height / grid);
+            let counts: Vec<f64> = (y0..y1)
+                .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+                .map(|(x, y)| data.sample(x, y).iterations as f64)
+                .collect();
+            if counts.is_empty() {
+                continue;
+            }
+            let mean = counts.iter().sum::<f64>() / counts.len() as f64;
+            let variance = counts.iter().map(|count| (count - mean).powi(2)).sum::<f64>() / counts.len() as f64;
+            if variance > best.map_or(0.0, |(score, _)| score) {
+                let x = x_min + (x_max - x_min) * (x0 + x1) as f64 / (2 * width) as f64;
+                let y = y_min + (y_max - y_min) * (y0 + y1) as f64 / (2 * height) as f64;
+                best = Some((variance, (x, y)));
+            }
+        }
+    }
+    best.map(|(_, center)| center)
+}
+
+/// `region` shrunk `factor` times around `center`.
+pub fn zoom_region(region: (f64, f64, f64, f64), center: (f64, f64), factor: f64) -> (f64, f64, f64, f64) {
+    let (x_min, x_max, y_min, y_max) = region;
+    let half_width = (x_max - x_min) / (2.0 * factor);
+    let half_height = (y_max - y_min) / (2.0 * factor);
+    (center.0 - half_width, center.0 + half_width, center.1 - half_height, center.1 + half_height)
+}
+
+/// Renders every frame of `animation`, using `compute` to produce the escape data of a region.
+/// Each frame is reported through `log` at info level.
+pub fn render(
+    animation: &ZoomAnimation,
+    renderer: &Renderer,
+    writer: &mut dyn FrameWriter,
+    compute: impl Fn(usize, (f64, f64, f64, f64)) -> RenderData,
+) -> std::io::Result<()> {
+    for frame in 0..animation.frames() {
+        let max_iters = animation.iterations(frame);
+        let data = compute(max_iters, animation.region(frame));
+        let pixels = renderer.to_rgb_buffer(&data);
+
+        let destination = writer.write_frame(frame, data.width(), data.height(), &pixels)?;
+        log::info!(
+            "frame {}/{}: zoom {:.3e}, {} iterations -> {}",
+            frame + 1,
+            animation.frames(),
+            animation.zoom(frame),
+            max_iters,
+            destination
+        );
+    }
+    writer.finish()
+}
+
+/// Writes each frame to its own numbered image file.
+pub struct ImageSequence {
+    base: String,
+    format: ImageFormat,
+    frames: usize,
+}
+
+impl ImageSequence {
+    pub fn new(base: &str, format: ImageFormat, frames: usize) -> Self {
+        Self {
+            base: base.to_string(),
+            format,
+            frames,
+        }
+    }
+}
+
+impl FrameWriter for ImageSequence {
+    fn write_frame(&mut self, index: usize, width: usize, height: usize, pixels: &[u8]) -> std::io::Result<String> {
+        let filename = frame_path(&self.base, index, self.frames);
+        let file = BufWriter::new(File::create(&filename)?);
+        renderer::write_image(file, self.format, width, height, pixels)?;
+        Ok(filename)
+    }
+}
+
+/// Writes every frame to one stream, back to back: raw RGB frames into ffmpeg, or PPMs into
+/// anything that reads an image sequence from a pipe.
+pub struct FrameStream<W: Write> {
+    out: W,
+    format: ImageFormat,
+    // what `write_frame` reports as the destination
+    name: String,
+}
+
+impl<W: Write> FrameStream<W> {
+    pub fn new(out: W, format: ImageFormat, name: &str) -> Self {
+        Self {
+            out,
+            format,
+            name: name.to_string(),
+        }
+    }
+}
+
+impl<W: Write> FrameWriter for FrameStream<W> {
+    fn write_frame(&mut self, _index: usize, width: usize, height: usize, pixels: &[u8]) -> std::io::Result<String> {
+        renderer::write_image(&mut self.out, self.format, width, height, pixels)?;
+        Ok(self.name.clone())
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// File extensions saved as video by ffmpeg rather than as images.
+pub const VIDEO_EXTENSIONS: [&str; 4] = ["mp4", "mkv", "mov", "webm"];
+
+/// Whether `path` names a video file, by its extension.
+pub fn is_video(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| VIDEO_EXTENSIONS.iter().any(|video| ext.eq_ignore_ascii_case(video)))
+}
+
+/// Codec for `path` when none is given: VP9 for webm, which can't hold H.264, and H.264
+/// everywhere else.
+pub fn default_codec(path: &str) -> &'static str {
+    if path.to_lowercase().ends_with(".webm") { "libvpx-vp9" } else { "libx264" }
+}
+
+/// Encodes frames into a video with ffmpeg, which gets them as raw RGB on its stdin, so no
+/// frame ever touches the disk.
+pub struct VideoEncoder {
+    ffmpeg: Child,
+    stdin: Option<ChildStdin>,
+    path: String,
+}
+
+impl VideoEncoder {
+    /// Starts ffmpeg writing `path` from `width`×`height` frames at `fps`, in `codec` at
+    /// constant quality `crf` (lower is better; 23 is the usual default).
+    pub fn create(path: &str, width: usize, height: usize, fps: f64, codec: &str, crf: u32) -> io::Result<Self> {
+        let mut ffmpeg = Command::new("ffmpeg")
+            .args(ffmpeg_args(path, width, height, fps, codec, crf))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => io::Error::new(e.kind(), "ffmpeg not found; install it, or save frames as images"),
+                _ => e,
+            })?;
+        Ok(Self {
+            stdin: ffmpeg.stdin.take(),
+            ffmpeg,
+            path: path.to_string(),
+        })
+    }
+}
+
+fn ffmpeg_args(path: &str, width: usize, height: usize, fps: f64, codec: &str, crf: u32) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "-hide_banner", "-loglevel", "error", "-y",
+        "-f", "rawvideo", "-pixel_format", "rgb24",
+        "-video_size", &format!("{}x{}", width, height),
+        "-framerate", &fps.to_string(),
+        "-i", "-",
+        // yuv420p, which every player reads, needs even sizes
+        "-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+        "-pix_fmt", "yuv420p",
+        "-c:v", codec,
+        "-crf", &crf.to_string(),
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect();
+    // VP8/9 only go by the crf without a target bitrate
+    if codec.starts_with("libvpx") {
+        args.extend(["-b:v".to_string(), "0".to_string()]);
+    }
+    args.push(path.to_string());
+    args
+}
+
+impl FrameWriter for VideoEncoder {
+    fn write_frame(&mut self, index: usize, _width: usize, _height: usize, pixels: &[u8]) -> io::Result<String> {
+        let stdin = self.stdin.as_mut().ok_or_else(|| io::Error::other("video already finished"))?;
+        stdin.write_all(pixels)?;
+        Ok(format!("{} frame {}", self.path, index))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        // closing stdin tells ffmpeg the frames are over
+        drop(self.stdin.take());
+        let status = self.ffmpeg.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!("ffmpeg failed ({})", status)))
+        }
+    }
+}
+
+/// Numbered file name for a frame, e.g. "zoom.png" -> "zoom_0007.png".
+pub fn frame_path(base: &str, frame: usize, frames: usize) -> String {
+    let path = Path::new(base);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("frame");
+    let digits = frames.saturating_sub(1).to_string().len().max(4);
+    let name = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}_{:0width$}.{}", stem, frame, ext, width = digits),
+        None => format!("{}_{:0width$}", stem, frame, width = digits),
+    };
+
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zoom_ends_on_target() {
+        let animation = ZoomAnimation::new((-2.0, 1.0, -1.0, 1.0), (-0.75, 0.1), 1000.0, 11, 100);
+
+        assert_eq!(animation.region(0), (-2.0, 1.0, -1.0, 1.0));
+        let (x_min, x_max, y_min, y_max) = animation.region(10);
+        assert!(((x_min + x_max) / 2.0 + 0.75).abs() < 1e-12);
+        assert!(((y_min + y_max) / 2.0 - 0.1).abs() < 1e-12);
+        assert!((x_max - x_min - 0.003).abs() < 1e-12);
+        assert_eq!(animation.iterations(10), 250);
+
+        assert_eq!(frame_path("out/zoom.png", 7, 11), "out/zoom_0007.png");
+    }
+
+    #[test]
+    fn test_videos_go_to_ffmpeg() {
+        assert!(is_video("out/zoom.MP4") && is_video("zoom.webm"));
+        assert!(!is_video("zoom.gif") && !is_video("mp4"));
+        assert_eq!(default_codec("zoom.webm"), "libvpx-vp9");
+
+        let args = ffmpeg_args("zoom.mp4", 320, 241, 30.0, "libx264", 23);
+        let joined = args.join(" ");
+        assert!(joined.contains("-video_size 320x241 -framerate 30 -i -"));
+        assert!(joined.ends_with("-c:v libx264 -crf 23 zoom.mp4"));
+        assert!(ffmpeg_args("zoom.webm", 2, 2, 24.0, "libvpx-vp9", 31).join(" ").ends_with("-b:v 0 zoom.webm"));
+    }
+
+    #[test]
+    fn test_most_detailed_finds_the_boundary() {
+        // flat everywhere but the bottom right quarter, half in the set and half out
+        let pixels = (0..16)
+            .map(|i| {
+                let (x, y) = (i % 4, i / 4);
+                let iterations = if x >= 2 && y >= 2 && x == y { 100 } else { 5 };
+                crate::EscapeResult { iterations, ..Default::default() }
+            })
+            .collect();
+        let data = RenderData::new(4, 4, pixels, 100);
+
+        assert_eq!(most_detailed(&data, (0.0, 4.0, 0.0, 4.0), 2), Some((3.0, 3.0)));
+        let flat = RenderData::new(2, 2, vec![crate::EscapeResult::default(); 4], 100);
+        assert_eq!(most_detailed(&flat, (0.0, 1.0, 0.0, 1.0), 2), None);
+        assert_eq!(zoom_region((0.0, 4.0, 0.0, 2.0), (3.0, 1.0), 2.0), (2.0, 4.0, 0.5, 1.5));
+    }
+}
@@ -0,0 +1,105 @@
+// Named views saved by the user, kept in ~/.config/mandelbrot/bookmarks.toml as
+//
+//     [minibrot1]
+//     center = [-1.7687, 0.0017]
+//     zoom = 3000.0
+//     iterations = 2000
+//     color = "fire"
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::DEFAULT_VIEW_WIDTH;
+use crate::error::MandelbrotError;
+
+/// A saved view: where, how deep, and how it was colored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub center: (f64, f64),
+    /// magnification relative to the default view, as for `region_around`
+    pub zoom: f64,
+    pub iterations: usize,
+    pub color: String,
+}
+
+impl Bookmark {
+    pub fn from_region((x_min, x_max, y_min, y_max): (f64, f64, f64, f64), iterations: usize, color: &str) -> Self {
+        Self {
+            center: ((x_min + x_max) / 2.0, (y_min + y_max) / 2.0),
+            zoom: DEFAULT_VIEW_WIDTH / (x_max - x_min),
+            iterations,
+            color: color.to_string(),
+        }
+    }
+}
+
+/// Bookmarks by name, as stored in the bookmarks file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Bookmarks(BTreeMap<String, Bookmark>);
+
+impl Bookmarks {
+    /// `$XDG_CONFIG_HOME/mandelbrot/bookmarks.toml`, or under `~/.config` without it.
+    pub fn default_path() -> Option<PathBuf> {
+        let config = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(config.join("mandelbrot").join("bookmarks.toml"))
+    }
+
+    /// Reads the bookmarks at `path`; a missing file has none.
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|e| MandelbrotError::file(path.display(), io::Error::new(io::ErrorKind::InvalidData, e))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(MandelbrotError::file(path.display(), e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> crate::Result<()> {
+        let content = toml::to_string(self).map_err(io::Error::other)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| MandelbrotError::file(dir.display(), e))?;
+        }
+        std::fs::write(path, content).map_err(|e| MandelbrotError::file(path.display(), e))
+    }
+
+    /// Looks a bookmark up by name, ignoring case like the built-in presets.
+    pub fn get(&self, name: &str) -> Option<&Bookmark> {
+        self.0.get(&name.to_lowercase())
+    }
+
+    /// Adds a bookmark, replacing any other with the same name.
+    pub fn insert(&mut self, name: &str, bookmark: Bookmark) {
+        self.0.insert(name.to_lowercase(), bookmark);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Bookmark)> {
+        self.0.iter().map(|(name, bookmark)| (name.as_str(), bookmark))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bookmarks_round_trip() {
+        let bookmark = Bookmark::from_region((-0.75, -0.735, 0.095, 0.105), 2000, "fire");
+        assert!((bookmark.zoom - 200.0).abs() < 1e-9);
+        let (x_min, x_max, y_min, y_max) = crate::region_around(bookmark.center, bookmark.zoom, 3, 2, 1.0);
+        assert!((x_min + 0.75).abs() < 1e-12 && (x_max + 0.735).abs() < 1e-12);
+        assert!((y_min - 0.095).abs() < 1e-12 && (y_max - 0.105).abs() < 1e-12);
+
+        let mut bookmarks = Bookmarks::default();
+        bookmarks.insert("Seahorse Tail", bookmark.clone());
+        let content = toml::to_string(&bookmarks).unwrap();
+        let loaded: Bookmarks = toml::from_str(&content).unwrap();
+        assert_eq!(loaded.get("seahorse tail"), Some(&bookmark));
+    }
+}
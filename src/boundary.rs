@@ -0,0 +1,143 @@
+// Mariani–Silver boundary tracing. Rather than computing every pixel, a rectangle computes only
+// its border: if every border pixel escaped at the same iteration (or none did), the inside is
+// taken to be the same and filled in without iterating anything, and otherwise the rectangle is
+// split in two and each half traced the same way. This leans on the set being connected, so a
+// band of equal escape counts can't hide anything that doesn't reach its border, and pays off
+// most where a view is mostly the inside of the set or a flat outside.
+//
+// Filled pixels copy a border pixel, so only the escape count is exact for them: smooth
+// coloring comes out flat across a filled band, which is the price of not iterating it.
+
+use std::sync::OnceLock;
+
+use crate::{ComputeOptions, PlaneMap};
+use crate::fractal::{EscapeResult, Fractal};
+use crate::progress::Progress;
+
+// rectangles this narrow are computed outright; their border is most of them anyway
+const MIN_SIZE: usize = 6;
+
+struct Tracer<'a> {
+    fractal: &'a Fractal,
+    max_iters: usize,
+    region: (f64, f64, f64, f64),
+    width: usize,
+    height: usize,
+    options: &'a ComputeOptions,
+    plane: PlaneMap,
+    progress: Option<&'a Progress>,
+    // each pixel is computed or filled at most once, though neighbouring rectangles share borders
+    pixels: Vec<OnceLock<EscapeResult>>,
+}
+
+impl Tracer<'_> {
+    fn at(&self, x: usize, y: usize) -> EscapeResult {
+        *self.pixels[y * self.width + x].get_or_init(|| {
+            // skipped pixels all match, so the rest of a cancelled view fills in quickly
+            if self.progress.is_some_and(Progress::is_cancelled) {
+                return EscapeResult::skipped(self.max_iters);
+            }
+            let (x_min, x_max, y_min, y_max) = self.region;
+            let (cx, cy) = self.plane.to_plane((
+                x_min + (x_max - x_min) * (x as f64 / self.width as f64),
+                y_min + (y_max - y_min) * (y as f64 / self.height as f64),
+            ));
+            if let Some(progress) = self.progress {
+                progress.advance(1);
+            }
+            self.fractal.at_point_with(cx, cy, self.max_iters, self.options)
+        })
+    }
+
+    // traces the pixels x0..x1 × y0..y1
+    fn trace(&self, (x0, x1): (usize, usize), (y0, y1): (usize, usize)) {
+        let (width, height) = (x1 - x0, y1 - y0);
+        if width <= MIN_SIZE || height <= MIN_SIZE {
+            for y in y0..y1 {
+                for x in x0..x1 {
+                    self.at(x, y);
+                }
+            }
+            return;
+        }
+
+        let top_and_bottom = (x0..x1).flat_map(|x| [(x, y0), (x, y1 - 1)]);
+        let sides = (y0 + 1..y1 - 1).flat_map(|y| [(x0, y), (x1 - 1, y)]);
+        let corner = self.at(x0, y0);
+        // newton basins can share escape counts, so the root has to match as well
+        let same = |result: EscapeResult| result.iterations == corner.iterations && result.root == corner.root;
+        if top_and_bottom.chain(sides).all(|(x, y)| same(self.at(x, y))) {
+            let mut filled = 0;
+            for y in y0 + 1..y1 - 1 {
+                for x in x0 + 1..x1 - 1 {
+                    filled += usize::from(self.pixels[y * self.width + x].set(corner).is_ok());
+                }
+            }
+            if let Some(progress) = self.progress {
+                progress.advance(filled);
+            }
+            return;
+        }
+
+        // the halves share the middle line, whose pixels are only computed once
+        if width >= height {
+            let middle = x0 + width / 2;
+            rayon::join(|| self.trace((x0, middle + 1), (y0, y1)), || self.trace((middle, x1), (y0, y1)));
+        } else {
+            let middle = y0 + height / 2;
+            rayon::join(|| self.trace((x0, x1), (y0, middle + 1)), || self.trace((x0, x1), (middle, y1)));
+        }
+    }
+}
+
+/// Escape results of `fractal` over `region` at `width`×`height` pixels, row by row, as
+/// `Fractal::calculate` gives them but with the inside of flat rectangles filled rather than
+/// computed.
+pub fn calculate(
+    fractal: &Fractal,
+    max_iters: usize,
+    region: (f64, f64, f64, f64),
+    width: usize,
+    height: usize,
+    options: &ComputeOptions,
+    progress: Option<&Progress>,
+) -> Vec<EscapeResult> {
+    let tracer = Tracer {
+        fractal,
+        max_iters,
+        region,
+        width,
+        height,
+        options,
+        plane: PlaneMap::new(options),
+        progress,
+        pixels: (0..width * height).map(|_| OnceLock::new()).collect(),
+    };
+    tracer.trace((0, width), (0, height));
+    tracer.pixels.into_iter().map(|pixel| pixel.into_inner().unwrap_or_default()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::newton::Polynomial;
+
+    #[test]
+    fn test_tracing_matches_the_full_computation() {
+        let options = ComputeOptions::default();
+        for (fractal, region) in [
+            (Fractal::Mandelbrot, (-2.0, 1.0, -1.0, 1.0)),
+            (Fractal::Mandelbrot, (-0.75, -0.73, 0.09, 0.11)),
+            (Fractal::Newton(Polynomial::default()), (-2.0, 2.0, -2.0, 2.0)),
+        ] {
+            let traced = calculate(&fractal, 200, region, 90, 60, &options, None);
+            let full = fractal.calculate(200, region, 90, 60, &options, None);
+            let differ = traced
+                .iter()
+                .zip(&full)
+                .filter(|(traced, full)| (traced.iterations, traced.root) != (full.iterations, full.root))
+                .count();
+            assert_eq!(differ, 0, "{} over {:?}", fractal.name(), region);
+        }
+    }
+}
@@ -0,0 +1,258 @@
+// Computed views kept on disk, in ~/.cache/mandelbrot, so rendering a view again (in another
+// palette, or as a frame of an animation run before) loads its escape data instead of
+// computing it. Each view is one `export::to_bytes` file named by the hash of everything that
+// goes into computing it: the fractal, region, size, iterations, compute options and the
+// crate version, so any change to those, or an upgrade, misses rather than loads stale data.
+//
+// Whole views are kept rather than tiles of a fixed grid: the views that come back, re-colored
+// or as frames of an animation run again, come back with the same bounds and size, while they
+// rarely line up with any grid, so tiles would have to be computed past the view's edges and
+// cut to fit every time.
+//
+// The cache holds at most a set number of bytes. Loading a view marks its file as used, and
+// storing one past the limit deletes the least recently used views until it fits again.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::ComputeOptions;
+use crate::error::MandelbrotError;
+use crate::export::{self, DataInfo};
+use crate::fractal::Fractal;
+use crate::renderer::RenderData;
+
+/// Bytes a cache holds by default, 1 GiB.
+pub const DEFAULT_LIMIT: u64 = 1 << 30;
+
+/// A directory of computed views.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    limit: u64,
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            limit: DEFAULT_LIMIT,
+        }
+    }
+
+    /// Most bytes of views to keep; storing past it deletes the least recently used ones.
+    pub fn with_limit(mut self, limit: u64) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// `$XDG_CACHE_HOME/mandelbrot`, or under `~/.cache` without it.
+    pub fn default_dir() -> Option<PathBuf> {
+        let cache = match std::env::var_os("XDG_CACHE_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+        };
+        Some(cache.join("mandelbrot"))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Deletes every view, and anything left half written; returns how many views there were.
+    pub fn clear(&self) -> crate::Result<usize> {
+        let mut cleared = 0;
+        for (path, ..) in files(&self.dir, |extension| extension == "bin" || extension == "partial")? {
+            fs::remove_file(&path).map_err(|e| MandelbrotError::file(path.display(), e))?;
+            cleared += usize::from(path.extension().is_some_and(|extension| extension == "bin"));
+        }
+        Ok(cleared)
+    }
+
+    /// Bytes the views take, together.
+    pub fn size(&self) -> crate::Result<u64> {
+        Ok(files(&self.dir, |extension| extension == "bin")?.iter().map(|(_, size, _)| size).sum())
+    }
+
+    // deletes the least recently used views, but never `keep`, until they fit the limit
+    fn trim(&self, keep: &Path) -> crate::Result<()> {
+        let mut views = files(&self.dir, |extension| extension == "bin")?;
+        let mut total: u64 = views.iter().map(|(_, size, _)| size).sum();
+        views.sort_by_key(|&(_, _, used)| used);
+        for (path, size, _) in views {
+            if total <= self.limit {
+                break;
+            }
+            if path != keep {
+                fs::remove_file(&path).map_err(|e| MandelbrotError::file(path.display(), e))?;
+                total -= size;
+            }
+        }
+        Ok(())
+    }
+
+    /// Where the view computed by `calculate` with these arguments is kept.
+    pub fn entry(
+        &self,
+        fractal: &Fractal,
+        max_iters: usize,
+        region: (f64, f64, f64, f64),
+        width: usize,
+        height: usize,
+        options: &ComputeOptions,
+    ) -> Entry {
+        // Debug formatting writes floats so they read back exactly, which makes it a fine key
+        let key = format!(
+            "{} {:?} {} {:?} {}x{} {:?}",
+            env!("CARGO_PKG_VERSION"),
+            fractal,
+            max_iters,
+            region,
+            width,
+            height,
+            options
+        );
+        let hash = blake3::hash(key.as_bytes()).to_hex();
+        Entry {
+            cache: self.clone(),
+            path: self.dir.join(format!("{}.bin", hash)),
+            info: DataInfo {
+                fractal: fractal.name().to_string(),
+                region,
+                width,
+                height,
+                max_iterations: max_iters,
+            },
+        }
+    }
+}
+
+/// One view's place in a `Cache`.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    cache: Cache,
+    path: PathBuf,
+    info: DataInfo,
+}
+
+impl Entry {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The view's escape data, if it's been stored, marking it as just used. Unreadable or
+    /// damaged files count as missing, to be computed and stored over.
+    pub fn load(&self) -> Option<RenderData> {
+        let bytes = fs::read(&self.path).ok()?;
+        let (info, data) = export::from_bytes(&bytes).ok()?;
+        // a view that can't be marked is only trimmed sooner
+        let _ = File::options().write(true).open(&self.path).and_then(|file| file.set_modified(SystemTime::now()));
+        (info == self.info).then_some(data)
+    }
+
+    /// Stores the view's escape data, then trims the cache to its limit. The file is written
+    /// under another name and moved into place, so a render running at the same time never
+    /// loads half of it.
+    pub fn store(&self, data: &RenderData) -> crate::Result<()> {
+        fs::create_dir_all(&self.cache.dir).map_err(|e| MandelbrotError::file(self.cache.dir.display(), e))?;
+        let partial = self.path.with_extension(format!("{}.partial", std::process::id()));
+        fs::write(&partial, export::to_bytes(&self.info, data)).map_err(|e| MandelbrotError::file(partial.display(), e))?;
+        fs::rename(&partial, &self.path).map_err(|e| MandelbrotError::file(self.path.display(), e))?;
+        self.cache.trim(&self.path)
+    }
+}
+
+// the files in `dir` with an extension `wanted` takes, as (path, size, last modified); none
+// when there's no such directory
+fn files(dir: &Path, wanted: impl Fn(&str) -> bool) -> crate::Result<Vec<(PathBuf, u64, SystemTime)>> {
+    let read = match fs::read_dir(dir) {
+        Ok(read) => read,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(MandelbrotError::file(dir.display(), e)),
+    };
+    let mut files = Vec::new();
+    for entry in read {
+        let entry = entry.map_err(|e| MandelbrotError::file(dir.display(), e))?;
+        let path = entry.path();
+        if !path.extension().and_then(|extension| extension.to_str()).is_some_and(&wanted) {
+            continue;
+        }
+        // another render may have trimmed it away since the listing
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        files.push((path, metadata.len(), metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)));
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a test's cache directory, named for the process and the test so parallel runs don't share
+    // it, and removed when the test ends, failed or not
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(test: &str) -> Self {
+            Self(std::env::temp_dir().join(format!("mandelbrot-{}-{}", std::process::id(), test)))
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn test_views_load_back_from_the_cache() {
+        let dir = TempDir::new("cache-load");
+        let cache = Cache::new(&dir.0);
+        let options = ComputeOptions::default();
+        let region = (-2.0, 1.0, -1.0, 1.0);
+        let entry = cache.entry(&Fractal::Mandelbrot, 50, region, 12, 8, &options);
+        assert!(entry.load().is_none());
+
+        let data = crate::calculate(&Fractal::Mandelbrot, 50, region, 12, 8, &options, None);
+        entry.store(&data).unwrap();
+        assert_eq!(entry.load().map(|loaded| loaded.checksum()), Some(data.checksum()));
+
+        // anything that changes the computation is another entry
+        let deeper = cache.entry(&Fractal::Mandelbrot, 51, region, 12, 8, &options);
+        let burning = cache.entry(&Fractal::BurningShip, 50, region, 12, 8, &options);
+        let distance = cache.entry(&Fractal::Mandelbrot, 50, region, 12, 8, &ComputeOptions { distance: true, ..options });
+        for other in [deeper, burning, distance] {
+            assert_ne!(other.path(), entry.path());
+            assert!(other.load().is_none());
+        }
+    }
+
+    #[test]
+    fn test_least_recently_used_views_go_first() {
+        let dir = TempDir::new("cache-trim");
+        let options = ComputeOptions::default();
+        let region = (-2.0, 1.0, -1.0, 1.0);
+        let view = |max_iters| crate::calculate(&Fractal::Mandelbrot, max_iters, region, 12, 8, &options, None);
+        let size = export::to_bytes(&Cache::new(&dir.0).entry(&Fractal::Mandelbrot, 10, region, 12, 8, &options).info, &view(10)).len() as u64;
+        // room for two views, a little over
+        let cache = Cache::new(&dir.0).with_limit(2 * size + size / 2);
+        let entries: Vec<Entry> = (10..13).map(|max_iters| cache.entry(&Fractal::Mandelbrot, max_iters, region, 12, 8, &options)).collect();
+
+        let past = |seconds| SystemTime::now() - std::time::Duration::from_secs(seconds);
+        entries[0].store(&view(10)).unwrap();
+        entries[1].store(&view(11)).unwrap();
+        // the first is used after the second, so the second is the one to go
+        File::options().write(true).open(entries[1].path()).unwrap().set_modified(past(60)).unwrap();
+        File::options().write(true).open(entries[0].path()).unwrap().set_modified(past(120)).unwrap();
+        assert!(entries[0].load().is_some());
+        entries[2].store(&view(12)).unwrap();
+        assert!(entries[0].load().is_some() && entries[1].load().is_none() && entries[2].load().is_some());
+        assert!(cache.size().unwrap() <= 2 * size + size / 2);
+
+        assert_eq!(cache.clear().unwrap(), 2);
+        assert_eq!(cache.size().unwrap(), 0);
+    }
+}
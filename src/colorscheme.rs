@@ -1,4 +1,9 @@
-#[derive(Debug, Clone, Copy)]
+use serde::Deserialize;
+
+use crate::error::MandelbrotError;
+
+/// An 8-bit RGB color.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
     pub r: u8, 
     pub g: u8, 
@@ -10,6 +15,28 @@ impl Color {
         Self { r, g, b }
     }
 
+    /// Parses "#rrggbb" or "#rgb" (the leading '#' is optional).
+    pub fn from_hex(s: &str) -> Option<Self> {
+        let hex = s.trim().trim_start_matches('#');
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        match hex.len() {
+            6 => Some(Color::new(
+                u8::from_str_radix(&hex[0..2], 16).ok()?,
+                u8::from_str_radix(&hex[2..4], 16).ok()?,
+                u8::from_str_radix(&hex[4..6], 16).ok()?,
+            )),
+            3 => {
+                let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).map(|d| d * 17).ok();
+                Some(Color::new(digit(0)?, digit(1)?, digit(2)?))
+            }
+            _ => None,
+        }
+    }
+
+    /// Linear interpolation towards `other`, with `t` clamped to 0.0 - 1.0.
     pub fn lerp(self, other: Color, t: f64) -> Color {
         let t = t.clamp(0.0, 1.0);
         Color {
@@ -19,14 +46,56 @@ impl Color {
         }
     }
 
+    /// Interpolation towards `other` in the given color space, with `t` clamped to 0.0 - 1.0.
+    pub fn mix(self, other: Color, t: f64, space: Interpolation) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let lerp = |a: f64, b: f64| a + (b - a) * t;
+        match space {
+            Interpolation::Rgb => self.lerp(other, t),
+            Interpolation::Hsv => {
+                let ([h1, s1, v1], [h2, s2, v2]) = (self.to_hsv(), other.to_hsv());
+                Color::from_hsv(lerp_hue(h1, s1, h2, s2, t), lerp(s1, s2), lerp(v1, v2))
+            }
+            Interpolation::Hsl => {
+                let ([h1, s1, l1], [h2, s2, l2]) = (self.to_hsl(), other.to_hsl());
+                Color::from_hsl(lerp_hue(h1, s1, h2, s2, t), lerp(s1, s2), lerp(l1, l2))
+            }
+            Interpolation::Oklab => {
+                let ([l1, a1, b1], [l2, a2, b2]) = (self.to_oklab(), other.to_oklab());
+                Color::from_oklab([lerp(l1, l2), lerp(a1, a2), lerp(b1, b2)])
+            }
+            Interpolation::Lch => {
+                // the polar form of oklab: lightness, chroma and hue
+                let polar = |[l, a, b]: [f64; 3]| [l, a.hypot(b), b.atan2(a).to_degrees().rem_euclid(360.0)];
+                let ([l1, c1, h1], [l2, c2, h2]) = (polar(self.to_oklab()), polar(other.to_oklab()));
+                let (l, c, h) = (lerp(l1, l2), lerp(c1, c2), lerp_hue(h1, c1, h2, c2, t).to_radians());
+                Color::from_oklab([l, c * h.cos(), c * h.sin()])
+            }
+        }
+    }
+
+    /// Converts hue (degrees), saturation and value (0.0 - 1.0) to RGB.
     pub fn from_hsv(h: f64, s: f64, v: f64) -> Self {
-        let h = h % 360.0;
         let s = s.clamp(0.0, 1.0);
         let v = v.clamp(0.0, 1.0);
 
         let c = v*s;
+        Self::from_chroma(h, c, v - c)
+    }
+
+    /// Converts hue (degrees), saturation and lightness (0.0 - 1.0) to RGB.
+    pub fn from_hsl(h: f64, s: f64, l: f64) -> Self {
+        let s = s.clamp(0.0, 1.0);
+        let l = l.clamp(0.0, 1.0);
+
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        Self::from_chroma(h, c, l - c / 2.0)
+    }
+
+    // the RGB color of hue `h` with chroma `c`, lifted by `m`
+    fn from_chroma(h: f64, c: f64, m: f64) -> Self {
+        let h = h % 360.0;
         let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
-        let m = v -c;
 
         let (r, g, b) = match h as i32 {
             0..=59 => (c, x, 0.0),
@@ -43,49 +112,461 @@ impl Color {
             b: ((b+m) * 255.0) as u8,
         }
     }
+
+    // hue in degrees, the largest channel and the chroma (0.0 - 1.0)
+    fn hue(self) -> (f64, f64, f64) {
+        let [r, g, b] = [self.r, self.g, self.b].map(|channel| channel as f64 / 255.0);
+        let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+        let c = max - min;
+        let hue = if c == 0.0 {
+            0.0
+        } else if max == r {
+            60.0 * ((g - b) / c).rem_euclid(6.0)
+        } else if max == g {
+            60.0 * ((b - r) / c + 2.0)
+        } else {
+            60.0 * ((r - g) / c + 4.0)
+        };
+        (hue, max, c)
+    }
+
+    /// Hue (degrees), saturation and value (0.0 - 1.0).
+    pub fn to_hsv(self) -> [f64; 3] {
+        let (hue, max, c) = self.hue();
+        [hue, if max == 0.0 { 0.0 } else { c / max }, max]
+    }
+
+    /// Hue (degrees), saturation and lightness (0.0 - 1.0).
+    pub fn to_hsl(self) -> [f64; 3] {
+        let (hue, max, c) = self.hue();
+        let l = max - c / 2.0;
+        let s = if l == 0.0 || l == 1.0 { 0.0 } else { c / (1.0 - (2.0 * l - 1.0).abs()) };
+        [hue, s, l]
+    }
+
+    /// Lightness, a and b in Björn Ottosson's Oklab space.
+    pub fn to_oklab(self) -> [f64; 3] {
+        let [r, g, b] = [self.r, self.g, self.b].map(|channel| {
+            let c = channel as f64 / 255.0;
+            if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+        });
+        let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+        let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+        let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+        [
+            0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+            1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+            0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+        ]
+    }
+
+    /// Converts an Oklab color back, clipping what falls outside sRGB.
+    pub fn from_oklab([lightness, a, b]: [f64; 3]) -> Self {
+        let l = (lightness + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+        let m = (lightness - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+        let s = (lightness - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+        let [r, g, b] = [
+            4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+            -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+            -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+        ]
+        .map(|c: f64| {
+            let c = if c <= 0.0031308 { 12.92 * c } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+            (c.clamp(0.0, 1.0) * 255.0).round() as u8
+        });
+        Color::new(r, g, b)
+    }
+}
+
+// hue between h1 and h2 the short way round the circle; a gray end (no saturation) has no
+// hue of its own and takes the other one's
+fn lerp_hue(h1: f64, s1: f64, h2: f64, s2: f64, t: f64) -> f64 {
+    let (h1, h2) = match (s1 > 1e-9, s2 > 1e-9) {
+        (false, true) => (h2, h2),
+        (true, false) => (h1, h1),
+        _ => (h1, h2),
+    };
+    let delta = (h2 - h1 + 540.0).rem_euclid(360.0) - 180.0;
+    (h1 + delta * t).rem_euclid(360.0)
+}
+
+/// Color space gradients interpolate in between their stops.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Interpolation {
+    /// straight lines between the RGB values
+    #[default]
+    Rgb,
+    /// around the hue circle the short way, as are Hsl and Lch
+    Hsv,
+    Hsl,
+    /// perceptually even steps in lightness and color
+    Oklab,
+    /// Oklab in polar form, keeping colors saturated through the midpoints
+    Lch,
+}
+
+impl Interpolation {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "rgb" => Some(Interpolation::Rgb),
+            "hsv" => Some(Interpolation::Hsv),
+            "hsl" => Some(Interpolation::Hsl),
+            "oklab" => Some(Interpolation::Oklab),
+            "lch" | "oklch" => Some(Interpolation::Lch),
+            _ => None,
+        }
+    }
 }
 
+/// Piecewise linear color ramp over 0.0 - 1.0.
 #[derive(Debug, Clone)]
 pub struct Gradient {
-    stops: Vec<(f64, Color)> // (position, color), where position has to be between 0.0 and 1.0
+    stops: Vec<(f64, Color)>, // (position, color), where position has to be between 0.0 and 1.0
+    interpolation: Interpolation,
 }
 
 impl Gradient {
+    /// Builds a gradient from (position, color) stops; they don't need to be sorted.
     pub fn new(stops: Vec<(f64, Color)>) -> Self {
         let mut stops = stops;
         stops.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
-        Self { stops }
+        Self {
+            stops,
+            interpolation: Interpolation::Rgb,
+        }
     }
 
-    pub fn get_color(&self, t: f64) -> Color {
-        let t = t.clamp(0.0, 1.0);
+    /// Blends between stops in `interpolation`'s color space rather than in RGB.
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
 
-        if self.stops.is_empty() {
-            return Color::new(0,0,0);
-        }
+    /// Parses a comma separated list of "position:color" stops,
+    /// e.g. "0.0:#000000,0.5:#ff00ff,1.0:#ffffff".
+    pub fn from_spec(spec: &str) -> crate::Result<Self> {
+        Self::parse_spec(spec).map_err(|reason| MandelbrotError::invalid(format_args!("gradient '{}'", spec), reason))
+    }
+
+    fn parse_spec(spec: &str) -> Result<Self, String> {
+        let stops = spec
+            .split(',')
+            .enumerate()
+            .map(|(i, stop)| {
+                let (position, color) = stop
+                    .split_once(':')
+                    .ok_or_else(|| format!("stop {} ('{}'): expected position:color", i + 1, stop.trim()))?;
+                parse_stop(position.trim(), color.trim()).map_err(|e| format!("stop {} ('{}'): {}", i + 1, stop.trim(), e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Self::validated(stops)
+    }
 
-        if t <= self.stops[0].0 {
-            return self.stops[0].1;
+    /// Parses a TOML palette of the form
+    ///
+    /// ```toml
+    /// interpolation = "oklab"  # optional, see `Interpolation`
+    /// stops = [
+    ///     { position = 0.0, color = "#000000" },
+    ///     { position = 1.0, color = "#ffffff" },
+    /// ]
+    /// ```
+    pub fn from_toml(content: &str) -> crate::Result<Self> {
+        Self::parse_toml(content).map_err(|reason| MandelbrotError::invalid("TOML gradient", reason))
+    }
+
+    fn parse_toml(content: &str) -> Result<Self, String> {
+        #[derive(Deserialize)]
+        struct Palette {
+            stops: Vec<Stop>,
+            interpolation: Option<String>,
         }
 
-        if t >= self.stops.last().unwrap().0 {
-            return self.stops.last().unwrap().1;
+        #[derive(Deserialize)]
+        struct Stop {
+            position: f64,
+            color: String,
         }
 
-        // find two stops to interpolate between them
-        for i in 0..self.stops.len() - 1 {
-            let (pos1, color1) = self.stops[i];
-            let (pos2, color2) = self.stops[i + 1];  
+        let palette: Palette = toml::from_str(content).map_err(|e| e.to_string())?;
+        let stops = palette
+            .stops
+            .iter()
+            .enumerate()
+            .map(|(i, stop)| {
+                parse_stop(&stop.position.to_string(), &stop.color).map_err(|e| format!("stop {}: {}", i + 1, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
 
-            if t >= pos1 && t <= pos2 {
-                let local_t = (t - pos1) / (pos2 - pos1);
-                return color1.lerp(color2, local_t);
-            }
+        let interpolation = match palette.interpolation {
+            Some(name) => Interpolation::from_str(&name).ok_or_else(|| format!("unknown interpolation '{}'", name))?,
+            None => Interpolation::Rgb,
+        };
+        Ok(Self::validated(stops)?.with_interpolation(interpolation))
+    }
+
+    pub fn from_file(path: &str) -> crate::Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| MandelbrotError::file(path, e))?;
+        Self::parse_toml(&content).map_err(|reason| MandelbrotError::invalid(format_args!("gradient file {}", path), reason))
+    }
+
+    /// Spreads `colors` evenly from 0.0 to 1.0.
+    pub fn from_colors(colors: &[Color]) -> crate::Result<Self> {
+        Self::spread(colors).map_err(|reason| MandelbrotError::invalid("palette", reason))
+    }
+
+    fn spread(colors: &[Color]) -> Result<Self, String> {
+        let last = colors.len().saturating_sub(1).max(1) as f64;
+        Self::validated(colors.iter().enumerate().map(|(i, &color)| (i as f64 / last, color)).collect())
+    }
+
+    /// Reads a palette file, picking the format from the extension: a GIMP palette (.gpl),
+    /// a Fractint map (.map) or anything else as a list of hex colors.
+    pub fn from_palette_file(path: &str) -> crate::Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| MandelbrotError::file(path, e))?;
+        let extension = std::path::Path::new(path).extension().and_then(|ext| ext.to_str()).unwrap_or("");
+        let colors = match extension.to_lowercase().as_str() {
+            "gpl" => parse_gpl(&content),
+            "map" => parse_rgb_lines(&content),
+            _ => parse_hex_list(&content),
+        };
+        colors
+            .and_then(|colors| Self::spread(&colors))
+            .map_err(|reason| MandelbrotError::invalid(format_args!("palette file {}", path), reason))
+    }
+
+    fn validated(stops: Vec<(f64, Color)>) -> Result<Self, String> {
+        if stops.len() < 2 {
+            return Err(format!("a gradient needs at least two stops, got {}", stops.len()));
         }
-        self.stops[0].1
+        Ok(Self::new(stops))
     }
+
+    /// Color at position `t`, interpolated between the surrounding stops.
+    pub fn get_color(&self, t: f64) -> Color {
+        interpolate(&self.stops, t, self.interpolation)
+    }
+}
+
+// color at `t` along stops sorted by position; before the first and after the last stop the
+// end colors carry on
+fn interpolate(stops: &[(f64, Color)], t: f64, space: Interpolation) -> Color {
+    let t = t.clamp(0.0, 1.0);
+    let (Some(&(first, first_color)), Some(&(last, last_color))) = (stops.first(), stops.last()) else {
+        return Color::new(0, 0, 0);
+    };
+    if t <= first {
+        return first_color;
+    }
+    if t >= last {
+        return last_color;
+    }
+
+    // the first stop past t, and the one before it
+    let next = stops.partition_point(|&(position, _)| position < t);
+    let (pos1, color1) = stops[next - 1];
+    let (pos2, color2) = stops[next];
+    color1.mix(color2, (t - pos1) / (pos2 - pos1), space)
 }
 
+fn parse_stop(position: &str, color: &str) -> Result<(f64, Color), String> {
+    let position: f64 = position
+        .parse()
+        .map_err(|_| format!("invalid position '{}', expected a number", position))?;
+    if !(0.0..=1.0).contains(&position) {
+        return Err(format!("position {} is outside 0.0 - 1.0", position));
+    }
+
+    let color = Color::from_hex(color).ok_or_else(|| format!("invalid color '{}', expected #rrggbb", color))?;
+    Ok((position, color))
+}
+
+// stops of the built-in schemes, sorted by position so they can be used as they are
+
+// blue to white classic
+const CLASSIC: &[(f64, Color)] = &[
+    (0.0, Color::new(0, 7, 100)),
+    (0.16, Color::new(32, 107, 203)),
+    (0.42, Color::new(237, 255, 255)),
+    (0.6425, Color::new(255, 170, 0)),
+    (0.8575, Color::new(0, 2, 0)),
+    (1.0, Color::new(0, 7, 100)),
+];
+
+const OCEAN: &[(f64, Color)] = &[
+    (0.0, Color::new(0, 0, 128)),
+    (0.3, Color::new(0, 128, 255)),
+    (0.6, Color::new(64, 224, 208)),
+    (1.0, Color::new(240, 255, 255)),
+];
+
+const FIRE: &[(f64, Color)] = &[
+    (0.0, Color::new(0, 0, 0)),
+    (0.25, Color::new(128, 0, 0)),
+    (0.5, Color::new(255, 0, 0)),
+    (0.75, Color::new(255, 165, 0)),
+    (1.0, Color::new(255, 255, 0)),
+];
+
+const FOREST: &[(f64, Color)] = &[
+    (0.0, Color::new(0, 20, 0)),
+    (0.3, Color::new(34, 139, 34)),
+    (0.6, Color::new(144, 238, 144)),
+    (1.0, Color::new(240, 255, 240)),
+];
+
+const SUNSET: &[(f64, Color)] = &[
+    (0.0, Color::new(25, 25, 112)),
+    (0.3, Color::new(255, 69, 0)),
+    (0.6, Color::new(255, 140, 0)),
+    (0.8, Color::new(255, 215, 0)),
+    (1.0, Color::new(255, 250, 205)),
+];
+
+// GIMP palette: a "GIMP Palette" line, optional Name and Columns headers, then "r g b name"
+// lines; '#' starts a comment
+fn parse_gpl(content: &str) -> Result<Vec<Color>, String> {
+    if content.lines().next().map(str::trim) != Some("GIMP Palette") {
+        return Err("not a GIMP palette, the first line should be 'GIMP Palette'".to_string());
+    }
+    // headers are blanked rather than dropped so errors keep the file's line numbers
+    let body: Vec<&str> = content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| if i == 0 || line.starts_with("Name:") || line.starts_with("Columns:") { "" } else { line })
+        .collect();
+    parse_rgb_lines(&body.join("\n"))
+}
+
+// one "r g b" triple of 0 - 255 values per line, anything after it ignored, as in Fractint
+// maps; blank lines and lines starting with '#' or ';' are skipped
+fn parse_rgb_lines(content: &str) -> Result<Vec<Color>, String> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with(['#', ';']))
+        .map(|(i, line)| {
+            let mut values = line.split_whitespace().map(|value| value.parse::<u8>());
+            match (values.next(), values.next(), values.next()) {
+                (Some(Ok(r)), Some(Ok(g)), Some(Ok(b))) => Ok(Color::new(r, g, b)),
+                _ => Err(format!("line {}: expected three values from 0 to 255, got '{}'", i + 1, line.trim())),
+            }
+        })
+        .collect()
+}
+
+// hex colors separated by whitespace or commas; lines starting with ';' or "//" are comments
+fn parse_hex_list(content: &str) -> Result<Vec<Color>, String> {
+    content
+        .lines()
+        .filter(|line| !line.trim_start().starts_with([';', '/']))
+        .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+        .filter(|token| !token.is_empty())
+        .map(|token| Color::from_hex(token).ok_or_else(|| format!("invalid color '{}', expected #rrggbb", token)))
+        .collect()
+}
+
+// perceptually uniform scientific colormaps, sampled at 17 points from the published polynomial
+// fits (matplotlib's viridis, magma and inferno, Google's turbo as fitted by d3)
+
+const VIRIDIS: &[(f64, Color)] = &[
+    (0.0, Color::new(71, 1, 85)),
+    (0.0625, Color::new(72, 24, 106)),
+    (0.125, Color::new(71, 45, 123)),
+    (0.1875, Color::new(67, 64, 134)),
+    (0.25, Color::new(61, 82, 140)),
+    (0.3125, Color::new(52, 99, 142)),
+    (0.375, Color::new(43, 114, 142)),
+    (0.4375, Color::new(35, 129, 141)),
+    (0.5, Color::new(31, 144, 139)),
+    (0.5625, Color::new(33, 159, 135)),
+    (0.625, Color::new(42, 174, 128)),
+    (0.6875, Color::new(61, 188, 116)),
+    (0.75, Color::new(90, 200, 97)),
+    (0.8125, Color::new(128, 211, 73)),
+    (0.875, Color::new(172, 220, 48)),
+    (0.9375, Color::new(216, 226, 29)),
+    (1.0, Color::new(252, 231, 33)),
+];
+
+const MAGMA: &[(f64, Color)] = &[
+    (0.0, Color::new(0, 0, 0)),
+    (0.0625, Color::new(10, 8, 38)),
+    (0.125, Color::new(30, 13, 73)),
+    (0.1875, Color::new(54, 17, 102)),
+    (0.25, Color::new(79, 22, 122)),
+    (0.3125, Color::new(105, 27, 132)),
+    (0.375, Color::new(131, 34, 134)),
+    (0.4375, Color::new(158, 42, 129)),
+    (0.5, Color::new(183, 53, 119)),
+    (0.5625, Color::new(207, 67, 108)),
+    (0.625, Color::new(228, 84, 99)),
+    (0.6875, Color::new(244, 106, 95)),
+    (0.75, Color::new(254, 132, 99)),
+    (0.8125, Color::new(255, 163, 111)),
+    (0.875, Color::new(254, 197, 132)),
+    (0.9375, Color::new(250, 228, 159)),
+    (1.0, Color::new(254, 249, 186)),
+];
+
+const INFERNO: &[(f64, Color)] = &[
+    (0.0, Color::new(0, 0, 0)),
+    (0.0625, Color::new(11, 6, 44)),
+    (0.125, Color::new(33, 9, 74)),
+    (0.1875, Color::new(59, 12, 93)),
+    (0.25, Color::new(86, 17, 104)),
+    (0.3125, Color::new(112, 23, 108)),
+    (0.375, Color::new(138, 31, 105)),
+    (0.4375, Color::new(162, 41, 96)),
+    (0.5, Color::new(186, 54, 82)),
+    (0.5625, Color::new(207, 69, 62)),
+    (0.625, Color::new(226, 88, 42)),
+    (0.6875, Color::new(241, 111, 24)),
+    (0.75, Color::new(249, 138, 15)),
+    (0.8125, Color::new(250, 169, 19)),
+    (0.875, Color::new(247, 203, 44)),
+    (0.9375, Color::new(243, 234, 93)),
+    (1.0, Color::new(250, 255, 168)),
+];
+
+const TURBO: &[(f64, Color)] = &[
+    (0.0, Color::new(35, 23, 27)),
+    (0.0625, Color::new(73, 62, 175)),
+    (0.125, Color::new(68, 106, 238)),
+    (0.1875, Color::new(50, 149, 247)),
+    (0.25, Color::new(38, 189, 225)),
+    (0.3125, Color::new(41, 221, 187)),
+    (0.375, Color::new(64, 243, 146)),
+    (0.4375, Color::new(102, 253, 109)),
+    (0.5, Color::new(150, 250, 80)),
+    (0.5625, Color::new(198, 235, 59)),
+    (0.625, Color::new(238, 208, 45)),
+    (0.6875, Color::new(255, 171, 36)),
+    (0.75, Color::new(255, 128, 29)),
+    (0.8125, Color::new(238, 84, 21)),
+    (0.875, Color::new(201, 45, 12)),
+    (0.9375, Color::new(161, 18, 2)),
+    (1.0, Color::new(144, 13, 0)),
+];
+
+/// Maps iteration counts to colors.
+///
+/// ```
+/// use mandelbrot::{Color, ColorScheme, Gradient};
+///
+/// let cyberpunk = Gradient::new(vec![
+///     (0.0, Color::new(0, 0, 0)),       // Black
+///     (0.2, Color::new(255, 0, 255)),   // Magenta
+///     (0.4, Color::new(0, 255, 255)),   // Cyan
+///     (0.7, Color::new(255, 0, 128)),   // Hot pink
+///     (1.0, Color::new(255, 255, 255)), // White
+/// ]);
+/// let scheme = ColorScheme::Custom(cyberpunk);
+/// let color = scheme.get_color(10, 100);
+/// ```
+#[derive(Debug, Clone)]
 pub enum ColorScheme {
     Grayscale,
     Classic,
@@ -94,10 +575,17 @@ pub enum ColorScheme {
     Psychedelic,
     Forest,
     Sunset,
+    Viridis,
+    Magma,
+    Inferno,
+    Turbo,
     Custom(Gradient),
+    /// another scheme run backwards, e.g. "viridis-r"
+    Reversed(Box<ColorScheme>),
 }
 
 impl ColorScheme {
+    /// Color for a point that escaped after `iterations`; points in the set are black.
     pub fn get_color(&self, iterations: usize, max_iterations: usize) -> Color {
         if iterations >= max_iterations {
             // point is in the set - return black
@@ -106,78 +594,22 @@ impl ColorScheme {
 
         // normalize to 0.0 - 1.0
         let t = iterations as f64 / max_iterations as f64;
-
-        match self {
-            ColorScheme::Grayscale => {
-                let intensity = (t * 255.0) as u8;
-                Color::new(intensity, intensity, intensity)
-            }
-            ColorScheme::Classic => {
-                // blue to white classic
-                let gradient = Gradient::new(vec![
-                    (0.0, Color::new(0, 7, 100)),
-                    (0.16, Color::new(32, 107, 203)),
-                    (0.42, Color::new(237, 255, 255)),
-                    (0.6425, Color::new(255, 170, 0)),
-                    (0.8575, Color::new(0, 2, 0)),
-                    (1.0, Color::new(0, 7, 100)),
-                ]);
-                gradient.get_color(t)
-            }
-            ColorScheme::Ocean => {
-                let gradient = Gradient::new(vec![
-                    (0.0, Color::new(0, 0, 128)),
-                    (0.3, Color::new(0, 128, 255)),
-                    (0.6, Color::new(64, 224, 208)),
-                    (1.0, Color::new(240, 255, 255)),
-                ]);
-                gradient.get_color(t)
-            }
-            ColorScheme::Fire => {
-                let gradient = Gradient::new(vec![
-                    (0.0, Color::new(0, 0, 0)),
-                    (0.25, Color::new(128, 0, 0)),
-                    (0.5, Color::new(255, 0, 0)),
-                    (0.75, Color::new(255, 165, 0)),
-                    (1.0, Color::new(255, 255, 0)),
-                ]);
-                gradient.get_color(t)
-            }
-            ColorScheme::Psychedelic => {
-                // hsv for smooth color cycling
-                Color::from_hsv(t * 360.0 * 3.0, 1.0, 1.0)
-            }
-            ColorScheme::Forest => {
-                let gradient = Gradient::new(vec![
-                    (0.0, Color::new(0, 20, 0)),
-                    (0.3, Color::new(34, 139, 34)),
-                    (0.6, Color::new(144, 238, 144)),
-                    (1.0, Color::new(240, 255, 240)),
-                ]);
-                gradient.get_color(t)
-            }
-            ColorScheme::Sunset => {
-                let gradient = Gradient::new(vec![
-                    (0.0, Color::new(25, 25, 112)),
-                    (0.3, Color::new(255, 69, 0)),
-                    (0.6, Color::new(255, 140, 0)),
-                    (0.8, Color::new(255, 215, 0)),
-                    (1.0, Color::new(255, 250, 205)),
-                ]);
-                gradient.get_color(t)
-            }
-            ColorScheme::Custom(gradient) => gradient.get_color(t),
-        }
+        self.color_at(t)
     }
 
-    pub fn get_smooth_color(&self, iterations: usize, max_iterations: usize, z_norm: f64) -> Color {
+    /// Like `get_color`, but uses the final |z| of a squaring formula's orbit that escaped past
+    /// `bailout` to remove banding between iteration counts.
+    pub fn get_smooth_color(&self, iterations: usize, max_iterations: usize, z_norm: f64, bailout: f64) -> Color {
         if iterations >= max_iterations {
             return Color::new(0, 0, 0);
         }
 
-        // smooth coloring using logarithmic smoothing
-        let smooth_iter = iterations as f64 + 1.0 - (z_norm.ln() / 2.0_f64.ln()).ln() / 2.0_f64.ln();
-        let t = smooth_iter / max_iterations as f64;
+        let t = smooth_iteration(iterations, z_norm, bailout, 2.0) / max_iterations as f64;
+        self.color_at(t)
+    }
+
+    /// Color at position `t` (0.0 - 1.0) of the scheme's palette.
+    pub fn color_at(&self, t: f64) -> Color {
         let t = t.clamp(0.0, 1.0);
 
         match self {
@@ -185,64 +617,62 @@ impl ColorScheme {
                 let intensity = (t * 255.0) as u8;
                 Color::new(intensity, intensity, intensity)
             }
-            ColorScheme::Classic => {
-                let gradient = Gradient::new(vec![
-                    (0.0, Color::new(0, 7, 100)),
-                    (0.16, Color::new(32, 107, 203)),
-                    (0.42, Color::new(237, 255, 255)),
-                    (0.6425, Color::new(255, 170, 0)),
-                    (0.8575, Color::new(0, 2, 0)),
-                    (1.0, Color::new(0, 7, 100)),
-                ]);
-                gradient.get_color(t)
-            }
-            ColorScheme::Ocean => {
-                let gradient = Gradient::new(vec![
-                    (0.0, Color::new(0, 0, 128)),
-                    (0.3, Color::new(0, 128, 255)),
-                    (0.6, Color::new(64, 224, 208)),
-                    (1.0, Color::new(240, 255, 255)),
-                ]);
-                gradient.get_color(t)
-            }
-            ColorScheme::Fire => {
-                let gradient = Gradient::new(vec![
-                    (0.0, Color::new(0, 0, 0)),
-                    (0.25, Color::new(128, 0, 0)),
-                    (0.5, Color::new(255, 0, 0)),
-                    (0.75, Color::new(255, 165, 0)),
-                    (1.0, Color::new(255, 255, 0)),
-                ]);
-                gradient.get_color(t)
-            }
+            ColorScheme::Classic => interpolate(CLASSIC, t, Interpolation::Rgb),
+            ColorScheme::Ocean => interpolate(OCEAN, t, Interpolation::Rgb),
+            ColorScheme::Fire => interpolate(FIRE, t, Interpolation::Rgb),
             ColorScheme::Psychedelic => {
+                // hsv for smooth color cycling
                 Color::from_hsv(t * 360.0 * 3.0, 1.0, 1.0)
             }
-            ColorScheme::Forest => {
-                let gradient = Gradient::new(vec![
-                    (0.0, Color::new(0, 20, 0)),
-                    (0.3, Color::new(34, 139, 34)),
-                    (0.6, Color::new(144, 238, 144)),
-                    (1.0, Color::new(240, 255, 240)),
-                ]);
-                gradient.get_color(t)
-            }
-            ColorScheme::Sunset => {
-                let gradient = Gradient::new(vec![
-                    (0.0, Color::new(25, 25, 112)),
-                    (0.3, Color::new(255, 69, 0)),
-                    (0.6, Color::new(255, 140, 0)),
-                    (0.8, Color::new(255, 215, 0)),
-                    (1.0, Color::new(255, 250, 205)),
-                ]);
-                gradient.get_color(t)
-            }
+            ColorScheme::Forest => interpolate(FOREST, t, Interpolation::Rgb),
+            ColorScheme::Sunset => interpolate(SUNSET, t, Interpolation::Rgb),
+            ColorScheme::Viridis => interpolate(VIRIDIS, t, Interpolation::Rgb),
+            ColorScheme::Magma => interpolate(MAGMA, t, Interpolation::Rgb),
+            ColorScheme::Inferno => interpolate(INFERNO, t, Interpolation::Rgb),
+            ColorScheme::Turbo => interpolate(TURBO, t, Interpolation::Rgb),
             ColorScheme::Custom(gradient) => gradient.get_color(t),
+            ColorScheme::Reversed(scheme) => scheme.color_at(1.0 - t),
         }
     }
 
+    /// Has the gradient schemes blend in `interpolation`'s color space, turning built-in ones
+    /// into custom gradients; the procedural grayscale and psychedelic stay as they are.
+    pub fn with_interpolation(self, interpolation: Interpolation) -> Self {
+        match self {
+            ColorScheme::Custom(gradient) => ColorScheme::Custom(gradient.with_interpolation(interpolation)),
+            ColorScheme::Reversed(scheme) => ColorScheme::Reversed(Box::new(scheme.with_interpolation(interpolation))),
+            scheme => match scheme.stops() {
+                Some(stops) => ColorScheme::Custom(Gradient::new(stops.to_vec()).with_interpolation(interpolation)),
+                None => scheme,
+            },
+        }
+    }
+
+    // stops of the built-in gradient schemes
+    fn stops(&self) -> Option<&'static [(f64, Color)]> {
+        match self {
+            ColorScheme::Classic => Some(CLASSIC),
+            ColorScheme::Ocean => Some(OCEAN),
+            ColorScheme::Fire => Some(FIRE),
+            ColorScheme::Forest => Some(FOREST),
+            ColorScheme::Sunset => Some(SUNSET),
+            ColorScheme::Viridis => Some(VIRIDIS),
+            ColorScheme::Magma => Some(MAGMA),
+            ColorScheme::Inferno => Some(INFERNO),
+            ColorScheme::Turbo => Some(TURBO),
+            _ => None,
+        }
+    }
+
+    /// Parses a scheme name; a "-r" or "_r" suffix reverses it.
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Option<Self> {
-        match s.to_lowercase().as_str() {
+        let name = s.to_lowercase();
+        if let Some(name) = name.strip_suffix("-r").or_else(|| name.strip_suffix("_r")) {
+            return Self::from_str(name).map(|scheme| ColorScheme::Reversed(Box::new(scheme)));
+        }
+
+        match name.as_str() {
             "grayscale" | "gray" => Some(ColorScheme::Grayscale),
             "classic" => Some(ColorScheme::Classic),
             "ocean" => Some(ColorScheme::Ocean),
@@ -250,6 +680,10 @@ impl ColorScheme {
             "psychedelic" | "rainbow" => Some(ColorScheme::Psychedelic),
             "forest" => Some(ColorScheme::Forest),
             "sunset" => Some(ColorScheme::Sunset),
+            "viridis" => Some(ColorScheme::Viridis),
+            "magma" => Some(ColorScheme::Magma),
+            "inferno" => Some(ColorScheme::Inferno),
+            "turbo" => Some(ColorScheme::Turbo),
             _ => None,
         }
     }
@@ -263,6 +697,117 @@ impl ColorScheme {
             "psychedelic",
             "forest",
             "sunset",
+            "viridis",
+            "magma",
+            "inferno",
+            "turbo",
         ]
     }
-}
\ No newline at end of file
+}
+
+/// Fractional escape count from the final |z|, removing the banding between iteration counts.
+/// `bailout` is the escape radius the orbit passed, which keeps the count between `iterations`
+/// and one more, as histogram coloring needs, and `degree` the fractal's (`Fractal::degree`).
+/// A larger radius makes the count more accurate, and puts it later by about
+/// log_degree(log2 bailout), as many steps as the orbit took to reach it.
+pub fn smooth_iteration(iterations: usize, z_norm: f64, bailout: f64, degree: f64) -> f64 {
+    // |z| goes to the power `degree` each step once it is large, so log_degree(ln|z| / ln
+    // bailout) is how far into the next step it went
+    iterations as f64 + 1.0 - (z_norm.ln() / bailout.ln()).ln() / degree.ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gradient_from_spec() {
+        let gradient = Gradient::from_spec("0.0:#000000, 1.0:#fff").unwrap();
+        let mid = gradient.get_color(0.5);
+        assert_eq!((mid.r, mid.g, mid.b), (127, 127, 127));
+
+        let err = Gradient::from_spec("0.0:#000000,1.5:#ffffff").unwrap_err().to_string();
+        assert!(err.contains("stop 2"), "{}", err);
+        assert!(Gradient::from_spec("0.0:#00000g,1.0:#ffffff").is_err());
+        assert!(Gradient::from_spec("0.0:#000000").is_err());
+    }
+
+    #[test]
+    fn test_gradient_from_toml() {
+        let gradient = Gradient::from_toml(
+            r##"stops = [{ position = 1.0, color = "#ff0000" }, { position = 0.0, color = "#0000ff" }]"##,
+        )
+        .unwrap();
+        assert_eq!(gradient.get_color(0.0).b, 255);
+        assert_eq!(gradient.get_color(1.0).r, 255);
+    }
+
+    #[test]
+    fn test_interpolation_spaces() {
+        let (red, blue) = (Color::new(255, 0, 0), Color::new(0, 0, 255));
+        assert_eq!(red.mix(blue, 0.5, Interpolation::Rgb), Color::new(127, 0, 127));
+        // around the hue circle through magenta, at full saturation
+        assert_eq!(red.mix(blue, 0.5, Interpolation::Hsv), Color::new(255, 0, 255));
+        assert_eq!(red.mix(blue, 0.5, Interpolation::Hsl), Color::new(255, 0, 255));
+        // gray has no hue, so blending towards it only desaturates
+        let gray = Color::new(128, 128, 128);
+        let [hue, ..] = red.mix(gray, 0.5, Interpolation::Hsl).to_hsl();
+        assert_eq!(hue, 0.0);
+
+        for color in [red, blue, gray, Color::new(12, 200, 99)] {
+            assert_eq!(Color::from_oklab(color.to_oklab()), color);
+            for space in [Interpolation::Oklab, Interpolation::Lch] {
+                assert_eq!(color.mix(blue, 0.0, space), color);
+                assert_eq!(color.mix(blue, 1.0, space), blue);
+            }
+        }
+        // oklab puts the midpoint of black and white at half the perceived lightness
+        let (black, white) = (Color::new(0, 0, 0), Color::new(255, 255, 255));
+        let [lightness, ..] = black.mix(white, 0.5, Interpolation::Oklab).to_oklab();
+        assert!((lightness - 0.5).abs() < 0.01);
+
+        let gradient = Gradient::from_toml(
+            r##"interpolation = "lch"
+            stops = [{ position = 0.0, color = "#ff0000" }, { position = 1.0, color = "#0000ff" }]"##,
+        )
+        .unwrap();
+        assert_eq!(gradient.get_color(0.5), red.mix(blue, 0.5, Interpolation::Lch));
+    }
+
+    #[test]
+    fn test_palette_formats() {
+        let gpl = "GIMP Palette\nName: Test\nColumns: 2\n#\n  0   0 255\tBlue\n255   0   0\tRed\n";
+        assert_eq!(parse_gpl(gpl).unwrap(), vec![Color::new(0, 0, 255), Color::new(255, 0, 0)]);
+        assert!(parse_gpl("0 0 0\n").is_err());
+
+        let map = "0 0 0 black\n; comment\n\n128 64 32\n255 255 255\n";
+        let colors = parse_rgb_lines(map).unwrap();
+        assert_eq!(colors[1], Color::new(128, 64, 32));
+        let err = parse_rgb_lines("0 0 0\n1 2\n").unwrap_err();
+        assert!(err.contains("line 2"), "{}", err);
+
+        let hex = "// palette\n#000000, #ff0000\nffffff\n";
+        let gradient = Gradient::from_colors(&parse_hex_list(hex).unwrap()).unwrap();
+        assert_eq!(gradient.get_color(0.5), Color::new(255, 0, 0));
+        assert_eq!(gradient.get_color(0.75), Color::new(255, 127, 127));
+        assert!(Gradient::from_colors(&[Color::new(0, 0, 0)]).is_err());
+    }
+
+    #[test]
+    fn test_builtin_stops_interpolate() {
+        for stops in [CLASSIC, OCEAN, FIRE, FOREST, SUNSET, VIRIDIS, MAGMA, INFERNO, TURBO] {
+            assert!(stops.windows(2).all(|pair| pair[0].0 < pair[1].0));
+            // landing on a stop gives its color exactly
+            for &(position, color) in stops {
+                assert_eq!(interpolate(stops, position, Interpolation::Rgb), color);
+            }
+        }
+        assert_eq!(ColorScheme::Fire.color_at(0.375), Color::new(191, 0, 0));
+
+        let reversed = ColorScheme::from_str("Viridis_R").unwrap();
+        assert_eq!(reversed.color_at(0.0), ColorScheme::Viridis.color_at(1.0));
+        assert_eq!(reversed.color_at(0.25), ColorScheme::Viridis.color_at(0.75));
+        assert!(ColorScheme::from_str("viridis-r-r").is_some());
+        assert!(ColorScheme::from_str("nope-r").is_none());
+    }
+}
@@ -0,0 +1,161 @@
+// Iso-iteration contours, traced with marching squares over the smooth iteration values of a
+// render and written as SVG line art.
+//
+// Samples sit at pixel centers. Each square of four neighbouring samples is classified by which
+// corners reach the level, giving up to two segments between points interpolated along its
+// edges. Segments are then joined into polylines through the edges they share, so a contour
+// comes out as one path rather than thousands of loose strokes.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use crate::export::smooth_value;
+use crate::renderer::RenderData;
+
+// a grid edge: from sample (x, y) to the right (false) or down (true)
+type Edge = (usize, usize, bool);
+
+/// A traced contour in pixel coordinates; closed ones end where they start.
+pub type Polyline = Vec<(f64, f64)>;
+
+/// Level just below the iteration limit, which traces the boundary of the set.
+pub fn boundary_level(max_iterations: usize) -> f64 {
+    max_iterations as f64 - 0.5
+}
+
+/// Traces where the smooth iteration value of `data` crosses `level`.
+pub fn trace(data: &RenderData, level: f64) -> Vec<Polyline> {
+    let (width, height) = (data.width(), data.height());
+    let values: Vec<f64> = data
+        .samples()
+        .map(|result| smooth_value(&result, data))
+        .collect();
+    let value = |x: usize, y: usize| values[y * width + x];
+
+    let mut segments = Vec::new();
+    for y in 0..height.saturating_sub(1) {
+        for x in 0..width.saturating_sub(1) {
+            let corners = [value(x, y), value(x + 1, y), value(x + 1, y + 1), value(x, y + 1)];
+            let case = corners
+                .iter()
+                .enumerate()
+                .fold(0, |case, (i, &v)| if v >= level { case | 1 << i } else { case });
+
+            let (top, right, bottom, left) = ((x, y, false), (x + 1, y, true), (x, y + 1, false), (x, y, true));
+            // saddles are split the way the cell's center value leans
+            let center_in = corners.iter().sum::<f64>() / 4.0 >= level;
+            let pairs: &[(Edge, Edge)] = match case {
+                1 | 14 => &[(left, top)],
+                2 | 13 => &[(top, right)],
+                3 | 12 => &[(left, right)],
+                4 | 11 => &[(right, bottom)],
+                6 | 9 => &[(top, bottom)],
+                7 | 8 => &[(left, bottom)],
+                5 if center_in => &[(top, right), (bottom, left)],
+                5 => &[(left, top), (right, bottom)],
+                10 if center_in => &[(left, top), (right, bottom)],
+                10 => &[(top, right), (bottom, left)],
+                _ => &[],
+            };
+            segments.extend_from_slice(pairs);
+        }
+    }
+
+    let point = |(x, y, down): Edge| {
+        let (x2, y2) = if down { (x, y + 1) } else { (x + 1, y) };
+        let (a, b) = (value(x, y), value(x2, y2));
+        let t = if a == b { 0.5 } else { ((level - a) / (b - a)).clamp(0.0, 1.0) };
+        (x as f64 + 0.5 + (x2 - x) as f64 * t, y as f64 + 0.5 + (y2 - y) as f64 * t)
+    };
+    join(&segments).into_iter().map(|edges| edges.into_iter().map(point).collect()).collect()
+}
+
+// chains segments that share an edge into runs of edges
+fn join(segments: &[(Edge, Edge)]) -> Vec<Vec<Edge>> {
+    let mut by_edge: HashMap<Edge, Vec<usize>> = HashMap::new();
+    for (i, &(a, b)) in segments.iter().enumerate() {
+        by_edge.entry(a).or_default().push(i);
+        by_edge.entry(b).or_default().push(i);
+    }
+
+    let mut used = vec![false; segments.len()];
+    let follow = |chain: &mut Vec<Edge>, used: &mut [bool]| {
+        while let Some(&next) = by_edge[chain.last().unwrap()].iter().find(|&&i| !used[i]) {
+            used[next] = true;
+            let (a, b) = segments[next];
+            chain.push(if a == *chain.last().unwrap() { b } else { a });
+        }
+    };
+
+    // open contours end on the image border, where an edge has a single segment; starting
+    // there keeps them in one piece. Whatever is left afterwards is a closed loop
+    let ends = segments.iter().enumerate().filter(|&(_, (a, b))| by_edge[a].len() == 1 || by_edge[b].len() == 1);
+    let starts: Vec<usize> = ends.map(|(i, _)| i).chain(0..segments.len()).collect();
+
+    let mut chains = Vec::new();
+    for start in starts {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+        let (a, b) = segments[start];
+        let mut chain = if by_edge[&b].len() == 1 { vec![b, a] } else { vec![a, b] };
+        follow(&mut chain, &mut used);
+        chains.push(chain);
+    }
+    chains
+}
+
+/// Writes the contours of `data` at each of `levels` as SVG paths, one per level, in a view
+/// box of the image's pixel size.
+pub fn write_svg(mut out: impl Write, data: &RenderData, levels: &[f64]) -> io::Result<()> {
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}" width="{}" height="{}">"#,
+        data.width(),
+        data.height(),
+        data.width(),
+        data.height()
+    )?;
+    for &level in levels {
+        write!(out, r#"  <path data-level="{}" fill="none" stroke="black" stroke-width="0.5" d=""#, level)?;
+        for polyline in trace(data, level) {
+            for (i, (x, y)) in polyline.iter().enumerate() {
+                write!(out, "{}{:.2} {:.2}", if i == 0 { "M" } else { "L" }, x, y)?;
+            }
+        }
+        writeln!(out, r#""/>"#)?;
+    }
+    writeln!(out, "</svg>")?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fractal::EscapeResult;
+
+    #[test]
+    fn test_traces_closed_loop() {
+        // a 3×3 block in the set in the middle of a 7×7 image
+        let pixels = (0..49)
+            .map(|i| {
+                let (x, y) = (i % 7, i / 7);
+                let inside = (2..5).contains(&x) && (2..5).contains(&y);
+                EscapeResult {
+                    iterations: if inside { 10 } else { 1 },
+                    z_norm: 4.0,
+                    ..Default::default()
+                }
+            })
+            .collect();
+        let data = RenderData::new(7, 7, pixels, 10);
+
+        let contours = trace(&data, boundary_level(10));
+        assert_eq!(contours.len(), 1);
+        let contour = &contours[0];
+        assert_eq!(contour.first(), contour.last());
+        // every point lies between the block's outer samples and its neighbours
+        assert!(contour.iter().all(|&(x, y)| (1.5..=5.5).contains(&x) && (1.5..=5.5).contains(&y)));
+    }
+}
@@ -0,0 +1,151 @@
+// Per-pixel comparison of two computations of one view, for checking that a faster way of
+// computing it, such as perturbation or boundary tracing, gives the same picture.
+//
+// Pixels differ by how far apart their smooth iteration counts are, with points in the set
+// counting as max_iterations as they do in exported data, so a point one computation finds in
+// the set and the other sees escape early differs by nearly the whole count. Newton points
+// that converge to different roots differ by the larger max_iterations.
+//
+// The heat map shrinks a difference to any size by keeping the largest difference each of its
+// pixels covers, so a single pixel that's off still shows in a thumbnail of a big render.
+
+use crate::error::MandelbrotError;
+use crate::export::smooth_value;
+use crate::fractal::EscapeResult;
+use crate::renderer::RenderData;
+
+// palette steps of the heat map above 0, which is for pixels within the tolerance
+const HEAT_LEVELS: usize = 255;
+
+/// How far apart two renders of the same size are, pixel by pixel, in iterations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    width: usize,
+    height: usize,
+    pixels: Vec<f64>,
+}
+
+impl Difference {
+    /// Compares `a` with `b`, which have to be the same size.
+    pub fn between(a: &RenderData, b: &RenderData) -> crate::Result<Self> {
+        if (a.width(), a.height()) != (b.width(), b.height()) {
+            let reason = format!("one render is {}x{} and the other {}x{}", a.width(), a.height(), b.width(), b.height());
+            return Err(MandelbrotError::invalid("comparison", reason));
+        }
+        let most = a.max_iterations.max(b.max_iterations) as f64;
+        let pixels = a
+            .samples()
+            .zip(b.samples())
+            .map(|(p, q)| if p.root != q.root { most } else { (smooth_value(&p, a) - smooth_value(&q, b)).abs() })
+            .collect();
+        Ok(Self {
+            width: a.width(),
+            height: a.height(),
+            pixels,
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn at(&self, x: usize, y: usize) -> f64 {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Pixels that came out exactly the same.
+    pub fn identical(&self) -> usize {
+        self.pixels.iter().filter(|&&d| d == 0.0).count()
+    }
+
+    /// Pixels that differ by more than `tolerance`.
+    pub fn beyond(&self, tolerance: f64) -> usize {
+        self.pixels.iter().filter(|&&d| d > tolerance).count()
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.pixels.iter().sum::<f64>() / self.pixels.len().max(1) as f64
+    }
+
+    /// The pixel that differs the most, as (x, y, difference); the first of them on a tie.
+    pub fn largest(&self) -> Option<(usize, usize, f64)> {
+        let (i, &d) = self.pixels.iter().enumerate().reduce(|best, next| if next.1 > best.1 { next } else { best })?;
+        Some((i % self.width, i / self.width, d))
+    }
+
+    /// The difference as `width`×`height` data for linear coloring: the start of the palette
+    /// where pixels are within `tolerance` of each other, and further along it, on a log scale
+    /// up to the largest difference, where they aren't.
+    pub fn heat_map(&self, width: usize, height: usize, tolerance: f64) -> RenderData {
+        let (width, height) = (width.max(1), height.max(1));
+        let top = self.largest().map_or(0.0, |(_, _, d)| d).ln_1p();
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let rows = (y * self.height / height)..((y + 1) * self.height / height).max(y * self.height / height + 1);
+            for x in 0..width {
+                let columns = (x * self.width / width)..((x + 1) * self.width / width).max(x * self.width / width + 1);
+                let d = rows
+                    .clone()
+                    .flat_map(|y| columns.clone().map(move |x| (x, y)))
+                    .filter(|&(x, y)| x < self.width && y < self.height)
+                    .map(|(x, y)| self.at(x, y))
+                    .fold(0.0, f64::max);
+                let level = if d > tolerance { ((d.ln_1p() / top * HEAT_LEVELS as f64).ceil() as usize).clamp(1, HEAT_LEVELS) } else { 0 };
+                pixels.push(EscapeResult {
+                    iterations: level,
+                    ..EscapeResult::default()
+                });
+            }
+        }
+        // one past the top level, so the most different pixels don't count as in the set
+        RenderData::new(width, height, pixels, HEAT_LEVELS + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fractal::Fractal;
+    use crate::ComputeOptions;
+
+    #[test]
+    fn test_boundary_tracing_matches_the_plain_computation() {
+        let view = |options: &ComputeOptions| crate::calculate(&Fractal::Mandelbrot, 200, (-2.0, 1.0, -1.0, 1.0), 90, 60, options, None);
+        let plain = view(&ComputeOptions::default());
+        let traced = view(&ComputeOptions { boundary_trace: true, ..ComputeOptions::default() });
+        // filled pixels copy the escape count of the border but not its fraction, so they're
+        // off by less than one iteration
+        let difference = Difference::between(&plain, &traced).unwrap();
+        assert!(difference.beyond(0.0) > 0 && difference.identical() > difference.beyond(0.0));
+        assert_eq!(difference.beyond(1.0), 0);
+
+        let coarse = crate::calculate(&Fractal::Mandelbrot, 200, (-2.0, 1.0, -1.0, 1.0), 45, 30, &ComputeOptions::default(), None);
+        assert!(Difference::between(&plain, &coarse).is_err());
+    }
+
+    #[test]
+    fn test_heat_map_keeps_lone_pixels() {
+        let data = |iterations: &[usize]| {
+            let pixels = iterations.iter().map(|&iterations| EscapeResult { iterations, z_norm: 4.0, ..EscapeResult::default() }).collect();
+            RenderData::new(4, 2, pixels, 10)
+        };
+        let a = data(&[1, 1, 1, 1, 1, 1, 1, 1]);
+        let b = data(&[1, 1, 1, 1, 1, 1, 3, 10]);
+        let difference = Difference::between(&a, &b).unwrap();
+        assert_eq!((difference.identical(), difference.beyond(1.0), difference.beyond(5.0)), (6, 2, 1));
+        assert_eq!(difference.largest().map(|(x, y, _)| (x, y)), Some((3, 1)));
+
+        // shrunk to 2x1, the right half keeps the point that escaped in only one of them
+        let heat = difference.heat_map(2, 1, 0.0);
+        assert_eq!(heat.sample(0, 0).iterations, 0);
+        assert_eq!(heat.sample(1, 0).iterations, HEAT_LEVELS);
+        // and within a tolerance of 2, only the largest difference is left to show
+        let heat = difference.heat_map(4, 2, 2.0);
+        assert_eq!(heat.sample(2, 1).iterations, 0);
+        assert!(heat.sample(3, 1).iterations > 0);
+    }
+}
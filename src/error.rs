@@ -0,0 +1,71 @@
+// The one error type of the library's fallible APIs: parsing user input (fractal formulas,
+// polynomials, gradients) and reading or writing files (palettes, bookmarks, exported data).
+
+use std::fmt::Display;
+use std::io;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MandelbrotError {
+    /// a name that isn't one of the choices, e.g. an unknown color scheme
+    #[error("unknown {kind} '{name}'")]
+    Unknown { kind: &'static str, name: String },
+    /// a value that doesn't parse or is out of range
+    #[error("invalid {what}: {reason}")]
+    Invalid { what: String, reason: String },
+    /// a file that couldn't be read, written or understood
+    #[error("{path}: {source}")]
+    File { path: String, source: io::Error },
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, MandelbrotError>;
+
+impl MandelbrotError {
+    pub fn unknown(kind: &'static str, name: &str) -> Self {
+        MandelbrotError::Unknown { kind, name: name.to_string() }
+    }
+
+    /// `what` names the value, e.g. "polynomial 'z^'", and `reason` says what is wrong with it.
+    pub fn invalid(what: impl Display, reason: impl Display) -> Self {
+        MandelbrotError::Invalid {
+            what: what.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn file(path: impl Display, source: io::Error) -> Self {
+        MandelbrotError::File {
+            path: path.to_string(),
+            source,
+        }
+    }
+
+    /// Exit status for a command line tool: 2 for bad settings, like clap's usage errors, and
+    /// 1 for failed I/O.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            MandelbrotError::Unknown { .. } | MandelbrotError::Invalid { .. } => 2,
+            MandelbrotError::File { .. } | MandelbrotError::Io(_) => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages_and_exit_codes() {
+        let unknown = MandelbrotError::unknown("color scheme", "plaid");
+        assert_eq!(unknown.to_string(), "unknown color scheme 'plaid'");
+        assert_eq!(unknown.exit_code(), 2);
+
+        let missing = MandelbrotError::file("palette.gpl", io::Error::from(io::ErrorKind::NotFound));
+        assert!(missing.to_string().starts_with("palette.gpl: "));
+        assert!(std::error::Error::source(&missing).is_some());
+        assert_eq!(missing.exit_code(), 1);
+    }
+}
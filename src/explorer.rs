@@ -0,0 +1,593 @@
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crossterm::{
+    cursor,
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, MouseButton, MouseEvent, MouseEventKind},
+    execute, queue,
+    style::{self, Color as TermColor},
+    terminal,
+};
+
+use num::complex::Complex;
+
+use crate::colorscheme::{self, ColorScheme};
+use crate::fractal::{Fractal, DEFAULT_BAILOUT};
+use crate::interior::{self, InteriorMode};
+use crate::overlay::Overlay;
+use crate::refine::{self, Pass};
+use crate::renderer::{self, OutputFormat, RenderData, Renderer};
+use crate::{ComputeOptions, DEFAULT_VIEW_WIDTH};
+
+const PAN_STEP: f64 = 0.1;
+const ZOOM_STEP: f64 = 1.5;
+const ITERATION_STEP: f64 = 1.25;
+// how far a press of an arrow moves the corner of a zoom box, as a share of the view
+const BOX_STEP: f64 = 0.025;
+// escape radius the readout's potential is taken at, far enough out that z_n ≈ Φ^(d^n)
+const POTENTIAL_BAILOUT: f64 = 1e10;
+
+pub struct Explorer {
+    fractal: Fractal,
+    center_x: f64,
+    center_y: f64,
+    x_span: f64,
+    max_iterations: usize,
+    scheme_index: usize,
+    output_format: OutputFormat,
+    smooth: bool,
+    // the julia set of the center beside the view
+    split: bool,
+    // corners of the zoom box being drawn: where it started, at the center, and where the
+    // arrows have taken the other
+    selection: Option<((f64, f64), (f64, f64))>,
+    // size in cells of the view as last drawn, not counting a julia panel
+    cells: (usize, usize),
+    // cell the left button was last seen at while held, and whether it has moved since it
+    // went down, which makes it a drag rather than a click
+    drag: Option<((u16, u16), bool)>,
+    // cell the pointer was last seen over, whose point the readout describes
+    pointer: Option<(u16, u16)>,
+    // the last view computed at full quality, and its region, to start the next one from
+    last: Option<((f64, f64, f64, f64), RenderData)>,
+}
+
+impl Explorer {
+    pub fn new(
+        fractal: Fractal,
+        (x_min, x_max, y_min, y_max): (f64, f64, f64, f64),
+        max_iterations: usize,
+        scheme: &str,
+        output_format: OutputFormat,
+        smooth: bool,
+    ) -> Self {
+        let schemes = ColorScheme::list_schemes();
+        let scheme_index = schemes
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(scheme))
+            .unwrap_or(1);
+
+        Self {
+            fractal,
+            center_x: (x_min + x_max) / 2.0,
+            center_y: (y_min + y_max) / 2.0,
+            x_span: x_max - x_min,
+            max_iterations: max_iterations.max(1),
+            scheme_index,
+            output_format,
+            smooth,
+            split: false,
+            selection: None,
+            cells: (80, 23),
+            drag: None,
+            pointer: None,
+            last: None,
+        }
+    }
+
+    /// Starts with the view on the left and the julia set of its center on the right, which
+    /// follows the center as the view pans. Only the mandelbrot set has julia sets to show.
+    pub fn with_split(mut self, split: bool) -> Self {
+        self.split = split && matches!(self.fractal, Fractal::Mandelbrot);
+        self
+    }
+
+    pub fn run(&mut self) -> io::Result<()> {
+        let mut stdout = io::stdout();
+
+        terminal::enable_raw_mode()?;
+        execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide, EnableMouseCapture)?;
+
+        let result = self.event_loop(&mut stdout);
+
+        // always restore the terminal, even if drawing failed
+        execute!(stdout, DisableMouseCapture, style::ResetColor, cursor::Show, terminal::LeaveAlternateScreen)?;
+        terminal::disable_raw_mode()?;
+        result
+    }
+
+    fn event_loop(&mut self, stdout: &mut io::Stdout) -> io::Result<()> {
+        self.draw(stdout)?;
+        loop {
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    if !self.handle_key(key.code) {
+                        return Ok(());
+                    }
+                }
+                // moving the pointer only changes the readout
+                Event::Mouse(mouse) if mouse.kind == MouseEventKind::Moved => {
+                    self.handle_mouse(mouse);
+                    let (cols, rows) = terminal::size()?;
+                    self.paint_readout(stdout, cols as usize, rows.saturating_sub(2))?;
+                    stdout.flush()?;
+                    continue;
+                }
+                Event::Mouse(mouse) if self.handle_mouse(mouse) => {}
+                Event::Resize(_, _) => {}
+                _ => continue,
+            }
+            self.draw(stdout)?;
+        }
+    }
+
+    // returns false when the explorer should exit
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        if self.selection.is_some() {
+            return self.handle_selection_key(code);
+        }
+        // steps of whole cells, so the part still in view can be moved over rather than computed
+        let (x_min, x_max, y_min, y_max) = self.bounds(self.cells.0, self.cells.1);
+        let (cell_width, cell_height) = ((x_max - x_min) / self.cells.0.max(1) as f64, (y_max - y_min) / self.cells.1.max(1) as f64);
+        let x_step = (self.x_span * PAN_STEP / cell_width).round().max(1.0) * cell_width;
+        let y_step = (self.x_span * PAN_STEP / cell_height).round().max(1.0) * cell_height;
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Left => self.center_x -= x_step,
+            KeyCode::Right => self.center_x += x_step,
+            KeyCode::Up => self.center_y -= y_step,
+            KeyCode::Down => self.center_y += y_step,
+            KeyCode::Char('+') | KeyCode::Char('=') => self.x_span /= ZOOM_STEP,
+            KeyCode::Char('-') | KeyCode::Char('_') => self.x_span *= ZOOM_STEP,
+            KeyCode::Char('c') => {
+                self.scheme_index = (self.scheme_index + 1) % ColorScheme::list_schemes().len();
+            }
+            KeyCode::Char(']') => {
+                self.max_iterations = ((self.max_iterations as f64 * ITERATION_STEP) as usize)
+                    .max(self.max_iterations + 1);
+            }
+            KeyCode::Char('[') => {
+                self.max_iterations = ((self.max_iterations as f64 / ITERATION_STEP) as usize).max(1);
+            }
+            KeyCode::Char('s') => self.smooth = !self.smooth,
+            KeyCode::Char('j') => self.split = !self.split && matches!(self.fractal, Fractal::Mandelbrot),
+            KeyCode::Char('z') => {
+                let center = (self.center_x, self.center_y);
+                self.selection = Some((center, center));
+            }
+            _ => {}
+        }
+        true
+    }
+
+    // while drawing a zoom box the arrows move its corner, enter or z zooms into it and esc
+    // leaves it
+    fn handle_selection_key(&mut self, code: KeyCode) -> bool {
+        let Some((anchor, (corner_x, corner_y))) = self.selection else {
+            return true;
+        };
+        let (x_min, x_max, y_min, y_max) = self.bounds(self.cells.0, self.cells.1);
+        let (x_step, y_step) = ((x_max - x_min) * BOX_STEP, (y_max - y_min) * BOX_STEP);
+        let corner = match code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Esc => {
+                self.selection = None;
+                return true;
+            }
+            KeyCode::Enter | KeyCode::Char('z') => {
+                self.zoom_into(anchor, (corner_x, corner_y), (y_max - y_min) / (x_max - x_min));
+                self.selection = None;
+                return true;
+            }
+            KeyCode::Left => (corner_x - x_step, corner_y),
+            KeyCode::Right => (corner_x + x_step, corner_y),
+            KeyCode::Up => (corner_x, corner_y - y_step),
+            KeyCode::Down => (corner_x, corner_y + y_step),
+            _ => (corner_x, corner_y),
+        };
+        self.selection = Some((anchor, corner));
+        true
+    }
+
+    // a click centers the view on the point clicked, dragging pans the view along with the
+    // pointer, and the wheel zooms keeping the point under the pointer still. Returns whether
+    // the view changed
+    fn handle_mouse(&mut self, mouse: MouseEvent) -> bool {
+        let position = (mouse.column, mouse.row);
+        self.pointer = Some(position);
+        if self.selection.is_some() {
+            return false;
+        }
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.drag = Some((position, false));
+                false
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                let Some(((column, row), _)) = self.drag else {
+                    return false;
+                };
+                let (x_min, x_max, y_min, y_max) = self.bounds(self.cells.0, self.cells.1);
+                self.center_x -= (mouse.column as f64 - column as f64) * (x_max - x_min) / self.cells.0.max(1) as f64;
+                self.center_y -= (mouse.row as f64 - row as f64) * (y_max - y_min) / self.cells.1.max(1) as f64;
+                self.drag = Some((position, true));
+                true
+            }
+            MouseEventKind::Up(MouseButton::Left) => match (self.drag.take(), self.cell_point(position)) {
+                (Some((_, false)), Some((x, y))) => {
+                    (self.center_x, self.center_y) = (x, y);
+                    true
+                }
+                _ => false,
+            },
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+                let Some((x, y)) = self.cell_point(position) else {
+                    return false;
+                };
+                let factor = if mouse.kind == MouseEventKind::ScrollUp { ZOOM_STEP } else { 1.0 / ZOOM_STEP };
+                self.center_x = x + (self.center_x - x) / factor;
+                self.center_y = y + (self.center_y - y) / factor;
+                self.x_span /= factor;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // the point shown in the cell at (column, row), if it's in the view rather than on the
+    // julia panel or the readout and status lines
+    fn cell_point(&self, (column, row): (u16, u16)) -> Option<(f64, f64)> {
+        let (cols, rows) = self.cells;
+        let (column, row) = (column as usize, row as usize);
+        if column >= cols || row >= rows {
+            return None;
+        }
+        // as `calculate` lays out the region, cell (x, y) shows the point at its top left
+        let (x_min, x_max, y_min, y_max) = self.bounds(cols, rows);
+        Some((x_min + (x_max - x_min) * column as f64 / cols as f64, y_min + (y_max - y_min) * row as f64 / rows as f64))
+    }
+
+    // centers the view on the box between corners `a` and `b` and zooms in until the box just
+    // fits, keeping the view's `aspect` (height over width) rather than the box's
+    fn zoom_into(&mut self, a: (f64, f64), b: (f64, f64), aspect: f64) {
+        let (width, height) = ((a.0 - b.0).abs(), (a.1 - b.1).abs());
+        let x_span = width.max(height / aspect);
+        if x_span > 0.0 && x_span.is_finite() {
+            self.center_x = (a.0 + b.0) / 2.0;
+            self.center_y = (a.1 + b.1) / 2.0;
+            self.x_span = x_span;
+        }
+    }
+
+    /// Region shown for a view of `cols`×`rows` cells, assuming cells are twice as tall as wide.
+    fn bounds(&self, cols: usize, rows: usize) -> (f64, f64, f64, f64) {
+        cell_region((self.center_x, self.center_y), self.x_span, cols, rows)
+    }
+
+    // cells of `width`×`height` that the view takes, leaving the rest to a julia panel
+    fn view_cells(&self, width: usize, height: usize) -> (usize, usize) {
+        if self.split { ((width / 2).max(1), height) } else { (width, height) }
+    }
+
+    // escape data of `pass` for `width`×`height` cells, unless `cancel` stops it, using `view`
+    // for the view's own cells if it's given; in split view, the view and the julia set of its
+    // center side by side. The overlay crosses the center out on the left in split view, and
+    // outlines any zoom box
+    fn frame(
+        &self,
+        width: usize,
+        height: usize,
+        pass: Pass,
+        view: Option<RenderData>,
+        cancel: &dyn Fn() -> bool,
+    ) -> Option<(RenderData, Option<Overlay>)> {
+        let compute = |fractal: &Fractal, region, width| refine::calculate(fractal, pass, region, width, height, cancel);
+        let (panel, _) = self.view_cells(width, height);
+        let region = self.bounds(panel, height);
+        let c = Complex::new(self.center_x, self.center_y);
+        let view = match view {
+            Some(view) => view,
+            None => compute(&self.fractal, region, panel)?,
+        };
+        let data = if self.split {
+            let julia_region = cell_region((0.0, 0.0), DEFAULT_VIEW_WIDTH, width - panel, height);
+            RenderData::side_by_side(&[view, compute(&Fractal::Julia(c), julia_region, width - panel)?])
+        } else {
+            view
+        };
+        if !self.split && self.selection.is_none() {
+            return Some((data, None));
+        }
+
+        // the view's coordinates carry on over any julia panel, which keeps the marks on the left
+        let (x_min, x_max, y_min, y_max) = region;
+        let x_max = x_min + (x_max - x_min) * width as f64 / panel as f64;
+        let mut overlay = Overlay::new(width, height, (x_min, x_max, y_min, y_max));
+        if self.split {
+            overlay.cross(c, 1);
+        }
+        if let Some((a, b)) = self.selection {
+            overlay.rectangle(Complex::new(a.0, a.1), Complex::new(b.0, b.1));
+        }
+        Some((data, Some(overlay)))
+    }
+
+    fn scheme_name(&self) -> &'static str {
+        ColorScheme::list_schemes()[self.scheme_index]
+    }
+
+    // draws the view a pass at a time, coarsest first, until the last pass is up or an event
+    // is waiting, which would change the view anyway. The first pass always finishes, so even
+    // a stream of drag events keeps the picture moving.
+    fn draw(&mut self, stdout: &mut io::Stdout) -> io::Result<()> {
+        let (cols, rows) = terminal::size()?;
+        let width = cols as usize;
+        // last two rows are reserved for the readout and the status line
+        let height = (rows as usize).saturating_sub(2);
+        self.cells = self.view_cells(width, height);
+        let region = self.bounds(self.cells.0, height);
+
+        let pending = || event::poll(Duration::ZERO).unwrap_or(false);
+        let mut passes = refine::passes(self.max_iterations);
+        let mut view = None;
+        let mut shown = false;
+        // the last frame is a head start: moved over when the view pans by whole cells, so only
+        // the strip that came into view is computed, or blown up as the preview when zooming in
+        // (the julia panel of split view changes with every move, so it gets none)
+        let last = self.last.take().filter(|(_, last)| !self.split && last.max_iterations == self.max_iterations);
+        if let Some((last_region, last)) = last {
+            if let Some(panned) = refine::pan(&self.fractal, &last, last_region, region) {
+                view = Some(panned);
+                passes = vec![Pass::full(self.max_iterations)];
+            } else if let Some(preview) = refine::resample(&last, last_region, region, self.cells.0, height) {
+                if let Some((data, overlay)) = self.frame(width, height, Pass::full(self.max_iterations), Some(preview), &|| false) {
+                    self.paint(stdout, &data, overlay, " (preview)")?;
+                    shown = true;
+                }
+                passes = vec![Pass::full(self.max_iterations)];
+            }
+        }
+
+        for (i, pass) in passes.into_iter().enumerate() {
+            let cancel: &dyn Fn() -> bool = if i == 0 && !shown { &|| false } else { &pending };
+            let Some((data, overlay)) = self.frame(width, height, pass, view.take(), cancel) else {
+                break;
+            };
+            let detail = if pass.scale > 1 { format!(" (preview 1/{})", pass.scale) } else { String::new() };
+            self.paint(stdout, &data, overlay, &detail)?;
+            if pass.scale == 1 && !self.split {
+                self.last = Some((region, data));
+            }
+        }
+        Ok(())
+    }
+
+    fn paint(&self, stdout: &mut io::Stdout, data: &RenderData, overlay: Option<Overlay>, detail: &str) -> io::Result<()> {
+        let (width, height) = (data.width(), data.height());
+        let scheme = ColorScheme::from_str(self.scheme_name()).unwrap_or(ColorScheme::Classic);
+        let renderer = Renderer::new(scheme, self.output_format).with_smooth_coloring(self.smooth);
+        let renderer = match overlay {
+            Some(overlay) => renderer.with_overlay(overlay),
+            None => renderer,
+        };
+
+        for y in 0..data.height() {
+            queue!(stdout, cursor::MoveTo(0, y as u16))?;
+            // runs of one color only need their background set once
+            let mut current = None;
+            for x in 0..data.width() {
+                let color = renderer.pixel_color(data, x, y);
+                let term_color = match self.output_format {
+                    OutputFormat::AnsiTrueColor
+                    | OutputFormat::HalfBlock { truecolor: true }
+                    | OutputFormat::Shade { truecolor: true } => TermColor::Rgb {
+                        r: color.r,
+                        g: color.g,
+                        b: color.b,
+                    },
+                    _ => TermColor::AnsiValue(renderer::rgb_to_ansi256(color.r, color.g, color.b)),
+                };
+                if current != Some(term_color) {
+                    queue!(stdout, style::SetBackgroundColor(term_color))?;
+                    current = Some(term_color);
+                }
+                queue!(stdout, style::Print(' '))?;
+            }
+        }
+
+        let keys = if self.selection.is_some() {
+            "zoom box: arrows move the corner  enter zoom in  esc cancel"
+        } else {
+            "arrows, drag pan  +/-, wheel zoom  click center  z zoom box  [/] iters  c colors  s smooth  j julia  q quit"
+        };
+        let status = format!(
+            " center={:.10}{:+.10}i  width={:.3e}  iters={}{}  colors={}  smooth={}  |  {}",
+            self.center_x,
+            self.center_y,
+            self.x_span,
+            self.max_iterations,
+            detail,
+            self.scheme_name(),
+            if self.smooth { "on" } else { "off" },
+            keys,
+        );
+        let status: String = status.chars().take(width).collect();
+
+        queue!(stdout, style::ResetColor)?;
+        self.paint_readout(stdout, width, height as u16)?;
+        queue!(
+            stdout,
+            cursor::MoveTo(0, height as u16 + 1),
+            terminal::Clear(terminal::ClearType::CurrentLine),
+            style::Print(status),
+        )?;
+        stdout.flush()
+    }
+
+    // the readout on `row`: what the point under the pointer does, or the center's when the
+    // pointer isn't over the view
+    fn paint_readout(&self, stdout: &mut io::Stdout, width: usize, row: u16) -> io::Result<()> {
+        let (label, (x, y)) = match self.pointer.and_then(|cell| self.cell_point(cell)) {
+            Some(point) => ("pointer", point),
+            None => ("center", (self.center_x, self.center_y)),
+        };
+        let readout = format!(" {}={:.10}{:+.10}i  {}", label, x, y, describe(&self.fractal, x, y, self.max_iterations));
+        let readout: String = readout.chars().take(width).collect();
+        queue!(
+            stdout,
+            style::ResetColor,
+            cursor::MoveTo(0, row),
+            terminal::Clear(terminal::ClearType::CurrentLine),
+            style::Print(readout),
+        )
+    }
+}
+
+// whether (x, y) is in the set and the period it settles on if so, or when it escapes with its
+// smooth count and potential, ln|z_n| / d^n taken far enough out to be about ln|Φ|
+fn describe(fractal: &Fractal, x: f64, y: f64, max_iterations: usize) -> String {
+    let result = fractal.at_point(x, y, max_iterations);
+    if let Fractal::Newton(_) = fractal {
+        return match result.root {
+            Some(root) => format!("converges to root {} after {} iterations", root + 1, result.iterations),
+            None => format!("converges to no root within {} iterations", max_iterations),
+        };
+    }
+    if result.iterations >= max_iterations {
+        return match interior::interior_at_point(fractal, x, y, max_iterations, InteriorMode::Period) as usize {
+            0 => format!("in the set, no cycle found within {} iterations", max_iterations),
+            period => format!("in the set, period {}", period),
+        };
+    }
+
+    let degree = fractal.degree();
+    let smooth = colorscheme::smooth_iteration(result.iterations, result.z_norm, DEFAULT_BAILOUT, degree);
+    let options = ComputeOptions {
+        bailout: POTENTIAL_BAILOUT,
+        ..ComputeOptions::default()
+    };
+    let far = fractal.at_point_with(x, y, max_iterations + 64, &options);
+    // an orbit started from 0 only reaches about Φ at z_1 = c, one started from the point at z_0
+    let ((z0, _), _) = fractal.start(x, y);
+    let steps = if z0 == Complex::new(0.0, 0.0) { far.iterations.saturating_sub(1) } else { far.iterations };
+    let potential = far.z_norm.ln() / degree.powi(steps as i32);
+    format!("escapes after {} iterations  smooth={:.3}  potential={:.4e}", result.iterations, smooth, potential)
+}
+
+// region of `cols`×`rows` cells twice as tall as wide, `x_span` across and centered on `center`
+fn cell_region(center: (f64, f64), x_span: f64, cols: usize, rows: usize) -> (f64, f64, f64, f64) {
+    let y_span = x_span * rows as f64 * 2.0 / cols.max(1) as f64;
+    (center.0 - x_span / 2.0, center.0 + x_span / 2.0, center.1 - y_span / 2.0, center.1 + y_span / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zoom_keeps_center() {
+        let mut explorer = Explorer::new(Fractal::Mandelbrot, (-2.0, 1.0, -1.0, 1.0), 100, "classic", OutputFormat::Ansi256, true);
+        explorer.handle_key(KeyCode::Char('+'));
+
+        let (x_min, x_max, y_min, y_max) = explorer.bounds(80, 20);
+        assert!(((x_min + x_max) / 2.0 + 0.5).abs() < 1e-12);
+        assert!(((y_min + y_max) / 2.0).abs() < 1e-12);
+        assert!((x_max - x_min - 2.0).abs() < 1e-12);
+
+        // arrows move by whole cells, so the last frame can be moved over
+        let before = explorer.bounds(80, 20);
+        explorer.cells = (80, 20);
+        explorer.handle_key(KeyCode::Down);
+        let data = RenderData::new(80, 20, vec![Default::default(); 1600], 100);
+        assert!(refine::pan(&explorer.fractal, &data, before, explorer.bounds(80, 20)).is_some());
+    }
+
+    #[test]
+    fn test_zoom_box_fits_the_view() {
+        let mut explorer = Explorer::new(Fractal::Mandelbrot, (-2.0, 1.0, -1.0, 1.0), 100, "classic", OutputFormat::Ansi256, true);
+        explorer.cells = (80, 20);
+        explorer.handle_key(KeyCode::Char('z'));
+        for code in [KeyCode::Right; 4].into_iter().chain([KeyCode::Down; 2]) {
+            explorer.handle_key(code);
+        }
+        assert!(explorer.frame(80, 20, Pass::full(100), None, &|| false).unwrap().1.unwrap().mark(40, 10).is_some());
+
+        // a wide box: its width sets the zoom, and the view keeps its shape
+        explorer.handle_key(KeyCode::Enter);
+        assert!(explorer.selection.is_none());
+        assert!((explorer.center_x + 0.35).abs() < 1e-12 && (explorer.center_y - 0.0375).abs() < 1e-12);
+        assert!((explorer.x_span - 0.3).abs() < 1e-12);
+
+        // esc leaves the box without zooming, and without quitting
+        explorer.handle_key(KeyCode::Char('z'));
+        explorer.handle_key(KeyCode::Down);
+        assert!(explorer.handle_key(KeyCode::Esc));
+        assert!(explorer.selection.is_none() && (explorer.x_span - 0.3).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_mouse_maps_cells_to_the_plane() {
+        let mouse = |kind, column, row| MouseEvent { kind, column, row, modifiers: event::KeyModifiers::NONE };
+        let mut explorer = Explorer::new(Fractal::Mandelbrot, (-2.0, 1.0, -1.0, 1.0), 100, "classic", OutputFormat::Ansi256, true);
+        explorer.cells = (80, 20);
+        assert_eq!(explorer.cell_point((0, 0)), Some((-2.0, -0.75)));
+        assert_eq!(explorer.cell_point((80, 0)), None);
+
+        // the wheel keeps the point under the pointer where it is
+        assert!(explorer.handle_mouse(mouse(MouseEventKind::ScrollUp, 0, 0)));
+        assert!((explorer.x_span - 2.0).abs() < 1e-12);
+        let (x, y) = explorer.cell_point((0, 0)).unwrap();
+        assert!((x + 2.0).abs() < 1e-12 && (y + 0.75).abs() < 1e-12);
+
+        // a click centers, a drag doesn't
+        explorer.handle_mouse(mouse(MouseEventKind::Down(MouseButton::Left), 60, 10));
+        assert!(explorer.handle_mouse(mouse(MouseEventKind::Up(MouseButton::Left), 60, 10)));
+        assert!((explorer.center_x + 0.5).abs() < 1e-12 && (explorer.center_y + 0.25).abs() < 1e-12);
+
+        explorer.handle_mouse(mouse(MouseEventKind::Down(MouseButton::Left), 10, 5));
+        assert!(explorer.handle_mouse(mouse(MouseEventKind::Drag(MouseButton::Left), 30, 5)));
+        assert!(!explorer.handle_mouse(mouse(MouseEventKind::Up(MouseButton::Left), 30, 5)));
+        assert!((explorer.center_x + 1.0).abs() < 1e-12 && (explorer.center_y + 0.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_split_shows_the_julia_set_of_the_center() {
+        let explorer = Explorer::new(Fractal::Mandelbrot, (-1.0, 0.0, -0.5, 0.5), 50, "classic", OutputFormat::Ansi256, true)
+            .with_split(true);
+        let (data, overlay) = explorer.frame(40, 10, Pass::full(50), None, &|| false).unwrap();
+        assert_eq!((data.width(), data.height()), (40, 10));
+
+        // the right half is julia of -0.5 around 0, which stays bounded at 0 itself
+        let julia = Fractal::Julia(Complex::new(-0.5, 0.0));
+        assert_eq!(data.sample(30, 5).iterations, 50);
+        assert_eq!(data.sample(30, 0).iterations, julia.at_point(0.0, -1.5, 50).iterations);
+        assert!(overlay.unwrap().mark(10, 5).is_some());
+
+        let plain = Explorer::new(Fractal::Tricorn, (-1.0, 0.0, -0.5, 0.5), 50, "classic", OutputFormat::Ansi256, true)
+            .with_split(true);
+        assert!(plain.frame(40, 10, Pass::full(50), None, &|| false).unwrap().1.is_none());
+    }
+
+    #[test]
+    fn test_readout_describes_the_point() {
+        assert_eq!(describe(&Fractal::Mandelbrot, 0.0, 0.0, 100), "in the set, period 1");
+        assert_eq!(describe(&Fractal::Mandelbrot, -1.0, 0.0, 100), "in the set, period 2");
+        assert_eq!(describe(&Fractal::Mandelbrot, -0.12, 0.75, 1000), "in the set, period 3");
+
+        // far out Φ(c) ≈ c, and Φ(z) = z exactly for the julia set of 0
+        let potential = |description: String| description.rsplit_once("potential=").unwrap().1.parse::<f64>().unwrap();
+        assert!((potential(describe(&Fractal::Mandelbrot, 100.0, 0.0, 100)) - 100f64.ln()).abs() < 0.01);
+        assert!((potential(describe(&Fractal::Julia(Complex::new(0.0, 0.0)), 0.0, 1.5, 100)) - 1.5f64.ln()).abs() < 1e-4);
+        assert!(describe(&Fractal::Mandelbrot, 0.3, 0.0, 1000).starts_with("escapes after 12 iterations"));
+    }
+}
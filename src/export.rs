@@ -0,0 +1,487 @@
+// Raw escape data export, for post-processing outside the crate: the iteration count and the
+// smooth iteration value of every pixel, along with what was rendered.
+//
+// - .npy: a NumPy structured array of (iterations, smooth) shaped (height, width). The .npy
+//   header has no room for anything else, so the metadata goes to a .meta.json file next to it
+// - .csv: `#` comment lines with the metadata, then an x,y,iterations,smooth row per pixel
+// - .json: the metadata plus `iterations` and `smooth` as arrays of rows
+// - .exr: OpenEXR with the smooth values as 32-bit floats in the Y channel and the counts in
+//   an `iterations` channel, for tone-mapping and compositing
+// - .png: 16-bit grayscale of smooth / max_iterations
+// - .bin: the metadata and the whole `RenderData` (distances, interior measures, newton roots
+//   and supersamples too) in postcard, a compact serde encoding, behind a magic number
+//
+// .exr and .png get a .meta.json file too. Points in the set have a smooth value of
+// max_iterations. `import_data` reads .npy, .csv and .json back for re-coloring, recovering
+// each pixel's final |z| from its smooth value, and .bin back exactly as it was.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::colorscheme;
+use crate::error::MandelbrotError;
+use crate::fractal::EscapeResult;
+use crate::renderer::RenderData;
+
+/// File formats for `export_data`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataFormat {
+    Npy,
+    Csv,
+    Json,
+    Exr,
+    /// 16-bit grayscale PNG
+    Png,
+    /// `to_bytes`, which keeps everything
+    Binary,
+}
+
+impl DataFormat {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "npy" => Some(DataFormat::Npy),
+            "csv" => Some(DataFormat::Csv),
+            "json" => Some(DataFormat::Json),
+            "exr" => Some(DataFormat::Exr),
+            "png" => Some(DataFormat::Png),
+            "bin" => Some(DataFormat::Binary),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from a file extension, e.g. "out.npy".
+    pub fn from_path(path: &str) -> Option<Self> {
+        Path::new(path).extension().and_then(|ext| ext.to_str()).and_then(Self::from_str)
+    }
+}
+
+/// What an export holds: the fractal, the region (x_min, x_max, y_min, y_max) and the grid.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DataInfo {
+    pub fractal: String,
+    pub region: (f64, f64, f64, f64),
+    pub width: usize,
+    pub height: usize,
+    pub max_iterations: usize,
+}
+
+impl DataInfo {
+    pub fn new(fractal: &str, region: (f64, f64, f64, f64), data: &RenderData) -> Self {
+        Self {
+            fractal: fractal.to_string(),
+            region,
+            width: data.width(),
+            height: data.height(),
+            max_iterations: data.max_iterations,
+        }
+    }
+}
+
+// starts every `to_bytes` encoding; the digit goes up when the layout changes
+const BINARY_MAGIC: &[u8; 4] = b"MBD6";
+
+#[derive(Serialize)]
+struct Binary<'a> {
+    info: &'a DataInfo,
+    data: &'a RenderData,
+}
+
+#[derive(Deserialize)]
+struct OwnedBinary {
+    info: DataInfo,
+    data: RenderData,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonData {
+    #[serde(flatten)]
+    info: DataInfo,
+    iterations: Vec<Vec<usize>>,
+    smooth: Vec<Vec<f64>>,
+}
+
+/// Fractional escape count of a pixel of `data`, or its max iterations for points in the set.
+pub fn smooth_value(result: &EscapeResult, data: &RenderData) -> f64 {
+    if result.iterations >= data.max_iterations {
+        data.max_iterations as f64
+    } else {
+        colorscheme::smooth_iteration(result.iterations, result.z_norm, data.bailout, data.degree)
+    }
+}
+
+/// `info` and all of `data` in a compact binary encoding, for caching renders or sending them
+/// elsewhere; `from_bytes` gets back the same, bit for bit.
+pub fn to_bytes(info: &DataInfo, data: &RenderData) -> Vec<u8> {
+    let mut bytes = BINARY_MAGIC.to_vec();
+    // encoding into memory fails only on types serde can't describe, which these aren't
+    bytes.extend(postcard::to_stdvec(&Binary { info, data }).expect("render data always serializes"));
+    bytes
+}
+
+/// Reads back what `to_bytes` wrote.
+pub fn from_bytes(bytes: &[u8]) -> crate::Result<(DataInfo, RenderData)> {
+    let invalid = |reason: String| MandelbrotError::invalid("render data", reason);
+    let encoded = bytes.strip_prefix(BINARY_MAGIC).ok_or_else(|| invalid("it doesn't start with MBD6".to_string()))?;
+    let OwnedBinary { info, data } = postcard::from_bytes(encoded).map_err(|e| invalid(e.to_string()))?;
+    Ok((info, data))
+}
+
+/// Writes the escape data of `data` to `path`, plus the .meta.json file for formats without
+/// room for `info`.
+pub fn export_data(path: &str, format: DataFormat, data: &RenderData, info: &DataInfo) -> crate::Result<()> {
+    write_data(path, format, data, info).map_err(|e| MandelbrotError::file(path, e))
+}
+
+fn write_data(path: &str, format: DataFormat, data: &RenderData, info: &DataInfo) -> io::Result<()> {
+    let create = || File::create(path).map(BufWriter::new);
+    match format {
+        DataFormat::Npy => write_npy(create()?, data)?,
+        DataFormat::Csv => write_csv(create()?, data, info)?,
+        DataFormat::Json => write_json(create()?, data, info)?,
+        DataFormat::Png => write_png16(create()?, data)?,
+        DataFormat::Binary => create()?.write_all(&to_bytes(info, data))?,
+        // the exr crate does its own buffering
+        DataFormat::Exr => write_exr(path, data)?,
+    }
+
+    if matches!(format, DataFormat::Npy | DataFormat::Exr | DataFormat::Png) {
+        let meta = Path::new(path).with_extension("meta.json");
+        serde_json::to_writer_pretty(BufWriter::new(File::create(meta)?), info)?;
+    }
+    Ok(())
+}
+
+/// Reads data written by `export_data` back, in the format its extension names.
+pub fn import_data(path: &str) -> crate::Result<(DataInfo, RenderData)> {
+    if DataFormat::from_path(path) == Some(DataFormat::Binary) {
+        let bytes = std::fs::read(path).map_err(|e| MandelbrotError::file(path, e))?;
+        return from_bytes(&bytes);
+    }
+    read_data(path).map_err(|e| MandelbrotError::file(path, e))
+}
+
+fn read_data(path: &str) -> io::Result<(DataInfo, RenderData)> {
+    let format = DataFormat::from_path(path).ok_or_else(|| invalid("expected a .npy, .csv, .json or .bin file".to_string()))?;
+    let file = BufReader::new(File::open(path)?);
+    let (info, pixels) = match format {
+        DataFormat::Npy => {
+            let meta = File::open(Path::new(path).with_extension("meta.json"))?;
+            let info: DataInfo = serde_json::from_reader(BufReader::new(meta))?;
+            let pixels = read_npy(file, &info)?;
+            (info, pixels)
+        }
+        DataFormat::Csv => read_csv(file)?,
+        DataFormat::Exr | DataFormat::Png | DataFormat::Binary => {
+            return Err(invalid("only .npy, .csv, .json and .bin data can be read back".to_string()));
+        }
+        DataFormat::Json => {
+            let json: JsonData = serde_json::from_reader(file)?;
+            let pixels = json.iterations.iter().flatten().zip(json.smooth.iter().flatten());
+            let pixels = pixels.map(|(&iterations, &smooth)| (iterations, smooth)).collect();
+            (json.info, pixels)
+        }
+    };
+
+    if pixels.len() != info.width * info.height {
+        return Err(invalid(format!(
+            "expected {}×{} pixels, found {}",
+            info.width,
+            info.height,
+            pixels.len()
+        )));
+    }
+    let max_iterations = info.max_iterations;
+    let pixels = pixels
+        .into_iter()
+        .map(|(iterations, smooth)| escape_result(iterations, smooth, max_iterations))
+        .collect();
+    let data = RenderData::new(info.width, info.height, pixels, max_iterations);
+    Ok((info, data))
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// inverse of `smooth_value`, for data at the default bailout and degree, which `RenderData::new`
+// gives it
+fn escape_result(iterations: usize, smooth: f64, max_iterations: usize) -> EscapeResult {
+    let z_norm = if iterations >= max_iterations {
+        0.0
+    } else {
+        2.0_f64.powf(2.0_f64.powf(iterations as f64 + 1.0 - smooth))
+    };
+    EscapeResult {
+        iterations,
+        z_norm,
+        ..EscapeResult::default()
+    }
+}
+
+fn write_npy(mut out: impl Write, data: &RenderData) -> io::Result<()> {
+    let mut header = format!(
+        "{{'descr': [('iterations', '<u8'), ('smooth', '<f8')], 'fortran_order': False, 'shape': ({}, {}), }}",
+        data.height(),
+        data.width()
+    );
+    // magic, version and header length take 10 bytes; the data has to start 64-byte aligned
+    let padding = 63 - (10 + header.len()) % 64;
+    header.extend(std::iter::repeat_n(' ', padding));
+    header.push('\n');
+
+    out.write_all(b"\x93NUMPY\x01\x00")?;
+    out.write_all(&(header.len() as u16).to_le_bytes())?;
+    out.write_all(header.as_bytes())?;
+    for result in data.samples() {
+        out.write_all(&(result.iterations as u64).to_le_bytes())?;
+        out.write_all(&smooth_value(&result, data).to_le_bytes())?;
+    }
+    out.flush()
+}
+
+fn read_npy(mut input: impl Read, info: &DataInfo) -> io::Result<Vec<(usize, f64)>> {
+    let mut preamble = [0; 10];
+    input.read_exact(&mut preamble)?;
+    if &preamble[..6] != b"\x93NUMPY" {
+        return Err(invalid("not a .npy file".to_string()));
+    }
+    let mut header = vec![0; u16::from_le_bytes([preamble[8], preamble[9]]) as usize];
+    input.read_exact(&mut header)?;
+    if !String::from_utf8_lossy(&header).contains("('iterations', '<u8'), ('smooth', '<f8')") {
+        return Err(invalid("the .npy file doesn't hold (iterations, smooth) pairs".to_string()));
+    }
+
+    let mut pixel = [0; 16];
+    let mut pixels = Vec::with_capacity(info.width * info.height);
+    for _ in 0..info.width * info.height {
+        input.read_exact(&mut pixel)?;
+        let iterations = u64::from_le_bytes(pixel[..8].try_into().unwrap()) as usize;
+        pixels.push((iterations, f64::from_le_bytes(pixel[8..].try_into().unwrap())));
+    }
+    Ok(pixels)
+}
+
+fn write_csv(mut out: impl Write, data: &RenderData, info: &DataInfo) -> io::Result<()> {
+    let (x_min, x_max, y_min, y_max) = info.region;
+    writeln!(out, "# fractal: {}", info.fractal)?;
+    writeln!(out, "# region: {},{},{},{}", x_min, x_max, y_min, y_max)?;
+    writeln!(out, "# size: {}x{}", info.width, info.height)?;
+    writeln!(out, "# max_iterations: {}", info.max_iterations)?;
+    writeln!(out, "x,y,iterations,smooth")?;
+    for y in 0..data.height() {
+        for x in 0..data.width() {
+            let result = data.sample(x, y);
+            writeln!(out, "{},{},{},{}", x, y, result.iterations, smooth_value(&result, data))?;
+        }
+    }
+    out.flush()
+}
+
+fn read_csv(input: impl BufRead) -> io::Result<(DataInfo, Vec<(usize, f64)>)> {
+    let mut info = DataInfo {
+        fractal: String::new(),
+        region: (0.0, 0.0, 0.0, 0.0),
+        width: 0,
+        height: 0,
+        max_iterations: 0,
+    };
+    let mut pixels = Vec::new();
+
+    for (number, line) in input.lines().enumerate() {
+        let line = line?;
+        let bad_line = || invalid(format!("line {}: can't read '{}'", number + 1, line));
+        if let Some(comment) = line.strip_prefix('#') {
+            let Some((key, value)) = comment.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "fractal" => info.fractal = value.to_string(),
+                "region" => {
+                    let bounds: Vec<f64> = value.split(',').map(|v| v.trim().parse()).collect::<Result<_, _>>().map_err(|_| bad_line())?;
+                    let [x_min, x_max, y_min, y_max] = bounds[..] else {
+                        return Err(bad_line());
+                    };
+                    info.region = (x_min, x_max, y_min, y_max);
+                }
+                "size" => {
+                    let (width, height) = value.split_once('x').ok_or_else(bad_line)?;
+                    info.width = width.parse().map_err(|_| bad_line())?;
+                    info.height = height.parse().map_err(|_| bad_line())?;
+                }
+                "max_iterations" => info.max_iterations = value.parse().map_err(|_| bad_line())?,
+                _ => {}
+            }
+        } else if line.starts_with(|c: char| c.is_ascii_digit()) {
+            // rows come in x,y order, so only the values are needed
+            let mut fields = line.split(',').skip(2);
+            let iterations = fields.next().and_then(|v| v.parse().ok()).ok_or_else(bad_line)?;
+            let smooth = fields.next().and_then(|v| v.parse().ok()).ok_or_else(bad_line)?;
+            pixels.push((iterations, smooth));
+        }
+    }
+    Ok((info, pixels))
+}
+
+fn write_json(mut out: impl Write, data: &RenderData, info: &DataInfo) -> io::Result<()> {
+    let json = JsonData {
+        info: info.clone(),
+        iterations: data.rows().map(|row| row.iter().map(|pixel| pixel.iterations as usize).collect()).collect(),
+        smooth: data
+            .rows()
+            .map(|row| row.iter().map(|&pixel| smooth_value(&pixel.into(), data)).collect())
+            .collect(),
+    };
+    serde_json::to_writer(&mut out, &json)?;
+    out.flush()
+}
+
+fn write_exr(path: &str, data: &RenderData) -> io::Result<()> {
+    use exr::prelude::*;
+
+    let channels = SpecificChannels::build()
+        .with_channel("Y")
+        .with_channel("iterations")
+        .with_pixel_fn(|position: Vec2<usize>| {
+            let result = data.sample(position.x(), position.y());
+            let iterations = result.iterations.min(u32::MAX as usize) as u32;
+            (smooth_value(&result, data) as f32, iterations)
+        });
+    Image::from_channels((data.width(), data.height()), channels)
+        .write()
+        .to_file(path)
+        .map_err(io::Error::other)
+}
+
+fn write_png16(out: impl Write, data: &RenderData) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(out, data.width() as u32, data.height() as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Sixteen);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+
+    let scale = data.max_iterations.max(1) as f64;
+    let samples: Vec<u8> = data
+        .samples()
+        .flat_map(|result| {
+            let level = (smooth_value(&result, data) / scale).clamp(0.0, 1.0);
+            // PNG samples are big-endian
+            ((level * u16::MAX as f64).round() as u16).to_be_bytes()
+        })
+        .collect();
+    writer.write_image_data(&samples).map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_npy_layout() {
+        let pixels = [3, 10, 10, 5, 10, 1].map(|iterations| EscapeResult {
+            iterations,
+            z_norm: 4.0,
+            ..Default::default()
+        });
+        let data = RenderData::new(3, 2, pixels.to_vec(), 10);
+        let mut out = Vec::new();
+        write_npy(&mut out, &data).unwrap();
+
+        let header_len = u16::from_le_bytes([out[8], out[9]]) as usize;
+        assert_eq!((10 + header_len) % 64, 0);
+        assert!(String::from_utf8_lossy(&out[10..10 + header_len]).contains("'shape': (2, 3)"));
+        assert_eq!(out.len(), 10 + header_len + 6 * 16);
+
+        // second pixel is in the set
+        let second = &out[10 + header_len + 16..10 + header_len + 32];
+        assert_eq!(u64::from_le_bytes(second[..8].try_into().unwrap()), 10);
+        assert_eq!(f64::from_le_bytes(second[8..].try_into().unwrap()), 10.0);
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let pixels = [3, 10, 1, 7].map(|iterations| EscapeResult {
+            iterations,
+            z_norm: 5.5,
+            ..Default::default()
+        });
+        let data = RenderData::new(2, 2, pixels.to_vec(), 10);
+        let info = DataInfo::new("mandelbrot", (-2.0, 1.0, -1.0, 1.0), &data);
+        let mut out = Vec::new();
+        write_csv(&mut out, &data, &info).unwrap();
+
+        let (read_info, read) = read_csv(&out[..]).unwrap();
+        assert_eq!(read_info, info);
+        let restored: Vec<_> = read.into_iter().map(|(i, smooth)| escape_result(i, smooth, 10)).collect();
+        assert_eq!(restored[1].iterations, 10);
+        assert!((restored[3].z_norm - 5.5).abs() < 1e-9, "{}", restored[3].z_norm);
+    }
+
+    #[test]
+    fn test_binary_round_trip_is_exact() {
+        let options = crate::ComputeOptions {
+            samples: 2,
+            distance: true,
+            ..Default::default()
+        };
+        let region = (-2.0, 1.0, -1.0, 1.0);
+        let data = crate::calculate(&crate::Fractal::Mandelbrot, 50, region, 12, 8, &options, None);
+        let info = DataInfo::new("mandelbrot", region, &data);
+
+        let bytes = to_bytes(&info, &data);
+        let (read_info, read) = from_bytes(&bytes).unwrap();
+        assert_eq!(read_info, info);
+        assert_eq!(read.checksum(), data.checksum());
+
+        assert!(from_bytes(&bytes[4..]).is_err());
+        assert!(from_bytes(&bytes[..bytes.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn test_exr_round_trip() {
+        use exr::prelude::*;
+
+        let data = crate::calculate(&crate::Fractal::Mandelbrot, 50, (-2.0, 1.0, -1.0, 1.0), 12, 8, &Default::default(), None);
+        let path = std::env::temp_dir().join(format!("mandelbrot-exr-test-{}.exr", std::process::id()));
+        write_exr(path.to_str().unwrap(), &data).unwrap();
+        let image = read_first_flat_layer_from_file(&path);
+        let _ = std::fs::remove_file(&path);
+
+        let layer = image.unwrap().layer_data;
+        assert_eq!(layer.size, Vec2(12, 8));
+        let channel = |name: &str| &layer.channel_data.list.iter().find(|channel| channel.name == *name).unwrap().sample_data;
+        let (FlatSamples::F32(smooth), FlatSamples::U32(iterations)) = (channel("Y"), channel("iterations")) else {
+            panic!("Y should be f32 and iterations u32");
+        };
+        let expected: Vec<EscapeResult> = data.samples().collect();
+        assert_eq!(iterations, &expected.iter().map(|result| result.iterations as u32).collect::<Vec<_>>());
+        assert_eq!(smooth, &expected.iter().map(|result| smooth_value(result, &data) as f32).collect::<Vec<_>>());
+        assert!(iterations.contains(&50) && iterations.iter().any(|&count| count < 50));
+    }
+
+    #[test]
+    fn test_png16_round_trip() {
+        let data = crate::calculate(&crate::Fractal::Mandelbrot, 50, (-2.0, 1.0, -1.0, 1.0), 12, 8, &Default::default(), None);
+        let mut out = Vec::new();
+        write_png16(&mut out, &data).unwrap();
+
+        let mut reader = png::Decoder::new(io::Cursor::new(out)).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let frame = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((frame.width, frame.height), (12, 8));
+        assert_eq!((frame.color_type, frame.bit_depth), (png::ColorType::Grayscale, png::BitDepth::Sixteen));
+
+        let levels: Vec<u16> = pixels.chunks_exact(2).map(|sample| u16::from_be_bytes([sample[0], sample[1]])).collect();
+        let expected: Vec<u16> = data
+            .samples()
+            .map(|result| (smooth_value(&result, &data) / 50.0 * u16::MAX as f64).round() as u16)
+            .collect();
+        assert_eq!(levels, expected);
+        // points in the set are white
+        assert!(levels.contains(&u16::MAX));
+    }
+}
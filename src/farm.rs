@@ -0,0 +1,319 @@
+// A render farm: worker processes on other machines (`mandelbrot serve-worker`) compute bands of
+// a view for a coordinator, which cuts every view it computes into bands of rows, hands them
+// out over TCP and stacks the results back up. Bands outnumber workers a few times over, so a
+// fast machine takes more of them than a slow one, and the bands of a worker that can't be
+// reached or drops out are computed by the coordinator itself.
+//
+// The protocol: the coordinator opens a connection with `MAGIC`, then sends any number of jobs,
+// each answered in turn by its escape data. Every message is a little-endian u32 length and
+// that many bytes of postcard, the same encoding as `export::to_bytes`.
+
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+pub use crate::Job;
+use crate::progress::Progress;
+use crate::renderer::RenderData;
+
+/// Port workers listen on when an address doesn't give one.
+pub const DEFAULT_PORT: u16 = 7878;
+
+// opens every connection; the digits go up when jobs or replies change shape
+const MAGIC: &[u8; 8] = b"MBFARM06";
+// bigger messages than this are taken for garbage rather than allocated
+const MAX_MESSAGE: usize = 1 << 30;
+const BANDS_PER_WORKER: usize = 4;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+// how long a worker gets to take a job, and for a band on top of what its size calls for
+const TIMEOUT: Duration = Duration::from_secs(10);
+// iterations a slow worker is still expected to manage per second
+const ITERATIONS_PER_SECOND: f64 = 1e7;
+// jobs of more samples than this are refused rather than allocated: 512 MiB of 8-byte pixels,
+// plus whichever side planes of escape data the job computes
+const MAX_SAMPLES: usize = 1 << 26;
+
+impl Job {
+    // every sample of every pixel; None if that doesn't fit a usize
+    fn samples(&self) -> Option<usize> {
+        let samples = self.options.samples.max(1);
+        self.width.checked_mul(self.height)?.checked_mul(samples)?.checked_mul(samples)
+    }
+
+    // `rows` rows from `first_row` on, placed like `TiledRender::tile_region` places them
+    fn band(&self, first_row: usize, rows: usize) -> Job {
+        let (x_min, x_max, y_min, y_max) = self.region;
+        let row_y = |row: usize| y_min + (y_max - y_min) * (row as f64 / self.height as f64);
+        Job {
+            region: (x_min, x_max, row_y(first_row), row_y(first_row + rows)),
+            height: rows,
+            ..self.clone()
+        }
+    }
+}
+
+/// Answers the jobs a coordinator sends over `stream` until it hangs up; returns how many.
+pub fn serve_connection(mut stream: TcpStream) -> io::Result<usize> {
+    stream.set_nodelay(true)?;
+    let mut magic = [0; MAGIC.len()];
+    stream.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("not a mandelbrot coordinator, or another version"));
+    }
+    let mut jobs = 0;
+    while let Some(job) = read_message::<Job>(&mut stream)? {
+        if job.samples().is_none_or(|samples| samples > MAX_SAMPLES) {
+            return Err(invalid("job too large"));
+        }
+        write_message(&mut stream, &job.calculate(None))?;
+        jobs += 1;
+    }
+    Ok(jobs)
+}
+
+/// Workers to spread computations over, by address.
+pub struct Farm {
+    // workers that haven't failed yet
+    workers: Mutex<Vec<String>>,
+    failures: Mutex<Vec<(String, io::Error)>>,
+    timeout: Duration,
+}
+
+impl Farm {
+    /// A farm of the workers at `addresses`, each `host` or `host:port`.
+    pub fn new(addresses: &[&str]) -> Self {
+        let workers = addresses
+            .iter()
+            .map(|address| match address.rsplit_once(':') {
+                Some((_, port)) if port.parse::<u16>().is_ok() => address.to_string(),
+                _ => format!("{}:{}", address, DEFAULT_PORT),
+            })
+            .collect();
+        Self {
+            workers: Mutex::new(workers),
+            failures: Mutex::new(Vec::new()),
+            timeout: TIMEOUT,
+        }
+    }
+
+    /// Time a worker gets to answer, on top of what a band's size calls for, before its band
+    /// goes back in the queue and it gets no more; 10 seconds by default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Workers that haven't failed yet.
+    pub fn workers(&self) -> Vec<String> {
+        self.workers.lock().unwrap().clone()
+    }
+
+    /// Workers that failed since the last call, with why. They get no more jobs.
+    pub fn take_failures(&self) -> Vec<(String, io::Error)> {
+        std::mem::take(&mut *self.failures.lock().unwrap())
+    }
+
+    /// Computes `job` in bands on the workers, and whatever bands they leave undone here; the
+    /// same data as `job.calculate`, up to float rounding at the band edges.
+    pub fn calculate(&self, job: &Job, progress: Option<&Progress>) -> RenderData {
+        let workers = self.workers();
+        let rows = job.height.div_ceil((workers.len() * BANDS_PER_WORKER).max(1)).max(1);
+        let count = job.height.div_ceil(rows);
+        let band = |index: usize| job.band(index * rows, rows.min(job.height - index * rows));
+        // popped from the end, so the top band goes out first
+        let queue = Mutex::new((0..count).rev().collect::<Vec<_>>());
+        let done = Mutex::new((0..count).map(|_| None).collect::<Vec<Option<RenderData>>>());
+
+        thread::scope(|scope| {
+            for worker in &workers {
+                let (queue, done, band) = (&queue, &done, &band);
+                scope.spawn(move || {
+                    if let Err(e) = work(worker, queue, done, band, self.timeout, progress) {
+                        self.workers.lock().unwrap().retain(|alive| alive != worker);
+                        self.failures.lock().unwrap().push((worker.clone(), e));
+                    }
+                });
+            }
+        });
+
+        let mut bands = done.into_inner().unwrap();
+        for index in queue.into_inner().unwrap() {
+            log::trace!("band {} computed here", index);
+            bands[index] = Some(band(index).calculate(progress));
+        }
+        RenderData::stacked(&bands.into_iter().flatten().collect::<Vec<_>>())
+    }
+}
+
+// sends bands from `queue` to `worker` one at a time until none are left; a band the worker
+// fails on, or takes longer than `timeout` plus the time its size calls for, goes back in the
+// queue
+fn work(
+    worker: &str,
+    queue: &Mutex<Vec<usize>>,
+    done: &Mutex<Vec<Option<RenderData>>>,
+    band: &dyn Fn(usize) -> Job,
+    timeout: Duration,
+    progress: Option<&Progress>,
+) -> io::Result<()> {
+    let mut stream = connect(worker)?;
+    stream.set_write_timeout(Some(timeout))?;
+    stream.write_all(MAGIC)?;
+    loop {
+        let Some(index) = queue.lock().unwrap().pop() else {
+            return Ok(());
+        };
+        let job = band(index);
+        let iterations = job.samples().unwrap_or(usize::MAX) as f64 * job.max_iters as f64;
+        stream.set_read_timeout(Some(timeout + Duration::from_secs_f64((iterations / ITERATIONS_PER_SECOND).min(1e9))))?;
+        let result = write_message(&mut stream, &job).and_then(|_| match read_message::<RenderData>(&mut stream)? {
+            Some(data) if (data.width(), data.height()) == (job.width, job.height) => Ok(data),
+            Some(_) => Err(invalid("the worker sent back a band of the wrong size")),
+            None => Err(io::ErrorKind::UnexpectedEof.into()),
+        });
+        match result {
+            Ok(data) => {
+                log::trace!("band {} of {} rows from {}", index, job.height, worker);
+                if let Some(progress) = progress {
+                    let samples = job.options.samples.max(1);
+                    progress.advance(job.width * job.height * samples * samples);
+                }
+                done.lock().unwrap()[index] = Some(data);
+            }
+            Err(e) => {
+                queue.lock().unwrap().push(index);
+                return Err(e);
+            }
+        }
+    }
+}
+
+fn connect(worker: &str) -> io::Result<TcpStream> {
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "the address doesn't resolve");
+    for address in worker.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(stream) => {
+                stream.set_nodelay(true)?;
+                return Ok(stream);
+            }
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
+
+fn write_message(stream: &mut TcpStream, message: &impl Serialize) -> io::Result<()> {
+    let bytes = postcard::to_stdvec(message).map_err(|e| invalid(&e.to_string()))?;
+    let length = u32::try_from(bytes.len()).map_err(|_| invalid("message too large"))?;
+    stream.write_all(&length.to_le_bytes())?;
+    stream.write_all(&bytes)
+}
+
+// the next message, or `None` if the other end hung up before one started
+fn read_message<T: DeserializeOwned>(stream: &mut TcpStream) -> io::Result<Option<T>> {
+    let mut length = [0; 4];
+    match stream.read_exact(&mut length) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let length = u32::from_le_bytes(length) as usize;
+    if length > MAX_MESSAGE {
+        return Err(invalid("message too large"));
+    }
+    let mut bytes = vec![0; length];
+    stream.read_exact(&mut bytes)?;
+    postcard::from_bytes(&bytes).map(Some).map_err(|e| invalid(&e.to_string()))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+    use crate::ComputeOptions;
+    use crate::formula::Formula;
+    use crate::fractal::Fractal;
+
+    #[test]
+    fn test_workers_compute_the_same_view() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                thread::spawn(move || serve_connection(stream.unwrap()));
+            }
+        });
+
+        // a formula and supersampling make it through the wire too
+        let job = Job {
+            fractal: Fractal::Formula(Formula::parse("z^2 + c").unwrap()),
+            max_iters: 80,
+            region: (-2.0, 1.0, -1.0, 1.0),
+            width: 30,
+            height: 21,
+            options: ComputeOptions {
+                samples: 2,
+                ..Default::default()
+            },
+        };
+        let iterations = |data: &RenderData| data.pixels().iter().map(|pixel| pixel.iterations).collect::<Vec<_>>();
+        let expected = job.calculate(None);
+
+        let farm = Farm::new(&[address.as_str(), address.as_str()]);
+        let data = farm.calculate(&job, None);
+        assert_eq!((data.width(), data.height()), (30, 21));
+        assert_eq!(iterations(&data), iterations(&expected));
+        assert_eq!(data.subsamples(29, 20).map(|samples| samples.len()), Some(4));
+        assert!(farm.take_failures().is_empty());
+
+        // a worker that isn't there leaves its bands to the coordinator, and gets no more
+        let farm = Farm::new(&["127.0.0.1:1", address.as_str()]);
+        assert_eq!(iterations(&farm.calculate(&job, None)), iterations(&expected));
+        assert_eq!(farm.take_failures().len(), 1);
+        assert_eq!(farm.workers(), vec![address]);
+    }
+
+    #[test]
+    fn test_hung_workers_and_huge_jobs_are_given_up_on() {
+        // a worker that takes jobs and never answers them
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let hung = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let mut streams = Vec::new();
+            for stream in listener.incoming() {
+                streams.push(stream.unwrap());
+            }
+        });
+        let job = Job {
+            fractal: Fractal::Mandelbrot,
+            max_iters: 50,
+            region: (-2.0, 1.0, -1.0, 1.0),
+            width: 20,
+            height: 10,
+            options: ComputeOptions::default(),
+        };
+        let farm = Farm::new(&[hung.as_str()]).with_timeout(Duration::from_millis(100));
+        let iterations = |data: &RenderData| data.pixels().iter().map(|pixel| pixel.iterations).collect::<Vec<_>>();
+        assert_eq!(iterations(&farm.calculate(&job, None)), iterations(&job.calculate(None)));
+        assert_eq!(farm.take_failures().len(), 1);
+
+        // and a job too large to compute
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || serve_connection(listener.accept().unwrap().0));
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(MAGIC).unwrap();
+        write_message(&mut stream, &Job { width: 1 << 20, height: 1 << 20, ..job }).unwrap();
+        assert_eq!(server.join().unwrap().unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
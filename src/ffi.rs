@@ -0,0 +1,146 @@
+// A C API (`ffi` feature), for applications in other languages that want the renderer without
+// reimplementing it. Build the shared library with:
+//
+//     cargo rustc --lib --release --features ffi --crate-type cdylib
+//
+// which leaves libmandelbrot.so (mandelbrot.dll, libmandelbrot.dylib) in target/release, and
+// include/mandelbrot.h declares what it exports. From Python, through ctypes:
+//
+//     lib = ctypes.CDLL("target/release/libmandelbrot.so")
+//     view = MandelbrotView(-2.0, 1.0, -1.0, 1.0, 640, 480, 500)   # a ctypes.Structure
+//     pixels = (ctypes.c_uint8 * (640 * 480 * 3))()
+//     lib.mandelbrot_render_region(b"mandelbrot", b"ocean", ctypes.byref(view), pixels, len(pixels))
+//
+// Every function returns a status rather than unwinding into the caller: a panic is caught and
+// reported as `MANDELBROT_ERROR_PANIC`.
+
+use std::ffi::{CStr, c_char, c_int};
+use std::panic;
+
+use crate::ComputeOptions;
+use crate::colorscheme::ColorScheme;
+use crate::fractal::Fractal;
+use crate::renderer::{OutputFormat, Renderer};
+
+pub const MANDELBROT_OK: c_int = 0;
+/// A pointer argument is null, or a name isn't UTF-8.
+pub const MANDELBROT_ERROR_ARGUMENT: c_int = 1;
+pub const MANDELBROT_ERROR_UNKNOWN_FRACTAL: c_int = 2;
+pub const MANDELBROT_ERROR_UNKNOWN_PALETTE: c_int = 3;
+/// The buffer holds fewer than width × height × 3 bytes.
+pub const MANDELBROT_ERROR_BUFFER_TOO_SMALL: c_int = 4;
+pub const MANDELBROT_ERROR_PANIC: c_int = 5;
+
+/// The region to render and how finely, laid out as `struct mandelbrot_view` in C.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct MandelbrotView {
+    pub x_min: f64,
+    pub x_max: f64,
+    pub y_min: f64,
+    pub y_max: f64,
+    pub width: usize,
+    pub height: usize,
+    pub max_iterations: usize,
+}
+
+/// Renders `view` of the fractal named `fractal` ("mandelbrot", "julia", "burning-ship", ...)
+/// in the palette named `palette` ("classic", "ocean", "viridis", ...; "-r" reverses one) into
+/// `buffer` as packed RGB, row by row from `y_min`. Returns `MANDELBROT_OK` or one of the
+/// `MANDELBROT_ERROR_` codes, leaving the buffer untouched on error.
+///
+/// # Safety
+///
+/// `fractal` and `palette` must be null or nul-terminated strings, `view` null or a valid
+/// `MandelbrotView`, and `buffer` null or valid for writing `buffer_len` bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mandelbrot_render_region(
+    fractal: *const c_char,
+    palette: *const c_char,
+    view: *const MandelbrotView,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> c_int {
+    if fractal.is_null() || palette.is_null() || view.is_null() || buffer.is_null() {
+        return MANDELBROT_ERROR_ARGUMENT;
+    }
+    // SAFETY: non-null, and valid as the caller promises
+    let (fractal, palette, view) = unsafe { (CStr::from_ptr(fractal), CStr::from_ptr(palette), *view) };
+    let (Ok(fractal), Ok(palette)) = (fractal.to_str(), palette.to_str()) else {
+        return MANDELBROT_ERROR_ARGUMENT;
+    };
+    let Some(fractal) = Fractal::from_name_with_defaults(fractal) else {
+        return MANDELBROT_ERROR_UNKNOWN_FRACTAL;
+    };
+    let Some(scheme) = ColorScheme::from_str(palette) else {
+        return MANDELBROT_ERROR_UNKNOWN_PALETTE;
+    };
+    if view.width.checked_mul(view.height).and_then(|pixels| pixels.checked_mul(3)).is_none_or(|len| len > buffer_len) {
+        return MANDELBROT_ERROR_BUFFER_TOO_SMALL;
+    }
+
+    let rendered = panic::catch_unwind(|| {
+        let region = (view.x_min, view.x_max, view.y_min, view.y_max);
+        let options = ComputeOptions::default();
+        let data = crate::calculate(&fractal, view.max_iterations.max(1), region, view.width, view.height, &options, None);
+        // the output format only matters for text, which this never draws
+        Renderer::new(scheme, OutputFormat::AnsiTrueColor).to_rgb_buffer(&data)
+    });
+    let Ok(rgb) = rendered else {
+        return MANDELBROT_ERROR_PANIC;
+    };
+    // SAFETY: the buffer holds at least `rgb.len()` bytes, checked above
+    unsafe { std::ptr::copy_nonoverlapping(rgb.as_ptr(), buffer, rgb.len()) };
+    MANDELBROT_OK
+}
+
+/// Whether `palette` names a palette `mandelbrot_render_region` takes: 1 if so, 0 if not.
+///
+/// # Safety
+///
+/// `palette` must be null or a nul-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mandelbrot_palette_exists(palette: *const c_char) -> c_int {
+    if palette.is_null() {
+        return 0;
+    }
+    // SAFETY: non-null, and nul-terminated as the caller promises
+    let palette = unsafe { CStr::from_ptr(palette) };
+    palette.to_str().ok().and_then(ColorScheme::from_str).is_some() as c_int
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renders_into_the_callers_buffer() {
+        let view = MandelbrotView {
+            x_min: -2.0,
+            x_max: 1.0,
+            y_min: -1.0,
+            y_max: 1.0,
+            width: 12,
+            height: 8,
+            max_iterations: 50,
+        };
+        let mut buffer = vec![0; 12 * 8 * 3];
+        let status =
+            unsafe { mandelbrot_render_region(c"mandelbrot".as_ptr(), c"ocean".as_ptr(), &view, buffer.as_mut_ptr(), buffer.len()) };
+        assert_eq!(status, MANDELBROT_OK);
+        let data = crate::calculate(&Fractal::Mandelbrot, 50, (-2.0, 1.0, -1.0, 1.0), 12, 8, &ComputeOptions::default(), None);
+        assert_eq!(buffer, Renderer::new(ColorScheme::Ocean, OutputFormat::AnsiTrueColor).to_rgb_buffer(&data));
+
+        let render = |fractal: &CStr, palette: &CStr, buffer: &mut [u8]| unsafe {
+            mandelbrot_render_region(fractal.as_ptr(), palette.as_ptr(), &view, buffer.as_mut_ptr(), buffer.len())
+        };
+        assert_eq!(render(c"mandelbrot", c"ocean", &mut buffer[1..]), MANDELBROT_ERROR_BUFFER_TOO_SMALL);
+        assert_eq!(render(c"mandelbread", c"ocean", &mut buffer), MANDELBROT_ERROR_UNKNOWN_FRACTAL);
+        assert_eq!(render(c"julia", c"no-such", &mut buffer), MANDELBROT_ERROR_UNKNOWN_PALETTE);
+        let null = unsafe { mandelbrot_render_region(std::ptr::null(), c"ocean".as_ptr(), &view, buffer.as_mut_ptr(), buffer.len()) };
+        assert_eq!(null, MANDELBROT_ERROR_ARGUMENT);
+
+        assert_eq!(unsafe { mandelbrot_palette_exists(c"viridis-r".as_ptr()) }, 1);
+        assert_eq!(unsafe { mandelbrot_palette_exists(c"plaid".as_ptr()) }, 0);
+    }
+}
@@ -0,0 +1,431 @@
+// User-defined iteration formulas, e.g. "z^2 + c*z + 0.1" or "sin(z) * c".
+//
+// The formula is parsed into an expression tree, constant parts are folded, and the rest is
+// compiled to a short program for a stack machine over complex numbers. Evaluating that is a
+// tight loop over a fixed array of ops, with no allocation or tree walking per iteration.
+//
+//     expr   = term (('+' | '-') term)*
+//     term   = unary (('*' | '/') unary | unary)*     juxtaposition multiplies: 2z, 3i
+//     unary  = '-' unary | power
+//     power  = atom ('^' unary)?                     right associative
+//     atom   = number | 'z' | 'c' | 'i' | name '(' expr ')' | '(' expr ')'
+
+use num::complex::Complex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::MandelbrotError;
+
+/// Most ops a compiled formula can hold, which keeps `Formula` a fixed-size `Copy` value.
+pub const MAX_OPS: usize = 64;
+const MAX_STACK: usize = 16;
+
+/// A function callable from a formula.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Function {
+    /// the complex conjugate, as in the tricorn
+    Conj,
+    /// |re| + |im|·i, the fold of the burning ship
+    Abs,
+    Sin,
+    Cos,
+    Exp,
+    Log,
+    Sqrt,
+}
+
+impl Function {
+    const NAMES: [(&'static str, Function); 7] = [
+        ("conj", Function::Conj),
+        ("abs", Function::Abs),
+        ("sin", Function::Sin),
+        ("cos", Function::Cos),
+        ("exp", Function::Exp),
+        ("log", Function::Log),
+        ("sqrt", Function::Sqrt),
+    ];
+
+    fn apply(self, z: Complex<f64>) -> Complex<f64> {
+        match self {
+            Function::Conj => z.conj(),
+            Function::Abs => Complex::new(z.re.abs(), z.im.abs()),
+            Function::Sin => z.sin(),
+            Function::Cos => z.cos(),
+            Function::Exp => z.exp(),
+            Function::Log => z.ln(),
+            Function::Sqrt => z.sqrt(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+enum Op {
+    Z,
+    C,
+    Const(Complex<f64>),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Neg,
+    /// integer powers, by repeated squaring
+    PowInt(i32),
+    Pow,
+    Call(Function),
+}
+
+/// A compiled iteration formula in z and c.
+///
+/// Serializes as its compiled ops, which are checked to leave the stack as `eval` expects
+/// when it's deserialized.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(into = "Vec<Op>", try_from = "Vec<Op>")]
+pub struct Formula {
+    ops: [Op; MAX_OPS],
+    len: usize,
+}
+
+impl Formula {
+    /// Parses and compiles `s`; the error says what was wrong and where.
+    pub fn parse(s: &str) -> crate::Result<Self> {
+        Self::compile(s).map_err(|reason| MandelbrotError::invalid(format_args!("formula '{}'", s), reason))
+    }
+
+    fn compile(s: &str) -> Result<Self, String> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser { tokens: &tokens, pos: 0 };
+        let expr = parser.expr()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("unexpected {}", token));
+        }
+
+        let mut formula = Formula {
+            ops: [Op::Z; MAX_OPS],
+            len: 0,
+        };
+        let max_depth = formula.emit(&expr.fold())?;
+        if max_depth > MAX_STACK {
+            return Err(format!("formula nests more than {} levels deep", MAX_STACK));
+        }
+        Ok(formula)
+    }
+
+    /// The next z of the orbit.
+    pub fn eval(&self, z: Complex<f64>, c: Complex<f64>) -> Complex<f64> {
+        let mut stack = [Complex::new(0.0, 0.0); MAX_STACK];
+        let mut top = 0;
+        for op in &self.ops[..self.len] {
+            match *op {
+                Op::Z => (stack[top], top) = (z, top + 1),
+                Op::C => (stack[top], top) = (c, top + 1),
+                Op::Const(value) => (stack[top], top) = (value, top + 1),
+                Op::Neg => stack[top - 1] = -stack[top - 1],
+                Op::PowInt(2) => stack[top - 1] = stack[top - 1] * stack[top - 1],
+                Op::PowInt(n) => stack[top - 1] = stack[top - 1].powi(n),
+                Op::Call(function) => stack[top - 1] = function.apply(stack[top - 1]),
+                Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Pow => {
+                    top -= 1;
+                    let (a, b) = (stack[top - 1], stack[top]);
+                    stack[top - 1] = match *op {
+                        Op::Add => a + b,
+                        Op::Sub => a - b,
+                        Op::Mul => a * b,
+                        Op::Div => a / b,
+                        _ => a.powc(b),
+                    };
+                }
+            }
+        }
+        stack[0]
+    }
+
+    // appends the ops of `expr`, returning the stack depth it needs
+    fn emit(&mut self, expr: &Expr) -> Result<usize, String> {
+        let (op, depth) = match expr {
+            Expr::Z => (Op::Z, 1),
+            Expr::C => (Op::C, 1),
+            Expr::Number(value) => (Op::Const(*value), 1),
+            Expr::Neg(a) => (Op::Neg, self.emit(a)?),
+            Expr::Call(function, a) => (Op::Call(*function), self.emit(a)?),
+            Expr::Binary(b'^', a, b) if integer_exponent(b).is_some() => {
+                (Op::PowInt(integer_exponent(b).unwrap()), self.emit(a)?)
+            }
+            Expr::Binary(op, a, b) => {
+                let depth = self.emit(a)?.max(self.emit(b)? + 1);
+                let op = match op {
+                    b'+' => Op::Add,
+                    b'-' => Op::Sub,
+                    b'*' => Op::Mul,
+                    b'/' => Op::Div,
+                    _ => Op::Pow,
+                };
+                (op, depth)
+            }
+        };
+        if self.len == MAX_OPS {
+            return Err(format!("formula is too long, at most {} operations", MAX_OPS));
+        }
+        self.ops[self.len] = op;
+        self.len += 1;
+        Ok(depth)
+    }
+}
+
+impl From<Formula> for Vec<Op> {
+    fn from(formula: Formula) -> Self {
+        formula.ops[..formula.len].to_vec()
+    }
+}
+
+impl TryFrom<Vec<Op>> for Formula {
+    type Error = String;
+
+    fn try_from(ops: Vec<Op>) -> Result<Self, String> {
+        if ops.len() > MAX_OPS {
+            return Err(format!("formula is too long, at most {} operations", MAX_OPS));
+        }
+        // every op must find the values it takes on the stack, which ends with just the result
+        let mut top = 0;
+        for op in &ops {
+            top = match op {
+                Op::Z | Op::C | Op::Const(_) if top < MAX_STACK => top + 1,
+                Op::Neg | Op::PowInt(_) | Op::Call(_) if top >= 1 => top,
+                Op::Add | Op::Sub | Op::Mul | Op::Div | Op::Pow if top >= 2 => top - 1,
+                _ => return Err("formula ops don't add up to one value".to_string()),
+            };
+        }
+        if top != 1 {
+            return Err("formula ops don't add up to one value".to_string());
+        }
+        let mut formula = Formula {
+            ops: [Op::Z; MAX_OPS],
+            len: ops.len(),
+        };
+        formula.ops[..ops.len()].copy_from_slice(&ops);
+        Ok(formula)
+    }
+}
+
+fn integer_exponent(expr: &Expr) -> Option<i32> {
+    match expr {
+        Expr::Number(n) if n.im == 0.0 && n.re.fract() == 0.0 && n.re.abs() <= 64.0 => Some(n.re as i32),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Z,
+    C,
+    Number(Complex<f64>),
+    Neg(Box<Expr>),
+    Call(Function, Box<Expr>),
+    Binary(u8, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    // evaluates every subtree that doesn't involve z or c
+    fn fold(self) -> Expr {
+        match self {
+            Expr::Neg(a) => match a.fold() {
+                Expr::Number(n) => Expr::Number(-n),
+                a => Expr::Neg(Box::new(a)),
+            },
+            Expr::Call(function, a) => match a.fold() {
+                Expr::Number(n) => Expr::Number(function.apply(n)),
+                a => Expr::Call(function, Box::new(a)),
+            },
+            Expr::Binary(op, a, b) => match (a.fold(), b.fold()) {
+                (Expr::Number(a), Expr::Number(b)) => Expr::Number(match op {
+                    b'+' => a + b,
+                    b'-' => a - b,
+                    b'*' => a * b,
+                    b'/' => a / b,
+                    _ => a.powc(b),
+                }),
+                (a, b) => Expr::Binary(op, Box::new(a), Box::new(b)),
+            },
+            expr => expr,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Symbol(u8),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "number {}", n),
+            Token::Name(name) => write!(f, "'{}'", name),
+            Token::Symbol(symbol) => write!(f, "'{}'", *symbol as char),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some(&(start, ch)) = chars.peek() {
+        if ch.is_whitespace() {
+            chars.next();
+        } else if ch.is_ascii_digit() || ch == '.' {
+            // digits, a fraction and an optional exponent such as 1.5e-3
+            let mut end = start;
+            let mut previous = ' ';
+            while let Some(&(i, ch)) = chars.peek() {
+                let exponent_sign = matches!(ch, '+' | '-') && matches!(previous, 'e' | 'E');
+                if !(ch.is_ascii_digit() || ch == '.' || matches!(ch, 'e' | 'E') || exponent_sign) {
+                    break;
+                }
+                (end, previous) = (i + ch.len_utf8(), ch);
+                chars.next();
+            }
+            let number = &s[start..end];
+            tokens.push(Token::Number(number.parse().map_err(|_| format!("invalid number '{}'", number))?));
+        } else if ch.is_alphabetic() {
+            let mut end = start;
+            while let Some(&(i, ch)) = chars.peek().filter(|(_, ch)| ch.is_alphanumeric()) {
+                end = i + ch.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token::Name(s[start..end].to_lowercase()));
+        } else if "+-*/^()".contains(ch) {
+            tokens.push(Token::Symbol(ch as u8));
+            chars.next();
+        } else {
+            return Err(format!("unexpected '{}'", ch));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, symbol: u8) -> bool {
+        let found = self.peek() == Some(&Token::Symbol(symbol));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut expr = self.term()?;
+        loop {
+            let op = if self.eat(b'+') {
+                b'+'
+            } else if self.eat(b'-') {
+                b'-'
+            } else {
+                return Ok(expr);
+            };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        loop {
+            let op = if self.eat(b'*') {
+                b'*'
+            } else if self.eat(b'/') {
+                b'/'
+            } else if matches!(self.peek(), Some(Token::Number(_) | Token::Name(_) | Token::Symbol(b'('))) {
+                b'*'
+            } else {
+                return Ok(expr);
+            };
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat(b'-') {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        let base = self.atom()?;
+        if self.eat(b'^') {
+            return Ok(Expr::Binary(b'^', Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        let token = self.peek().cloned().ok_or("unexpected end of formula")?;
+        self.pos += 1;
+        match token {
+            Token::Number(n) => Ok(Expr::Number(Complex::new(n, 0.0))),
+            Token::Symbol(b'(') => {
+                let expr = self.expr()?;
+                if !self.eat(b')') {
+                    return Err("missing ')'".to_string());
+                }
+                Ok(expr)
+            }
+            Token::Name(name) => match name.as_str() {
+                "z" => Ok(Expr::Z),
+                "c" => Ok(Expr::C),
+                "i" => Ok(Expr::Number(Complex::new(0.0, 1.0))),
+                _ => {
+                    let (_, function) = Function::NAMES
+                        .iter()
+                        .find(|(known, _)| *known == name)
+                        .ok_or_else(|| format!("unknown name '{}', use z, c, i or a function", name))?;
+                    if !self.eat(b'(') {
+                        return Err(format!("'{}' needs an argument in brackets", name));
+                    }
+                    let argument = self.expr()?;
+                    if !self.eat(b')') {
+                        return Err("missing ')'".to_string());
+                    }
+                    Ok(Expr::Call(*function, Box::new(argument)))
+                }
+            },
+            token => Err(format!("unexpected {}", token)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluates_like_the_written_formula() {
+        let (z, c) = (Complex::new(0.3, -0.7), Complex::new(-0.4, 0.6));
+        let cases: [(&str, Complex<f64>); 5] = [
+            ("z^2 + c*z + 0.1", z * z + c * z + 0.1),
+            ("2z^3 - c/2", z.powu(3) * 2.0 - c / 2.0),
+            ("-z^2 + (1+2i)c", -(z * z) + Complex::new(1.0, 2.0) * c),
+            ("sin(z) * c + conj(z)^2", z.sin() * c + z.conj() * z.conj()),
+            ("abs(z)^2 + c * exp(i)", {
+                let folded = Complex::new(z.re.abs(), z.im.abs());
+                folded * folded + c * Complex::new(0.0, 1.0).exp()
+            }),
+        ];
+        for (source, expected) in cases {
+            let formula = Formula::parse(source).unwrap();
+            assert!((formula.eval(z, c) - expected).norm() < 1e-12, "{}", source);
+        }
+
+        // constants are folded, and integer powers don't go through powc
+        let formula = Formula::parse("z^(1+1) + 2*3").unwrap();
+        assert_eq!(&formula.ops[..formula.len], &[Op::Z, Op::PowInt(2), Op::Const(Complex::new(6.0, 0.0)), Op::Add]);
+
+        for invalid in ["", "z +", "z^2 + q", "sin z", "(z", "z $ c", "z)"] {
+            assert!(Formula::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
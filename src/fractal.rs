@@ -0,0 +1,640 @@
+use num::complex::Complex;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{ComputeOptions, PlaneMap};
+use crate::formula::Formula;
+use crate::newton::Polynomial;
+use crate::progress::Progress;
+
+// an orbit that comes back this close to an earlier point has fallen into a cycle
+const PERIODICITY_TOLERANCE: f64 = 1e-12;
+
+/// The escape radius the formulas need at the least, and `ComputeOptions`' default: no point
+/// of the mandelbrot set ever gets further than 2 from the origin.
+pub const DEFAULT_BAILOUT: f64 = 2.0;
+
+/// The parameters `Fractal::from_name_with_defaults` fills in, which are also the defaults of
+/// `--julia-c`, `--power` and `--phoenix-p`.
+pub const DEFAULT_JULIA_C: Complex<f64> = Complex::new(-0.8, 0.156);
+pub const DEFAULT_POWER: f64 = 3.0;
+pub const DEFAULT_PHOENIX_P: Complex<f64> = Complex::new(-0.5, 0.0);
+
+/// Outcome of iterating a single point.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct EscapeResult {
+    /// iterations before |z| exceeded the bailout, or max_iters if it never did
+    pub iterations: usize,
+    /// |z| at the last iteration, used for smooth coloring
+    pub z_norm: f64,
+    /// estimated distance to the set for escaped points, 0.0 inside or when not tracked
+    pub distance: f64,
+    /// `interior::interior_at_point` measure for points that never escaped, when computed
+    pub interior: f64,
+    /// `layers::stripe_at_point` stripe average for escaped points, when computed
+    pub stripe: f64,
+    /// index into `Polynomial::roots` of the root a newton point converged to
+    pub root: Option<usize>,
+    /// z at the last iteration, when the computation kept it; the GPU and data read back from
+    /// exports only have `z_norm`
+    pub z: Option<Complex<f64>>,
+    /// dz/dc at the last iteration (dz/dz0 for julia sets), when the derivative was followed
+    /// for distance estimates; it's what `distance` and surface normals come from
+    pub derivative: Option<Complex<f64>>,
+}
+
+impl EscapeResult {
+    /// A pixel a cancelled computation never got to: counted in the set, so it comes out in the
+    /// interior color, black unless an interior mode says otherwise.
+    pub fn skipped(max_iters: usize) -> Self {
+        Self {
+            iterations: max_iters,
+            ..Default::default()
+        }
+    }
+}
+
+/// z^d: by repeated squaring for whole powers, which is faster and exact, and on the principal
+/// branch for the others, which cuts the plane along the negative real axis.
+pub(crate) fn pow(z: Complex<f64>, d: f64) -> Complex<f64> {
+    if d.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(&d) {
+        z.powu(d as u32)
+    } else {
+        z.powf(d)
+    }
+}
+
+/// The burning ship's family: z² + c with absolute values taken of parts of z before squaring
+/// or of the real part after, and the imaginary part flipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fold {
+    /// square |re z| rather than re z
+    pub abs_re: bool,
+    /// square |im z| rather than im z
+    pub abs_im: bool,
+    /// take |re z²| after squaring
+    pub abs_square_re: bool,
+    /// negate im z², which mirrors the set across the real axis
+    pub negate_im: bool,
+}
+
+impl Fold {
+    /// z folded as the flags say, then squared: the step without `+ c`.
+    pub fn square(&self, z: Complex<f64>) -> Complex<f64> {
+        let x = if self.abs_re { z.re.abs() } else { z.re };
+        let y = if self.abs_im { z.im.abs() } else { z.im };
+        let re = x * x - y * y;
+        let im = 2.0 * x * y;
+        Complex::new(if self.abs_square_re { re.abs() } else { re }, if self.negate_im { -im } else { im })
+    }
+}
+
+/// An escape-time formula, built in or user-defined, or the newton fractal's root finding.
+// a Fractal is made once per render and copied around rarely, so the inline polynomial and
+// formula are worth keeping it Copy
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Fractal {
+    Mandelbrot,
+    Julia(Complex<f64>), // fixed c, z0 comes from the pixel
+    BurningShip,
+    Tricorn,
+    Multibrot(f64), // z^d + c, for any real d; whole ones iterate faster
+    Celtic,
+    Perpendicular,
+    Buffalo,
+    Heart,
+    Phoenix(Complex<f64>), // z² + c + p·z_prev, for a fixed p
+    Lambda,                // λz(1 - z), the logistic map, with λ from the pixel
+    Newton(Polynomial), // z0 from the pixel, converges to a root instead of escaping
+    Formula(Formula),   // z = formula(z, c) from --formula
+}
+
+impl Fractal {
+    /// Parses a fractal name; `julia_c`, `power`, `phoenix_p` and `polynomial` fill in the
+    /// parameters of julia, multibrot, phoenix and newton.
+    pub fn from_str(s: &str, julia_c: Complex<f64>, power: f64, phoenix_p: Complex<f64>, polynomial: Polynomial) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "mandelbrot" => Some(Fractal::Mandelbrot),
+            "julia" => Some(Fractal::Julia(julia_c)),
+            "burning-ship" | "burningship" | "ship" => Some(Fractal::BurningShip),
+            "tricorn" | "mandelbar" => Some(Fractal::Tricorn),
+            "multibrot" => Some(Fractal::Multibrot(power)),
+            "celtic" => Some(Fractal::Celtic),
+            "perpendicular" | "perpendicular-mandelbrot" => Some(Fractal::Perpendicular),
+            "buffalo" => Some(Fractal::Buffalo),
+            "heart" => Some(Fractal::Heart),
+            "phoenix" => Some(Fractal::Phoenix(phoenix_p)),
+            "lambda" | "logistic" => Some(Fractal::Lambda),
+            "newton" => Some(Fractal::Newton(polynomial)),
+            _ => None,
+        }
+    }
+
+    /// Parses a fractal name alone, with the command line's default parameters and polynomial.
+    pub fn from_name_with_defaults(s: &str) -> Option<Self> {
+        Self::from_str(s, DEFAULT_JULIA_C, DEFAULT_POWER, DEFAULT_PHOENIX_P, Polynomial::default())
+    }
+
+    pub fn list_fractals() -> Vec<&'static str> {
+        vec![
+            "mandelbrot",
+            "julia",
+            "burning-ship",
+            "tricorn",
+            "multibrot",
+            "celtic",
+            "perpendicular",
+            "buffalo",
+            "heart",
+            "phoenix",
+            "lambda",
+            "newton",
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Fractal::Mandelbrot => "mandelbrot",
+            Fractal::Julia(_) => "julia",
+            Fractal::BurningShip => "burning-ship",
+            Fractal::Tricorn => "tricorn",
+            Fractal::Multibrot(_) => "multibrot",
+            Fractal::Celtic => "celtic",
+            Fractal::Perpendicular => "perpendicular",
+            Fractal::Buffalo => "buffalo",
+            Fractal::Heart => "heart",
+            Fractal::Phoenix(_) => "phoenix",
+            Fractal::Lambda => "lambda",
+            Fractal::Newton(_) => "newton",
+            Fractal::Formula(_) => "formula",
+        }
+    }
+
+    /// The power z is raised to, which is how fast |z| grows once it's large: smooth coloring
+    /// counts the steps past the bailout in logarithms of this base. User formulas are taken
+    /// to square, like the rest.
+    pub fn degree(&self) -> f64 {
+        match *self {
+            Fractal::Multibrot(power) => power,
+            _ => 2.0,
+        }
+    }
+
+    /// The folds of the burning ship and its relatives, which all iterate a `Fold`.
+    pub fn fold(&self) -> Option<Fold> {
+        let fold = |abs_re, abs_im, abs_square_re, negate_im| {
+            Some(Fold {
+                abs_re,
+                abs_im,
+                abs_square_re,
+                negate_im,
+            })
+        };
+        match self {
+            Fractal::BurningShip => fold(true, true, false, false),
+            // (re - i im)² is z² with its imaginary part negated
+            Fractal::Tricorn => fold(false, false, false, true),
+            Fractal::Celtic => fold(false, false, true, false),
+            Fractal::Perpendicular => fold(true, false, false, true),
+            Fractal::Buffalo => fold(true, true, true, true),
+            Fractal::Heart => fold(true, false, false, false),
+            _ => None,
+        }
+    }
+
+    /// Iterates the point (cx, cy) of the complex plane.
+    pub fn at_point(&self, cx: f64, cy: f64, max_iters: usize) -> EscapeResult {
+        self.at_point_with(cx, cy, max_iters, &ComputeOptions::default())
+    }
+
+    /// Like `at_point`, but also tracks the derivative of z to estimate the distance to the set.
+    pub fn at_point_with_distance(&self, cx: f64, cy: f64, max_iters: usize) -> EscapeResult {
+        let options = ComputeOptions {
+            distance: true,
+            ..ComputeOptions::default()
+        };
+        self.at_point_with(cx, cy, max_iters, &options)
+    }
+
+    /// Iterates the point (cx, cy) with the distance and early bailout settings of `options`.
+    pub fn at_point_with(&self, cx: f64, cy: f64, max_iters: usize, options: &ComputeOptions) -> EscapeResult {
+        let point = Complex::new(cx, cy);
+        let (distance, periodicity, bailout) = (options.distance, options.early_bailout, options.bailout);
+        if periodicity && matches!(self, Fractal::Mandelbrot) && in_cardioid_or_bulb(point) {
+            return EscapeResult {
+                iterations: max_iters,
+                ..EscapeResult::default()
+            };
+        }
+
+        let zero = Complex::new(0.0, 0.0);
+        let one = Complex::new(1.0, 0.0);
+        // the folding formulas aren't holomorphic, so only |dz/dc| is followed, as if they were z² + c
+        let folded = |z: Complex<f64>, dz: Complex<f64>| Complex::new(2.0 * z.norm() * dz.norm() + 1.0, 0.0);
+
+        match *self {
+            Fractal::Mandelbrot => escape_time(zero, point, max_iters, bailout, periodicity, |z, c| z * z + c, {
+                distance.then_some((zero, |z: Complex<f64>, dz| z * dz * 2.0 + one))
+            }),
+            // dz/dz0 rather than dz/dc, since c is fixed
+            Fractal::Julia(c) => escape_time(point, c, max_iters, bailout, periodicity, |z, c| z * z + c, {
+                distance.then_some((one, |z: Complex<f64>, dz| z * dz * 2.0))
+            }),
+            Fractal::BurningShip | Fractal::Tricorn | Fractal::Celtic | Fractal::Perpendicular | Fractal::Buffalo | Fractal::Heart => {
+                let fold = self.fold().expect("the burning ship's family folds");
+                escape_time(zero, point, max_iters, bailout, periodicity, |z, c| fold.square(z) + c, distance.then_some((zero, folded)))
+            }
+            Fractal::Multibrot(power) => escape_time(zero, point, max_iters, bailout, periodicity, |z, c| pow(z, power) + c, {
+                distance.then_some((zero, |z: Complex<f64>, dz| pow(z, power - 1.0) * dz * power + one))
+            }),
+            // the distance estimate would need the derivative of z_prev as well, so there is none
+            Fractal::Phoenix(p) => {
+                let step = |(z, previous): (Complex<f64>, Complex<f64>), c| (z * z + c + p * previous, z);
+                escape_time((zero, zero), point, max_iters, bailout, periodicity, step, None::<(Complex<f64>, fn(_, _) -> _)>)
+            }
+            // w = λ(1/2 - z) turns the logistic map into w² + c, for which |w| > 2 is an exact
+            // escape test; dc/dλ = (1 - λ) / 2
+            Fractal::Lambda => {
+                let dc = (one - point) * 0.5;
+                escape_time(zero, lambda_c(point), max_iters, bailout, periodicity, |z, c| z * z + c, {
+                    distance.then_some((zero, move |z: Complex<f64>, dz| z * dz * 2.0 + dc))
+                })
+            }
+            Fractal::Newton(polynomial) => polynomial.converge(point, max_iters),
+            Fractal::Formula(ref formula) => {
+                // dz/dc by finite differences, as the formula's derivatives aren't known
+                let h = 1e-8;
+                let derivative = |z: Complex<f64>, dz: Complex<f64>| {
+                    let f = formula.eval(z, point);
+                    let df_dz = (formula.eval(z + h, point) - f) / h;
+                    let df_dc = (formula.eval(z, point + h) - f) / h;
+                    df_dz * dz + df_dc
+                };
+                escape_time(zero, point, max_iters, bailout, periodicity, |z, c| formula.eval(z, c), {
+                    distance.then_some((zero, derivative))
+                })
+            }
+        }
+    }
+
+    /// Starting z, with the z before it, and the constant c for the pixel at (cx, cy).
+    pub(crate) fn start(&self, cx: f64, cy: f64) -> ((Complex<f64>, Complex<f64>), Complex<f64>) {
+        let point = Complex::new(cx, cy);
+        let zero = Complex::new(0.0, 0.0);
+        match *self {
+            Fractal::Julia(c) => ((point, zero), c),
+            Fractal::Newton(_) => ((point, zero), zero),
+            Fractal::Lambda => ((zero, zero), lambda_c(point)),
+            _ => ((zero, zero), point),
+        }
+    }
+
+    /// One iteration of the formula from z and the z before it, giving the next z and z;
+    /// `at_point` inlines the same formulas for speed.
+    pub(crate) fn step(&self, (z, previous): (Complex<f64>, Complex<f64>), c: Complex<f64>) -> (Complex<f64>, Complex<f64>) {
+        let next = match *self {
+            Fractal::Mandelbrot | Fractal::Julia(_) | Fractal::Lambda => z * z + c,
+            Fractal::Phoenix(p) => z * z + c + p * previous,
+            Fractal::BurningShip | Fractal::Tricorn | Fractal::Celtic | Fractal::Perpendicular | Fractal::Buffalo | Fractal::Heart => {
+                self.fold().expect("the burning ship's family folds").square(z) + c
+            }
+            Fractal::Multibrot(power) => pow(z, power) + c,
+            Fractal::Newton(polynomial) => polynomial.newton_step(z),
+            Fractal::Formula(ref formula) => formula.eval(z, c),
+        };
+        (next, z)
+    }
+
+    /// The points z visits from the pixel at (cx, cy), starting value first, up to and
+    /// including the one that escapes, or `max_iters` steps if none does.
+    pub fn orbit(&self, cx: f64, cy: f64, max_iters: usize) -> Vec<Complex<f64>> {
+        let (mut state, c) = self.start(cx, cy);
+        let mut orbit = vec![state.0];
+        for _ in 0..max_iters {
+            if state.0.norm() > 2.0 && !matches!(self, Fractal::Newton(_)) {
+                break;
+            }
+            state = self.step(state, c);
+            orbit.push(state.0);
+        }
+        orbit
+    }
+
+    /// Computes the escape result of every pixel of `region` (x_min, x_max, y_min, y_max),
+    /// row by row, as `at_point_with` does for a single point.
+    pub fn calculate(
+        &self,
+        max_iters: usize,
+        (x_min, x_max, y_min, y_max): (f64, f64, f64, f64),
+        width: usize,
+        height: usize,
+        options: &ComputeOptions,
+        progress: Option<&Progress>,
+    ) -> Vec<EscapeResult> {
+        let plane = PlaneMap::new(options);
+        let mut results = vec![EscapeResult::default(); width * height];
+        // rows are computed in parallel, each into its own slice of the buffer
+        results
+            .par_chunks_mut(width.max(1))
+            .enumerate()
+            .for_each(|(img_y, row)| {
+                if progress.is_some_and(Progress::is_cancelled) {
+                    row.fill(EscapeResult::skipped(max_iters));
+                    return;
+                }
+                for (img_x, result) in row.iter_mut().enumerate() {
+                    let x_percent = img_x as f64 / width as f64;
+                    let y_percent = img_y as f64 / height as f64;
+                    let (cx, cy) = plane.to_plane((x_min + (x_max - x_min) * x_percent, y_min + (y_max - y_min) * y_percent));
+
+                    *result = self.at_point_with(cx, cy, max_iters, options);
+                }
+
+                if let Some(progress) = progress {
+                    progress.advance(width);
+                }
+            });
+        results
+    }
+}
+
+/// Iterates z = z² + c for c = (cx, cy), starting from z = 0, until |z| passes `bailout`.
+pub fn mandelbrot_at_point(cx: f64, cy: f64, max_iters: usize, bailout: f64) -> EscapeResult {
+    let options = ComputeOptions {
+        bailout,
+        ..ComputeOptions::default()
+    };
+    Fractal::Mandelbrot.at_point_with(cx, cy, max_iters, &options)
+}
+
+// main cardioid and period-2 bulb of the mandelbrot set, which never escape
+fn in_cardioid_or_bulb(c: Complex<f64>) -> bool {
+    let x = c.re - 0.25;
+    let q = x * x + c.im * c.im;
+    let in_cardioid = q * (q + x) <= 0.25 * c.im * c.im;
+    let in_bulb = (c.re + 1.0) * (c.re + 1.0) + c.im * c.im <= 0.0625;
+    in_cardioid || in_bulb
+}
+
+// the c of z² + c whose orbits are those of the logistic map for `lambda`, moved by
+// w = λ(1/2 - z): λ/2 - λ²/4
+fn lambda_c(lambda: Complex<f64>) -> Complex<f64> {
+    lambda * (Complex::new(2.0, 0.0) - lambda) * 0.25
+}
+
+// what an orbit carries from one iteration to the next: z, and for the phoenix the z before it
+trait State: Copy {
+    fn z(&self) -> Complex<f64>;
+    // squared distance to another state, for cycle detection
+    fn gap(&self, other: &Self) -> f64;
+}
+
+impl State for Complex<f64> {
+    fn z(&self) -> Complex<f64> {
+        *self
+    }
+
+    fn gap(&self, other: &Self) -> f64 {
+        (self - other).norm_sqr()
+    }
+}
+
+impl State for (Complex<f64>, Complex<f64>) {
+    fn z(&self) -> Complex<f64> {
+        self.0
+    }
+
+    fn gap(&self, other: &Self) -> f64 {
+        (self.0 - other.0).norm_sqr() + (self.1 - other.1).norm_sqr()
+    }
+}
+
+// iterates z = step(z, c) from z0 until |z| > bailout or max_iters is reached; `derivative` gives
+// the starting dz and its update dz = f(z, dz) when the distance estimate is wanted.
+// With `periodicity`, orbits caught in a cycle stop early (Brent's cycle detection).
+fn escape_time<S, D>(
+    z0: S,
+    c: Complex<f64>,
+    max_iters: usize,
+    bailout: f64,
+    periodicity: bool,
+    step: impl Fn(S, Complex<f64>) -> S,
+    derivative: Option<(Complex<f64>, D)>,
+) -> EscapeResult
+where
+    S: State,
+    D: Fn(Complex<f64>, Complex<f64>) -> Complex<f64>,
+{
+    let mut z = z0;
+    let (mut dz, derivative) = match derivative {
+        Some((dz0, update)) => (dz0, Some(update)),
+        None => (Complex::new(0.0, 0.0), None),
+    };
+    // compare against a saved point, saved again after 1, 2, 4, ... iterations
+    let (mut saved, mut saved_at, mut interval) = (z, 0, 1);
+
+    for i in 0..max_iters {
+        let norm = z.z().norm();
+        if norm > bailout {
+            // exterior distance estimate, 0.5·|z|·ln|z| / |dz|
+            let distance = if derivative.is_some() && dz.norm() > 0.0 {
+                0.5 * norm * norm.ln() / dz.norm()
+            } else {
+                0.0
+            };
+            return EscapeResult {
+                iterations: i,
+                z_norm: norm,
+                distance,
+                interior: 0.0,
+                stripe: 0.0,
+                root: None,
+                z: Some(z.z()),
+                derivative: derivative.is_some().then_some(dz),
+            };
+        }
+        if let Some(update) = &derivative {
+            dz = update(z.z(), dz);
+        }
+        z = step(z, c);
+
+        if periodicity {
+            if z.gap(&saved) < PERIODICITY_TOLERANCE * PERIODICITY_TOLERANCE {
+                break;
+            }
+            if i + 1 - saved_at == interval {
+                (saved, saved_at, interval) = (z, i + 1, interval * 2);
+            }
+        }
+    }
+    EscapeResult {
+        iterations: max_iters,
+        z_norm: z.z().norm(),
+        distance: 0.0,
+        interior: 0.0,
+        stripe: 0.0,
+        root: None,
+        z: Some(z.z()),
+        derivative: derivative.is_some().then_some(dz),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_julia_uses_pixel_as_z0() {
+        // the origin never escapes for c = 0, but (1.5, 0) does right away
+        let julia = Fractal::Julia(Complex::new(0.0, 0.0));
+        assert_eq!(julia.at_point(0.0, 0.0, 50).iterations, 50);
+        assert_eq!(julia.at_point(1.5, 0.0, 50).iterations, 1);
+
+        assert_eq!(Fractal::Mandelbrot.at_point(0.0, 0.0, 50).iterations, 50);
+        assert_eq!(Fractal::Mandelbrot.at_point(1.5, 0.0, 50).iterations, 2);
+    }
+
+    #[test]
+    fn test_formula_variants() {
+        for &(cx, cy) in &[(-0.75, 0.1), (0.3, 0.5), (-1.2, -0.2)] {
+            let mandelbrot = Fractal::Mandelbrot.at_point(cx, cy, 200).iterations;
+            assert_eq!(Fractal::Multibrot(2.0).at_point(cx, cy, 200).iterations, mandelbrot);
+        }
+
+        // fractional powers go through the principal branch, and the origin stays put
+        let z = Complex::new(-0.3, 0.8);
+        assert!((pow(z, 2.5) - z * z * z.sqrt()).norm() < 1e-12);
+        assert_eq!(pow(Complex::new(0.0, 0.0), 2.5), Complex::new(0.0, 0.0));
+        assert_eq!(Fractal::Multibrot(2.5).at_point(0.0, 0.0, 50).iterations, 50);
+        assert_eq!(Fractal::Multibrot(2.5).at_point(1.5, 0.0, 50).iterations, 2);
+
+        // the burning ship folds both axes, so the upper and lower halves differ
+        let ship_up = Fractal::BurningShip.at_point(-1.75, 0.03, 200).iterations;
+        let ship_down = Fractal::BurningShip.at_point(-1.75, -0.03, 200).iterations;
+        assert_ne!(ship_up, ship_down);
+
+        // folding only the real part keeps the mirror symmetry the ship loses
+        let z = Complex::new(-0.4, 0.7);
+        assert_eq!(Fractal::Tricorn.fold().unwrap().square(z), z.conj() * z.conj());
+        for fractal in [Fractal::Heart, Fractal::Perpendicular] {
+            for &(cx, cy) in &[(-1.6, 0.02), (-0.3, 0.6), (0.2, -0.5)] {
+                let up = fractal.at_point(cx, cy, 200).iterations;
+                assert_eq!(fractal.at_point(cx, -cy, 200).iterations, up, "{}", fractal.name());
+            }
+        }
+    }
+
+    #[test]
+    fn test_bailout_shifts_the_smooth_count() {
+        use crate::colorscheme::smooth_iteration;
+        for &(cx, cy) in &[(0.4, 0.3), (-0.74, 0.2), (0.26, 0.0), (-1.9, 0.1)] {
+            let small = mandelbrot_at_point(cx, cy, 1000, DEFAULT_BAILOUT);
+            let large = mandelbrot_at_point(cx, cy, 1000, 256.0);
+            assert!(large.iterations > small.iterations && large.z_norm > 256.0);
+
+            // each count stays in its band, and 256 = 2^8 is log2(8) = 3 squarings further out
+            let smooth = |result: EscapeResult, bailout| smooth_iteration(result.iterations, result.z_norm, bailout, 2.0);
+            let (at_2, at_256) = (smooth(small, 2.0), smooth(large, 256.0));
+            assert!(at_256 > large.iterations as f64 && at_256 <= large.iterations as f64 + 1.0);
+            assert!((at_256 - at_2 - 3.0).abs() < 0.05, "{} at radius 2, {} at 256", at_2, at_256);
+        }
+
+        // cubing gets there in log3(8) steps instead, which base-2 logarithms would miscount
+        let cubic = Fractal::Multibrot(3.0);
+        let at = |bailout| {
+            let options = ComputeOptions {
+                bailout,
+                ..ComputeOptions::default()
+            };
+            let result = cubic.at_point_with(0.5, 0.6, 1000, &options);
+            smooth_iteration(result.iterations, result.z_norm, bailout, cubic.degree())
+        };
+        assert!((at(256.0) - at(2.0) - 8f64.log(3.0)).abs() < 0.05, "{} at radius 2, {} at 256", at(2.0), at(256.0));
+    }
+
+    #[test]
+    fn test_results_keep_the_final_z_and_derivative() {
+        let result = Fractal::Mandelbrot.at_point_with_distance(0.3, 0.6, 200);
+        let (z, derivative) = (result.z.unwrap(), result.derivative.unwrap());
+        assert_eq!(z.norm(), result.z_norm);
+        assert!((0.5 * z.norm() * z.norm().ln() / derivative.norm() - result.distance).abs() < 1e-15);
+
+        // z at the escape is the last point of the orbit, and without distances there is no derivative
+        let plain = Fractal::Mandelbrot.at_point(0.3, 0.6, 200);
+        assert_eq!(plain.z, Fractal::Mandelbrot.orbit(0.3, 0.6, 200).last().copied());
+        assert_eq!(plain.derivative, None);
+    }
+
+    #[test]
+    fn test_phoenix_and_lambda() {
+        // with no weight on the previous z the phoenix is the mandelbrot set, with one it isn't
+        let points = [(-0.75, 0.1), (0.3, 0.5), (-1.2, -0.2), (0.26, 0.0)];
+        for &(cx, cy) in &points {
+            let mandelbrot = Fractal::Mandelbrot.at_point(cx, cy, 200).iterations;
+            assert_eq!(Fractal::Phoenix(Complex::new(0.0, 0.0)).at_point(cx, cy, 200).iterations, mandelbrot);
+        }
+        let phoenix = Fractal::Phoenix(Complex::new(-0.5, 0.0));
+        assert!(points.iter().any(|&(cx, cy)| {
+            phoenix.at_point(cx, cy, 200).iterations != Fractal::Mandelbrot.at_point(cx, cy, 200).iterations
+        }));
+        // the kernel and the step `orbit` takes agree on when the orbit escapes
+        for &(cx, cy) in &points {
+            let result = phoenix.at_point_with(cx, cy, 200, &ComputeOptions::default());
+            assert_eq!(phoenix.orbit(cx, cy, 200).len(), result.iterations + 1);
+        }
+
+        // λ = 1 keeps its fixed point at 0, λ = 4.5 throws 1/2 out, and in between the lambda
+        // set is the mandelbrot set at c = λ/2 - λ²/4
+        assert_eq!(Fractal::Lambda.at_point(1.0, 0.0, 100).iterations, 100);
+        assert!(Fractal::Lambda.at_point(4.5, 0.0, 100).iterations < 5);
+        for &(re, im) in &[(2.5, 1.3), (-0.9, 1.1), (3.1, -0.4)] {
+            let c = lambda_c(Complex::new(re, im));
+            let lambda = Fractal::Lambda.at_point_with_distance(re, im, 300);
+            assert_eq!(lambda.iterations, Fractal::Mandelbrot.at_point(c.re, c.im, 300).iterations);
+
+            // the distance estimate agrees with how far a small step in λ moves c
+            let mandelbrot = Fractal::Mandelbrot.at_point_with_distance(c.re, c.im, 300).distance;
+            let dc_dlambda = (1.0 - Complex::new(re, im)).norm() * 0.5;
+            assert!(mandelbrot > 0.0 && (lambda.distance * dc_dlambda / mandelbrot - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_formula_matches_builtin() {
+        let formula = Fractal::Formula(Formula::parse("z^2 + c").unwrap());
+        for &(cx, cy) in &[(-0.75, 0.1), (0.3, 0.5), (-1.2, -0.2), (0.26, 0.0)] {
+            let builtin = Fractal::Mandelbrot.at_point_with_distance(cx, cy, 200);
+            let custom = formula.at_point_with_distance(cx, cy, 200);
+            assert_eq!(custom.iterations, builtin.iterations);
+            assert!((custom.distance - builtin.distance).abs() <= 1e-4 * builtin.distance.max(1e-9));
+        }
+    }
+
+    #[test]
+    fn test_distance_estimate_shrinks_near_boundary() {
+        // the cusp of the main cardioid sits at c = 0.25
+        let distance = |cx| Fractal::Mandelbrot.at_point_with_distance(cx, 0.0, 1000).distance;
+        assert!(distance(0.26) < distance(0.3));
+        assert!(distance(0.3) < distance(0.5));
+        assert!(distance(0.5) > 0.0);
+
+        assert_eq!(Fractal::Mandelbrot.at_point_with_distance(-0.5, 0.0, 1000).distance, 0.0);
+        assert_eq!(Fractal::Mandelbrot.at_point(0.5, 0.0, 1000).distance, 0.0);
+    }
+
+    #[test]
+    fn test_early_bailout_keeps_iterations() {
+        let exhaustive = ComputeOptions {
+            early_bailout: false,
+            ..ComputeOptions::default()
+        };
+        let region = (-2.0, 1.0, -1.2, 1.2);
+        for fractal in [Fractal::Mandelbrot, Fractal::Julia(Complex::new(-0.8, 0.156)), Fractal::BurningShip] {
+            let fast = fractal.calculate(300, region, 60, 40, &ComputeOptions::default(), None);
+            let slow = fractal.calculate(300, region, 60, 40, &exhaustive, None);
+            let iterations = |grid: &[EscapeResult]| grid.iter().map(|r| r.iterations).collect::<Vec<_>>();
+            assert_eq!(iterations(&fast), iterations(&slow), "{}", fractal.name());
+        }
+    }
+}
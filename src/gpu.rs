@@ -0,0 +1,256 @@
+// GPU compute backend (`gpu` feature).
+//
+// The escape-time loop runs in a wgpu compute shader (gpu.wgsl), one invocation per pixel, in
+// f32: fine for overviews and big posters, but it runs out of precision around a 1e-5 wide
+// region, where the CPU path (or perturbation) has to take over. The image is processed in
+// bands of rows so the output buffer stays under the device's storage limits and no single
+// dispatch runs long enough to trip a driver watchdog.
+
+use std::sync::{OnceLock, mpsc};
+
+use wgpu::util::DeviceExt;
+
+use crate::ComputeOptions;
+use crate::fractal::{EscapeResult, Fold, Fractal};
+use crate::progress::Progress;
+
+const SHADER: &str = include_str!("gpu.wgsl");
+const WORKGROUP_SIZE: u32 = 8;
+// iterations and z_norm, as the shader's Pixel struct
+const PIXEL_BYTES: u64 = 8;
+const MAX_BAND_PIXELS: usize = 1 << 20;
+
+struct Gpu {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+}
+
+static GPU: OnceLock<Option<Gpu>> = OnceLock::new();
+
+// the device is set up on first use and shared by every later computation
+fn context() -> Option<&'static Gpu> {
+    GPU.get_or_init(|| pollster::block_on(Gpu::new())).as_ref()
+}
+
+/// Whether a GPU adapter was found; sets the device up if this is the first call.
+pub fn is_available() -> bool {
+    context().is_some()
+}
+
+impl Gpu {
+    async fn new() -> Option<Self> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::new_without_display_handle());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                ..Default::default()
+            })
+            .await
+            .ok()?;
+        let (device, queue) = adapter
+            .request_device(&wgpu::DeviceDescriptor {
+                label: Some("mandelbrot"),
+                required_limits: adapter.limits(),
+                ..Default::default()
+            })
+            .await
+            .ok()?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("escape time"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("escape time"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        Some(Self { device, queue, pipeline })
+    }
+
+    // runs the shader over `rows` rows of `width` pixels; `params` is the shader's Params struct,
+    // whose row_offset says which rows
+    fn run_band(&self, params: &[u8], width: usize, rows: usize) -> Option<Vec<EscapeResult>> {
+        let size = (width * rows) as u64 * PIXEL_BYTES;
+        let uniform = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("params"),
+            contents: params,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let output = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("pixels"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: output.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(
+                (width as u32).div_ceil(WORKGROUP_SIZE),
+                (rows as u32).div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+        encoder.copy_buffer_to_buffer(&output, 0, &staging, 0, size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let (sender, receiver) = mpsc::channel();
+        staging.map_async(wgpu::MapMode::Read, .., move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::PollType::wait_indefinitely()).ok()?;
+        receiver.recv().ok()?.ok()?;
+
+        let bytes = staging.get_mapped_range(..).ok()?;
+        let pixels = bytes
+            .chunks_exact(PIXEL_BYTES as usize)
+            .map(|pixel| EscapeResult {
+                iterations: u32::from_le_bytes(pixel[0..4].try_into().unwrap()) as usize,
+                z_norm: f32::from_le_bytes(pixel[4..8].try_into().unwrap()) as f64,
+                ..EscapeResult::default()
+            })
+            .collect();
+        drop(bytes);
+        staging.unmap();
+
+        Some(pixels)
+    }
+}
+
+// a fold's flags as bits of the shader's power slot
+fn fold_flags(fold: Fold) -> u32 {
+    fold.abs_re as u32 | (fold.abs_im as u32) << 1 | (fold.abs_square_re as u32) << 2 | (fold.negate_im as u32) << 3
+}
+
+// formula selector and parameters as the shader expects them: (kind, power, julia c or the
+// phoenix's p)
+fn fractal_kind(fractal: &Fractal) -> Option<(u32, u32, (f32, f32))> {
+    match *fractal {
+        Fractal::Mandelbrot => Some((0, 2, (0.0, 0.0))),
+        Fractal::Julia(c) => Some((1, 2, (c.re as f32, c.im as f32))),
+        Fractal::BurningShip
+        | Fractal::Tricorn
+        | Fractal::Celtic
+        | Fractal::Perpendicular
+        | Fractal::Buffalo
+        | Fractal::Heart => Some((2, fold_flags(fractal.fold()?), (0.0, 0.0))),
+        Fractal::Phoenix(p) => Some((3, 2, (p.re as f32, p.im as f32))),
+        Fractal::Multibrot(power) if power.fract() == 0.0 && power <= u32::MAX as f64 => Some((4, power as u32, (0.0, 0.0))),
+        Fractal::Lambda => Some((5, 2, (0.0, 0.0))),
+        // root finding, user formulas and fractional powers have no shader
+        Fractal::Multibrot(_) | Fractal::Newton(_) | Fractal::Formula(_) => None,
+    }
+}
+
+/// GPU equivalent of `Fractal::calculate` without distance estimates; of the options, only
+/// `early_bailout` and `bailout` apply. Returns None when there is no adapter, the image
+/// doesn't fit the device limits or the fractal has no shader, so the caller can use the CPU.
+pub fn calculate(
+    fractal: &Fractal,
+    max_iters: usize,
+    (x_min, x_max, y_min, y_max): (f64, f64, f64, f64),
+    width: usize,
+    height: usize,
+    options: &ComputeOptions,
+    progress: Option<&Progress>,
+) -> Option<Vec<EscapeResult>> {
+    let (kind, power, (julia_re, julia_im)) = fractal_kind(fractal)?;
+    let gpu = context()?;
+    let limits = gpu.device.limits();
+    let max_workgroups = limits.max_compute_workgroups_per_dimension as usize * WORKGROUP_SIZE as usize;
+    if width == 0 || width > max_workgroups {
+        return None;
+    }
+
+    let max_bytes = limits.max_storage_buffer_binding_size.min(limits.max_buffer_size);
+    let band = (max_bytes / (width as u64 * PIXEL_BYTES)) as usize;
+    let band = band.min(MAX_BAND_PIXELS / width).min(max_workgroups).max(1);
+
+    let mut results = Vec::with_capacity(width * height);
+    for row_offset in (0..height).step_by(band) {
+        let rows = band.min(height - row_offset);
+
+        let mut params = Vec::with_capacity(64);
+        for value in [x_min, x_max, y_min, y_max] {
+            params.extend_from_slice(&(value as f32).to_le_bytes());
+        }
+        for value in [julia_re, julia_im] {
+            params.extend_from_slice(&value.to_le_bytes());
+        }
+        let max_iters = max_iters.min(u32::MAX as usize) as u32;
+        for value in [width as u32, height as u32, row_offset as u32, rows as u32, max_iters, kind, power] {
+            params.extend_from_slice(&value.to_le_bytes());
+        }
+        params.extend_from_slice(&(options.early_bailout as u32).to_le_bytes());
+        params.extend_from_slice(&(options.bailout as f32).to_le_bytes());
+        params.extend_from_slice(&0u32.to_le_bytes());
+
+        if progress.is_some_and(Progress::is_cancelled) {
+            results.resize(width * height, EscapeResult::skipped(max_iters as usize));
+            break;
+        }
+        results.extend(gpu.run_band(&params, width, rows)?);
+        if let Some(progress) = progress {
+            progress.advance(rows * width);
+        }
+    }
+    Some(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_cpu() {
+        // nothing to compare against on machines without an adapter
+        if !is_available() {
+            return;
+        }
+
+        let region = (-2.0, 1.0, -1.0, 1.0);
+        let options = ComputeOptions::default();
+        let gpu = calculate(&Fractal::Mandelbrot, 200, region, 64, 40, &options, None).unwrap();
+        let cpu = Fractal::Mandelbrot.calculate(200, region, 64, 40, &options, None);
+
+        // f32 rounding moves a few boundary pixels by an iteration or two
+        let mismatched = gpu
+            .iter()
+            .zip(&cpu)
+            .filter(|(a, b)| a.iterations.abs_diff(b.iterations) > 2)
+            .count();
+        assert!(mismatched <= 40, "{} of 2560 pixels differ", mismatched);
+    }
+}
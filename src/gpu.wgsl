@@ -0,0 +1,128 @@
+// Escape-time iteration of one band of rows, one invocation per pixel. Mirrors
+// Fractal::at_point_with in f32, without distance estimates.
+
+struct Params {
+    region: vec4<f32>, // x_min, x_max, y_min, y_max
+    julia_c: vec2<f32>, // or the phoenix's p
+    width: u32,
+    height: u32,
+    row_offset: u32,
+    rows: u32,
+    max_iters: u32,
+    kind: u32, // see gpu::fractal_kind
+    power: u32, // the multibrot's power, or the burning ship family's fold flags
+    early_bailout: u32,
+    bailout: f32,
+    _padding: u32,
+}
+
+struct Pixel {
+    iterations: u32,
+    z_norm: f32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read_write> pixels: array<Pixel>;
+
+// orbits coming back this close to an earlier point are caught in a cycle (squared, f32-sized)
+const PERIODICITY_TOLERANCE_SQR: f32 = 1e-12;
+
+fn cmul(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+fn iterate_once(z: vec2<f32>, c: vec2<f32>) -> vec2<f32> {
+    switch params.kind {
+        // the burning ship's family, folded as gpu::fold_flags packs it
+        case 2u: {
+            let flags = params.power;
+            let a = select(z, abs(z), vec2<bool>((flags & 1u) != 0u, (flags & 2u) != 0u));
+            let z2 = cmul(a, a);
+            let re = select(z2.x, abs(z2.x), (flags & 4u) != 0u);
+            let im = select(z2.y, -z2.y, (flags & 8u) != 0u);
+            return vec2<f32>(re, im) + c;
+        }
+        // multibrot
+        case 4u: {
+            var p = z;
+            for (var i = 1u; i < params.power; i++) {
+                p = cmul(p, z);
+            }
+            return p + c;
+        }
+        // mandelbrot, julia and lambda; the phoenix adds p·z_prev in main
+        default: {
+            return cmul(z, z) + c;
+        }
+    }
+}
+
+@compute @workgroup_size(8, 8)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= params.width || id.y >= params.rows {
+        return;
+    }
+    let index = id.y * params.width + id.x;
+    let row = params.row_offset + id.y;
+    let point = vec2<f32>(
+        params.region.x + (params.region.y - params.region.x) * (f32(id.x) / f32(params.width)),
+        params.region.z + (params.region.w - params.region.z) * (f32(row) / f32(params.height)),
+    );
+
+    var z = vec2<f32>(0.0, 0.0);
+    var previous = z;
+    var c = point;
+    if params.kind == 1u {
+        z = point;
+        c = params.julia_c;
+    }
+    // the logistic map for λ = point, as z² + c with c = λ/2 - λ²/4
+    if params.kind == 5u {
+        c = cmul(point, vec2<f32>(2.0, 0.0) - point) * 0.25;
+    }
+
+    // main cardioid and period-2 bulb
+    if params.early_bailout == 1u && params.kind == 0u {
+        let x = c.x - 0.25;
+        let q = x * x + c.y * c.y;
+        let bulb = (c.x + 1.0) * (c.x + 1.0) + c.y * c.y;
+        if q * (q + x) <= 0.25 * c.y * c.y || bulb <= 0.0625 {
+            pixels[index] = Pixel(params.max_iters, 0.0);
+            return;
+        }
+    }
+
+    var saved = z;
+    var saved_previous = previous;
+    var saved_at = 0u;
+    var interval = 1u;
+    for (var i = 0u; i < params.max_iters; i++) {
+        let norm = length(z);
+        if norm > params.bailout {
+            pixels[index] = Pixel(i, norm);
+            return;
+        }
+        var next = iterate_once(z, c);
+        if params.kind == 3u {
+            next += cmul(params.julia_c, previous);
+        }
+        previous = z;
+        z = next;
+
+        if params.early_bailout == 1u {
+            let d = z - saved;
+            // only the phoenix needs z_prev back as well to be in a cycle
+            let d_previous = select(vec2<f32>(0.0, 0.0), previous - saved_previous, params.kind == 3u);
+            if dot(d, d) + dot(d_previous, d_previous) < PERIODICITY_TOLERANCE_SQR {
+                break;
+            }
+            if i + 1u - saved_at == interval {
+                saved = z;
+                saved_previous = previous;
+                saved_at = i + 1u;
+                interval *= 2u;
+            }
+        }
+    }
+    pixels[index] = Pixel(params.max_iters, length(z));
+}
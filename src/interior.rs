@@ -0,0 +1,144 @@
+// Coloring measures for points inside the set, which escape-time data leaves flat black.
+//
+// Every measure starts by iterating the point for max_iters so the orbit settles onto its
+// attracting cycle, then looks at that cycle: the angle of the last z, the cycle's period,
+// or the interior distance estimate, which needs the period and the derivatives of f^p:
+//     d = (1 - |∂z|²) / |∂c∂z + ∂z∂z·∂c / (1 - ∂z)|
+
+use num::complex::Complex;
+use serde::{Deserialize, Serialize};
+
+use crate::fractal::{Fractal, pow};
+
+// two points of the settled orbit closer than this are the same point of the cycle
+const CYCLE_TOLERANCE: f64 = 1e-10;
+const NEWTON_STEPS: usize = 4;
+
+/// How points that never escape are colored.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum InteriorMode {
+    /// flat black
+    Black,
+    /// estimated distance to the boundary, in pixels, faded in over `falloff` pixels like the
+    /// exterior `ColoringMode::Distance`; only the mandelbrot and multibrot formulas have one
+    Distance { falloff: f64 },
+    /// angle of the final z
+    Angle,
+    /// period of the attracting cycle the orbit settles on
+    Period,
+}
+
+impl InteriorMode {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "black" | "none" => Some(InteriorMode::Black),
+            "distance" => Some(InteriorMode::Distance {
+                falloff: crate::renderer::DEFAULT_DISTANCE_FALLOFF,
+            }),
+            "angle" => Some(InteriorMode::Angle),
+            "period" => Some(InteriorMode::Period),
+            _ => None,
+        }
+    }
+}
+
+/// The `mode` measure of a point that didn't escape within `max_iters`: the angle in radians,
+/// the period (0 if no cycle was found) or the distance in complex-plane units (0 if unknown).
+pub fn interior_at_point(fractal: &Fractal, cx: f64, cy: f64, max_iters: usize, mode: InteriorMode) -> f64 {
+    let (mut z, c) = fractal.start(cx, cy);
+    for _ in 0..max_iters {
+        z = fractal.step(z, c);
+    }
+
+    match mode {
+        InteriorMode::Black => 0.0,
+        InteriorMode::Angle => z.0.arg(),
+        InteriorMode::Period => period(fractal, z, c, max_iters) as f64,
+        InteriorMode::Distance { .. } => {
+            let degree = match *fractal {
+                Fractal::Mandelbrot => 2.0,
+                Fractal::Multibrot(power) => power,
+                _ => return 0.0,
+            };
+            match period(fractal, z, c, max_iters) {
+                0 => 0.0,
+                period => interior_distance(degree, z.0, c, period),
+            }
+        }
+    }
+}
+
+// smallest p with f^p(z) = z, searching up to `max_period`; 0 if the orbit hasn't settled.
+// z comes with the z before it, and the phoenix's cycles have to bring both back.
+fn period(fractal: &Fractal, z0: (Complex<f64>, Complex<f64>), c: Complex<f64>, max_period: usize) -> usize {
+    let mut z = z0;
+    for p in 1..=max_period {
+        z = fractal.step(z, c);
+        let back = match fractal {
+            Fractal::Phoenix(_) => (z.1 - z0.1).norm() < CYCLE_TOLERANCE,
+            _ => true,
+        };
+        if back && (z.0 - z0.0).norm() < CYCLE_TOLERANCE {
+            return p;
+        }
+    }
+    0
+}
+
+// distance estimate for z^d + c, with z0 close to a point of the attracting `period`-cycle
+fn interior_distance(d: f64, z0: Complex<f64>, c: Complex<f64>, period: usize) -> f64 {
+    let one = Complex::new(1.0, 0.0);
+    let zero = Complex::new(0.0, 0.0);
+
+    // derivatives of f^p along the cycle: ∂z, ∂z∂z, ∂c, ∂c∂z
+    let derivatives = |z0: Complex<f64>| {
+        let (mut z, mut dz, mut dzdz, mut dc, mut dcdz) = (z0, one, zero, zero, zero);
+        for _ in 0..period {
+            let f1 = pow(z, d - 1.0) * d;
+            let f2 = pow(z, d - 2.0) * (d * (d - 1.0));
+            dcdz = f2 * dc * dz + f1 * dcdz;
+            dc = f1 * dc + one;
+            dzdz = f2 * dz * dz + f1 * dzdz;
+            dz = f1 * dz;
+            z = pow(z, d) + c;
+        }
+        (z, dz, dzdz, dc, dcdz)
+    };
+
+    // Newton on f^p(z) - z pulls z0 exactly onto the cycle
+    let mut z0 = z0;
+    for _ in 0..NEWTON_STEPS {
+        let (z, dz, ..) = derivatives(z0);
+        let step = (z - z0) / (dz - one);
+        if !step.is_finite() {
+            break;
+        }
+        z0 -= step;
+    }
+
+    let (_, dz, dzdz, dc, dcdz) = derivatives(z0);
+    let distance = (1.0 - dz.norm_sqr()) / (dcdz + dzdz * dc / (one - dz)).norm();
+    if distance.is_finite() { distance.max(0.0) } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_period_of_bulb_centers() {
+        let period = |cx, cy| interior_at_point(&Fractal::Mandelbrot, cx, cy, 500, InteriorMode::Period);
+        assert_eq!(period(0.0, 0.0), 1.0);
+        assert_eq!(period(-1.0, 0.0), 2.0);
+        assert_eq!(period(-0.1225, 0.7449), 3.0);
+
+        // deeper inside the cardioid is farther from the boundary
+        let mode = InteriorMode::Distance { falloff: 1.0 };
+        let distance = |cx| interior_at_point(&Fractal::Mandelbrot, cx, 0.0, 500, mode);
+        assert!(distance(-0.7) < distance(-0.5));
+        assert!(distance(-0.5) < distance(-0.2));
+        // the true distance from c = -0.5 to the cusp at -0.75 is 0.25
+        assert!((distance(-0.5) - 0.25).abs() < 0.2, "{}", distance(-0.5));
+    }
+}
@@ -0,0 +1,378 @@
+// Animation scripts: a list of keyframes, each a view to pass through, with an easing curve for
+// the way from each to the next. Written in TOML as
+//
+//     [[keyframe]]
+//     center = [-0.5, 0.0]
+//     frames = 120               # frames on the way to the next keyframe
+//     easing = "ease-in-out"
+//
+//     [[keyframe]]
+//     center = [-0.743644, 0.131826]
+//     zoom = 1e5
+//     iterations = 3000
+//     palette-offset = 0.5
+//
+// A keyframe can also set the julia constant `c` or the multibrot exponent `power`, which then
+// glide from keyframe to keyframe like the view does.
+//
+// or as the same structure in JSON, {"keyframe": [{"center": [-0.5, 0.0], ...}, ...]}.
+
+use std::io;
+use std::path::Path;
+
+use num::complex::Complex;
+use serde::Deserialize;
+
+use crate::animation::iterations_at;
+use crate::error::{MandelbrotError, Result};
+use crate::renderer::{FrameWriter, RenderData, Renderer};
+
+/// How the way from one keyframe to the next speeds up and slows down.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// stays on the keyframe and jumps to the next at the end
+    Hold,
+}
+
+impl Easing {
+    /// How far along the way to be `t` (0.0 - 1.0) of the way through its frames.
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut if t < 0.5 => 4.0 * t * t * t,
+            Easing::EaseInOut => 1.0 - (2.0 - 2.0 * t).powi(3) / 2.0,
+            Easing::Hold => 0.0,
+        }
+    }
+}
+
+/// A view the animation passes through.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Keyframe {
+    pub center: (f64, f64),
+    /// magnification relative to the default view, as for `region_around`
+    #[serde(default = "default_zoom")]
+    pub zoom: f64,
+    /// max iterations; raised with zoom from the base iterations when left out
+    pub iterations: Option<usize>,
+    /// shift of the palette, in turns of the color cycle
+    #[serde(default)]
+    pub palette_offset: f64,
+    /// julia constant, as [re, im]; kept from the keyframe before when left out
+    pub c: Option<(f64, f64)>,
+    /// multibrot exponent; kept from the keyframe before when left out
+    pub power: Option<f64>,
+    /// frames on the way to the next keyframe; unused on the last
+    #[serde(default = "default_frames")]
+    pub frames: usize,
+    /// easing on the way to the next keyframe
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+fn default_zoom() -> f64 {
+    1.0
+}
+
+fn default_frames() -> usize {
+    60
+}
+
+/// One frame of a script, interpolated between the keyframes around it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    pub center: (f64, f64),
+    pub zoom: f64,
+    pub iterations: usize,
+    pub palette_offset: f64,
+    pub c: Option<Complex<f64>>,
+    pub power: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Script {
+    #[serde(rename = "keyframe", alias = "keyframes")]
+    keyframes: Vec<Keyframe>,
+}
+
+impl Script {
+    /// At least one keyframe, with finite centers, positive zooms and powers above 1.
+    pub fn new(mut keyframes: Vec<Keyframe>) -> Result<Self> {
+        if keyframes.is_empty() {
+            return Err(MandelbrotError::invalid("animation script", "it has no keyframes"));
+        }
+        for (i, keyframe) in keyframes.iter().enumerate() {
+            let (re, im) = keyframe.center;
+            if !re.is_finite() || !im.is_finite() {
+                return Err(MandelbrotError::invalid(format_args!("keyframe {}", i + 1), "its center must be finite"));
+            }
+            if !(keyframe.zoom > 0.0 && keyframe.zoom.is_finite()) {
+                return Err(MandelbrotError::invalid(format_args!("keyframe {}", i + 1), "its zoom must be a positive number"));
+            }
+            if keyframe.power.is_some_and(|power| !(power > 1.0 && power.is_finite())) {
+                return Err(MandelbrotError::invalid(format_args!("keyframe {}", i + 1), "its power must be a number above 1"));
+            }
+        }
+        let (mut c, mut power) = (None, None);
+        for keyframe in &mut keyframes {
+            keyframe.c = keyframe.c.or(c);
+            keyframe.power = keyframe.power.or(power);
+            (c, power) = (keyframe.c, keyframe.power);
+        }
+        Ok(Self { keyframes })
+    }
+
+    /// Reads the script at `path`, as JSON if it ends in .json and TOML otherwise.
+    pub fn load(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| MandelbrotError::file(path, e))?;
+        let invalid_data = |e: String| MandelbrotError::file(path, io::Error::new(io::ErrorKind::InvalidData, e));
+        let script: Script = if Path::new(path).extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) {
+            serde_json::from_str(&content).map_err(|e| invalid_data(e.to_string()))?
+        } else {
+            toml::from_str(&content).map_err(|e| invalid_data(e.to_string()))?
+        };
+        Self::new(script.keyframes)
+    }
+
+    /// Frames from the first keyframe to the last, both included.
+    pub fn frames(&self) -> usize {
+        let (_, ways) = self.keyframes.split_last().expect("scripts have a keyframe");
+        ways.iter().map(|keyframe| keyframe.frames).sum::<usize>() + 1
+    }
+
+    /// The view of `frame`; keyframes without iterations get `base_iterations` raised for
+    /// their zoom.
+    pub fn view(&self, frame: usize, base_iterations: usize) -> View {
+        let iterations = |keyframe: &Keyframe| keyframe.iterations.unwrap_or_else(|| iterations_at(base_iterations, keyframe.zoom));
+
+        let mut frame = frame;
+        for pair in self.keyframes.windows(2) {
+            let (from, to) = (&pair[0], &pair[1]);
+            if frame >= from.frames {
+                frame -= from.frames;
+                continue;
+            }
+            let s = from.easing.apply(frame as f64 / from.frames as f64);
+            let lerp = |a: f64, b: f64| a + (b - a) * s;
+
+            let zoom = from.zoom * (to.zoom / from.zoom).powf(s);
+            // whichever end is deeper drifts to the middle at a steady pace on screen, as in
+            // ZoomAnimation, instead of flying out of the view on long zooms
+            let drift = |a: f64, b: f64| {
+                if to.zoom >= from.zoom {
+                    b + (a - b) * (1.0 - s) * from.zoom / zoom
+                } else {
+                    a + (b - a) * s * to.zoom / zoom
+                }
+            };
+            let c = match (from.c, to.c) {
+                (Some(a), Some(b)) => Some((lerp(a.0, b.0), lerp(a.1, b.1))),
+                (a, b) => a.or(b),
+            };
+            let power = match (from.power, to.power) {
+                (Some(a), Some(b)) => Some(lerp(a, b)),
+                (a, b) => a.or(b),
+            };
+            return View {
+                center: (drift(from.center.0, to.center.0), drift(from.center.1, to.center.1)),
+                zoom,
+                iterations: lerp(iterations(from) as f64, iterations(to) as f64).round() as usize,
+                palette_offset: lerp(from.palette_offset, to.palette_offset),
+                c: c.map(|(re, im)| Complex::new(re, im)),
+                power,
+            };
+        }
+
+        let last = self.keyframes.last().expect("scripts have a keyframe");
+        View {
+            center: last.center,
+            zoom: last.zoom,
+            iterations: iterations(last),
+            palette_offset: last.palette_offset,
+            c: last.c.map(|(re, im)| Complex::new(re, im)),
+            power: last.power,
+        }
+    }
+
+    /// A julia morph: `frames` frames of the view at `center` and `zoom` held still while c goes
+    /// once around `path`, ending a frame short of where it started so the video loops.
+    pub fn morph(path: JuliaPath, center: (f64, f64), zoom: f64, iterations: usize, frames: usize) -> Result<Self> {
+        let frames = frames.max(1);
+        let keyframes = (0..frames)
+            .map(|frame| {
+                let c = path.at(frame as f64 / frames as f64);
+                Keyframe {
+                    center,
+                    zoom,
+                    iterations: Some(iterations),
+                    palette_offset: 0.0,
+                    c: Some((c.re, c.im)),
+                    power: None,
+                    frames: 1,
+                    easing: Easing::Linear,
+                }
+            })
+            .collect();
+        Self::new(keyframes)
+    }
+
+    /// Whether any keyframe sets the julia constant.
+    pub fn sets_c(&self) -> bool {
+        self.keyframes.iter().any(|keyframe| keyframe.c.is_some())
+    }
+
+    /// Whether any keyframe sets the multibrot exponent.
+    pub fn sets_power(&self) -> bool {
+        self.keyframes.iter().any(|keyframe| keyframe.power.is_some())
+    }
+}
+
+/// Closed paths for the julia constant to go around.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JuliaPath {
+    /// the boundary of the main cardioid of the mandelbrot set, where julia sets change most
+    Cardioid,
+    /// a circle around 0 of the given radius
+    Circle(f64),
+}
+
+impl JuliaPath {
+    /// "cardioid", "circle" (radius 0.7885) or "circle:R".
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        let s = s.to_lowercase();
+        match s.as_str() {
+            "cardioid" => Some(JuliaPath::Cardioid),
+            "circle" => Some(JuliaPath::Circle(0.7885)),
+            _ => {
+                let radius = s.strip_prefix("circle:")?.trim().parse::<f64>().ok()?;
+                radius.is_finite().then_some(JuliaPath::Circle(radius))
+            }
+        }
+    }
+
+    /// The point `t` (0.0 - 1.0) of the way around, starting on the positive real axis.
+    pub fn at(&self, t: f64) -> Complex<f64> {
+        let angle = std::f64::consts::TAU * t;
+        match self {
+            // c = e^(iθ)/2 - e^(2iθ)/4
+            JuliaPath::Cardioid => Complex::from_polar(0.5, angle) - Complex::from_polar(0.25, 2.0 * angle),
+            JuliaPath::Circle(radius) => Complex::from_polar(*radius, angle),
+        }
+    }
+}
+
+/// Renders every frame of `script`, using `compute` to produce the escape data of a view and
+/// shifting the palette of `renderer` by each view's offset. Frames are reported as
+/// `animation::render` reports them.
+pub fn render(
+    script: &Script,
+    base_iterations: usize,
+    renderer: &Renderer,
+    writer: &mut dyn FrameWriter,
+    compute: impl Fn(&View) -> RenderData,
+) -> io::Result<()> {
+    let (repeat, offset) = renderer.color_cycle();
+    for frame in 0..script.frames() {
+        let view = script.view(frame, base_iterations);
+        let data = compute(&view);
+        let pixels = renderer.clone().with_color_cycle(repeat, offset + view.palette_offset).to_rgb_buffer(&data);
+
+        let destination = writer.write_frame(frame, data.width(), data.height(), &pixels)?;
+        log::info!(
+            "frame {}/{}: zoom {:.3e}, {} iterations -> {}",
+            frame + 1,
+            script.frames(),
+            view.zoom,
+            view.iterations,
+            destination
+        );
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_views_pass_through_the_keyframes() {
+        let script: Script = toml::from_str(
+            r#"
+            [[keyframe]]
+            center = [-0.5, 0.0]
+            iterations = 100
+            c = [-0.8, 0.0]
+            power = 2.0
+            frames = 10
+            easing = "ease-in-out"
+
+            [[keyframe]]
+            center = [-0.75, 0.1]
+            zoom = 1000.0
+            palette-offset = 1.0
+            c = [-0.6, 0.4]
+            power = 4.0
+            frames = 4
+            easing = "hold"
+
+            [[keyframe]]
+            center = [0.25, 0.0]
+            "#,
+        )
+        .unwrap();
+        let script = Script::new(script.keyframes).unwrap();
+        assert_eq!(script.frames(), 15);
+
+        let first = script.view(0, 50);
+        assert_eq!((first.center, first.zoom, first.iterations), ((-0.5, 0.0), 1.0, 100));
+
+        // halfway in time is halfway in the easing, and in zoom by ratio
+        let middle = script.view(5, 50);
+        assert!((middle.zoom - 1000f64.sqrt()).abs() < 1e-9);
+        assert!((middle.palette_offset - 0.5).abs() < 1e-12);
+        assert_eq!(middle.iterations, 113);
+        assert_eq!(middle.c, Some(Complex::new(-0.7, 0.2)));
+        assert_eq!(middle.power, Some(3.0));
+
+        // held until the last frame, which is the last keyframe
+        let held = script.view(13, 50);
+        assert_eq!((held.center, held.zoom), ((-0.75, 0.1), 1000.0));
+        let last = script.view(14, 50);
+        assert_eq!((last.center, last.zoom, last.iterations, last.c), ((0.25, 0.0), 1.0, 50, Some(Complex::new(-0.6, 0.4))));
+        assert_eq!(last.power, Some(4.0));
+
+        assert_eq!(Easing::EaseIn.apply(0.5), 0.125);
+        assert_eq!(Easing::EaseOut.apply(0.5), 0.875);
+        assert!(Script::new(Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_morph_loops_around_the_cardioid() {
+        assert_eq!(JuliaPath::from_str("circle:0.5"), Some(JuliaPath::Circle(0.5)));
+        assert_eq!(JuliaPath::from_str("Cardioid"), Some(JuliaPath::Cardioid));
+        assert_eq!(JuliaPath::from_str("circle:big"), None);
+
+        // the cusp, then the tip of the period-2 bulb's side
+        let path = JuliaPath::Cardioid;
+        assert!((path.at(0.0) - Complex::new(0.25, 0.0)).norm() < 1e-12);
+        assert!((path.at(0.5) - Complex::new(-0.75, 0.0)).norm() < 1e-12);
+
+        let script = Script::morph(path, (0.0, 0.0), 1.0, 200, 8).unwrap();
+        assert_eq!(script.frames(), 8);
+        let quarter = script.view(2, 50);
+        assert_eq!((quarter.center, quarter.zoom, quarter.iterations), ((0.0, 0.0), 1.0, 200));
+        assert!((quarter.c.unwrap() - path.at(0.25)).norm() < 1e-12);
+    }
+}
@@ -0,0 +1,275 @@
+// Layered coloring: several coloring algorithms composited from the bottom up, each blended
+// onto the ones below with a blend mode and an opacity. The layers come from a TOML file:
+//     [[layers]]
+//     source = "palette"
+//
+//     [[layers]]
+//     source = "stripes"
+//     density = 5
+//     blend = "multiply"
+//     opacity = 0.6
+//
+//     [[layers]]
+//     source = "edges"
+//     falloff = 3
+//     blend = "multiply"
+//
+// Stripes are the stripe average of the escaping orbit, the mean of ½ + ½·sin(density·arg z)
+// over its points, blended between the means without and with the last point by the smooth
+// count so it doesn't band. The escape pass doesn't keep it, so escaped points are iterated
+// again for it, like interior measures are for points in the set.
+
+use num::complex::Complex;
+use serde::{Deserialize, Serialize};
+
+use crate::colorscheme::{self, Color};
+use crate::error::MandelbrotError;
+use crate::fractal::Fractal;
+
+const DEFAULT_STRIPE_DENSITY: f64 = 5.0;
+const DEFAULT_EDGE_FALLOFF: f64 = 3.0;
+
+/// What a layer draws.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "lowercase")]
+pub enum Source {
+    /// the renderer's own coloring, as it draws without layers
+    Palette,
+    /// the stripe average of the orbit as gray, or through the palette with `palette`; points
+    /// in the set are left to the layers below
+    Stripes {
+        #[serde(default = "default_stripe_density")]
+        density: f64,
+        #[serde(default)]
+        palette: bool,
+    },
+    /// gray from black at the boundary to white about `falloff` pixels out, by distance
+    /// estimate; multiplied, it darkens the edges of the set
+    Edges {
+        #[serde(default = "default_edge_falloff")]
+        falloff: f64,
+    },
+}
+
+fn default_stripe_density() -> f64 {
+    DEFAULT_STRIPE_DENSITY
+}
+
+fn default_edge_falloff() -> f64 {
+    DEFAULT_EDGE_FALLOFF
+}
+
+/// How a layer combines with the color below it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Blend {
+    /// the layer covers what's below
+    #[default]
+    Normal,
+    /// darkens: white leaves the color below, black makes black
+    Multiply,
+    /// lightens: black leaves the color below, white makes white
+    Screen,
+    /// multiplies the dark parts of the color below and screens the light ones
+    Overlay,
+}
+
+impl Blend {
+    /// `above` blended onto `below` with this mode, then mixed with `below` by `opacity`.
+    pub fn apply(self, below: Color, above: Color, opacity: f64) -> Color {
+        let channel = |b: u8, a: u8| {
+            let (b, a) = (b as f64 / 255.0, a as f64 / 255.0);
+            let blended = match self {
+                Blend::Normal => a,
+                Blend::Multiply => b * a,
+                Blend::Screen => 1.0 - (1.0 - b) * (1.0 - a),
+                Blend::Overlay if b < 0.5 => 2.0 * b * a,
+                Blend::Overlay => 1.0 - 2.0 * (1.0 - b) * (1.0 - a),
+            };
+            ((b + (blended - b) * opacity) * 255.0).round().clamp(0.0, 255.0) as u8
+        };
+        Color::new(channel(below.r, above.r), channel(below.g, above.g), channel(below.b, above.b))
+    }
+}
+
+/// One layer: a source blended onto the layers below it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Layer {
+    #[serde(flatten)]
+    pub source: Source,
+    #[serde(default)]
+    pub blend: Blend,
+    /// 0.0 - 1.0
+    #[serde(default = "opaque")]
+    pub opacity: f64,
+}
+
+fn opaque() -> f64 {
+    1.0
+}
+
+/// Layers from the bottom up, for `Renderer::with_layers`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Layers {
+    layers: Vec<Layer>,
+}
+
+impl Layers {
+    /// Checks that there's a layer, that opacities are between 0 and 1, and that stripe
+    /// layers share one density, as the computation keeps a single stripe average per point.
+    pub fn new(layers: Vec<Layer>) -> crate::Result<Self> {
+        Self::validated(layers).map_err(|reason| MandelbrotError::invalid("layers", reason))
+    }
+
+    fn validated(layers: Vec<Layer>) -> Result<Self, String> {
+        if layers.is_empty() {
+            return Err("there has to be at least one layer".to_string());
+        }
+        let mut density = None;
+        for (i, layer) in layers.iter().enumerate() {
+            if !(0.0..=1.0).contains(&layer.opacity) {
+                return Err(format!("layer {}: its opacity must be between 0 and 1", i + 1));
+            }
+            match layer.source {
+                Source::Stripes { density: d, .. } if !(d > 0.0 && d.is_finite()) => {
+                    return Err(format!("layer {}: its density must be a positive number", i + 1));
+                }
+                Source::Stripes { density: d, .. } if density.is_some_and(|density| density != d) => {
+                    return Err(format!("layer {}: stripe layers must all have the same density", i + 1));
+                }
+                Source::Stripes { density: d, .. } => density = Some(d),
+                Source::Edges { falloff } if !(falloff > 0.0 && falloff.is_finite()) => {
+                    return Err(format!("layer {}: its falloff must be a positive number", i + 1));
+                }
+                _ => {}
+            }
+        }
+        Ok(Self { layers })
+    }
+
+    fn parse_toml(content: &str) -> Result<Self, String> {
+        let layers: Layers = toml::from_str(content).map_err(|e| e.to_string())?;
+        Self::validated(layers.layers)
+    }
+
+    pub fn from_file(path: &str) -> crate::Result<Self> {
+        let content = std::fs::read_to_string(path).map_err(|e| MandelbrotError::file(path, e))?;
+        Self::parse_toml(&content).map_err(|reason| MandelbrotError::invalid(format_args!("layers file {}", path), reason))
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Layer> {
+        self.layers.iter()
+    }
+
+    /// Density of the stripe average the computation has to keep, if any layer draws stripes;
+    /// see `ComputeOptions::stripes`.
+    pub fn stripe_density(&self) -> Option<f64> {
+        self.layers.iter().find_map(|layer| match layer.source {
+            Source::Stripes { density, .. } => Some(density),
+            _ => None,
+        })
+    }
+
+    /// Whether a layer draws from distance estimates, which `ComputeOptions::distance` turns on.
+    pub fn needs_distance(&self) -> bool {
+        self.layers.iter().any(|layer| matches!(layer.source, Source::Edges { .. }))
+    }
+}
+
+/// Stripe average, 0.0 - 1.0, of the orbit of (cx, cy), which escaped `bailout` after
+/// `iterations` steps.
+pub fn stripe_at_point(fractal: &Fractal, cx: f64, cy: f64, iterations: usize, density: f64, bailout: f64) -> f64 {
+    let stripe = |z: Complex<f64>| 0.5 + 0.5 * (density * z.arg()).sin();
+    let (mut z, c) = fractal.start(cx, cy);
+    let mut sum = 0.0;
+    for _ in 0..iterations {
+        z = fractal.step(z, c);
+        sum += stripe(z.0);
+    }
+    if iterations < 2 {
+        return sum;
+    }
+
+    let last = stripe(z.0);
+    let (mean, mean_before) = (sum / iterations as f64, (sum - last) / (iterations - 1) as f64);
+    // the smooth count runs from `iterations` to one more across the band, where the band
+    // after it starts from this mean
+    let smooth = colorscheme::smooth_iteration(iterations, z.0.norm(), bailout, fractal.degree());
+    let fraction = (smooth - iterations as f64).clamp(0.0, 1.0);
+    mean_before + (mean - mean_before) * fraction
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layers_file() {
+        let layers = Layers::parse_toml(
+            r#"
+            [[layers]]
+            source = "palette"
+
+            [[layers]]
+            source = "stripes"
+            density = 4
+            blend = "multiply"
+            opacity = 0.5
+            "#,
+        )
+        .unwrap();
+        let layers: Vec<&Layer> = layers.iter().collect();
+        assert_eq!(layers[0].source, Source::Palette);
+        assert_eq!((layers[0].blend, layers[0].opacity), (Blend::Normal, 1.0));
+        assert_eq!(layers[1].source, Source::Stripes { density: 4.0, palette: false });
+        assert_eq!((layers[1].blend, layers[1].opacity), (Blend::Multiply, 0.5));
+
+        assert!(Layers::parse_toml("layers = []").is_err());
+        assert!(Layers::parse_toml("[[layers]]\nsource = \"edges\"\nopacity = 2").is_err());
+        assert!(Layers::parse_toml("[[layers]]\nsource = \"sparkles\"").is_err());
+    }
+
+    #[test]
+    fn test_stripes_dont_band() {
+        let options = crate::ComputeOptions { bailout: 1000.0, ..Default::default() };
+        let stripe = |cx: f64| {
+            let result = Fractal::Mandelbrot.at_point_with(cx, 0.3, 100, &options);
+            (result.iterations, stripe_at_point(&Fractal::Mandelbrot, cx, 0.3, result.iterations, 5.0, 1000.0))
+        };
+        // stepping out from the set crosses from one escape count to the next several times,
+        // and the average moves smoothly across each
+        let points: Vec<(usize, f64)> = (0..=1000).map(|i| stripe(0.45 + i as f64 * 5e-4)).collect();
+        assert!(points.first().unwrap().0 > points.last().unwrap().0 + 2);
+        for pair in points.windows(2) {
+            assert!((0.0..=1.0).contains(&pair[1].1));
+            assert!((pair[1].1 - pair[0].1).abs() < 0.02, "{:?}", pair);
+        }
+
+        // the computation only keeps them for points outside the set
+        let data = crate::calculate(
+            &Fractal::Mandelbrot,
+            100,
+            (-2.0, 1.0, -1.0, 1.0),
+            30,
+            20,
+            &crate::ComputeOptions { stripes: Some(5.0), ..options },
+            None,
+        );
+        assert_eq!(data.sample(20, 10).stripe, 0.0);
+        assert!(data.sample(0, 0).stripe > 0.0);
+    }
+
+    #[test]
+    fn test_blend_modes() {
+        let (gray, white, black) = (Color::new(128, 128, 128), Color::new(255, 255, 255), Color::new(0, 0, 0));
+        assert_eq!(Blend::Multiply.apply(gray, white, 1.0), gray);
+        assert_eq!(Blend::Multiply.apply(gray, black, 1.0), black);
+        assert_eq!(Blend::Screen.apply(gray, black, 1.0), gray);
+        assert_eq!(Blend::Screen.apply(gray, white, 1.0), white);
+        // overlay keeps black and white below, and mid gray is left alone by mid gray
+        assert_eq!(Blend::Overlay.apply(black, gray, 1.0), black);
+        assert_eq!(Blend::Overlay.apply(white, gray, 1.0), white);
+        assert_eq!(Blend::Normal.apply(black, white, 0.5), Color::new(128, 128, 128));
+        assert_eq!(Blend::Multiply.apply(gray, black, 0.0), gray);
+    }
+}
@@ -0,0 +1,509 @@
+//! Escape-time fractal computation (Mandelbrot, Julia and friends), coloring and rendering.
+//!
+//! ```
+//! use mandelbrot::{ColorScheme, ComputeOptions, Fractal, Job, OutputFormat, Renderer};
+//!
+//! let job = Job {
+//!     fractal: Fractal::Mandelbrot,
+//!     max_iters: 100,
+//!     region: (-2.0, 1.0, -1.0, 1.0),
+//!     width: 60,
+//!     height: 20,
+//!     options: ComputeOptions::default(),
+//! };
+//! let data = mandelbrot::compute(&job);
+//! let renderer = Renderer::new(ColorScheme::Ocean, OutputFormat::AnsiTrueColor);
+//! let pixels = renderer.to_rgb_buffer(&data);
+//! assert_eq!(pixels.len(), 60 * 20 * 3);
+//! ```
+
+pub mod animation;
+pub mod bookmarks;
+pub mod boundary;
+pub mod cache;
+pub mod colorscheme;
+pub mod contour;
+pub mod diff;
+pub mod error;
+#[cfg(feature = "terminal")]
+pub mod explorer;
+pub mod export;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod farm;
+pub mod formula;
+pub mod fractal;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod interior;
+pub mod keyframes;
+pub mod layers;
+pub mod lighting;
+pub mod metadata;
+pub mod minibrot;
+pub mod newton;
+pub mod overlay;
+pub mod perturbation;
+pub mod presets;
+pub mod rays;
+pub mod progress;
+pub mod refine;
+pub mod renderer;
+#[cfg(feature = "script")]
+pub mod script;
+#[cfg(feature = "terminal")]
+pub mod screensaver;
+pub mod server;
+pub mod stats;
+pub mod symmetry;
+pub mod terminfo;
+pub mod tiles;
+pub mod timing;
+pub mod transform;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub use colorscheme::{Color, ColorScheme, Gradient, Interpolation};
+pub use error::{MandelbrotError, Result};
+pub use fractal::{DEFAULT_BAILOUT, DEFAULT_JULIA_C, DEFAULT_PHOENIX_P, DEFAULT_POWER, EscapeResult, Fractal, mandelbrot_at_point};
+pub use interior::InteriorMode;
+pub use layers::Layers;
+pub use lighting::Lighting;
+pub use progress::Progress;
+pub use transform::{PlaneMap, Rotation, Transform};
+pub use renderer::{ColorMap, ColoringMode, ImageFormat, OutputFormat, Pixel, RenderData, Renderer};
+
+/// Width of the complex plane shown at zoom 1, which takes in the whole Mandelbrot set.
+pub const DEFAULT_VIEW_WIDTH: f64 = 3.0;
+
+/// Region (x_min, x_max, y_min, y_max) centered on `center` at `zoom` times the default view,
+/// shaped like a `width`×`height` image whose pixels are `pixel_aspect` times taller than wide,
+/// so nothing comes out stretched.
+pub fn region_around(center: (f64, f64), zoom: f64, width: usize, height: usize, pixel_aspect: f64) -> (f64, f64, f64, f64) {
+    let (re, im) = center;
+    let half_width = DEFAULT_VIEW_WIDTH / zoom / 2.0;
+    let half_height = half_width * pixel_aspect * height as f64 / width.max(1) as f64;
+    (re - half_width, re + half_width, im - half_height, im + half_height)
+}
+
+/// The region that `ComputeOptions::rotation` turns onto `region` turned by `degrees` about
+/// its center: the same size, around the point the turn takes to the region's center. This is
+/// the region to compute a rotated view with.
+pub fn rotated_region(region: (f64, f64, f64, f64), degrees: f64) -> (f64, f64, f64, f64) {
+    let (x_min, x_max, y_min, y_max) = region;
+    let center = ((x_min + x_max) / 2.0, (y_min + y_max) / 2.0);
+    let (re, im) = Rotation::degrees(degrees).to_view(center);
+    let (dx, dy) = (re - center.0, im - center.1);
+    (x_min + dx, x_max + dx, y_min + dy, y_max + dy)
+}
+
+/// Max iterations that bring out the detail of a view at `zoom` times the default one without
+/// wasting time on it: 200 at the default view, and 300 more for every factor of 10 deeper.
+pub fn auto_iterations(zoom: f64) -> usize {
+    (200.0 + 300.0 * zoom.log10().max(0.0)).round() as usize
+}
+
+/// Where `calculate` runs the escape-time iteration.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Backend {
+    Cpu,
+    /// wgpu compute shader, in f32 precision; needs the `gpu` feature. Distance estimates and
+    /// perturbation stay on the CPU
+    Gpu,
+}
+
+impl Backend {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "cpu" => Some(Backend::Cpu),
+            "gpu" => Some(Backend::Gpu),
+            _ => None,
+        }
+    }
+
+    /// Whether the backend can run here; the GPU needs the `gpu` feature and an adapter.
+    pub fn is_available(&self) -> bool {
+        match self {
+            Backend::Cpu => true,
+            #[cfg(feature = "gpu")]
+            Backend::Gpu => gpu::is_available(),
+            #[cfg(not(feature = "gpu"))]
+            Backend::Gpu => false,
+        }
+    }
+}
+
+/// Knobs for how `calculate` computes a region.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ComputeOptions {
+    /// compute the Mandelbrot set with perturbation theory, which stays accurate at deep
+    /// zooms; other fractals ignore it
+    pub perturbation: bool,
+    /// supersampling grid per pixel along each axis, 1 disables it
+    pub samples: usize,
+    /// also estimate each point's distance to the set, for `ColoringMode::Distance`
+    pub distance: bool,
+    /// measure to compute for points in the set, for `Renderer::with_interior`
+    pub interior: InteriorMode,
+    /// density of the stripe average to compute for escaped points, for the stripe layers of
+    /// `Renderer::with_layers`; like interior measures it takes iterating them again
+    pub stripes: Option<f64>,
+    /// stop iterating points known to be in the set: the mandelbrot main cardioid and
+    /// period-2 bulb, and orbits caught in a cycle. Only worth disabling for benchmarks
+    pub early_bailout: bool,
+    /// falls back to the CPU for anything the chosen backend can't compute
+    pub backend: Backend,
+    /// fill rectangles whose whole border escapes at the same iteration instead of computing
+    /// them (Mariani–Silver); see `boundary`. Ignored with distance estimates, which differ
+    /// from pixel to pixel
+    pub boundary_trace: bool,
+    /// copy the rows of a mandelbrot view mirrored across the real axis instead of computing
+    /// them twice; see `symmetry`. Only worth disabling to check the mirrored rows
+    pub symmetry: bool,
+    /// degrees to turn every point about the origin before computing it, counterclockwise;
+    /// `rotated_region` finds the region that shows a view turned about its own center. The GPU
+    /// and mirrored rows only compute unturned views
+    pub rotation: f64,
+    /// conformal map to take every point through after turning it; see `transform`. Like
+    /// turning, it keeps views off the GPU and mirrored rows, and off perturbation too
+    pub transform: Transform,
+    /// escape radius: a point escapes once |z| passes it. Radii well above the default 2, like
+    /// 256, make smooth coloring and distance estimates more accurate, for a few more iterations
+    /// per point
+    pub bailout: f64,
+    /// keep where each point's orbit ended, which only color scripts that color points read;
+    /// renders leave it out of their escape data otherwise
+    pub final_z: bool,
+}
+
+impl Default for ComputeOptions {
+    fn default() -> Self {
+        Self {
+            perturbation: false,
+            samples: 1,
+            distance: false,
+            interior: InteriorMode::Black,
+            stripes: None,
+            early_bailout: true,
+            backend: Backend::Cpu,
+            boundary_trace: false,
+            symmetry: true,
+            rotation: 0.0,
+            transform: Transform::None,
+            bailout: fractal::DEFAULT_BAILOUT,
+            final_z: false,
+        }
+    }
+}
+
+/// Everything that decides the escape data of a view: what `compute` takes, and what a render
+/// farm sends its workers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    pub fractal: Fractal,
+    pub max_iters: usize,
+    pub region: (f64, f64, f64, f64),
+    pub width: usize,
+    pub height: usize,
+    pub options: ComputeOptions,
+}
+
+impl Job {
+    /// `calculate` for this view, with `progress`.
+    pub fn calculate(&self, progress: Option<&Progress>) -> RenderData {
+        calculate(&self.fractal, self.max_iters, self.region, self.width, self.height, &self.options, progress)
+    }
+}
+
+/// The escape data of `job`, computed on the CPU with nothing to report to, read or written,
+/// so the same job always gives the same data, whatever the machine. `options.backend` is
+/// ignored, since the GPU computes in f32.
+pub fn compute(job: &Job) -> RenderData {
+    let options = ComputeOptions {
+        backend: Backend::Cpu,
+        ..job.options
+    };
+    calculate(&job.fractal, job.max_iters, job.region, job.width, job.height, &options, None)
+}
+
+/// Computes `fractal` over `region` (x_min, x_max, y_min, y_max) at `width`×`height` pixels.
+///
+/// `progress` counts computed points, which is `samples²` per pixel when supersampling.
+pub fn calculate(
+    fractal: &Fractal,
+    max_iters: usize,
+    region: (f64, f64, f64, f64),
+    width: usize,
+    height: usize,
+    options: &ComputeOptions,
+    progress: Option<&Progress>,
+) -> RenderData {
+    let _span = timing::span("compute", || format!("{} {}×{}, {} iterations", fractal.name(), width, height, max_iters));
+    let samples = options.samples.max(1);
+    // distances are measured in output pixels, so the falloff doesn't change with zoom or samples
+    let pixel_size = (region.1 - region.0) / width.max(1) as f64;
+    let (width, height) = (width * samples, height * samples);
+    let plane = PlaneMap::new(options);
+    let (x_min, x_max, y_min, y_max) = region;
+    let point = |x: usize, y: usize| {
+        (x_min + (x_max - x_min) * (x as f64 / width as f64), y_min + (y_max - y_min) * (y as f64 / height as f64))
+    };
+
+    let mut results = match fractal {
+        Fractal::Mandelbrot if options.perturbation && options.transform == Transform::None => {
+            perturbation::calculate(max_iters, region, width, height, options, progress)
+        }
+        _ if options.backend == Backend::Gpu && !options.distance && plane.is_identity() => {
+            calculate_on_gpu(fractal, max_iters, region, width, height, options, progress)
+                .unwrap_or_else(|| fractal.calculate(max_iters, region, width, height, options, progress))
+        }
+        _ if options.boundary_trace && !options.distance => {
+            boundary::calculate(fractal, max_iters, region, width, height, options, progress)
+        }
+        _ if options.symmetry && plane.is_identity() && symmetry::mirror(fractal, region, height).is_some() => {
+            symmetry::calculate(fractal, max_iters, region, width, height, options, progress)
+                .unwrap_or_else(|| fractal.calculate(max_iters, region, width, height, options, progress))
+        }
+        _ => fractal.calculate(max_iters, region, width, height, options, progress),
+    };
+    // a transform shrinks or grows the plane from point to point, and pixels with it
+    let pixel_size_at = |i: usize| pixel_size * plane.stretch(point(i % width.max(1), i / width.max(1)));
+    if options.distance {
+        for (i, result) in results.iter_mut().enumerate() {
+            result.distance /= pixel_size_at(i);
+        }
+    }
+
+    // interior measures re-iterate the points in the set, which the escape pass only counted,
+    // and stripes the points outside it
+    if options.interior != InteriorMode::Black || options.stripes.is_some() {
+        results.par_chunks_mut(width.max(1)).enumerate().for_each(|(y, row)| {
+            if progress.is_some_and(Progress::is_cancelled) {
+                return;
+            }
+            for (x, result) in row.iter_mut().enumerate() {
+                let (cx, cy) = plane.to_plane(point(x, y));
+                fill_interior(fractal, result, cx, cy, max_iters, options.interior, pixel_size_at(y * width + x));
+                if let Some(density) = options.stripes {
+                    fill_stripe(fractal, result, cx, cy, max_iters, density, options.bailout);
+                }
+            }
+        });
+    }
+
+    if !options.final_z {
+        results.iter_mut().for_each(|result| result.z = None);
+    }
+
+    let mut data = if samples == 1 {
+        RenderData::new(width, height, results, max_iters)
+    } else {
+        RenderData::supersampled(&results, width, max_iters, samples)
+    };
+    (data.bailout, data.degree) = (options.bailout, fractal.degree());
+    data
+}
+
+#[cfg(feature = "gpu")]
+fn calculate_on_gpu(
+    fractal: &Fractal,
+    max_iters: usize,
+    region: (f64, f64, f64, f64),
+    width: usize,
+    height: usize,
+    options: &ComputeOptions,
+    progress: Option<&Progress>,
+) -> Option<Vec<EscapeResult>> {
+    gpu::calculate(fractal, max_iters, region, width, height, options, progress)
+}
+
+#[cfg(not(feature = "gpu"))]
+fn calculate_on_gpu(
+    _fractal: &Fractal,
+    _max_iters: usize,
+    _region: (f64, f64, f64, f64),
+    _width: usize,
+    _height: usize,
+    _options: &ComputeOptions,
+    _progress: Option<&Progress>,
+) -> Option<Vec<EscapeResult>> {
+    None
+}
+
+// sets `result.interior` if the point is in the set, with distances converted to pixels
+fn fill_interior(
+    fractal: &Fractal,
+    result: &mut EscapeResult,
+    cx: f64,
+    cy: f64,
+    max_iters: usize,
+    mode: InteriorMode,
+    pixel_size: f64,
+) {
+    if result.iterations < max_iters {
+        return;
+    }
+    result.interior = interior::interior_at_point(fractal, cx, cy, max_iters, mode);
+    if let InteriorMode::Distance { .. } = mode {
+        result.interior /= pixel_size;
+    }
+}
+
+// sets `result.stripe` if the point escaped; newton points converge rather than escape
+fn fill_stripe(fractal: &Fractal, result: &mut EscapeResult, cx: f64, cy: f64, max_iters: usize, density: f64, bailout: f64) {
+    if result.iterations < max_iters && result.root.is_none() {
+        result.stripe = layers::stripe_at_point(fractal, cx, cy, result.iterations, density, bailout);
+    }
+}
+
+/// Second anti-aliasing pass: supersamples, on a `samples`×`samples` grid, only the pixels
+/// whose color differs from a neighbour by more than `threshold` (0.0 - 1.0 of the largest
+/// possible RGB distance). Returns how many pixels were refined.
+///
+/// `data` must be a plain one-sample-per-pixel computation of `fractal` over `region`, turned
+/// and transformed as `plane` maps it; the new samples carry whatever the renderer's coloring
+/// and interior modes need.
+pub fn refine_edges(
+    fractal: &Fractal,
+    data: &mut RenderData,
+    region: (f64, f64, f64, f64),
+    plane: PlaneMap,
+    renderer: &Renderer,
+    samples: usize,
+    threshold: f64,
+) -> usize {
+    let (width, height) = (data.width(), data.height());
+    let colors = renderer.to_rgb_buffer(data);
+    let color = |x: usize, y: usize| {
+        let i = (y * width + x) * 3;
+        [colors[i] as f64, colors[i + 1] as f64, colors[i + 2] as f64]
+    };
+    let max_distance = threshold.clamp(0.0, 1.0) * 255.0 * 3f64.sqrt();
+
+    let mut edges = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let here = color(x, y);
+            let neighbours = [
+                (x.wrapping_sub(1), y),
+                (x + 1, y),
+                (x, y.wrapping_sub(1)),
+                (x, y + 1),
+            ];
+            let is_edge = neighbours
+                .iter()
+                .filter(|&&(nx, ny)| nx < width && ny < height)
+                .any(|&(nx, ny)| {
+                    let there = color(nx, ny);
+                    let distance = here.iter().zip(there).map(|(a, b)| (a - b) * (a - b)).sum::<f64>().sqrt();
+                    distance > max_distance
+                });
+            if is_edge {
+                edges.push((x, y));
+            }
+        }
+    }
+
+    // same sample positions as a full supersampled `calculate`
+    let (x_min, x_max, y_min, y_max) = region;
+    let (grid_width, grid_height) = ((width * samples) as f64, (height * samples) as f64);
+    let layers = renderer.layers();
+    let options = ComputeOptions {
+        distance: matches!(renderer.coloring(), ColoringMode::Distance { .. }) || layers.is_some_and(Layers::needs_distance),
+        bailout: data.bailout,
+        final_z: renderer.script_colors_points(),
+        ..ComputeOptions::default()
+    };
+    let stripes = layers.and_then(Layers::stripe_density);
+    let interior = renderer.interior();
+    let pixel_size = (x_max - x_min) / width as f64;
+    let refined: Vec<Vec<EscapeResult>> = edges
+        .par_iter()
+        .map(|&(x, y)| {
+            let mut points = Vec::with_capacity(samples * samples);
+            for sy in y * samples..(y + 1) * samples {
+                for sx in x * samples..(x + 1) * samples {
+                    let point = (
+                        x_min + (x_max - x_min) * (sx as f64 / grid_width),
+                        y_min + (y_max - y_min) * (sy as f64 / grid_height),
+                    );
+                    let (cx, cy) = plane.to_plane(point);
+                    let pixel_size = pixel_size * plane.stretch(point);
+                    let mut result = fractal.at_point_with(cx, cy, data.max_iterations, &options);
+                    result.distance /= pixel_size;
+                    if interior != InteriorMode::Black {
+                        fill_interior(fractal, &mut result, cx, cy, data.max_iterations, interior, pixel_size);
+                    }
+                    if let Some(density) = stripes {
+                        fill_stripe(fractal, &mut result, cx, cy, data.max_iterations, density, data.bailout);
+                    }
+                    if !options.final_z {
+                        result.z = None;
+                    }
+                    points.push(result);
+                }
+            }
+            points
+        })
+        .collect();
+
+    for (&(x, y), points) in edges.iter().zip(refined) {
+        data.set_subsamples(x, y, points);
+    }
+    edges.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auto_iterations_grow_with_depth() {
+        assert_eq!(auto_iterations(1.0), 200);
+        assert_eq!(auto_iterations(0.5), 200);
+        assert_eq!(auto_iterations(1e3), 1100);
+        assert_eq!(auto_iterations(1e10), 3200);
+    }
+
+    #[test]
+    fn test_rotation_turns_the_view() {
+        // a quarter turn of a view centered on the origin, on a grid where every point is exact
+        let region = (-2.0, 2.0, -2.0, 2.0);
+        let iterations = |options: &ComputeOptions| {
+            let data = calculate(&Fractal::Mandelbrot, 100, region, 32, 32, options, None);
+            data.pixels().iter().map(|pixel| pixel.iterations).collect::<Vec<_>>()
+        };
+        let straight = iterations(&ComputeOptions::default());
+        let turned = iterations(&ComputeOptions {
+            rotation: 90.0,
+            ..Default::default()
+        });
+        for y in 1..32 {
+            for x in 0..32 {
+                assert_eq!(turned[y * 32 + x], straight[x * 32 + 32 - y], "pixel {}, {}", x, y);
+            }
+        }
+
+        // turned about its own center, a region still shows that center
+        let (x_min, x_max, y_min, y_max) = rotated_region((-0.8, -0.7, 0.1, 0.15), 30.0);
+        let (re, im) = Rotation::degrees(30.0).to_plane(((x_min + x_max) / 2.0, (y_min + y_max) / 2.0));
+        assert!((re + 0.75).abs() < 1e-12 && (im - 0.125).abs() < 1e-12);
+        assert!((x_max - x_min - 0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_refine_edges_only_touches_boundary() {
+        let region = (-2.0, 1.0, -1.0, 1.0);
+        let mut data = calculate(&Fractal::Mandelbrot, 50, region, 30, 20, &ComputeOptions::default(), None);
+        let renderer = Renderer::new(ColorScheme::Grayscale, OutputFormat::Ascii);
+
+        let refined = refine_edges(&Fractal::Mandelbrot, &mut data, region, PlaneMap::new(&ComputeOptions::default()), &renderer, 2, 0.1);
+        assert!(refined > 0 && refined < 30 * 20 / 2, "refined {} pixels", refined);
+        // the far left column is flat exterior
+        assert!(data.subsamples(0, 10).is_none());
+    }
+}
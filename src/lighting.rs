@@ -0,0 +1,142 @@
+// Hillshading: the smooth iteration count read as a height field and lit like terrain, which
+// gives the exterior an embossed look.
+//
+// The height of a pixel is ln(1 + smooth count), so slopes near the set, where counts climb
+// steeply, and far out from it come out comparably steep. The normal comes from central
+// differences with the four neighbouring pixels, falling back to one side where a neighbour is
+// off the image or inside the set. Light is Lambert diffuse plus a Blinn-Phong highlight, with
+// the viewer straight above the image:
+//     shade = color · (ambient + (1 - ambient) · n·l) + white · specular · (n·h)^shininess
+
+use crate::colorscheme::Color;
+use crate::export::smooth_value;
+use crate::renderer::RenderData;
+
+pub const DEFAULT_AZIMUTH: f64 = 315.0;
+pub const DEFAULT_ELEVATION: f64 = 45.0;
+pub const DEFAULT_RELIEF: f64 = 20.0;
+
+/// A light over the smooth iteration surface of a render.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lighting {
+    /// direction the light comes from, in degrees clockwise from the top of the image
+    pub azimuth: f64,
+    /// height of the light above the image plane, in degrees
+    pub elevation: f64,
+    /// how steep the surface is; higher exaggerates the embossing
+    pub relief: f64,
+    /// light that reaches slopes facing away, as a fraction of the full light
+    pub ambient: f64,
+    /// strength of the highlight
+    pub specular: f64,
+    /// Blinn-Phong exponent; higher makes the highlight smaller and sharper
+    pub shininess: f64,
+}
+
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            azimuth: DEFAULT_AZIMUTH,
+            elevation: DEFAULT_ELEVATION,
+            relief: DEFAULT_RELIEF,
+            ambient: 0.3,
+            specular: 0.3,
+            shininess: 24.0,
+        }
+    }
+}
+
+impl Lighting {
+    /// The default light from "azimuth,elevation" in degrees, e.g. "315,45"; the elevation has to
+    /// be between 0 and 90.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        let (azimuth, elevation) = s.split_once(',')?;
+        let (azimuth, elevation): (f64, f64) = (azimuth.trim().parse().ok()?, elevation.trim().parse().ok()?);
+        (azimuth.is_finite() && (0.0..=90.0).contains(&elevation)).then_some(Self {
+            azimuth,
+            elevation,
+            ..Self::default()
+        })
+    }
+
+    pub fn with_relief(mut self, relief: f64) -> Self {
+        self.relief = relief;
+        self
+    }
+
+    /// `color` of pixel (x, y) lit by the slope of the surface there; points that don't escape
+    /// have no height and keep their color.
+    pub fn shade(&self, color: Color, data: &RenderData, x: usize, y: usize) -> Color {
+        let Some((dx, dy)) = gradient(data, x, y) else {
+            return color;
+        };
+
+        // x to the right, y down the image, z out of it toward the viewer
+        let normal = normalize([-dx * self.relief, -dy * self.relief, 1.0]);
+        let (azimuth, elevation) = (self.azimuth.to_radians(), self.elevation.to_radians());
+        let light = [
+            azimuth.sin() * elevation.cos(),
+            -azimuth.cos() * elevation.cos(),
+            elevation.sin(),
+        ];
+        let halfway = normalize([light[0], light[1], light[2] + 1.0]);
+
+        let diffuse = dot(normal, light).max(0.0);
+        let highlight = self.specular * dot(normal, halfway).max(0.0).powf(self.shininess);
+        let lit = self.ambient + (1.0 - self.ambient) * diffuse;
+        let channel = |c: u8| (c as f64 * lit + 255.0 * highlight).round().clamp(0.0, 255.0) as u8;
+        Color::new(channel(color.r), channel(color.g), channel(color.b))
+    }
+}
+
+// change in height per pixel across and down at (x, y), or None if it's inside the set
+fn gradient(data: &RenderData, x: usize, y: usize) -> Option<(f64, f64)> {
+    let (width, height) = (data.width(), data.height());
+    let height_at = |x: usize, y: usize| {
+        let result = data.sample(x, y);
+        (result.iterations < data.max_iterations && result.root.is_none()).then(|| smooth_value(&result, data).max(0.0).ln_1p())
+    };
+    let center = height_at(x, y)?;
+
+    // a central difference where both neighbours have a height, one-sided where only one does
+    let slope = |before: Option<f64>, after: Option<f64>| match (before, after) {
+        (Some(before), Some(after)) => (after - before) / 2.0,
+        (Some(before), None) => center - before,
+        (None, Some(after)) => after - center,
+        (None, None) => 0.0,
+    };
+    let dx = slope(
+        x.checked_sub(1).and_then(|x| height_at(x, y)),
+        (x + 1 < width).then(|| height_at(x + 1, y)).flatten(),
+    );
+    let dy = slope(
+        y.checked_sub(1).and_then(|y| height_at(x, y)),
+        (y + 1 < height).then(|| height_at(x, y + 1)).flatten(),
+    );
+    Some((dx, dy))
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn normalize(v: [f64; 3]) -> [f64; 3] {
+    let length = dot(v, v).sqrt();
+    [v[0] / length, v[1] / length, v[2] / length]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_light_spec() {
+        let lighting = Lighting::from_str("90, 30").unwrap();
+        assert_eq!((lighting.azimuth, lighting.elevation), (90.0, 30.0));
+        assert_eq!(lighting.relief, DEFAULT_RELIEF);
+        assert!(Lighting::from_str("90").is_none());
+        assert!(Lighting::from_str("90,120").is_none());
+        assert!(Lighting::from_str("x,30").is_none());
+    }
+}
@@ -1,16 +1,409 @@
-mod colorscheme;
-mod renderer;
+use std::fs::{self, File};
+use std::ffi::OsString;
+use std::f64::consts::TAU;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::thread;
+use std::time::Instant;
 
-use clap::Parser;
+use clap::{ArgAction, CommandFactory, Parser, Subcommand};
+use log::{Level, LevelFilter, Log, Metadata, Record, debug, error, info, warn};
 use num::complex::Complex;
-use colorscheme::{Color, ColorScheme, Gradient};
-use renderer::{OutputFormat, RenderData, Renderer};
+use serde::Serialize;
+use mandelbrot::animation::{self, FrameStream, ImageSequence, VideoEncoder, ZoomAnimation};
+use mandelbrot::bookmarks::{Bookmark, Bookmarks};
+use mandelbrot::cache::{self, Cache};
+use mandelbrot::contour;
+use mandelbrot::diff::Difference;
+use mandelbrot::explorer::Explorer;
+use mandelbrot::keyframes::{self, JuliaPath, Script};
+use mandelbrot::layers::Layers;
+use mandelbrot::lighting::{self, Lighting};
+use mandelbrot::screensaver::Screensaver;
+use mandelbrot::server::{self as tile_server, TILE_SIZE};
+use mandelbrot::stats::Stats;
+use mandelbrot::export::{self, DataFormat, DataInfo};
+use mandelbrot::farm::{self, Farm, Job};
+use mandelbrot::metadata;
+use mandelbrot::minibrot;
+use mandelbrot::formula::Formula;
+use mandelbrot::newton::Polynomial;
+use mandelbrot::overlay::Overlay;
+use mandelbrot::presets::{Preset, Presets};
+use mandelbrot::rays::{self, Angle};
+#[cfg(feature = "script")]
+use mandelbrot::script::ColorScript;
+use mandelbrot::renderer::{self, DEFAULT_DISTANCE_FALLOFF, FrameWriter, GifEncoder};
+use mandelbrot::tiles::{DEFAULT_TILE_ROWS, RowWriter, TiledRender};
+use mandelbrot::{DEFAULT_BAILOUT, DEFAULT_JULIA_C, DEFAULT_PHOENIX_P, DEFAULT_POWER, DEFAULT_VIEW_WIDTH, Backend, PlaneMap, Rotation, Transform, MandelbrotError, ColorMap, ColoringMode, ComputeOptions, ColorScheme, Gradient, Fractal, ImageFormat, InteriorMode, Interpolation, OutputFormat, Progress, RenderData, Renderer};
+
+// the file name that stands for stdout in --save and --output-raw
+const STDOUT: &str = "-";
+
+// where `say!` and `sayln!` send the program's messages
+static MESSAGES: AtomicU8 = AtomicU8::new(TO_STDOUT);
+const TO_STDOUT: u8 = 0;
+// while stdout carries the image
+const TO_STDERR: u8 = 1;
+// under --json, which prints nothing but its record
+const SILENT: u8 = 2;
+
+// print! and println! for everything but the image itself
+macro_rules! say {
+    ($($arg:tt)*) => {
+        match MESSAGES.load(Ordering::Relaxed) {
+            TO_STDOUT => print!($($arg)*),
+            TO_STDERR => eprint!($($arg)*),
+            _ => {}
+        }
+    };
+}
+
+macro_rules! sayln {
+    ($($arg:tt)*) => {
+        match MESSAGES.load(Ordering::Relaxed) {
+            TO_STDOUT => println!($($arg)*),
+            TO_STDERR => eprintln!($($arg)*),
+            _ => {}
+        }
+    };
+}
+
+// set by the first Ctrl-C during a render that can stop partway and still save what it has;
+// any other Ctrl-C exits on the spot
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static CANCELLABLE: AtomicBool = AtomicBool::new(false);
+// how a shell reports a process stopped by SIGINT
+const INTERRUPTED_STATUS: i32 = 130;
+// what `diff` exits with when the renders differ, as diff(1) does
+const DIFFERENT_STATUS: i32 = 1;
+
+// prints this crate's log records: info as the program's messages, where `sayln!` puts them,
+// warnings and errors on stderr, and the debug and trace records of -v and -vv on stderr too
+struct Logger;
+
+static LOGGER: Logger = Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // dependencies such as wgpu log plenty of their own
+        metadata.level() <= log::max_level() && metadata.target().starts_with("mandelbrot")
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            Level::Error => eprintln!("error: {}", record.args()),
+            Level::Warn => eprintln!("Warning: {}", record.args()),
+            Level::Info => sayln!("{}", record.args()),
+            Level::Debug => eprintln!("debug: {}", record.args()),
+            Level::Trace => eprintln!("trace: {}", record.args()),
+        }
+    }
+
+    fn flush(&self) {}
+}
 
 #[derive(Parser)]
 #[command(name = "Mandelbrot Renderer")]
 #[command(author = "rodrigo s")]
 #[command(version = "0.1")]
+#[command(args_override_self = true, args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// without a subcommand the flags are those of `render`
+    #[command(flatten)]
+    args: Args,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// render to the terminal or an image file (the default without a subcommand)
+    #[command(args_override_self = true)]
+    Render(Args),
+
+    /// explore interactively: arrows pan, +/- zoom, c cycles colors, [/] change iterations
+    #[command(args_override_self = true)]
+    Explore(Args),
+
+    /// render a zoom animation, saved as numbered images or a single gif (see --save)
+    #[command(args_override_self = true)]
+    Animate {
+        #[command(flatten)]
+        animation: AnimateArgs,
+
+        #[command(flatten)]
+        args: Args,
+    },
+
+    /// dive into random spots near the boundary one after another, until a key is pressed
+    #[command(args_override_self = true)]
+    Screensaver {
+        #[command(flatten)]
+        screensaver: ScreensaverArgs,
+
+        #[command(flatten)]
+        args: Args,
+    },
+
+    /// render the view's center and everything around it down to --depth times deeper as one
+    /// log-polar strip (--transform exponential), which tools can unroll into an endless zoom
+    #[command(args_override_self = true)]
+    Strip {
+        #[command(flatten)]
+        strip: StripArgs,
+
+        #[command(flatten)]
+        args: Args,
+    },
+
+    /// time repeated computations of a view, without drawing it
+    #[command(args_override_self = true)]
+    Bench {
+        /// number of timed runs
+        #[arg(long, default_value_t = 5)]
+        runs: usize,
+
+        #[command(flatten)]
+        args: Args,
+    },
+
+    /// list or preview color schemes
+    #[command(subcommand)]
+    Palette(PaletteCommand),
+
+    /// show the settings saved in a PNG by render, or render it again from them
+    Info {
+        /// PNG file saved with --save
+        file: String,
+
+        /// render the image again from its settings
+        #[arg(long)]
+        render: bool,
+
+        /// flags to change for --render, after a --, e.g. -- --fit --save copy.png
+        #[arg(last = true)]
+        overrides: Vec<String>,
+    },
+
+    /// serve map tiles of the set at http://HOST:PORT/{z}/{x}/{y}.png, with a map of them at /
+    #[command(args_override_self = true)]
+    Serve {
+        #[command(flatten)]
+        server: ServeArgs,
+
+        #[command(flatten)]
+        args: Args,
+    },
+
+    /// compute bands of views for renders run with --workers on other machines, until killed
+    ServeWorker {
+        /// address to listen on
+        #[arg(long, default_value = "0.0.0.0:7878")]
+        listen: String,
+
+        /// number of threads to compute with (default: all cores)
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+
+    /// list the presets, with what they show, and saved bookmarks, or preview them
+    #[command(subcommand)]
+    Presets(PresetsCommand),
+
+    /// find the exact center and size of the minibrot of a period near a point, with Newton's
+    /// method, and the view that frames it
+    LocateMinibrot {
+        /// approximate center, as "re,im"
+        #[arg(allow_hyphen_values = true)]
+        center: String,
+
+        /// period of the minibrot: the iterations its center takes to come back to 0
+        #[arg(long)]
+        period: usize,
+
+        /// save the view that frames it as a bookmark of this name
+        #[arg(long)]
+        bookmark: Option<String>,
+    },
+
+    /// compare two renders of a view pixel by pixel and draw a heat map of where they differ,
+    /// e.g. to check that --perturbation or --boundary-trace leave a view as it was; exits with
+    /// status 1 if any pixel differs by more than --tolerance
+    Diff {
+        /// data saved with --export-data, or settings to render it from: a --config TOML file
+        /// or a PNG saved by render
+        a: String,
+
+        /// what to compare it with, as for the first
+        b: String,
+
+        /// iterations, of the smooth count, that a pixel can be off by and still count as the same
+        #[arg(long, default_value_t = 0.0)]
+        tolerance: f64,
+
+        /// color scheme of the heat map, from no difference to the largest
+        #[arg(long, default_value = "inferno")]
+        color: String,
+
+        /// save the heat map, at the size of the renders, to an image file
+        #[arg(short, long)]
+        save: Option<String>,
+    },
+
+    /// list the available fractals
+    #[command(subcommand)]
+    Fractals(ListCommand),
+}
+
+#[derive(Subcommand)]
+enum PaletteCommand {
+    /// list the built-in color schemes
+    List,
+
+    /// draw a color scheme, or a gradient or palette file, across the terminal
+    Show {
+        /// scheme name, e.g. viridis-r, or the path of a .toml gradient or palette file
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum PresetsCommand {
+    /// list the presets, with what they show, and saved bookmarks
+    List,
+
+    /// draw a thumbnail of every preset, in rows across the terminal
+    Preview {
+        /// thumbnail width, in terminal columns
+        #[arg(long, default_value_t = 24)]
+        size: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum ListCommand {
+    List,
+}
+
+#[derive(clap::Args)]
+struct AnimateArgs {
+    /// play a script of keyframes (TOML, or JSON if it ends in .json) instead of a straight
+    /// zoom, which --frames, --target and --final-zoom then don't apply to
+    #[arg(long)]
+    script: Option<String>,
+
+    /// hold the view still and move the julia constant once around a path, looping: cardioid
+    /// (the main cardioid's boundary), circle, or circle:R for radius R
+    #[arg(long, conflicts_with = "script")]
+    morph: Option<String>,
+
+    /// number of frames
+    #[arg(long, default_value_t = 100)]
+    frames: usize,
+
+    /// point to zoom into, as "re,im" (defaults to the region center)
+    #[arg(long, allow_hyphen_values = true)]
+    target: Option<String>,
+
+    /// zoom factor of the last frame relative to the first
+    #[arg(long, default_value_t = 1000.0)]
+    final_zoom: f64,
+
+    /// delay between frames in milliseconds (gif output)
+    #[arg(long, default_value_t = 100)]
+    frame_delay: u32,
+
+    /// frames per second of a video (--save with .mp4, .mkv, .mov or .webm, encoded by ffmpeg)
+    #[arg(long, default_value_t = 30.0)]
+    fps: f64,
+
+    /// ffmpeg video codec, e.g. libx265 (defaults to libx264, or libvpx-vp9 for webm)
+    #[arg(long)]
+    codec: Option<String>,
+
+    /// video quality, lower is better: 0 is lossless, 23 is the usual default
+    #[arg(long, default_value_t = 23, value_parser = clap::value_parser!(u32).range(0..=63))]
+    crf: u32,
+
+    /// how many times a gif animation plays, 0 loops forever
+    #[arg(long, default_value_t = 0)]
+    loop_count: u16,
+
+    /// gif palette quantization speed, from 1 (best colors) to 30 (fastest)
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(i32).range(1..=30))]
+    gif_speed: i32,
+}
+
+#[derive(clap::Args)]
+struct StripArgs {
+    /// how many times smaller the innermost circle is than the outermost, which sets the
+    /// strip's width for its height (overrides --width)
+    #[arg(long, default_value_t = 1e6)]
+    depth: f64,
+}
+
+#[derive(clap::Args)]
+struct ScreensaverArgs {
+    /// length of each dive in seconds
+    #[arg(long, default_value_t = 20.0)]
+    seconds: f64,
+
+    /// zoom factor reached at the end of each dive
+    #[arg(long, default_value_t = 1e4)]
+    final_zoom: f64,
+}
+
+#[derive(clap::Args)]
+struct ServeArgs {
+    /// port to serve tiles on
+    #[arg(long, default_value_t = 8080)]
+    port: u16,
+
+    /// address to serve on; 0.0.0.0 lets other machines in
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    /// tiles kept in memory once made
+    #[arg(long, default_value_t = 4096)]
+    tile_cache: usize,
+}
+
+// what the subcommands that render a view go on to do with it
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Render,
+    Explore,
+    Animate,
+    Screensaver,
+    Bench,
+    Serve,
+}
+
+#[derive(clap::Args, Serialize)]
 struct Args {
+    /// load settings from a TOML file of flag names and values, e.g. `width = 800`, instead of
+    /// ~/.config/mandelbrot/config.toml; MANDELBROT_ environment variables such as
+    /// MANDELBROT_COLOR=ocean win over it, and flags given on the command line win over both
+    #[arg(long)]
+    #[serde(skip)]
+    config: Option<String>,
+
+    /// print the current settings as a --config file and exit
+    #[arg(long)]
+    #[serde(skip)]
+    dump_config: bool,
+
+    /// warn about invalid settings and carry on with a default instead of stopping
+    #[arg(long)]
+    lenient: bool,
+
     /// width in pixels
     #[arg(short, long, default_value_t = 150)]
     width: usize,
@@ -19,26 +412,151 @@ struct Args {
     #[arg(short = 'H', long, default_value_t = 50)]
     height: usize,
 
-    /// max iterations
-    #[arg(short, long, default_value_t = 1000)]
+    /// size the render to fill the terminal, below the header (overrides --width and --height)
+    #[arg(long)]
+    fit: bool,
+
+    /// max iterations, or "auto" to pick them from the zoom: more the deeper the view
+    #[arg(short = 'i', long = "iterations", default_value = "1000")]
+    #[serde(rename = "iterations")]
+    max_iterations: String,
+
+    /// escape radius, 2 or more; a large one like 256 makes smooth coloring and distance
+    /// estimates more accurate
+    #[arg(long, default_value_t = DEFAULT_BAILOUT)]
+    bailout: f64,
+
+    // the count --iterations comes to, once the view is known
+    #[arg(skip)]
+    #[serde(skip)]
     iterations: usize,
 
-    /// color scheme: classic, ocean, fire, psychedelic, forest, sunset, grayscale
+    /// color scheme: classic, ocean, fire, psychedelic, forest, sunset, grayscale, viridis,
+    /// magma, inferno, turbo; add "-r" to reverse one, e.g. viridis-r
     #[arg(short, long, default_value = "classic")]
     color: String,
 
-    /// output format: auto, ascii, extended, ansi256, truecolor
+    /// times the palette repeats over the iteration range, to bring out detail in deep zooms
+    #[arg(long, default_value_t = 1.0)]
+    color_repeat: f64,
+
+    /// shift of the palette, as a fraction of it (0.0 - 1.0)
+    #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    color_offset: f64,
+
+    /// custom gradient, e.g. "0.0:#000000,0.5:#ff00ff,1.0:#ffffff" (overrides --color)
+    #[arg(long)]
+    gradient: Option<String>,
+
+    /// load a custom gradient from a TOML file with a `stops` list (overrides --color)
+    #[arg(long)]
+    gradient_file: Option<String>,
+
+    /// color space to blend gradient stops in: rgb, hsv, hsl, oklab or lch
+    #[arg(long)]
+    interpolation: Option<String>,
+
+    /// load a palette from a GIMP .gpl, Fractint .map or hex color list file (overrides --color)
+    #[arg(long)]
+    palette_file: Option<String>,
+
+    /// color with a Rhai script defining fn color(t) as the palette, or fn color(t, iterations,
+    /// in_set, orbit) to color each point itself (overrides --color)
+    #[arg(long)]
+    color_script: Option<String>,
+
+    /// output format: auto (from the terminal, ascii when NO_COLOR is set), ascii, extended, ansi256,
+    /// truecolor, halfblock, halfblock256, shade, shade256, braille, or an inline image: kitty, iterm2, or image to pick whichever the terminal supports
     #[arg(short, long, default_value = "auto")]
     format: String,
 
-    /// save to PPM file
+    /// save to an image file (PPM, PNG or GIF, chosen by extension)
     #[arg(short, long)]
     save: Option<String>,
 
-    /// coordinate preset: default, seahorse, spiral, elephant, julia
+    /// image format for --save: ppm, png, gif, raw (overrides the file extension)
+    #[arg(long)]
+    image_format: Option<String>,
+
+    /// write nothing but the image to FILE, "-" for stdout (which sends every message to stderr), as raw RGB bytes unless --image-format picks another format; for piping into ImageMagick or ffmpeg
+    #[arg(long, value_name = "FILE")]
+    output_raw: Option<String>,
+
+    /// dump the raw iteration counts and smooth values to a .npy, .csv, .json, .exr (float) or .png (16-bit gray) file, or all the escape data to a .bin file
+    #[arg(long)]
+    export_data: Option<String>,
+
+    /// trace iso-iteration contours into an SVG file (line art of the set boundary by default)
+    #[arg(long)]
+    contours: Option<String>,
+
+    /// iteration counts to trace with --contours, e.g. "5,10,20" (defaults to the set boundary)
+    #[arg(long)]
+    contour_levels: Option<String>,
+
+    /// re-color data saved with --export-data instead of computing anything
+    #[arg(long)]
+    import_data: Option<String>,
+
+    /// fractal: mandelbrot, julia, burning-ship, tricorn, multibrot, celtic, perpendicular, buffalo, heart, phoenix, lambda, newton
+    #[arg(long, default_value = "mandelbrot")]
+    fractal: String,
+
+    /// julia set constant c, e.g. "-0.8+0.156i"
+    #[arg(long, default_value_t = DEFAULT_JULIA_C.to_string(), allow_hyphen_values = true)]
+    julia_c: String,
+
+    /// exponent d for the multibrot fractal (z^d + c), any number above 1, e.g. 2.5; scripts
+    /// for animate can change it from keyframe to keyframe
+    #[arg(long, default_value_t = DEFAULT_POWER)]
+    power: f64,
+
+    /// weight p of the previous z in the phoenix fractal (z² + c + p·z_prev), e.g. "-0.5+0.1i"
+    #[arg(long, default_value_t = DEFAULT_PHOENIX_P.to_string(), allow_hyphen_values = true)]
+    phoenix_p: String,
+
+    /// iterate a formula in z and c instead of --fractal, e.g. "z^2 + c*z + 0.1" or "sin(z)*c";
+    /// supports + - * / ^, conj, abs, sin, cos, exp, log and sqrt
+    #[arg(long, allow_hyphen_values = true)]
+    formula: Option<String>,
+
+    /// polynomial whose roots the newton fractal finds, e.g. "z^3-1" or "z^4+(1+i)z-2"
+    #[arg(long, default_value = "z^3-1", allow_hyphen_values = true)]
+    polynomial: String,
+
+    /// view to start from: a preset such as seahorse, spiral or minibrot (see `presets list`), or
+    /// a saved bookmark; its suggested iterations and colors apply unless others are given
     #[arg(short, long)]
     preset: Option<String>,
 
+    /// save the current view, iterations and color scheme as a bookmark for --preset
+    #[arg(long)]
+    #[serde(skip)]
+    bookmark_add: Option<String>,
+
+    /// center of the view as "re,im"; the region's height follows the output's aspect ratio
+    #[arg(long, allow_hyphen_values = true)]
+    center: Option<String>,
+
+    /// magnification relative to the default 3-wide view, e.g. 1e9 (with --center)
+    #[arg(long)]
+    zoom: Option<f64>,
+
+    /// width of the view in the complex plane, instead of --zoom
+    #[arg(long, conflicts_with = "zoom")]
+    scale: Option<f64>,
+
+    /// turn the view counterclockwise about its center by this many degrees, e.g. 45 to frame
+    /// a spiral diagonally
+    #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+    rotate: f64,
+
+    /// map the view through a conformal transform before computing it: none, inversion (1/z,
+    /// the set turned inside out) or exponential (log-polar about the view's center, with the
+    /// log of the radius across and the angle down, as infinite zoom videos are made from)
+    #[arg(long, default_value = "none")]
+    transform: String,
+
     /// min X coordinate
     #[arg(long)]
     xmin: Option<f64>,
@@ -55,170 +573,2316 @@ struct Args {
     #[arg(long)]
     ymax: Option<f64>,
 
-    /// list available color schemes
+    /// disable smooth coloring (same as --coloring linear)
     #[arg(long)]
-    list_colors: bool,
+    no_smooth: bool,
 
-    /// disable smooth coloring
+    /// turn off the ordered dithering of the 256-color and shade formats
     #[arg(long)]
-    no_smooth: bool,
-}
-struct MandelbrotResult {
-    iterations: usize, 
-    z_norm: f64,
+    no_dither: bool,
+
+    /// also dither the characters of the ascii formats, which flat areas otherwise keep to one
+    #[arg(long, conflicts_with = "no_dither")]
+    dither: bool,
+
+    /// coloring: smooth, linear, histogram, distance
+    #[arg(long, default_value = "smooth")]
+    coloring: String,
+
+    /// iteration to palette mapping for linear and smooth coloring: linear, log, sqrt or
+    /// power:<gamma>, e.g. power:0.5
+    #[arg(long, default_value = "linear")]
+    color_map: String,
+
+    /// interior coloring: black, distance, angle, period
+    #[arg(long, default_value = "black")]
+    interior: String,
+
+    /// pixels over which distance coloring fades from the boundary into the palette
+    #[arg(long, default_value_t = DEFAULT_DISTANCE_FALLOFF)]
+    distance_falloff: f64,
+
+    /// composite coloring layers from a TOML file of `[[layers]]`, each a source (palette,
+    /// stripes or edges) with a blend mode (normal, multiply, screen, overlay) and an opacity
+    #[arg(long)]
+    layers: Option<String>,
+
+    /// light the exterior as if the smooth iteration count were terrain, for an embossed look;
+    /// a --bailout of 100 or more hides the banding the default one leaves in the slopes
+    #[arg(long)]
+    hillshade: bool,
+
+    /// direction of the --hillshade light as "azimuth,elevation" in degrees, the azimuth
+    /// clockwise from the top of the image
+    #[arg(long, default_value = "315,45")]
+    light: String,
+
+    /// steepness of the --hillshade surface; higher exaggerates the embossing
+    #[arg(long, default_value_t = lighting::DEFAULT_RELIEF)]
+    relief: f64,
+
+    /// number of worker threads (defaults to all cores)
+    #[arg(short, long)]
+    threads: Option<usize>,
+
+    /// use perturbation with a high-precision reference orbit (mandelbrot only, for deep zooms)
+    #[arg(long)]
+    perturbation: bool,
+
+    /// iterate every point in the set to the limit: no cardioid/bulb or periodicity checks (for benchmarking)
+    #[arg(long)]
+    no_early_bailout: bool,
+
+    /// skip computing rectangles whose border escapes at one iteration (mariani-silver); smooth shading inside them comes out flat
+    #[arg(long)]
+    boundary_trace: bool,
+
+    /// compute mirrored rows of a mandelbrot view centered on the real axis instead of copying them (for verification)
+    #[arg(long)]
+    no_symmetry: bool,
+
+    /// compute backend: cpu, gpu (needs the gpu feature; falls back to cpu without an adapter)
+    #[arg(long, default_value = "cpu")]
+    backend: String,
+
+    /// hide the progress bar and status messages, leaving warnings and errors
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// log more: -v for how long computing, coloring and encoding take, -vv for every band and tile
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// supersample each pixel on an N×N grid and average the colors (anti-aliasing)
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..=16))]
+    samples: u16,
+
+    /// only supersample pixels that differ sharply from their neighbours (needs --samples > 1)
+    #[arg(long)]
+    adaptive: bool,
+
+    /// neighbour color difference (0.0 - 1.0) that triggers --adaptive supersampling
+    #[arg(long, default_value_t = 0.1)]
+    aa_threshold: f64,
+
+    /// render straight into the --save file tile by tile, holding one tile in memory (no terminal preview)
+    #[arg(long)]
+    tiled: bool,
+
+    /// rows per tile for --tiled
+    #[arg(long, default_value_t = DEFAULT_TILE_ROWS)]
+    tile_rows: usize,
+
+    /// keep finished tiles of a --tiled render in <save>.checkpoint so it can be resumed
+    #[arg(long)]
+    checkpoint: bool,
+
+    /// continue an interrupted --checkpoint render with the same settings (implies --tiled --checkpoint)
+    #[arg(long)]
+    resume: bool,
+
+    /// print rows to the terminal as they are computed instead of after the whole image
+    #[arg(long)]
+    stream: bool,
+
+    /// draw the orbit of a point over the render, e.g. "-0.75+0.1i" (its z0 for julia)
+    #[arg(long, allow_hyphen_values = true)]
+    orbit: Option<String>,
+
+    /// show the mandelbrot view on the left and the julia set of --julia-c on the right (in
+    /// explore, of the view's center, following it as it pans; j toggles it)
+    #[arg(long)]
+    split: bool,
+
+    /// draw the real and imaginary axes, with coordinates along them
+    #[arg(long)]
+    axes: bool,
+
+    /// draw a grid of round coordinates, with the coordinates along the axes or edges
+    #[arg(long)]
+    grid: bool,
+
+    /// draw a bar of round length in the bottom right corner, labeled with its length
+    #[arg(long)]
+    scale_bar: bool,
+
+    /// draw the external rays of these angles, in turns, e.g. "1/3,2/3,0.25", labeled where
+    /// they come into view (mandelbrot and julia only)
+    #[arg(long)]
+    rays: Option<String>,
+
+    /// draw this many equipotentials: the first where |Φ| = 2, far out around the set, and
+    /// each after it at half the potential of the one before (mandelbrot and julia only)
+    #[arg(long, default_value_t = 0)]
+    equipotentials: usize,
+
+    /// zoom N times toward the most detailed part of the view, drawing every step (and saving
+    /// each as a numbered image with --save)
+    #[arg(long, value_name = "N")]
+    autozoom: Option<usize>,
+
+    /// print BLAKE3 checksums of the escape data and the colored pixels, to check that two
+    /// renders are bit-identical
+    #[arg(long)]
+    checksum: bool,
+
+    /// print a summary of the escape data after rendering: share in the set, escape iterations
+    /// and their histogram, and the iteration work done
+    #[arg(long)]
+    stats: bool,
+
+    /// print a JSON record of the run when it's done (settings, view, timing, files written and
+    /// statistics) instead of the banner and the render in the terminal
+    #[arg(long)]
+    json: bool,
+
+    /// keep computed views in ~/.cache/mandelbrot and load them instead of computing them again,
+    /// e.g. to re-color a render or re-run an animation
+    #[arg(long)]
+    cache: bool,
+
+    /// directory for --cache (implies it)
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<String>,
+
+    /// most megabytes --cache keeps, deleting the views used least recently past it
+    #[arg(long, value_name = "MB", default_value_t = cache::DEFAULT_LIMIT >> 20)]
+    cache_limit: u64,
+
+    /// compute everything, even when a config file turns --cache on
+    #[arg(long)]
+    no_cache: bool,
+
+    /// delete every view in the cache directory (--cache-dir, or ~/.cache/mandelbrot) first
+    #[arg(long)]
+    clear_cache: bool,
+
+    /// share the computing with `mandelbrot serve-worker` processes at these addresses, e.g.
+    /// host1,host2:7000 (port 7878 by default); bands a worker can't take are computed here
+    #[arg(long, value_name = "ADDRESSES")]
+    workers: Option<String>,
 }
 
-fn get_preset_coords(preset: &str) -> Option<(f64, f64, f64, f64)> {
-    match preset.to_lowercase().as_str() {
-        "default" => Some((-2.0, 1.0, -1.0, 1.0)),
-        "seahorse" => Some((-0.75, -0.735, 0.095, 0.11)),
-        "spiral" => Some((-0.7269, -0.7266, 0.1889, 0.1892)),
-        "elephant" => Some((0.275, 0.285, 0.005, 0.015)),
-        "julia" => Some((-1.5, 1.5, -1.5, 1.5)),
-        _ => None,
+impl Cli {
+    // the view settings of the subcommands that render one
+    fn args(&self) -> Option<&Args> {
+        match &self.command {
+            None => Some(&self.args),
+            Some(Command::Render(args) | Command::Explore(args)) => Some(args),
+            Some(
+                Command::Animate { args, .. }
+                | Command::Screensaver { args, .. }
+                | Command::Strip { args, .. }
+                | Command::Bench { args, .. }
+                | Command::Serve { args, .. },
+            ) => Some(args),
+            Some(
+                Command::Info { .. }
+                | Command::ServeWorker { .. }
+                | Command::Palette(_)
+                | Command::Presets(_)
+                | Command::LocateMinibrot { .. }
+                | Command::Diff { .. }
+                | Command::Fractals(_),
+            ) => None,
+        }
     }
 }
+// parses the command line, with the environment's settings, the config file's before those,
+// and then a --preset's suggested iterations and colors placed in front of it
+fn parse_args(presets: &Presets, bookmarks: &Bookmarks) -> Cli {
+    let mut cli = Cli::parse();
+    let mut settings = Vec::new();
+    if let Some(args) = cli.args() {
+        settings = env_settings().unwrap_or_else(|e| recover(args.lenient, e, "ignoring the environment", Vec::new()));
+        if !settings.is_empty() {
+            cli = parse_with(&settings);
+        }
+    }
+    // MANDELBROT_CONFIG names the file as well as --config does; the default one may not exist
+    let config = cli.args().and_then(|args| match &args.config {
+        Some(path) => Some((PathBuf::from(path), args.lenient)),
+        None => default_config_path().filter(|path| path.is_file()).map(|path| (path, args.lenient)),
+    });
+    if let Some((path, lenient)) = config {
+        match load_config(&path) {
+            Ok(mut config) => {
+                config.append(&mut settings);
+                settings = config;
+                cli = parse_with(&settings);
+            }
+            Err(e) => recover(lenient, e, "ignoring it", ()),
+        }
+    }
 
-fn calculate_mandelbrot(
-    max_iters: usize, 
-    x_min: f64, 
-    x_max: f64, 
-    y_min: f64, 
-    y_max: f64, 
-    width: usize, 
-    height: usize
-) -> (Vec<Vec<usize>>, Vec<Vec<f64>>) {
-    let mut iterations: Vec<Vec<usize>> = Vec::with_capacity(height);
-    let mut z_norms: Vec<Vec<f64>> = Vec::with_capacity(height);
+    let suggested = match cli.args().and_then(|args| args.preset.as_deref()) {
+        Some(name) => match (presets.get(name), bookmarks.get(name)) {
+            (Some(preset), _) => {
+                let iterations = preset.iterations.map(|iterations| format!("--iterations={}", iterations));
+                let color = preset.color.as_ref().map(|color| format!("--color={}", color));
+                let fractal = preset.fractal.as_ref().map(|fractal| format!("--fractal={}", fractal));
+                iterations.into_iter().chain(color).chain(fractal).collect()
+            }
+            (None, Some(bookmark)) => vec![format!("--iterations={}", bookmark.iterations), format!("--color={}", bookmark.color)],
+            (None, None) => Vec::new(),
+        },
+        None => Vec::new(),
+    };
+    if !suggested.is_empty() {
+        cli = parse_with(&[suggested, settings].concat());
+    }
+    cli
+}
 
-    for img_y in 0..height {
-        let mut iter_row: Vec<usize> = Vec::with_capacity(width);
-        let mut norm_row: Vec<f64> = Vec::with_capacity(width);
+// the settings go in front of the command line's flags, after the subcommand that takes them
+fn parse_with(settings: &[String]) -> Cli {
+    let mut command_line: Vec<OsString> = std::env::args_os().collect();
+    let subcommand = command_line.get(1).and_then(|arg| arg.to_str()).and_then(|name| Cli::command().find_subcommand(name).cloned());
+    let at = if subcommand.is_some() { 2 } else { 1 }.min(command_line.len());
+    command_line.splice(at..at, settings.iter().map(Into::into));
+    Cli::parse_from(command_line)
+}
 
-        for img_x in 0..width {
-            let x_percent = img_x as f64 / width as f64;
-            let y_percent = img_y as f64 / height as f64;
-            let cx = x_min + (x_max - x_min) * x_percent;
-            let cy = y_min + (y_max - y_min) * y_percent;
+fn load_presets() -> Presets {
+    let Some(path) = Presets::default_path() else {
+        return Presets::built_in();
+    };
+    Presets::load(&path).unwrap_or_else(|e| {
+        warn!("could not read presets: {}", e);
+        Presets::built_in()
+    })
+}
 
-            let result = mandelbrot_at_point(cx, cy, max_iters);
-            iter_row.push(result.iterations);
-            norm_row.push(result.z_norm);
-        }
+fn load_bookmarks() -> Bookmarks {
+    let Some(path) = Bookmarks::default_path() else {
+        return Bookmarks::default();
+    };
+    Bookmarks::load(&path).unwrap_or_else(|e| {
+        warn!("could not read bookmarks: {}", e);
+        Bookmarks::default()
+    })
+}
 
-        iterations.push(iter_row);
-        z_norms.push(norm_row);
+fn add_bookmark(presets: &Presets, mut bookmarks: Bookmarks, name: &str, bookmark: Bookmark) -> mandelbrot::Result<()> {
+    if presets.get(name).is_some() {
+        return Err(MandelbrotError::invalid(format_args!("bookmark name '{}'", name), "it is a preset"));
     }
+    let path = Bookmarks::default_path().ok_or_else(|| io::Error::other("no home directory to keep bookmarks in"))?;
+    bookmarks.insert(name, bookmark);
+    bookmarks.save(&path)
+}
 
-    (iterations, z_norms)
+// `$XDG_CONFIG_HOME/mandelbrot/config.toml`, or under `~/.config` without it
+fn default_config_path() -> Option<PathBuf> {
+    let config = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config.join("mandelbrot").join("config.toml"))
 }
 
-// 
-fn mandelbrot_at_point(cx: f64, cy: f64, max_iters: usize) -> MandelbrotResult {
-    let mut z = Complex { re: 0.0, im: 0.0};
-    let c = Complex::new(cx, cy);
+// turns a table of `flag = value` pairs into the equivalent command line arguments
+fn load_config(path: &Path) -> mandelbrot::Result<Vec<String>> {
+    let content = fs::read_to_string(path).map_err(|e| MandelbrotError::file(path.display(), e))?;
+    config_settings(&content, &format!("config {}", path.display()))
+}
 
-    for i in 0..max_iters {
-        if z.norm() > 2.0 {
-            return MandelbrotResult {
-                iterations: i, 
-                z_norm: z.norm(),
-            };
+// the flags the MANDELBROT_ environment variables stand for: MANDELBROT_TILE_ROWS=64 is
+// --tile-rows=64, a switch such as MANDELBROT_STATS is on for 1 or true and off for 0 or false,
+// and MANDELBROT_VERBOSE=2 is -vv. Variables that name no flag are left alone
+fn env_settings() -> mandelbrot::Result<Vec<String>> {
+    let command = Cli::command();
+    let mut settings = Vec::new();
+    for (name, value) in std::env::vars_os() {
+        let (Some(name), Some(value)) = (name.to_str(), value.to_str()) else {
+            continue;
+        };
+        let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        let flag = key.to_lowercase().replace('_', "-");
+        let Some(arg) = command.get_arguments().find(|arg| arg.get_long() == Some(flag.as_str())) else {
+            continue;
+        };
+        let invalid = |reason| MandelbrotError::invalid(format_args!("{}={}", name, value), reason);
+        match arg.get_action() {
+            ArgAction::SetTrue => match value.to_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => settings.push(format!("--{}", flag)),
+                "0" | "false" | "no" | "off" | "" => {}
+                _ => return Err(invalid("expected 1 or 0, true or false")),
+            },
+            ArgAction::Count => {
+                let count = value.parse().map_err(|_| invalid("expected a count"))?;
+                settings.extend(std::iter::repeat_n(format!("--{}", flag), count));
+            }
+            action if action.takes_values() => settings.push(format!("--{}={}", flag, value)),
+            _ => {}
         }
-        z = z * z + c;
     }
-    MandelbrotResult {
-        iterations: max_iters, 
-        z_norm: z.norm(),
+    Ok(settings)
+}
+
+// the flags a --config file's TOML stands for; `source` names it in errors
+fn config_settings(content: &str, source: &str) -> mandelbrot::Result<Vec<String>> {
+    let invalid = |reason| MandelbrotError::invalid(source, reason);
+    let table: toml::Table = toml::from_str(content).map_err(|e| invalid(e.to_string()))?;
+
+    let mut settings = Vec::new();
+    for (key, value) in table {
+        let flag = format!("--{}", key.replace('_', "-"));
+        match value {
+            toml::Value::Boolean(true) => settings.push(flag),
+            toml::Value::Boolean(false) => {}
+            toml::Value::String(value) => settings.push(format!("{}={}", flag, value)),
+            toml::Value::Integer(value) => settings.push(format!("{}={}", flag, value)),
+            toml::Value::Float(value) => settings.push(format!("{}={}", flag, value)),
+            value => return Err(invalid(format!("'{}' must be a string, number or boolean, not {}", key, value.type_str()))),
+        }
     }
+    Ok(settings)
 }
 
-fn main() {
-    let args = Args::parse();
-    if args.list_colors {
-        println!("Available color schemes:");
-        for scheme in ColorScheme::list_schemes() {
-            println!(" • {}", scheme);
+// environment variables named this and a flag, e.g. MANDELBROT_COLOR, set that flag
+const ENV_PREFIX: &str = "MANDELBROT_";
+
+// lines printed around a terminal render: the header, progress bar and the next prompt
+const HEADER_LINES: u16 = 11;
+
+// width of `palette show` and `presets preview` when the terminal size is unknown
+const PALETTE_COLUMNS: usize = 64;
+// between the thumbnails of `presets preview`
+const PREVIEW_GAP: &str = "  ";
+
+const DEFAULT_REGION: (f64, f64, f64, f64) = (-2.0, 1.0, -1.0, 1.0);
+// the whole of the set's outside under inversion, which takes the cusp to 4 and -2 to -1/2
+const INVERTED_REGION: (f64, f64, f64, f64) = (-1.75, 4.25, -2.0, 2.0);
+// the lambda set, which mirrors itself about λ = 1 and reaches from -2 to 4
+const LAMBDA_REGION: (f64, f64, f64, f64) = (-2.25, 4.25, -2.1, 2.1);
+
+// orbit markers are 2·(width / ORBIT_MARKER_SCALE) + 1 pixels across, so they stay visible in
+// large images and take a single cell in the terminal
+const ORBIT_MARKER_SCALE: usize = 300;
+
+// --autozoom looks for detail on a grid of this many tiles across and down, then zooms in this
+// far around the best one
+const AUTOZOOM_TILES: usize = 4;
+const AUTOZOOM_STEP: f64 = 2.0;
+
+// a minibrot's own bulbs and filaments take some multiple of its period to show
+const MINIBROT_ITERATIONS_PER_PERIOD: usize = 20;
+
+// settings left out of the ones saved in images: those that only choose where the render goes,
+// and those that place the view, which is saved as its exact bounds instead
+const OUTPUT_SETTINGS: [&str; 25] = [
+    "save", "image_format", "output_raw", "export_data", "contours", "contour_levels", "import_data", "tiled", "tile_rows", "checkpoint",
+    "resume", "stream", "fit", "autozoom", "quiet", "verbose", "checksum", "stats", "json", "cache", "cache_dir",
+    "cache_limit", "no_cache", "clear_cache", "workers",
+];
+const VIEW_SETTINGS: [&str; 8] = ["preset", "center", "zoom", "scale", "xmin", "xmax", "ymin", "ymax"];
+
+fn parse_point(s: &str) -> Option<(f64, f64)> {
+    let (re, im) = s.split_once(',')?;
+    let (re, im): (f64, f64) = (re.trim().parse().ok()?, im.trim().parse().ok()?);
+    (re.is_finite() && im.is_finite()).then_some((re, im))
+}
+
+// checks the size and view settings before anything is computed: counts must be at least 1,
+// coordinates finite and zooms positive. Bounds given the wrong way round are swapped
+fn validate_geometry(args: &mut Args) -> mandelbrot::Result<()> {
+    let lenient = args.lenient;
+    let counts = [
+        ("--width", &mut args.width),
+        ("--height", &mut args.height),
+        ("--tile-rows", &mut args.tile_rows),
+    ];
+    for (flag, count) in counts {
+        if *count == 0 {
+            let error = MandelbrotError::invalid(format_args!("{} 0", flag), "it must be at least 1");
+            *count = tolerate(lenient, error, "using 1", 1)?;
         }
-        return;
     }
 
-    let (x_min, x_max, y_min, y_max) = if let Some(preset) = &args.preset {
-        get_preset_coords(preset)
-            .unwrap_or_else(|| {
-                eprintln!("Warning: Unknown preset: '{}', using default", preset);
-                (-2.0, 1.0, -1.0, 1.0)
-            })
-    } else {
-        (
-            args.xmin.unwrap_or(-2.0), 
-            args.xmax.unwrap_or(1.0),
-            args.ymin.unwrap_or(-1.0),
-            args.ymax.unwrap_or(1.0),
-        )
-    };
-
-    let color_scheme = ColorScheme::from_str(&args.color)
-        .unwrap_or_else(|| {
-            eprintln!("Warning: Unknown color scheme '{}', using 'classic'", args.color);
-            ColorScheme::Classic
-        });
-
-    let output_format = match args.format.to_lowercase().as_str() {
-        "auto" => renderer::detect_terminal_capabilities(),
-        "ascii" => OutputFormat::Ascii,
-        "extended" => OutputFormat::AsciiExtended,
-        "ansi256" => OutputFormat::Ansi256,
-        "truecolor" => OutputFormat::AnsiTrueColor,
-        _ => {
-            eprintln!("Warning: Unknown format '{}', using auto-detect", args.format);
-            renderer::detect_terminal_capabilities()
+    let bounds = [("--xmin", &mut args.xmin), ("--xmax", &mut args.xmax), ("--ymin", &mut args.ymin), ("--ymax", &mut args.ymax)];
+    for (flag, bound) in bounds {
+        if let Some(value) = *bound
+            && !value.is_finite()
+        {
+            let error = MandelbrotError::invalid(format_args!("{} {}", flag, value), "it must be a finite number");
+            *bound = tolerate(lenient, error, "using the default", None)?;
         }
-    };
+    }
+
+    let (x_min, x_max, y_min, y_max) = DEFAULT_REGION;
+    let axes = [("x", &mut args.xmin, &mut args.xmax, (x_min, x_max)), ("y", &mut args.ymin, &mut args.ymax, (y_min, y_max))];
+    for (axis, min, max, (default_min, default_max)) in axes {
+        let (low, high) = (min.unwrap_or(default_min), max.unwrap_or(default_max));
+        if low > high {
+            warn!("--{0}min {1} is above --{0}max {2}, swapping them", axis, low, high);
+            (*min, *max) = (Some(high), Some(low));
+        } else if low == high {
+            let error = MandelbrotError::invalid(format_args!("{}-range from {} to {}", axis, low, high), "it is empty");
+            (*min, *max) = tolerate(lenient, error, "using the default", (None, None))?;
+        }
+    }
 
-    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━");
-    println!("Resolution: {}×{}", args.width, args.height);
-    println!("Iterations: {}", args.iterations);
-    println!("Region: x=[{:.4}, {:.4}], y=[{:.4}, {:.4}]", x_min, x_max, y_min, y_max);
-    println!("Colors: {}", args.color);
-    println!();
+    if !args.rotate.is_finite() {
+        let error = MandelbrotError::invalid(format_args!("--rotate {}", args.rotate), "it must be a finite number");
+        args.rotate = tolerate(lenient, error, "not rotating", 0.0)?;
+    }
 
-    println!("Calculating Mandelbrot set...");
-    let (iterations, z_norms) = calculate_mandelbrot(
-        args.iterations, 
-        x_min, 
-        x_max, 
-        y_min, 
-        y_max, 
-        args.width,
-        args.height,
-    );
+    // below 2, points of the mandelbrot set would pass the radius and count as escaped
+    if !(args.bailout >= DEFAULT_BAILOUT && args.bailout.is_finite()) {
+        let error = MandelbrotError::invalid(format_args!("--bailout {}", args.bailout), "it must be a number from 2 up");
+        args.bailout = tolerate(lenient, error, "using 2", DEFAULT_BAILOUT)?;
+    }
 
-    let render_data = RenderData::new(iterations, z_norms, args.iterations);
-    let renderer = Renderer::new(color_scheme, output_format)
-        .with_smooth_coloring(!args.no_smooth);
+    for (flag, zoom) in [("--zoom", &mut args.zoom), ("--scale", &mut args.scale)] {
+        if let Some(value) = *zoom
+            && !(value > 0.0 && value.is_finite())
+        {
+            let error = MandelbrotError::invalid(format_args!("{} {}", flag, value), "it must be a positive number");
+            *zoom = tolerate(lenient, error, "ignoring it", None)?;
+        }
+    }
+    Ok(())
+}
 
-    renderer.render_to_terminal(&render_data);
-    if let Some(filename) = args.save {
-        println!("\n saving to {}...", filename);
-        match renderer.save_as_ppm(&render_data, &filename) {
-            Ok(_) => println!("saved successfully"),
-            Err(e) => eprintln!("error: {}", e),
+// --iterations, or for auto as many as a view `zoom` times closer than the default one needs
+fn max_iterations(args: &Args, zoom: f64) -> mandelbrot::Result<usize> {
+    if args.max_iterations.eq_ignore_ascii_case("auto") {
+        return Ok(mandelbrot::auto_iterations(zoom));
+    }
+    match args.max_iterations.parse::<usize>() {
+        Ok(0) => tolerate(args.lenient, MandelbrotError::invalid("--iterations 0", "it must be at least 1"), "using 1", 1),
+        Ok(iterations) => Ok(iterations),
+        Err(e) => {
+            let error = MandelbrotError::invalid(format_args!("--iterations '{}'", args.max_iterations), e);
+            tolerate(args.lenient, error, "using 1000", 1000)
         }
     }
 }
 
-/*
-fn custom_schemes_ex() {
-    let cyberpunk = Gradient::new(vec![
-        (0.0, Color::new(0, 0, 0)),           // Black
-        (0.2, Color::new(255, 0, 255)),       // Magenta
-        (0.4, Color::new(0, 255, 255)),       // Cyan
-        (0.7, Color::new(255, 0, 128)),       // Hot pink
-        (1.0, Color::new(255, 255, 255)),     // White
-    ]);
-    let scheme = ColorScheme::Custom(cyberpunk);
+// the fractal with the parameters that set it apart, for headers and image summaries
+fn describe_fractal(fractal: &Fractal, formula: Option<&str>) -> String {
+    match fractal {
+        Fractal::Julia(c) => format!("julia (c = {})", c),
+        Fractal::Multibrot(power) => format!("multibrot (d = {})", power),
+        Fractal::Phoenix(p) => format!("phoenix (p = {})", p),
+        Fractal::Newton(polynomial) => format!("newton (p = {})", polynomial),
+        Fractal::Formula(_) => format!("z = {}", formula.unwrap_or_default()),
+        _ => fractal.name().to_string(),
+    }
 }
-*/
 
+// what a saved PNG records about its render: a summary, and the settings as a --config file
+// with the view pinned to the exact region and without the flags that only pick outputs
+fn image_metadata(args: &Args, fractal: &Fractal, region: (f64, f64, f64, f64), color_name: &str) -> Vec<(String, String)> {
+    let (x_min, x_max, y_min, y_max) = region;
+    let mut settings = toml::Table::try_from(args).unwrap_or_default();
+    for key in OUTPUT_SETTINGS.iter().chain(&VIEW_SETTINGS) {
+        settings.remove(*key);
+    }
+    for (key, value) in [("xmin", x_min), ("xmax", x_max), ("ymin", y_min), ("ymax", y_max)] {
+        settings.insert(key.to_string(), toml::Value::Float(value));
+    }
+
+    vec![
+        ("Software".to_string(), format!("mandelbrot {}", env!("CARGO_PKG_VERSION"))),
+        ("Fractal".to_string(), describe_fractal(fractal, args.formula.as_deref())),
+        ("Center".to_string(), format!("{},{}", (x_min + x_max) / 2.0, (y_min + y_max) / 2.0)),
+        ("Zoom".to_string(), format!("{}", DEFAULT_VIEW_WIDTH / (x_max - x_min))),
+        ("Iterations".to_string(), args.iterations.to_string()),
+        ("Palette".to_string(), color_name.to_string()),
+        (metadata::SETTINGS.to_string(), toml::to_string(&settings).unwrap_or_default()),
+    ]
+}
 
+// prints the text stored in a PNG and returns its settings, if it has any
+fn show_info(file: &str) -> Option<String> {
+    let text = metadata::read_text(file).unwrap_or_else(|e| fail(e));
+    if text.is_empty() {
+        sayln!("{} has no text chunks", file);
+    }
+    let mut settings = None;
+    for (keyword, value) in text {
+        if keyword == metadata::SETTINGS {
+            settings = Some(value);
+        } else {
+            sayln!("{}: {}", keyword, value);
+        }
+    }
+    if let Some(settings) = &settings {
+        sayln!("{}:", metadata::SETTINGS);
+        for line in settings.lines() {
+            sayln!("  {}", line);
+        }
+    }
+    settings
+}
+
+// prints the minibrot of `period` near `center` and the flags that frame it, bookmarking
+// that view as `bookmark`
+fn locate_minibrot(presets: &Presets, bookmarks: Bookmarks, center: &str, period: usize, bookmark: Option<&str>) {
+    let Some((re, im)) = parse_point(center) else {
+        fail(MandelbrotError::invalid(format_args!("center '{}'", center), "expected two finite numbers, re,im"));
+    };
+    let found = minibrot::locate(Complex::new(re, im), period).unwrap_or_else(|e| fail(e));
+    let (zoom, iterations) = (found.zoom(), mandelbrot::auto_iterations(found.zoom()).max(MINIBROT_ITERATIONS_PER_PERIOD * period));
+    sayln!("minibrot of period {}", period);
+    sayln!("  center:      {:?}, {:?}", found.center.re, found.center.im);
+    sayln!("  size:        {:.6e} of the whole set", found.size());
+    sayln!("  orientation: {:.2}°", found.orientation());
+    let mut view = format!("--center={:?},{:?} --zoom {:.6e} -i {}", found.center.re, found.center.im, zoom, iterations);
+    // the whole set points along the real axis; turning the view by as much sets it upright
+    if found.orientation().abs() > 0.5 {
+        view.push_str(&format!(" --rotate={:.2}", found.orientation()));
+    }
+    sayln!("  view:        {}", view);
+
+    if let Some(name) = bookmark {
+        let view = Bookmark {
+            center: (found.center.re, found.center.im),
+            zoom,
+            iterations,
+            color: "classic".to_string(),
+        };
+        match add_bookmark(presets, bookmarks, name, view) {
+            Ok(_) => info!("bookmarked as '{}'", name),
+            Err(e) => fail(e),
+        }
+    }
+}
+
+// draws where `a` and `b` differ and sums it up, exiting with DIFFERENT_STATUS if anything
+// differs beyond `tolerance`
+fn diff(a: &str, b: &str, tolerance: f64, color: &str, save: Option<&str>) {
+    if !(tolerance >= 0.0 && tolerance.is_finite()) {
+        fail(MandelbrotError::invalid(format_args!("--tolerance {}", tolerance), "it must be a number from 0 up"));
+    }
+    let color_scheme = ColorScheme::from_str(color).unwrap_or_else(|| fail(MandelbrotError::unknown("color scheme", color)));
+    let (info, first) = diff_input(a, "a");
+    let (other, second) = diff_input(b, "b");
+    if info.region != other.region || info.fractal != other.fractal {
+        warn!("{} and {} aren't of the same view, comparing them anyway", a, b);
+    }
+    let difference = Difference::between(&first, &second).unwrap_or_else(|e| fail(e));
+    let (width, height) = (difference.width(), difference.height());
+
+    // the heat map fits the terminal, each cell showing the largest difference it covers
+    let format = renderer::detect_terminal_capabilities();
+    let (across, down) = format.pixels_per_cell();
+    let (columns, rows) = crossterm::terminal::size().map_or((PALETTE_COLUMNS, PALETTE_COLUMNS / 2), |(columns, rows)| {
+        (columns as usize, rows.saturating_sub(HEADER_LINES) as usize)
+    });
+    let (x_min, x_max, y_min, y_max) = info.region;
+    let cells = (columns.max(1)).min(width.div_ceil(across));
+    let cell_rows = ((cells as f64 * (y_max - y_min) / (x_max - x_min) / format.pixel_aspect()).round() as usize).clamp(1, rows.max(1));
+    let renderer = Renderer::new(color_scheme, format).with_coloring(ColoringMode::Linear);
+    info!("Comparing {} with {} ({}×{}, {} and {} iterations)", a, b, width, height, first.max_iterations, second.max_iterations);
+    info!("");
+    show(&renderer, &difference.heat_map(cells * across, cell_rows * down, tolerance));
+
+    let (pixels, beyond) = (width * height, difference.beyond(tolerance));
+    let share = |count: usize| count as f64 * 100.0 / pixels as f64;
+    sayln!();
+    sayln!(" identical:        {} of {} pixels ({:.2}%)", difference.identical(), pixels, share(difference.identical()));
+    sayln!(" beyond tolerance: {} ({:.2}%) by more than {} iterations", beyond, share(beyond), tolerance);
+    sayln!(" mean difference:  {:.3e} iterations", difference.mean());
+    if let Some((x, y, largest)) = difference.largest().filter(|&(_, _, largest)| largest > 0.0) {
+        sayln!(" largest:          {:.4} iterations, at pixel {}, {}", largest, x, y);
+    }
+
+    if let Some(filename) = save {
+        let heat_map = difference.heat_map(width, height, tolerance);
+        match renderer.save_image(&heat_map, filename, image_format_for(None, filename)) {
+            Ok(_) => info!("\n saved the heat map to {}", filename),
+            Err(e) => fail(MandelbrotError::file(filename, e)),
+        }
+    }
+    if beyond > 0 {
+        std::process::exit(DIFFERENT_STATUS);
+    }
+}
+
+// the escape data `source` holds, or that rendering it gives: data saved with --export-data is
+// read back, while settings are rendered by running this program on them, quietly into a
+// temporary .bin file named after `side`
+fn diff_input(source: &str, side: &str) -> (DataInfo, RenderData) {
+    let extension = Path::new(source).extension().and_then(|extension| extension.to_str()).map(str::to_lowercase);
+    let command = match extension.as_deref() {
+        Some("toml") => ["render", "--config", source].map(OsString::from).to_vec(),
+        Some("png") => ["info", source, "--render", "--"].map(OsString::from).to_vec(),
+        _ => return export::import_data(source).unwrap_or_else(|e| fail(e)),
+    };
+
+    let data = std::env::temp_dir().join(format!("mandelbrot-diff-{}-{}.bin", std::process::id(), side));
+    let program = std::env::current_exe().unwrap_or_else(|e| fail(e.into()));
+    info!("Rendering {}...", source);
+    let status = std::process::Command::new(program)
+        .args(command)
+        .args(["--quiet", "--json", "--export-data"])
+        .arg(&data)
+        .stdout(std::process::Stdio::null())
+        .status()
+        .unwrap_or_else(|e| fail(e.into()));
+    if !status.success() {
+        let _ = fs::remove_file(&data);
+        fail(MandelbrotError::invalid(source, "rendering it failed"));
+    }
+    let imported = export::import_data(&data.to_string_lossy());
+    let _ = fs::remove_file(&data);
+    imported.unwrap_or_else(|e| fail(e))
+}
+
+// --image-format, or else the file extension
+fn image_format_for(format: Option<ImageFormat>, filename: &str) -> ImageFormat {
+    format.or_else(|| ImageFormat::from_path(filename)).unwrap_or(ImageFormat::Ppm)
+}
+
+// a bad setting stops the program, unless --lenient asks to warn about it and carry on
+// with `fallback`, described by `instead`
+fn recover<T>(lenient: bool, error: MandelbrotError, instead: &str, fallback: T) -> T {
+    tolerate(lenient, error, instead, fallback).unwrap_or_else(|e| fail(e))
+}
+
+// `recover` that hands the error back instead of stopping
+fn tolerate<T>(lenient: bool, error: MandelbrotError, instead: &str, fallback: T) -> mandelbrot::Result<T> {
+    if !lenient {
+        return Err(error);
+    }
+    warn!("{}, {}", error, instead);
+    Ok(fallback)
+}
+
+fn fail(error: MandelbrotError) -> ! {
+    error!("{}", error);
+    std::process::exit(error.exit_code());
+}
+
+fn main() {
+    // until the flags say otherwise
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+    let handled = ctrlc::set_handler(|| {
+        if CANCELLABLE.load(Ordering::SeqCst) && !INTERRUPTED.swap(true, Ordering::SeqCst) {
+            eprintln!("\nstopping, Ctrl-C again to quit without saving");
+            return;
+        }
+        eprintln!();
+        std::process::exit(INTERRUPTED_STATUS);
+    });
+    if let Err(e) = handled {
+        debug!("Ctrl-C will quit on the spot: {}", e);
+    }
+    let presets = load_presets();
+    let bookmarks = load_bookmarks();
+    let cli = parse_args(&presets, &bookmarks);
+    let result = match cli.command {
+        Some(Command::Render(args)) => run_render(args, &presets, bookmarks),
+        Some(Command::Explore(args)) => run_explore(args, &presets, bookmarks),
+        Some(Command::Animate { animation, args }) => run_animate(&animation, args, &presets, bookmarks),
+        Some(Command::Screensaver { screensaver, args }) => run_screensaver(&screensaver, args, &presets, bookmarks),
+        Some(Command::Strip { strip, args }) => run_strip(&strip, args, &presets, bookmarks),
+        Some(Command::Bench { runs, args }) => run_bench(runs, args, &presets, bookmarks),
+        Some(Command::Serve { server, args }) => run_serve(&server, args, &presets, bookmarks),
+        Some(Command::Info { file, render, overrides }) => run_info(&file, render, overrides, &presets, bookmarks),
+        Some(Command::ServeWorker { listen, threads }) => {
+            serve_worker(&listen, threads);
+            Ok(())
+        }
+        Some(Command::Palette(command)) => run_palette(command),
+        Some(Command::Presets(command)) => run_presets(command, &presets, &bookmarks),
+        Some(Command::LocateMinibrot { center, period, bookmark }) => {
+            locate_minibrot(&presets, bookmarks, &center, period, bookmark.as_deref());
+            Ok(())
+        }
+        Some(Command::Diff { a, b, tolerance, color, save }) => {
+            diff(&a, &b, tolerance, &color, save.as_deref());
+            Ok(())
+        }
+        Some(Command::Fractals(ListCommand::List)) => run_fractals(),
+        None => run_render(cli.args, &presets, bookmarks),
+    };
+    if let Err(e) = result {
+        fail(e);
+    }
+}
+
+fn run_render(args: Args, presets: &Presets, bookmarks: Bookmarks) -> mandelbrot::Result<()> {
+    match Setup::new(Mode::Render, args, presets, bookmarks, None)? {
+        Some(setup) => render(setup.session()),
+        None => Ok(()),
+    }
+}
+
+// strips are renders through the exponential map, as wide as their depth takes
+fn run_strip(strip: &StripArgs, mut args: Args, presets: &Presets, bookmarks: Bookmarks) -> mandelbrot::Result<()> {
+    if !(strip.depth > 1.0 && strip.depth.is_finite()) {
+        return Err(MandelbrotError::invalid(format_args!("--depth {}", strip.depth), "it must be a number above 1"));
+    }
+    args.transform = "exponential".to_string();
+    match Setup::new(Mode::Render, args, presets, bookmarks, Some(strip.depth))? {
+        Some(setup) => render(setup.session()),
+        None => Ok(()),
+    }
+}
+
+fn run_info(file: &str, render: bool, overrides: Vec<String>, presets: &Presets, bookmarks: Bookmarks) -> mandelbrot::Result<()> {
+    let settings = show_info(file);
+    if !render {
+        return Ok(());
+    }
+    let Some(settings) = settings else {
+        return Err(MandelbrotError::invalid(file, "it has no render settings to render from"));
+    };
+    let settings = config_settings(&settings, file)?;
+    let command_line = ["mandelbrot".to_string(), "render".to_string()].into_iter().chain(settings).chain(overrides);
+    let Some(Command::Render(args)) = Cli::parse_from(command_line).command else {
+        unreachable!("the command line starts with render");
+    };
+    sayln!();
+    run_render(args, presets, bookmarks)
+}
+
+fn run_explore(args: Args, presets: &Presets, bookmarks: Bookmarks) -> mandelbrot::Result<()> {
+    let Some(setup) = Setup::new(Mode::Explore, args, presets, bookmarks, None)? else {
+        return Ok(());
+    };
+    let Setup { args, fractal, region, output_format, split, .. } = setup;
+    let mut explorer = Explorer::new(fractal, region, args.iterations, &args.color, output_format, !args.no_smooth).with_split(split);
+    explorer.run()?;
+    Ok(())
+}
+
+fn run_screensaver(screensaver: &ScreensaverArgs, args: Args, presets: &Presets, bookmarks: Bookmarks) -> mandelbrot::Result<()> {
+    let Some(setup) = Setup::new(Mode::Screensaver, args, presets, bookmarks, None)? else {
+        return Ok(());
+    };
+    let Session { setup, renderer, views, .. } = setup.session();
+    let lenient = setup.args.lenient;
+    let seconds = if screensaver.seconds > 0.0 && screensaver.seconds.is_finite() {
+        screensaver.seconds
+    } else {
+        let error = MandelbrotError::invalid(format_args!("--seconds {}", screensaver.seconds), "it must be a positive number");
+        recover(lenient, error, "using 20", 20.0)
+    };
+    let final_zoom = if screensaver.final_zoom > 0.0 && screensaver.final_zoom.is_finite() {
+        screensaver.final_zoom
+    } else {
+        let error = MandelbrotError::invalid(format_args!("--final-zoom {}", screensaver.final_zoom), "it must be a positive number");
+        recover(lenient, error, "using 10000", 1e4)
+    };
+    let mut screensaver = Screensaver::new(setup.fractal, renderer, views.options, setup.region, setup.args.iterations)
+        .with_dive(seconds, final_zoom);
+    screensaver.run()?;
+    Ok(())
+}
+
+fn run_animate(animate: &AnimateArgs, args: Args, presets: &Presets, bookmarks: Bookmarks) -> mandelbrot::Result<()> {
+    let Some(setup) = Setup::new(Mode::Animate, args, presets, bookmarks, None)? else {
+        return Ok(());
+    };
+    let mut session = setup.session();
+    if let Some(path) = session.setup.args.import_data.take() {
+        return show_import(&path, session);
+    }
+    let Session {
+        setup: Setup { args, fractal, julia_c, region, mut report, .. },
+        renderer,
+        views,
+        image_format,
+        ..
+    } = session;
+    let lenient = args.lenient;
+    let (x_min, x_max, y_min, y_max) = region;
+
+    let final_zoom = if animate.final_zoom > 0.0 && animate.final_zoom.is_finite() {
+        animate.final_zoom
+    } else {
+        let error = MandelbrotError::invalid(format_args!("--final-zoom {}", animate.final_zoom), "it must be a positive number");
+        recover(lenient, error, "using 1000", 1000.0)
+    };
+    let target = match &animate.target {
+        Some(target) => parse_point(target).unwrap_or_else(|| {
+            let error = MandelbrotError::invalid(format_args!("target '{}'", target), "expected two finite numbers, re,im");
+            recover(lenient, error, "zooming into the region center", ((x_min + x_max) / 2.0, (y_min + y_max) / 2.0))
+        }),
+        None => ((x_min + x_max) / 2.0, (y_min + y_max) / 2.0),
+    };
+    let animation = ZoomAnimation::new(region, target, final_zoom, animate.frames, args.iterations);
+    let morph = match animate.morph.as_deref() {
+        Some(path) => {
+            let path = JuliaPath::from_str(path).unwrap_or_else(|| {
+                recover(lenient, MandelbrotError::unknown("julia path", path), "using 'cardioid'", JuliaPath::Cardioid)
+            });
+            let zoom = DEFAULT_VIEW_WIDTH / (x_max - x_min);
+            Some(Script::morph(path, target, zoom, args.iterations, animate.frames)?)
+        }
+        None => None,
+    };
+    let fractal = match fractal {
+        Fractal::Julia(_) => fractal,
+        _ if morph.is_some() => {
+            let error = MandelbrotError::invalid("--morph", "it moves the constant of --fractal julia");
+            recover(lenient, error, "morphing julia", Fractal::Julia(julia_c))
+        }
+        _ => fractal,
+    };
+    let script = match morph {
+        Some(morph) => Some(morph),
+        None => animate.script.as_deref().map(Script::load).transpose()?,
+    };
+    if script.as_ref().is_some_and(Script::sets_c) && !matches!(fractal, Fractal::Julia(_)) {
+        warn!("the script sets c, which only julia has, ignoring it");
+    }
+    if script.as_ref().is_some_and(Script::sets_power) && !matches!(fractal, Fractal::Multibrot(_)) {
+        warn!("the script sets power, which only multibrot has, ignoring it");
+    }
+    let frames = script.as_ref().map_or(animation.frames(), Script::frames);
+    let base = args.save.clone().unwrap_or_else(|| "frame.png".to_string());
+    let image_format = image_format_for(image_format, &base);
+
+    // a gif holds the whole animation, every other format gets one file per frame
+    let video = animation::is_video(&base);
+    let mut writer: Box<dyn FrameWriter> = match image_format {
+        _ if video => {
+            let fps = if animate.fps > 0.0 && animate.fps.is_finite() {
+                animate.fps
+            } else {
+                let error = MandelbrotError::invalid(format_args!("--fps {}", animate.fps), "it must be a positive number");
+                recover(lenient, error, "using 30", 30.0)
+            };
+            let codec = animate.codec.as_deref().unwrap_or_else(|| animation::default_codec(&base));
+            let encoder = VideoEncoder::create(&base, args.width, args.height, fps, codec, animate.crf);
+            Box::new(encoder.map_err(|e| MandelbrotError::file(&base, e))?)
+        }
+        ImageFormat::Gif => {
+            let encoder = create(&base).and_then(|out| {
+                GifEncoder::new(out, args.width, args.height, animate.frame_delay, animate.loop_count, animate.gif_speed)
+            });
+            Box::new(encoder.map_err(|e| MandelbrotError::file(&base, e))?)
+        }
+        _ if base == STDOUT => Box::new(FrameStream::new(BufWriter::new(io::stdout()), image_format, "stdout")),
+        _ => Box::new(ImageSequence::new(&base, image_format, frames)),
+    };
+
+    let result = match &script {
+        Some(script) => keyframes::render(script, args.iterations, &renderer, writer.as_mut(), |view| {
+            let fractal = match (fractal, view.c, view.power) {
+                (Fractal::Julia(_), Some(c), _) => Fractal::Julia(c),
+                (Fractal::Multibrot(_), _, Some(power)) => Fractal::Multibrot(power),
+                _ => fractal,
+            };
+            let region = mandelbrot::region_around(view.center, view.zoom, args.width, args.height, 1.0);
+            views.compute(&fractal, view.iterations, region, args.width, args.height, None)
+        }),
+        None => animation::render(&animation, &renderer, writer.as_mut(), |max_iters, region| {
+            views.compute(&fractal, max_iters, region, args.width, args.height, None)
+        }),
+    };
+    result?;
+    if video || image_format == ImageFormat::Gif {
+        info!("saved animation to {}", output_name(&base));
+    }
+    if video || image_format == ImageFormat::Gif || base == STDOUT {
+        report.output(&base);
+    } else {
+        (0..frames).for_each(|frame| report.output(&animation::frame_path(&base, frame, frames)));
+    }
+    report.finish();
+    Ok(())
+}
+
+fn run_bench(runs: usize, args: Args, presets: &Presets, bookmarks: Bookmarks) -> mandelbrot::Result<()> {
+    let Some(setup) = Setup::new(Mode::Bench, args, presets, bookmarks, None)? else {
+        return Ok(());
+    };
+    let mut session = setup.session();
+    if let Some(path) = session.setup.args.import_data.take() {
+        return show_import(&path, session);
+    }
+    session.print_header();
+    let Session { setup: Setup { args, fractal, region, mut report, .. }, views, .. } = session;
+    let options = views.options;
+
+    let mut times = Vec::new();
+    for run in 1..=runs.max(1) {
+        let start = Instant::now();
+        let rotated = mandelbrot::rotated_region(region, options.rotation);
+        let render_data = mandelbrot::calculate(&fractal, args.iterations, rotated, args.width, args.height, &options, None);
+        std::hint::black_box(render_data);
+        times.push(start.elapsed().as_secs_f64());
+        sayln!("run {}: {:.3}s", run, times[run - 1]);
+    }
+    let best = times.iter().copied().fold(f64::INFINITY, f64::min);
+    let mean = times.iter().sum::<f64>() / times.len() as f64;
+    let pixels = (args.width * options.samples * args.height * options.samples) as f64;
+    sayln!("best {:.3}s, mean {:.3}s, {:.2} Mpixels/s", best, mean, pixels / best / 1e6);
+    report.runs = times;
+    report.finish();
+    Ok(())
+}
+
+fn run_serve(server: &ServeArgs, args: Args, presets: &Presets, bookmarks: Bookmarks) -> mandelbrot::Result<()> {
+    let Some(setup) = Setup::new(Mode::Serve, args, presets, bookmarks, None)? else {
+        return Ok(());
+    };
+    let mut session = setup.session();
+    if let Some(path) = session.setup.args.import_data.take() {
+        return show_import(&path, session);
+    }
+    session.print_header();
+    let Session { setup, renderer, views, .. } = session;
+
+    let listener = TcpListener::bind((server.host.as_str(), server.port))
+        .map_err(|e| MandelbrotError::file(format_args!("{}:{}", server.host, server.port), e))?;
+    info!("serving tiles at http://{}:{}/ (ctrl-c to stop)", server.host, server.port);
+    tile_server::serve(&listener, server.tile_cache, |region| {
+        // auto iterations follow each tile's depth, and fixed ones stay fixed
+        let zoom = DEFAULT_VIEW_WIDTH / (region.1 - region.0);
+        let max_iters = if setup.auto_iterations { mandelbrot::auto_iterations(zoom) } else { setup.args.iterations };
+        let render_data = views.compute(&setup.fractal, max_iters, region, TILE_SIZE, TILE_SIZE, None);
+        let mut png = Vec::new();
+        let rgb = renderer.to_rgb_buffer(&render_data);
+        // writing into memory can't fail
+        let _ = renderer::write_image(&mut png, ImageFormat::Png, TILE_SIZE, TILE_SIZE, &rgb);
+        png
+    });
+    Ok(())
+}
+
+fn run_palette(command: PaletteCommand) -> mandelbrot::Result<()> {
+    match command {
+        PaletteCommand::List => {
+            sayln!("Available color schemes:");
+            for scheme in ColorScheme::list_schemes() {
+                sayln!(" • {}", scheme);
+            }
+            sayln!("Add -r to a name to reverse it, e.g. viridis-r");
+        }
+        PaletteCommand::Show { name } => show_palette(&name),
+    }
+    Ok(())
+}
+
+fn run_presets(command: PresetsCommand, presets: &Presets, bookmarks: &Bookmarks) -> mandelbrot::Result<()> {
+    match command {
+        PresetsCommand::Preview { size } => preview_presets(presets, size),
+        PresetsCommand::List => {
+            sayln!("Available presets:");
+            let width = presets.iter().map(|preset| preset.name.chars().count()).max().unwrap_or(0);
+            for preset in presets.iter() {
+                sayln!(" • {:width$}  {}", preset.name, preset.description, width = width);
+            }
+            for (name, bookmark) in bookmarks.iter() {
+                let (re, im) = bookmark.center;
+                sayln!(" • {} (bookmark: {}, {} at zoom {:.3e})", name, re, im, bookmark.zoom);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_fractals() -> mandelbrot::Result<()> {
+    sayln!("Available fractals:");
+    for fractal in Fractal::list_fractals() {
+        sayln!(" • {}", fractal);
+    }
+    Ok(())
+}
+
+// a single render of a view, or of a split view, an autozoom, a tiled or a streamed one
+fn render(session: Session) -> mandelbrot::Result<()> {
+    let mut session = session;
+    if let Some(path) = session.setup.args.import_data.take() {
+        return show_import(&path, session);
+    }
+    session.print_header();
+    let Session {
+        setup:
+            Setup {
+                args,
+                fractal,
+                julia_c,
+                split,
+                output_format,
+                transform,
+                view,
+                region,
+                color_name,
+                orbit,
+                ray_angles,
+                equipotentials,
+                annotated,
+                mut report,
+                ..
+            },
+        renderer,
+        views,
+        image_format,
+        tiled_format,
+        stream,
+    } = session;
+    let lenient = args.lenient;
+    let options = views.options;
+    let (x_min, x_max, y_min, y_max) = region;
+    let samples = options.samples;
+    // saved images record how to make them again, and strips what their axes are for tools that
+    // unroll them
+    let mut metadata = image_metadata(&args, &fractal, view, &color_name);
+    if let Transform::Exponential { center: (re, im) } = transform {
+        let axes = format!(
+            "centered on {},{}: the natural log of the radius from {} to {} across, the angle from {} to {} radians down",
+            re, im, x_min, x_max, y_min, y_max
+        );
+        metadata.push(("LogPolar".to_string(), axes));
+    }
+
+    if let Some(steps) = args.autozoom {
+        let mut region = region;
+        for step in 0..steps {
+            let zoom = AUTOZOOM_STEP.powi(step as i32);
+            let max_iters = animation::iterations_at(args.iterations, zoom);
+            let render_data = views.compute(&fractal, max_iters, region, args.width, args.height, None);
+            let (x_min, x_max, y_min, y_max) = region;
+            let center = ((x_min + x_max) / 2.0, (y_min + y_max) / 2.0);
+            info!("step {}/{}: center {}, {} at zoom {:.3e}, {} iterations", step + 1, steps, center.0, center.1, zoom, max_iters);
+            show(&renderer, &render_data);
+            report.record(&renderer, &render_data);
+            if let Some(filename) = &args.save {
+                // frames written to stdout follow each other
+                let path = if filename == STDOUT { filename.clone() } else { animation::frame_path(filename, step, steps) };
+                save(&renderer, &render_data, &path, image_format, &mut report);
+            }
+
+            // the detail is found in the picture, which --rotate turned about the center
+            let detailed = animation::most_detailed(&render_data, region, AUTOZOOM_TILES).map(|(re, im)| {
+                let (re, im) = Rotation::degrees(options.rotation).to_plane((re - center.0, im - center.1));
+                (center.0 + re, center.1 + im)
+            });
+            match detailed {
+                Some(target) => region = animation::zoom_region(region, target, AUTOZOOM_STEP),
+                None => {
+                    info!("nothing left to zoom into");
+                    break;
+                }
+            }
+        }
+        report.finish();
+        return Ok(());
+    }
+
+    if split {
+        if tiled_format.is_some() || stream {
+            warn!("--split renders in one piece");
+        }
+        // each half gets its own view at the original zoom; julia sets fit the default one
+        let panel = (args.width / 2).max(1);
+        let center = ((x_min + x_max) / 2.0, (y_min + y_max) / 2.0);
+        let left = mandelbrot::region_around(center, DEFAULT_VIEW_WIDTH / (x_max - x_min), panel, args.height, output_format.pixel_aspect());
+        let right = mandelbrot::region_around((0.0, 0.0), 1.0, args.width - panel, args.height, output_format.pixel_aspect());
+
+        let progress = Progress::new(args.width * samples, args.height * samples, args.quiet).with_cancel(&INTERRUPTED);
+        let julia = Fractal::Julia(julia_c);
+        let (render_data, interrupted) = cancellable(|| {
+            RenderData::side_by_side(&[
+                views.compute(&fractal, args.iterations, left, panel, args.height, Some(&progress)),
+                views.compute(&julia, args.iterations, right, args.width - panel, args.height, Some(&progress)),
+            ])
+        });
+        progress.finish();
+        let keep = !interrupted || keep_partial(&progress, args.save.as_deref());
+
+        // the left view's coordinates carry on over the julia panel, which keeps the cross on c
+        let (x_min, x_max, y_min, y_max) = left;
+        let x_max = x_min + (x_max - x_min) * args.width as f64 / panel as f64;
+        let mut overlay = Overlay::new(args.width, args.height, (x_min, x_max, y_min, y_max));
+        if options.rotation == 0.0 {
+            overlay.cross(julia_c, (args.width / ORBIT_MARKER_SCALE).max(1));
+        }
+        let renderer = renderer.with_overlay(overlay).with_metadata(metadata);
+
+        show(&renderer, &render_data);
+        sayln!("\n julia set of c = {}", julia_c);
+        if args.checksum {
+            print_checksums(&renderer, &render_data);
+        }
+        if args.stats {
+            say!("\n{}", Stats::of(&render_data));
+        }
+        report.record(&renderer, &render_data);
+        if let Some(filename) = &args.save
+            && keep
+        {
+            save(&renderer, &render_data, filename, image_format, &mut report);
+        }
+        report.finish();
+        if interrupted {
+            std::process::exit(INTERRUPTED_STATUS);
+        }
+        return Ok(());
+    }
+
+    if let (Some(format), Some(filename)) = (tiled_format, &args.save) {
+        if args.export_data.is_some() || args.contours.is_some() || args.checksum || args.stats || annotated {
+            warn!("--export-data, --contours, --checksum, --stats and overlays like --orbit aren't supported for tiled renders, skipping them");
+        }
+        let tiled = TiledRender {
+            fractal: &fractal,
+            max_iters: args.iterations,
+            region: mandelbrot::rotated_region(region, options.rotation),
+            width: args.width,
+            height: args.height,
+            tile_rows: args.tile_rows,
+            options,
+            refine: (args.adaptive && args.samples > 1).then_some((args.samples as usize, args.aa_threshold)),
+            farm: views.farm.as_ref(),
+        };
+
+        let checkpoint = (args.checkpoint || args.resume).then(|| PathBuf::from(format!("{}.checkpoint", filename)));
+        if let Some(path) = &checkpoint {
+            if !args.resume {
+                let _ = fs::remove_file(path);
+            } else if !path.exists() {
+                warn!("no checkpoint at {}, starting from scratch", path.display());
+            }
+        }
+
+        info!("Rendering {} tiles of {} rows to {}...", tiled.tiles(), args.tile_rows.max(1), output_name(filename));
+        let progress = Progress::new(args.width * samples, args.height * samples, args.quiet).with_cancel(&INTERRUPTED);
+        let (result, _) = cancellable(|| {
+            create(filename).and_then(|out| {
+                let out = RowWriter::new(out, format, args.width, args.height, &metadata)?;
+                tiled.render(&renderer, out, checkpoint.as_deref(), Some(&progress))
+            })
+        });
+        progress.finish();
+
+        let complete = result.map_err(|e| MandelbrotError::file(filename, e))?;
+        report.output(filename);
+        if complete {
+            info!("saved successfully");
+            report.finish();
+            return Ok(());
+        }
+        // the tiles went out as they were done, so there's nothing left to offer saving
+        warn!("interrupted with {:.0}% computed, the rest of {} is black", progress.fraction_done() * 100.0, output_name(filename));
+        if let Some(path) = &checkpoint {
+            info!("kept {} to carry on from with --resume", path.display());
+        }
+        report.finish();
+        std::process::exit(INTERRUPTED_STATUS);
+    }
+
+    if stream {
+        // one band per line of terminal cells
+        let streamed = TiledRender {
+            fractal: &fractal,
+            max_iters: args.iterations,
+            region: mandelbrot::rotated_region(region, options.rotation),
+            width: args.width,
+            height: args.height,
+            tile_rows: output_format.pixels_per_cell().1,
+            options,
+            refine: (args.adaptive && args.samples > 1).then_some((args.samples as usize, args.aa_threshold)),
+            farm: views.farm.as_ref(),
+        };
+        streamed.stream(&renderer, None, |band| renderer.render_to_terminal(&band));
+        return Ok(());
+    }
+
+    let renderer = renderer.with_metadata(metadata);
+    // the grid goes under the axes, then come equipotentials, rays and the orbit over them all
+    let renderer = if annotated {
+        let mut overlay = Overlay::new(args.width, args.height, region);
+        if args.grid {
+            overlay.grid();
+        }
+        if args.axes {
+            overlay.axes();
+        }
+        let pixel_size = (region.1 - region.0) / args.width as f64;
+        for i in 0..equipotentials {
+            overlay.equipotential(&rays::equipotential(&fractal, 2f64.ln() / 2f64.powi(i as i32), pixel_size));
+        }
+        for (text, angle) in &ray_angles {
+            overlay.external_ray(&rays::external_ray(&fractal, *angle, pixel_size / 2.0), text);
+        }
+        if let Some(point) = orbit {
+            overlay.orbit(&fractal.orbit(point.re, point.im, args.iterations), args.width / ORBIT_MARKER_SCALE);
+        }
+        if args.axes || args.grid {
+            overlay.coordinate_labels();
+        }
+        if args.scale_bar {
+            overlay.scale_bar();
+        }
+        renderer.with_overlay(overlay)
+    } else {
+        renderer
+    };
+
+    let progress = Progress::new(args.width * samples, args.height * samples, args.quiet).with_cancel(&INTERRUPTED);
+    let (mut render_data, interrupted) =
+        cancellable(|| views.compute(&fractal, args.iterations, region, args.width, args.height, Some(&progress)));
+    progress.finish();
+    let keep = !interrupted || keep_partial(&progress, args.save.as_deref());
+
+    if args.adaptive && args.samples > 1 && !interrupted {
+        let refined = mandelbrot::refine_edges(
+            &fractal,
+            &mut render_data,
+            mandelbrot::rotated_region(region, options.rotation),
+            PlaneMap::new(&options),
+            &renderer,
+            args.samples as usize,
+            args.aa_threshold,
+        );
+        info!("Anti-aliased {} edge pixels with {}×{} samples", refined, args.samples, args.samples);
+    }
+
+    show(&renderer, &render_data);
+    if let Some(point) = orbit {
+        let iterations = fractal.at_point(point.re, point.im, args.iterations).iterations;
+        if iterations < args.iterations {
+            sayln!("\n orbit of {} escapes after {} iterations", point, iterations);
+        } else {
+            sayln!("\n orbit of {} stays bounded for {} iterations", point, args.iterations);
+        }
+    }
+    if args.checksum {
+        print_checksums(&renderer, &render_data);
+    }
+    if args.stats {
+        say!("\n{}", Stats::of(&render_data));
+    }
+    report.record(&renderer, &render_data);
+    if let Some(filename) = &args.export_data {
+        let format = DataFormat::from_path(filename).unwrap_or_else(|| {
+            recover(lenient, MandelbrotError::unknown("data format", filename), "using csv", DataFormat::Csv)
+        });
+        let info = DataInfo::new(fractal.name(), region, &render_data);
+        export::export_data(filename, format, &render_data, &info)?;
+        info!("\n exported data to {}", filename);
+        report.output(filename);
+    }
+
+    if let Some(filename) = &args.contours {
+        let levels = match &args.contour_levels {
+            Some(levels) => parse_levels(levels).unwrap_or_else(|| {
+                let error = MandelbrotError::invalid(format_args!("contour levels '{}'", levels), "expected a list of numbers");
+                recover(lenient, error, "tracing the set boundary", vec![contour::boundary_level(args.iterations)])
+            }),
+            None => vec![contour::boundary_level(args.iterations)],
+        };
+        let result = File::create(filename).and_then(|file| contour::write_svg(BufWriter::new(file), &render_data, &levels));
+        result.map_err(|e| MandelbrotError::file(filename, e))?;
+        info!("\n traced {} contour levels to {}", levels.len(), filename);
+        report.output(filename);
+    }
+
+    if let Some(filename) = &args.save
+        && keep
+    {
+        save(&renderer, &render_data, filename, image_format, &mut report);
+    }
+    report.finish();
+    if interrupted {
+        std::process::exit(INTERRUPTED_STATUS);
+    }
+    Ok(())
+}
+
+// imported data only needs coloring, whatever the other settings say about computing it
+fn show_import(path: &str, session: Session) -> mandelbrot::Result<()> {
+    let Session { setup: Setup { args, mut report, .. }, renderer, image_format, .. } = session;
+    let (info, render_data) = export::import_data(path)?;
+    let (x_min, x_max, y_min, y_max) = info.region;
+    info!("Data: {} ({}×{}, {} iterations)", path, info.width, info.height, info.max_iterations);
+    info!("Region: x=[{:.4}, {:.4}], y=[{:.4}, {:.4}]", x_min, x_max, y_min, y_max);
+    info!("");
+    show(&renderer, &render_data);
+    if args.checksum {
+        print_checksums(&renderer, &render_data);
+    }
+    if args.stats {
+        say!("\n{}", Stats::of(&render_data));
+    }
+    (report.region, report.width, report.height) = (info.region, info.width, info.height);
+    report.iterations = info.max_iterations;
+    report.record(&renderer, &render_data);
+    if let Some(filename) = &args.save {
+        save(&renderer, &render_data, filename, image_format, &mut report);
+    }
+    report.finish();
+    Ok(())
+}
+
+// the flags of a subcommand that renders a view, worked out into the fractal, the view and how
+// to show it; everything after this depends on what the subcommand does with the view
+struct Setup {
+    args: Args,
+    julia_c: Complex<f64>,
+    fractal: Fractal,
+    split: bool,
+    color_scheme: ColorScheme,
+    #[cfg(feature = "script")]
+    color_script: Option<ColorScript>,
+    color_name: String,
+    output_format: OutputFormat,
+    transform: Transform,
+    // what was asked for, before the exponential map unrolls it into `region`
+    view: (f64, f64, f64, f64),
+    region: (f64, f64, f64, f64),
+    auto_iterations: bool,
+    orbit: Option<Complex<f64>>,
+    ray_angles: Vec<(String, Angle)>,
+    equipotentials: usize,
+    annotated: bool,
+    report: Report,
+}
+
+impl Setup {
+    // None when --dump-config printed the settings instead
+    fn new(mode: Mode, mut args: Args, presets: &Presets, bookmarks: Bookmarks, strip_depth: Option<f64>) -> mandelbrot::Result<Option<Self>> {
+        if args.dump_config {
+            print!("{}", toml::to_string(&args).map_err(io::Error::other)?);
+            return Ok(None);
+        }
+
+        if let Some(threads) = args.threads
+            && let Err(e) = rayon::ThreadPoolBuilder::new().num_threads(threads).build_global()
+        {
+            warn!("could not configure {} threads: {}", threads, e);
+        }
+
+        validate_geometry(&mut args)?;
+        let lenient = args.lenient;
+        // --output-raw is --save, raw by default
+        if let Some(path) = args.output_raw.take() {
+            if args.save.is_some() {
+                warn!("--output-raw replaces --save");
+            }
+            args.save = Some(path);
+            args.image_format.get_or_insert_with(|| "raw".to_string());
+        }
+        let messages = match args.save.as_deref() {
+            _ if args.json => SILENT,
+            Some(STDOUT) => TO_STDERR,
+            _ => TO_STDOUT,
+        };
+        MESSAGES.store(messages, Ordering::Relaxed);
+        log::set_max_level(match args.verbose {
+            _ if args.quiet => LevelFilter::Warn,
+            0 => LevelFilter::Info,
+            1 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        });
+
+        let julia_c = args.julia_c.parse::<Complex<f64>>().unwrap_or_else(|e| {
+            let error = MandelbrotError::invalid(format_args!("julia constant '{}'", args.julia_c), e);
+            recover(lenient, error, &format!("using {}", DEFAULT_JULIA_C), DEFAULT_JULIA_C)
+        });
+
+        let phoenix_p = args.phoenix_p.parse::<Complex<f64>>().unwrap_or_else(|e| {
+            let error = MandelbrotError::invalid(format_args!("phoenix weight '{}'", args.phoenix_p), e);
+            recover(lenient, error, &format!("using {}", DEFAULT_PHOENIX_P), DEFAULT_PHOENIX_P)
+        });
+
+        let polynomial = Polynomial::parse(&args.polynomial)
+            .unwrap_or_else(|e| recover(lenient, e, "using z^3-1", Polynomial::default()));
+
+        let formula = args.formula.as_ref().and_then(|source| match Formula::parse(source) {
+            Ok(formula) => Some(Fractal::Formula(formula)),
+            Err(e) => recover(lenient, e, "using --fractal", None),
+        });
+
+        if !(args.power > 1.0 && args.power.is_finite()) {
+            let error = MandelbrotError::invalid(format_args!("--power {}", args.power), "it must be a number above 1");
+            args.power = recover(lenient, error, &format!("using {}", DEFAULT_POWER), DEFAULT_POWER);
+        }
+
+        let fractal = formula
+            .or_else(|| Fractal::from_str(&args.fractal, julia_c, args.power, phoenix_p, polynomial))
+            .unwrap_or_else(|| {
+                recover(lenient, MandelbrotError::unknown("fractal", &args.fractal), "using 'mandelbrot'", Fractal::Mandelbrot)
+            });
+
+        let split = args.split
+            && (matches!(fractal, Fractal::Mandelbrot) || {
+                let error = MandelbrotError::invalid("--split", "it pairs the mandelbrot set with its julia sets");
+                recover(lenient, error, "rendering without it", false)
+            });
+
+        if args.perturbation && !matches!(fractal, Fractal::Mandelbrot) {
+            warn!("perturbation only supports the mandelbrot fractal, ignoring");
+        }
+
+        let custom_gradient = match (&args.gradient, &args.gradient_file, &args.palette_file) {
+            (Some(spec), _, _) => Some(Gradient::from_spec(spec)),
+            (None, Some(path), _) => Some(Gradient::from_file(path)),
+            (None, None, Some(path)) => Some(Gradient::from_palette_file(path)),
+            (None, None, None) => None,
+        };
+
+        let color_scheme = match custom_gradient {
+            Some(Ok(gradient)) => ColorScheme::Custom(gradient),
+            Some(Err(e)) => {
+                let scheme = recover(lenient, e, &format!("using '{}'", args.color), ColorScheme::from_str(&args.color));
+                scheme.unwrap_or(ColorScheme::Classic)
+            }
+            None => ColorScheme::from_str(&args.color).unwrap_or_else(|| {
+                recover(lenient, MandelbrotError::unknown("color scheme", &args.color), "using 'classic'", ColorScheme::Classic)
+            }),
+        };
+
+        #[cfg(feature = "script")]
+        let color_script = args.color_script.as_deref().and_then(|path| match ColorScript::from_file(path) {
+            Ok(script) => Some(script),
+            Err(e) => recover(lenient, e, "using the color scheme", None),
+        });
+        #[cfg(not(feature = "script"))]
+        let color_script = args.color_script.as_deref().and_then(|path| {
+            let error = MandelbrotError::invalid(format_args!("--color-script {}", path), "this build doesn't have the script feature");
+            recover(lenient, error, "using the color scheme", None::<()>)
+        });
+
+        let color_name = match color_scheme {
+            _ if color_script.is_some() => args.color_script.clone().unwrap_or_default(),
+            ColorScheme::Custom(_) => "custom gradient".to_string(),
+            _ => args.color.clone(),
+        };
+
+        let color_scheme = match &args.interpolation {
+            Some(name) => match Interpolation::from_str(name) {
+                Some(interpolation) => color_scheme.with_interpolation(interpolation),
+                None => {
+                    let error = MandelbrotError::unknown("interpolation", name);
+                    recover(lenient, error, "keeping the scheme's own", color_scheme)
+                }
+            },
+            None => color_scheme,
+        };
+
+        let output_format = match args.format.to_lowercase().as_str() {
+            "auto" => renderer::detect_terminal_capabilities(),
+            "ascii" => OutputFormat::Ascii,
+            "extended" => OutputFormat::AsciiExtended,
+            "ansi256" => OutputFormat::Ansi256,
+            "truecolor" => OutputFormat::AnsiTrueColor,
+            "halfblock" => OutputFormat::HalfBlock {
+                truecolor: matches!(renderer::detect_terminal_capabilities(), OutputFormat::AnsiTrueColor),
+            },
+            "halfblock256" => OutputFormat::HalfBlock { truecolor: false },
+            "shade" => OutputFormat::Shade {
+                truecolor: matches!(renderer::detect_terminal_capabilities(), OutputFormat::AnsiTrueColor),
+            },
+            "shade256" => OutputFormat::Shade { truecolor: false },
+            "braille" => OutputFormat::Braille,
+            "kitty" => OutputFormat::Kitty,
+            "iterm2" | "iterm" => OutputFormat::Iterm2,
+            "image" => renderer::detect_image_protocol().unwrap_or_else(|| {
+                warn!("this terminal doesn't seem to show inline images, using kitty");
+                OutputFormat::Kitty
+            }),
+            _ => recover(
+                lenient,
+                MandelbrotError::unknown("format", &args.format),
+                "using auto-detect",
+                renderer::detect_terminal_capabilities(),
+            ),
+        };
+        let output_format = if output_format.is_image() && mode == Mode::Screensaver {
+            warn!("the screensaver draws with text, not inline images");
+            renderer::detect_terminal_capabilities()
+        } else {
+            output_format
+        };
+        let output_format = if output_format.uses_escapes() && args.save.is_none() && !renderer::enable_ansi() {
+            warn!("this console can't show colors, using ascii");
+            OutputFormat::Ascii
+        } else {
+            output_format
+        };
+
+        if args.fit {
+            match crossterm::terminal::size() {
+                Ok((columns, rows)) => {
+                    let (mut across, mut down) = output_format.pixels_per_cell();
+                    // images fill the window in real pixels when the terminal says how many there are
+                    if output_format.is_image()
+                        && let Ok(window) = crossterm::terminal::window_size()
+                        && window.width > 0
+                        && window.height > 0
+                    {
+                        across = window.width as usize / columns.max(1) as usize;
+                        down = window.height as usize / rows.max(1) as usize;
+                    }
+                    args.width = columns.max(1) as usize * across.max(1);
+                    args.height = rows.saturating_sub(HEADER_LINES).max(1) as usize * down.max(1);
+                }
+                Err(e) => warn!("could not get the terminal size ({}), using {}×{}", e, args.width, args.height),
+            }
+        }
+
+        let transform = Transform::from_str(&args.transform, (0.0, 0.0)).unwrap_or_else(|| {
+            recover(lenient, MandelbrotError::unknown("transform", &args.transform), "using 'none'", Transform::None)
+        });
+        let transform = if transform != Transform::None && (!matches!(mode, Mode::Render | Mode::Bench) || split || args.autozoom.is_some()) {
+            warn!("--transform only maps single renders of a view, ignoring it");
+            Transform::None
+        } else {
+            transform
+        };
+        let default_region = match (transform, &fractal) {
+            (Transform::Inversion, _) => INVERTED_REGION,
+            (_, Fractal::Lambda) => LAMBDA_REGION,
+            _ => DEFAULT_REGION,
+        };
+
+        // the view starts from a bookmark, preset or explicit bounds, then --center and --zoom/--scale
+        // move and resize it; text cells in a terminal are taller than image pixels
+        let pixel_aspect = if args.save.is_some() { 1.0 } else { output_format.pixel_aspect() };
+        let preset = args.preset.as_deref().and_then(|name| presets.get(name));
+        let bookmark = args.preset.as_deref().filter(|_| preset.is_none()).and_then(|name| bookmarks.get(name));
+        let region = if let Some(preset) = preset {
+            preset.region(args.width, args.height, pixel_aspect)
+        } else if let Some(bookmark) = bookmark {
+            mandelbrot::region_around(bookmark.center, bookmark.zoom, args.width, args.height, pixel_aspect)
+        } else if let Some(name) = &args.preset {
+            recover(lenient, MandelbrotError::unknown("preset", name), "using default", default_region)
+        } else {
+            let (x_min, x_max, y_min, y_max) = default_region;
+            (
+                args.xmin.unwrap_or(x_min),
+                args.xmax.unwrap_or(x_max),
+                args.ymin.unwrap_or(y_min),
+                args.ymax.unwrap_or(y_max),
+            )
+        };
+
+        let center = args.center.as_deref().and_then(|center| {
+            parse_point(center).or_else(|| {
+                let error = MandelbrotError::invalid(format_args!("center '{}'", center), "expected two finite numbers, re,im");
+                recover(lenient, error, "keeping the region's center", None)
+            })
+        });
+        let zoom = args.scale.map(|scale| DEFAULT_VIEW_WIDTH / scale).or(args.zoom);
+        let (x_min, x_max, y_min, y_max) = if center.is_some() || zoom.is_some() {
+            let (x_min, x_max, y_min, y_max) = region;
+            let center = center.unwrap_or(((x_min + x_max) / 2.0, (y_min + y_max) / 2.0));
+            let zoom = zoom.unwrap_or(DEFAULT_VIEW_WIDTH / (x_max - x_min));
+            mandelbrot::region_around(center, zoom, args.width, args.height, pixel_aspect)
+        } else {
+            region
+        };
+
+        let orbit = args.orbit.as_deref().and_then(|point| match point.parse::<Complex<f64>>() {
+            Ok(point) => Some(point),
+            Err(e) => recover(lenient, MandelbrotError::invalid(format_args!("orbit point '{}'", point), e), "drawing no orbit", None),
+        });
+        let mut ray_angles: Vec<(String, Angle)> = args
+            .rays
+            .as_deref()
+            .unwrap_or("")
+            .split(',')
+            .map(str::trim)
+            .filter(|angle| !angle.is_empty())
+            .filter_map(|angle| match Angle::from_str(angle) {
+                Some(parsed) => Some((angle.to_string(), parsed)),
+                None => {
+                    let error = MandelbrotError::invalid(format_args!("ray angle '{}'", angle), "expected a fraction of a turn, e.g. 1/3 or 0.25");
+                    recover(lenient, error, "leaving it out", None)
+                }
+            })
+            .collect();
+        let mut equipotentials = args.equipotentials;
+        if (!ray_angles.is_empty() || equipotentials > 0) && !rays::supports(&fractal) {
+            warn!("--rays and --equipotentials only support mandelbrot and julia, ignoring them");
+            (ray_angles, equipotentials) = (Vec::new(), 0);
+        }
+        let annotated = orbit.is_some() || args.axes || args.grid || args.scale_bar || !ray_angles.is_empty() || equipotentials > 0;
+        if args.rotate != 0.0 && matches!(mode, Mode::Explore | Mode::Screensaver | Mode::Serve) {
+            warn!("--rotate only turns renders and animations, ignoring it");
+            args.rotate = 0.0;
+        }
+        let annotated = if annotated && (args.rotate != 0.0 || transform != Transform::None) {
+            warn!("--orbit, --axes, --grid, --scale-bar, --rays and --equipotentials aren't drawn on rotated or transformed views, ignoring them");
+            false
+        } else {
+            annotated
+        };
+        if annotated && (mode != Mode::Render || args.autozoom.is_some() || args.import_data.is_some() || split) {
+            warn!("--orbit, --axes, --grid, --scale-bar, --rays and --equipotentials are only drawn on a single render of a computed view, ignoring them");
+        }
+
+        let auto_iterations = args.max_iterations.eq_ignore_ascii_case("auto");
+        args.iterations = max_iterations(&args, DEFAULT_VIEW_WIDTH / (x_max - x_min))?;
+        // renders print the count in their header
+        if auto_iterations && !matches!(mode, Mode::Render | Mode::Bench | Mode::Serve) {
+            info!("Iterations: {} (auto)", args.iterations);
+        }
+
+        // the exponential map is centered on the view and shows it out to the circle through its
+        // corners, on a strip that --rotate turns by sliding it along; `view` stays what was asked for
+        let view = (x_min, x_max, y_min, y_max);
+        let (transform, (x_min, x_max, y_min, y_max)) = match transform {
+            Transform::Exponential { .. } => {
+                let center = ((x_min + x_max) / 2.0, (y_min + y_max) / 2.0);
+                let radius = (x_max - x_min).hypot(y_max - y_min) / 2.0;
+                // a strip is as wide as it takes to go --depth times deeper at the scale its height sets
+                if let Some(depth) = strip_depth {
+                    args.width = ((depth.ln() * args.height as f64 * pixel_aspect / TAU).round() as usize).max(1);
+                }
+                let (x_min, x_max, y_min, y_max) = Transform::log_polar_region(radius, args.width, args.height, pixel_aspect);
+                let turn = args.rotate.to_radians();
+                (Transform::Exponential { center }, (x_min, x_max, y_min + turn, y_max + turn))
+            }
+            _ => (transform, (x_min, x_max, y_min, y_max)),
+        };
+        if args.perturbation && transform != Transform::None {
+            warn!("perturbation doesn't follow --transform, computing in plain f64");
+        }
+
+        let report = Report::new(mode, &args, &fractal, view, &color_name);
+
+        if let Some(name) = &args.bookmark_add {
+            let bookmark = Bookmark::from_region(view, args.iterations, &args.color);
+            add_bookmark(presets, bookmarks, name, bookmark)?;
+            info!("bookmarked as '{}'", name);
+        }
+
+        Ok(Some(Setup {
+            args,
+            julia_c,
+            fractal,
+            split,
+            color_scheme,
+            #[cfg(feature = "script")]
+            color_script,
+            color_name,
+            output_format,
+            transform,
+            view,
+            region: (x_min, x_max, y_min, y_max),
+            auto_iterations,
+            orbit,
+            ray_angles,
+            equipotentials,
+            annotated,
+            report,
+        }))
+    }
+
+    // the renderer, and how views get computed, for subcommands that go on to compute them
+    fn session(self) -> Session {
+        let args = &self.args;
+        let lenient = args.lenient;
+        let coloring = match ColoringMode::from_str(&args.coloring) {
+            Some(ColoringMode::Smooth) if args.no_smooth => ColoringMode::Linear,
+            Some(ColoringMode::Distance { .. }) => ColoringMode::Distance {
+                falloff: args.distance_falloff,
+            },
+            Some(coloring) => coloring,
+            None => {
+                let error = MandelbrotError::unknown("coloring", &args.coloring);
+                recover(lenient, error, "using 'smooth'", ColoringMode::Smooth)
+            }
+        };
+
+        let color_map = ColorMap::from_str(&args.color_map).unwrap_or_else(|| {
+            recover(lenient, MandelbrotError::unknown("color map", &args.color_map), "using 'linear'", ColorMap::Linear)
+        });
+
+        let interior = match InteriorMode::from_str(&args.interior) {
+            Some(InteriorMode::Distance { .. }) => {
+                if !matches!(self.fractal, Fractal::Mandelbrot | Fractal::Multibrot(_)) {
+                    warn!("interior distance only supports mandelbrot and multibrot, interiors stay flat");
+                }
+                InteriorMode::Distance {
+                    falloff: args.distance_falloff,
+                }
+            }
+            Some(interior) => interior,
+            None => {
+                let error = MandelbrotError::unknown("interior coloring", &args.interior);
+                recover(lenient, error, "using 'black'", InteriorMode::Black)
+            }
+        };
+
+        let layers = args.layers.as_deref().and_then(|path| match Layers::from_file(path) {
+            Ok(layers) => Some(layers),
+            Err(e) => recover(lenient, e, "drawing without layers", None),
+        });
+        let distance = matches!(coloring, ColoringMode::Distance { .. }) || layers.as_ref().is_some_and(Layers::needs_distance);
+
+        let backend = match Backend::from_str(&args.backend) {
+            Some(Backend::Gpu) if !Backend::Gpu.is_available() => {
+                warn!("no GPU adapter found (or built without the gpu feature), using the cpu");
+                Backend::Cpu
+            }
+            Some(Backend::Gpu) if distance => {
+                warn!("distance coloring and edge layers aren't supported on the gpu, using the cpu");
+                Backend::Cpu
+            }
+            Some(backend) => backend,
+            None => recover(lenient, MandelbrotError::unknown("backend", &args.backend), "using 'cpu'", Backend::Cpu),
+        };
+
+        if args.boundary_trace && distance {
+            warn!("boundary tracing doesn't work with distance coloring or edge layers, computing every pixel");
+        }
+
+        #[cfg(feature = "script")]
+        let final_z = self.color_script.as_ref().is_some_and(|script| !script.is_palette());
+        #[cfg(not(feature = "script"))]
+        let final_z = false;
+
+        // adaptive anti-aliasing computes one sample per pixel first and refines edges afterwards
+        let options = ComputeOptions {
+            perturbation: args.perturbation,
+            samples: if args.adaptive { 1 } else { args.samples as usize },
+            distance,
+            interior,
+            stripes: layers.as_ref().and_then(Layers::stripe_density),
+            early_bailout: !args.no_early_bailout,
+            backend,
+            boundary_trace: args.boundary_trace,
+            symmetry: !args.no_symmetry,
+            // the strip slid up by the turn already
+            rotation: if matches!(self.transform, Transform::Exponential { .. }) { 0.0 } else { args.rotate },
+            transform: self.transform,
+            bailout: args.bailout,
+            final_z,
+        };
+
+        let cache_dir = args.cache_dir.as_ref().map(PathBuf::from).or_else(Cache::default_dir);
+        if args.clear_cache {
+            match cache_dir.as_ref().map(|dir| Cache::new(dir).clear()) {
+                Some(Ok(cleared)) => info!("cleared {} views from the cache", cleared),
+                Some(Err(e)) => recover(lenient, e, "keeping the cache", ()),
+                None => warn!("no home directory, so no cache to clear"),
+            }
+        }
+        let cache = match (cache_dir, args.cache || args.cache_dir.is_some()) {
+            _ if args.no_cache => None,
+            (Some(dir), true) => Some(Cache::new(dir).with_limit(args.cache_limit.saturating_mul(1 << 20))),
+            (None, true) => {
+                warn!("no home directory to keep --cache in, computing everything");
+                None
+            }
+            (_, false) => None,
+        };
+        let farm = args.workers.as_deref().map(|workers| {
+            let addresses: Vec<&str> = workers.split(',').map(str::trim).filter(|address| !address.is_empty()).collect();
+            Farm::new(&addresses)
+        });
+
+        let image_format = args.image_format.as_deref().map(|format| {
+            ImageFormat::from_str(format).unwrap_or_else(|| {
+                recover(lenient, MandelbrotError::unknown("image format", format), "using ppm", ImageFormat::Ppm)
+            })
+        });
+
+        // tiles are written as they finish, so the image format has to support streaming
+        let tiled_format = match &args.save {
+            Some(filename) if args.tiled || args.resume => match image_format_for(image_format, filename) {
+                ImageFormat::Gif => {
+                    warn!("GIF can't be written tile by tile, rendering in one piece");
+                    None
+                }
+                format => Some(format),
+            },
+            None if args.tiled || args.resume => {
+                warn!("--tiled needs --save, rendering in one piece");
+                None
+            }
+            _ => None,
+        };
+        // each tile only sees its own iteration counts
+        let coloring = if tiled_format.is_some() && coloring == ColoringMode::Histogram {
+            warn!("histogram coloring can't be tiled, using 'smooth'");
+            ColoringMode::Smooth
+        } else {
+            coloring
+        };
+
+        let stream = args.stream
+            && if args.save.is_some() || args.json {
+                warn!("--stream only draws to the terminal, rendering in one piece");
+                false
+            } else if args.export_data.is_some() || args.contours.is_some() || args.checksum || args.stats || self.annotated {
+                warn!("--export-data, --contours, --checksum, --stats and overlays like --orbit need the whole image, rendering in one piece");
+                false
+            } else if coloring == ColoringMode::Histogram {
+                warn!("histogram coloring needs the whole image, rendering in one piece");
+                false
+            } else if matches!(self.output_format, OutputFormat::Braille) {
+                warn!("braille shading needs the whole image, rendering in one piece");
+                false
+            } else if self.output_format.is_image() {
+                warn!("inline images are sent whole, rendering in one piece");
+                false
+            } else {
+                true
+            };
+
+        let color_repeat = if args.color_repeat > 0.0 && args.color_repeat.is_finite() {
+            args.color_repeat
+        } else {
+            let error = MandelbrotError::invalid(format_args!("--color-repeat {}", args.color_repeat), "it must be positive");
+            recover(lenient, error, "using 1", 1.0)
+        };
+
+        let mut renderer = Renderer::new(self.color_scheme.clone(), self.output_format)
+            .with_coloring(coloring)
+            .with_interior(interior)
+            .with_color_map(color_map)
+            .with_color_cycle(color_repeat, args.color_offset);
+        if args.dither || args.no_dither {
+            renderer = renderer.with_dither(args.dither);
+        }
+        #[cfg(feature = "script")]
+        if let Some(script) = self.color_script.clone() {
+            renderer = renderer.with_color_script(script);
+        }
+        if let Some(layers) = layers {
+            renderer = renderer.with_layers(layers);
+        }
+
+        if args.hillshade {
+            let light = Lighting::from_str(&args.light).unwrap_or_else(|| {
+                let error = MandelbrotError::invalid(format_args!("--light {}", args.light), "it must be \"azimuth,elevation\" with the elevation between 0 and 90");
+                recover(lenient, error, "using 315,45", Lighting::default())
+            });
+            let relief = if args.relief >= 0.0 && args.relief.is_finite() {
+                args.relief
+            } else {
+                let error = MandelbrotError::invalid(format_args!("--relief {}", args.relief), "it must be zero or more");
+                recover(lenient, error, "using the default", lighting::DEFAULT_RELIEF)
+            };
+            renderer = renderer.with_lighting(light.with_relief(relief));
+        }
+
+        Session {
+            setup: self,
+            renderer,
+            views: Views { options, cache, farm },
+            image_format,
+            tiled_format,
+            stream,
+        }
+    }
+}
+
+struct Session {
+    setup: Setup,
+    renderer: Renderer,
+    views: Views,
+    image_format: Option<ImageFormat>,
+    // the format tiles stream out in, when they do
+    tiled_format: Option<ImageFormat>,
+    stream: bool,
+}
+
+impl Session {
+    fn print_header(&self) {
+        let Setup { args, fractal, region: (x_min, x_max, y_min, y_max), .. } = &self.setup;
+        info!("━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        info!("Fractal: {}", describe_fractal(fractal, args.formula.as_deref()));
+        info!("Resolution: {}×{}", args.width, args.height);
+        info!("Iterations: {}{}", args.iterations, if self.setup.auto_iterations { " (auto)" } else { "" });
+        info!("Region: x=[{:.4}, {:.4}], y=[{:.4}, {:.4}]", x_min, x_max, y_min, y_max);
+        info!("Colors: {}", self.setup.color_name);
+        match self.views.options.backend {
+            Backend::Cpu => info!("Threads: {}", rayon::current_num_threads()),
+            Backend::Gpu => info!("Backend: gpu"),
+        }
+        info!("");
+    }
+}
+
+// computes views, on the workers if there are any, or loads them from the cache when they've
+// been computed before
+struct Views {
+    options: ComputeOptions,
+    cache: Option<Cache>,
+    farm: Option<Farm>,
+}
+
+impl Views {
+    // `region` is where the view is before --rotate turns it
+    fn compute(&self, fractal: &Fractal, max_iters: usize, region: (f64, f64, f64, f64), width: usize, height: usize, progress: Option<&Progress>) -> RenderData {
+        let options = self.options;
+        let region = mandelbrot::rotated_region(region, options.rotation);
+        let calculate = || match &self.farm {
+            Some(farm) => {
+                let job = Job {
+                    fractal: *fractal,
+                    max_iters,
+                    region,
+                    width,
+                    height,
+                    options,
+                };
+                let render_data = farm.calculate(&job, progress);
+                report_failures(farm);
+                render_data
+            }
+            None => mandelbrot::calculate(fractal, max_iters, region, width, height, &options, progress),
+        };
+        let Some(cache) = &self.cache else {
+            return calculate();
+        };
+        let entry = cache.entry(fractal, max_iters, region, width, height, &options);
+        if let Some(render_data) = entry.load() {
+            debug!("loaded {} from the cache", entry.path().display());
+            return render_data;
+        }
+        debug!("computing {} for the cache", entry.path().display());
+        let render_data = calculate();
+        if progress.is_some_and(Progress::is_cancelled) {
+            return render_data;
+        }
+        if let Err(e) = entry.store(&render_data) {
+            warn!("could not cache the view: {}", e);
+        }
+        render_data
+    }
+}
+
+// runs a render that the first Ctrl-C stops partway instead of quitting; also returns whether
+// one did
+fn cancellable<T>(render: impl FnOnce() -> T) -> (T, bool) {
+    CANCELLABLE.store(true, Ordering::SeqCst);
+    let rendered = render();
+    CANCELLABLE.store(false, Ordering::SeqCst);
+    (rendered, INTERRUPTED.load(Ordering::SeqCst))
+}
+
+// says how much of an interrupted render got computed, and whether to save it to `save`: asked
+// on a terminal, and yes without one
+fn keep_partial(progress: &Progress, save: Option<&str>) -> bool {
+    warn!("interrupted with {:.0}% computed, the rest is black", progress.fraction_done() * 100.0);
+    let Some(filename) = save else {
+        return false;
+    };
+    if !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        return true;
+    }
+    eprint!("save the partial image to {}? [Y/n] ", output_name(filename));
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer).is_ok() && !answer.trim().to_lowercase().starts_with('n')
+}
+
+// a band of the palette across the terminal, or a few of its colors as hex where the terminal
+// has none
+fn show_palette(name: &str) {
+    let color_scheme = match ColorScheme::from_str(name) {
+        Some(color_scheme) => color_scheme,
+        None if Path::new(name).is_file() => {
+            let gradient = if name.ends_with(".toml") { Gradient::from_file(name) } else { Gradient::from_palette_file(name) };
+            ColorScheme::Custom(gradient.unwrap_or_else(|e| fail(e)))
+        }
+        None => fail(MandelbrotError::unknown("color scheme", name)),
+    };
+
+    let format = renderer::detect_terminal_capabilities();
+    let columns = crossterm::terminal::size().map_or(PALETTE_COLUMNS, |(columns, _)| columns as usize).max(2);
+    let colors = (0..columns).map(|i| color_scheme.color_at(i as f64 / (columns - 1) as f64));
+    let band: String = match format {
+        OutputFormat::AnsiTrueColor => colors.map(|c| format!("\x1b[48;2;{};{};{}m ", c.r, c.g, c.b)).collect(),
+        OutputFormat::Ansi256 => colors.map(|c| format!("\x1b[48;5;{}m ", renderer::rgb_to_ansi256(c.r, c.g, c.b))).collect(),
+        _ => {
+            let hex: Vec<String> = (0..=8)
+                .map(|i| color_scheme.color_at(i as f64 / 8.0))
+                .map(|c| format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b))
+                .collect();
+            sayln!("{}: {}", name, hex.join(" "));
+            return;
+        }
+    };
+    sayln!("{}", name);
+    for _ in 0..2 {
+        sayln!("{}\x1b[0m", band);
+    }
+}
+
+// thumbnails of the presets side by side across the terminal, each over its name, in the
+// preset's suggested fractal, iterations and colors
+fn preview_presets(presets: &Presets, size: usize) {
+    // half blocks draw twice as many pixels as colored cells, and inline images don't line up
+    let format = match renderer::detect_terminal_capabilities() {
+        OutputFormat::AnsiTrueColor => OutputFormat::HalfBlock { truecolor: true },
+        OutputFormat::Ansi256 => OutputFormat::HalfBlock { truecolor: false },
+        format => format,
+    };
+    let (across, down) = format.pixels_per_cell();
+    let (columns, rows) = (size.max(4), (size * 3 / 8).max(2));
+    let (width, height) = (columns * across, rows * down);
+    let terminal = crossterm::terminal::size().map_or(PALETTE_COLUMNS, |(columns, _)| columns as usize);
+    let per_line = ((terminal + PREVIEW_GAP.len()) / (columns + PREVIEW_GAP.len())).max(1);
+
+    let presets: Vec<&Preset> = presets.iter().collect();
+    for line in presets.chunks(per_line) {
+        let thumbnails: Vec<String> = line
+            .iter()
+            .map(|preset| {
+                let fractal = preset.fractal.as_deref().unwrap_or("mandelbrot");
+                let fractal = Fractal::from_name_with_defaults(fractal).unwrap_or(Fractal::Mandelbrot);
+                let color_scheme = preset.color.as_deref().and_then(ColorScheme::from_str).unwrap_or(ColorScheme::Classic);
+                let iterations = preset.iterations.unwrap_or_else(|| mandelbrot::auto_iterations(preset.zoom));
+                let region = preset.region(width, height, format.pixel_aspect());
+                let data = mandelbrot::calculate(&fractal, iterations, region, width, height, &ComputeOptions::default(), None);
+                Renderer::new(color_scheme, format).render_to_string(&data)
+            })
+            .collect();
+        let lines: Vec<Vec<&str>> = thumbnails.iter().map(|thumbnail| thumbnail.lines().collect()).collect();
+        for row in 0..rows {
+            let cells: Vec<&str> = lines.iter().map(|lines| lines.get(row).copied().unwrap_or_default()).collect();
+            sayln!("{}", cells.join(PREVIEW_GAP));
+        }
+        let names: Vec<String> = line.iter().map(|preset| format!("{:columns$.columns$}", preset.name, columns = columns)).collect();
+        sayln!("{}\n", names.join(PREVIEW_GAP).trim_end());
+    }
+}
+
+// answers coordinators on `listen` until killed, each connection on its own thread
+fn serve_worker(listen: &str, threads: Option<usize>) {
+    if let Some(threads) = threads
+        && let Err(e) = rayon::ThreadPoolBuilder::new().num_threads(threads).build_global()
+    {
+        warn!("could not configure {} threads: {}", threads, e);
+    }
+    let listener = TcpListener::bind(listen).unwrap_or_else(|e| fail(MandelbrotError::file(listen, e)));
+    info!("worker listening on {} with {} threads", listen, rayon::current_num_threads());
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("could not accept a connection: {}", e);
+                continue;
+            }
+        };
+        let peer = stream.peer_addr().map_or_else(|_| "?".to_string(), |address| address.to_string());
+        thread::spawn(move || match farm::serve_connection(stream) {
+            Ok(jobs) => info!("{}: computed {} bands", peer, jobs),
+            Err(e) => warn!("{}: {}", peer, e),
+        });
+    }
+}
+
+// warns about the workers that dropped out of `farm`
+fn report_failures(farm: &Farm) {
+    for (worker, e) in farm.take_failures() {
+        warn!("worker {} failed ({}), computing its share here", worker, e);
+    }
+}
+
+fn parse_levels(s: &str) -> Option<Vec<f64>> {
+    s.split(',').map(|level| level.trim().parse().ok()).collect()
+}
+
+fn print_checksums(renderer: &Renderer, render_data: &RenderData) {
+    sayln!("\n data checksum: {}", render_data.checksum());
+    sayln!(" rgb checksum:  {}", renderer.rgb_checksum(render_data));
+}
+
+fn save(renderer: &Renderer, render_data: &RenderData, filename: &str, format: Option<ImageFormat>, report: &mut Report) {
+    let image_format = image_format_for(format, filename);
+
+    info!("\n saving to {}...", output_name(filename));
+    let result = create(filename).and_then(|out| renderer.write_image(render_data, out, image_format));
+    match result {
+        Ok(_) => info!("saved successfully"),
+        Err(e) => fail(MandelbrotError::file(filename, e)),
+    }
+    report.output(filename);
+}
+
+// `filename` created for writing, or stdout for "-"
+fn create(filename: &str) -> io::Result<Box<dyn Write>> {
+    if filename == STDOUT {
+        return Ok(Box::new(BufWriter::new(io::stdout())));
+    }
+    Ok(Box::new(BufWriter::new(File::create(filename)?)))
+}
+
+// `filename` as messages name it
+fn output_name(filename: &str) -> &str {
+    if filename == STDOUT { "stdout" } else { filename }
+}
+
+// draws `render_data` in the terminal, unless stdout carries the image or --json's record
+fn show(renderer: &Renderer, render_data: &RenderData) {
+    if MESSAGES.load(Ordering::Relaxed) == TO_STDOUT {
+        renderer.render_to_terminal(render_data);
+    }
+}
+
+// the record --json prints at the end of a run, for scripts that start many of them
+#[derive(Serialize)]
+struct Report {
+    mode: &'static str,
+    fractal: String,
+    region: (f64, f64, f64, f64),
+    width: usize,
+    height: usize,
+    iterations: usize,
+    colors: String,
+    // every setting, from the command line and --config alike
+    settings: serde_json::Value,
+    // files written in order, "-" for stdout
+    outputs: Vec<String>,
+    seconds: f64,
+    // of the last view rendered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<Stats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checksums: Option<Checksums>,
+    // seconds each run of bench took
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    runs: Vec<f64>,
+    #[serde(skip)]
+    enabled: bool,
+    #[serde(skip)]
+    checksum: bool,
+    #[serde(skip)]
+    started: Instant,
+}
+
+#[derive(Serialize)]
+struct Checksums {
+    data: String,
+    rgb: String,
+}
+
+impl Report {
+    fn new(mode: Mode, args: &Args, fractal: &Fractal, region: (f64, f64, f64, f64), color_name: &str) -> Self {
+        Report {
+            mode: match mode {
+                Mode::Render => "render",
+                Mode::Explore => "explore",
+                Mode::Animate => "animate",
+                Mode::Screensaver => "screensaver",
+                Mode::Bench => "bench",
+                Mode::Serve => "serve",
+            },
+            fractal: describe_fractal(fractal, args.formula.as_deref()),
+            region,
+            width: args.width,
+            height: args.height,
+            iterations: args.iterations,
+            colors: color_name.to_string(),
+            settings: serde_json::to_value(args).unwrap_or_default(),
+            outputs: Vec::new(),
+            seconds: 0.0,
+            stats: None,
+            checksums: None,
+            runs: Vec::new(),
+            enabled: args.json,
+            checksum: args.checksum,
+            started: Instant::now(),
+        }
+    }
+
+    fn record(&mut self, renderer: &Renderer, render_data: &RenderData) {
+        if !self.enabled {
+            return;
+        }
+        self.stats = Some(Stats::of(render_data));
+        if self.checksum {
+            self.checksums = Some(Checksums {
+                data: render_data.checksum(),
+                rgb: renderer.rgb_checksum(render_data),
+            });
+        }
+    }
+
+    fn output(&mut self, path: &str) {
+        // frames written to stdout one after another are one output
+        if !self.outputs.iter().any(|output| output == path) {
+            self.outputs.push(path.to_string());
+        }
+    }
+
+    fn finish(mut self) {
+        if !self.enabled {
+            return;
+        }
+        self.seconds = self.started.elapsed().as_secs_f64();
+        let json = serde_json::to_string_pretty(&self).unwrap_or_else(|e| fail(io::Error::other(e).into()));
+        // stdout may be carrying the image
+        if self.outputs.iter().any(|output| output == STDOUT) {
+            eprintln!("{}", json);
+        } else {
+            println!("{}", json);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the view settings of `mandelbrot render` with `flags`
+    fn render_args(flags: &[&str]) -> Args {
+        let command_line = ["mandelbrot", "render"].iter().chain(flags);
+        let Some(Command::Render(args)) = Cli::try_parse_from(command_line).unwrap().command else {
+            unreachable!("the command line starts with render");
+        };
+        args
+    }
+
+    fn assert_invalid(flags: &[&str], result: mandelbrot::Result<impl std::fmt::Debug>) {
+        match result {
+            Err(error @ MandelbrotError::Invalid { .. }) => assert_eq!(error.exit_code(), 2, "{:?}", flags),
+            other => panic!("{:?} gave {:?}, not an invalid setting", flags, other),
+        }
+    }
+
+    #[test]
+    fn test_bad_geometry_is_invalid() {
+        let bad: [&[&str]; 12] = [
+            &["--width", "0"],
+            &["--height", "0"],
+            &["--tile-rows", "0"],
+            &["--xmin", "NaN"],
+            &["--ymax", "inf"],
+            &["--xmin", "1", "--xmax", "1"],
+            &["--ymin=-0.5", "--ymax=-0.5"],
+            &["--rotate", "NaN"],
+            &["--bailout", "1.5"],
+            &["--zoom", "0"],
+            &["--zoom=-2"],
+            &["--scale", "inf"],
+        ];
+        for flags in bad {
+            assert_invalid(flags, validate_geometry(&mut render_args(flags)));
+        }
+    }
+
+    #[test]
+    fn test_bad_iterations_are_invalid() {
+        for iterations in ["0", "many", "-5"] {
+            let flags = [&format!("--iterations={}", iterations)[..]];
+            assert_invalid(&flags, max_iterations(&render_args(&flags), 1.0));
+        }
+        assert_eq!(max_iterations(&render_args(&["--iterations", "300"]), 1.0).unwrap(), 300);
+        assert_eq!(max_iterations(&render_args(&["--iterations", "auto"]), 1e6).unwrap(), mandelbrot::auto_iterations(1e6));
+    }
+
+    #[test]
+    fn test_lenient_geometry_is_fixed_up() {
+        let mut args = render_args(&["--lenient", "--width", "0", "--zoom", "0", "--xmin", "1", "--xmax", "1", "--iterations", "0"]);
+        validate_geometry(&mut args).unwrap();
+        assert_eq!((args.width, args.zoom, args.xmin, args.xmax), (1, None, None, None));
+        assert_eq!(max_iterations(&args, 1.0).unwrap(), 1);
+
+        // inverted bounds aren't an error, they're swapped
+        let mut args = render_args(&["--xmin", "1", "--xmax=-2", "--ymin", "1.5"]);
+        validate_geometry(&mut args).unwrap();
+        assert_eq!((args.xmin, args.xmax, args.ymin, args.ymax), (Some(-2.0), Some(1.0), Some(1.0), Some(1.5)));
+    }
+}
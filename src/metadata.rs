@@ -0,0 +1,64 @@
+// Text stored in PNG files alongside the pixels, so an image can say how it was made: iTXt
+// chunks of UTF-8 text, each under a keyword, which image viewers and exiftool show as well.
+
+use std::fs::File;
+use std::io::{self, BufReader, Write};
+
+use crate::error::{MandelbrotError, Result};
+
+/// Keyword of the chunk holding the full render settings, in the `--config` TOML format.
+pub const SETTINGS: &str = "Settings";
+
+/// Adds `text`, as (keyword, text) pairs, to the chunks `encoder` writes ahead of the pixels.
+pub fn add_text<W: Write>(encoder: &mut png::Encoder<W>, text: &[(String, String)]) -> io::Result<()> {
+    for (keyword, value) in text {
+        encoder.add_itxt_chunk(keyword.clone(), value.clone()).map_err(io::Error::other)?;
+    }
+    Ok(())
+}
+
+/// The text chunks of the PNG file at `path`, of any of the three kinds, as (keyword, text)
+/// pairs in the order they come in the file.
+pub fn read_text(path: &str) -> Result<Vec<(String, String)>> {
+    let file = File::open(path).map_err(|e| MandelbrotError::file(path, e))?;
+    let reader = png::Decoder::new(BufReader::new(file))
+        .read_info()
+        .map_err(|e| MandelbrotError::file(path, io::Error::other(e)))?;
+    let info = reader.info();
+
+    let mut text: Vec<(String, String)> = info
+        .uncompressed_latin1_text
+        .iter()
+        .map(|chunk| (chunk.keyword.clone(), chunk.text.clone()))
+        .collect();
+    let compressed = info.compressed_latin1_text.iter().map(|chunk| (chunk.keyword.clone(), chunk.get_text()));
+    let utf8 = info.utf8_text.iter().map(|chunk| (chunk.keyword.clone(), chunk.get_text()));
+    for (keyword, value) in compressed.chain(utf8) {
+        let value = value.map_err(|e| MandelbrotError::file(path, io::Error::other(e)))?;
+        text.push((keyword, value));
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_round_trip() {
+        let text = vec![
+            ("Software".to_string(), "mandelbrot".to_string()),
+            (SETTINGS.to_string(), "formula = \"z² + c\"\n".to_string()),
+        ];
+        let path = std::env::temp_dir().join("mandelbrot_test_metadata.png");
+        let mut encoder = png::Encoder::new(File::create(&path).unwrap(), 1, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        add_text(&mut encoder, &text).unwrap();
+        encoder.write_header().unwrap().write_image_data(&[0, 0, 0]).unwrap();
+
+        let path = path.to_str().unwrap();
+        assert_eq!(read_text(path).unwrap(), text);
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(read_text(path).unwrap_err().exit_code(), 1);
+    }
+}
@@ -0,0 +1,126 @@
+// Locating mini-Mandelbrots (minibrots) from a rough position and their period, to plan deep
+// zooms onto them.
+//
+// The center of a minibrot's main cardioid, its nucleus, is a c whose orbit comes back to 0
+// after `period` steps, a root of z_p(c) = 0 with z_0 = 0. Newton's method finds it from a
+// guess close enough to it, stepping by z_p / (dz_p/dc) with dz/dc iterated alongside z.
+//
+// Its size and orientation relative to the whole set come from the same orbit, as in
+// Heiland-Allen's estimate: with l_0 = 1, l_i = 2·z_i·l_(i-1) and b the sum of 1/l_i, both
+// for i < p,
+//     s = 1 / (b · l_(p-1)²)
+// is about the complex scale factor from the whole set onto the minibrot.
+
+use num::complex::Complex;
+
+use crate::error::MandelbrotError;
+
+const NEWTON_STEPS: usize = 64;
+
+/// A minibrot found by `locate`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Minibrot {
+    /// the nucleus, at the center of its main cardioid
+    pub center: Complex<f64>,
+    pub period: usize,
+    /// scale factor from the whole set onto the minibrot, whose argument is how far it's
+    /// turned; see `size` and `orientation`
+    pub scale: Complex<f64>,
+}
+
+impl Minibrot {
+    /// Size relative to the whole set.
+    pub fn size(&self) -> f64 {
+        self.scale.norm()
+    }
+
+    /// Degrees the minibrot is turned counterclockwise from the whole set.
+    pub fn orientation(&self) -> f64 {
+        self.scale.arg().to_degrees()
+    }
+
+    /// The zoom, as for `region_around`, that frames the minibrot as the default view frames
+    /// the whole set.
+    pub fn zoom(&self) -> f64 {
+        1.0 / self.size()
+    }
+}
+
+/// The minibrot of `period` whose nucleus Newton's method reaches from `guess`; an error if it
+/// doesn't converge there, or reaches a nucleus of a smaller period dividing `period`.
+pub fn locate(guess: Complex<f64>, period: usize) -> crate::Result<Minibrot> {
+    let what = || format!("period {} near {}", period, guess);
+    if period == 0 {
+        return Err(MandelbrotError::invalid(what(), "the period must be at least 1"));
+    }
+
+    let mut c = guess;
+    let mut converged = false;
+    for _ in 0..NEWTON_STEPS {
+        let (z, dc) = orbit(c, period);
+        let step = z / dc;
+        if !step.is_finite() {
+            break;
+        }
+        c -= step;
+        if step.norm() <= c.norm().max(f64::MIN_POSITIVE) * f64::EPSILON * 4.0 {
+            converged = true;
+            break;
+        }
+    }
+    if !converged || !c.is_finite() {
+        return Err(MandelbrotError::invalid(what(), "Newton's method found no nucleus from there; try a closer center"));
+    }
+
+    // a nucleus of a period dividing this one is a root of z_p too
+    let tolerance = c.norm().max(1.0) * f64::EPSILON * 64.0;
+    if let Some(smaller) = (1..period).filter(|&k| period.is_multiple_of(k)).find(|&k| {
+        let (z, dc) = orbit(c, k);
+        (z / dc).norm() < tolerance
+    }) {
+        return Err(MandelbrotError::invalid(what(), format_args!("Newton's method went to the nucleus of period {} at {}", smaller, c)));
+    }
+
+    let (mut z, mut l, mut b) = (Complex::new(0.0, 0.0), Complex::new(1.0, 0.0), Complex::new(1.0, 0.0));
+    for _ in 1..period {
+        z = z * z + c;
+        l *= z * 2.0;
+        b += l.inv();
+    }
+    Ok(Minibrot {
+        center: c,
+        period,
+        scale: (b * l * l).inv(),
+    })
+}
+
+// z_n(c) and dz_n/dc
+fn orbit(c: Complex<f64>, n: usize) -> (Complex<f64>, Complex<f64>) {
+    let (mut z, mut dc) = (Complex::new(0.0, 0.0), Complex::new(0.0, 0.0));
+    for _ in 0..n {
+        dc = z * dc * 2.0 + 1.0;
+        z = z * z + c;
+    }
+    (z, dc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locates_the_period_3_minibrot() {
+        let minibrot = locate(Complex::new(-1.75, 0.01), 3).unwrap();
+        assert!((minibrot.center - Complex::new(-1.754_877_666_246_693, 0.0)).norm() < 1e-14, "{}", minibrot.center);
+        // about 1/52 of the whole set, and the right way up along the real axis
+        assert!((minibrot.size() - 0.019).abs() < 0.001, "{}", minibrot.size());
+        assert!(minibrot.orientation().abs() < 1e-6);
+
+        // the main cardioid is the whole set, at its own size
+        let whole = locate(Complex::new(0.1, 0.1), 1).unwrap();
+        assert!(whole.center.norm() < 1e-15 && whole.size() == 1.0);
+        // from near 0, Newton on z_2 goes to the period 1 nucleus at 0 instead
+        assert!(locate(Complex::new(0.01, 0.0), 2).is_err());
+        assert!(locate(Complex::new(0.0, 0.0), 0).is_err());
+    }
+}
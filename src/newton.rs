@@ -0,0 +1,338 @@
+// Newton fractals: each point is the starting guess of Newton's method, z ← z - p(z)/p'(z),
+// for a polynomial p, and is colored by the root it converges to and how fast.
+//
+// Polynomials are written as a sum of terms in z, e.g. "z^3 - 1", "2z^4 + (1+2i)*z - 3i".
+// The roots are found once up front (Durand–Kerner), so the per-point loop only has to check
+// how close z has come to each of them.
+
+use std::fmt;
+
+use num::complex::Complex;
+use serde::{Deserialize, Serialize};
+
+use crate::error::MandelbrotError;
+use crate::fractal::EscapeResult;
+
+/// Highest supported degree, which keeps `Polynomial` a fixed-size `Copy` value.
+pub const MAX_DEGREE: usize = 12;
+// a point has converged once it is this close to a root
+const ROOT_TOLERANCE: f64 = 1e-6;
+// Durand–Kerner approximations closer than this are one repeated root
+const DUPLICATE_TOLERANCE: f64 = 1e-4;
+const ROOT_SEARCH_ITERATIONS: usize = 1000;
+
+/// A polynomial in z with complex coefficients, along with its distinct roots.
+///
+/// Serializes as its coefficients; the roots are found again when it's deserialized.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(into = "Vec<Complex<f64>>", try_from = "Vec<Complex<f64>>")]
+pub struct Polynomial {
+    /// coefficients from z^0 up to z^degree
+    coefficients: [Complex<f64>; MAX_DEGREE + 1],
+    degree: usize,
+    roots: [Complex<f64>; MAX_DEGREE],
+    root_count: usize,
+}
+
+impl From<Polynomial> for Vec<Complex<f64>> {
+    fn from(polynomial: Polynomial) -> Self {
+        polynomial.coefficients[..=polynomial.degree].to_vec()
+    }
+}
+
+impl TryFrom<Vec<Complex<f64>>> for Polynomial {
+    type Error = String;
+
+    fn try_from(terms: Vec<Complex<f64>>) -> Result<Self, String> {
+        if terms.len() > MAX_DEGREE + 1 {
+            return Err(format!("degree {} is above the maximum of {}", terms.len() - 1, MAX_DEGREE));
+        }
+        let mut coefficients = [Complex::new(0.0, 0.0); MAX_DEGREE + 1];
+        coefficients[..terms.len()].copy_from_slice(&terms);
+        Self::from_coefficients(coefficients)
+    }
+}
+
+impl Default for Polynomial {
+    /// z³ - 1, whose basins around the three cube roots of unity are the classic picture.
+    fn default() -> Self {
+        Self::parse("z^3-1").unwrap()
+    }
+}
+
+impl Polynomial {
+    /// Parses a sum of terms such as `3z^2`, `-z`, `(1+2i)*z^4`, `2.5i` or `7`.
+    pub fn parse(s: &str) -> crate::Result<Self> {
+        Self::parse_terms(s).map_err(|reason| MandelbrotError::invalid(format_args!("polynomial '{}'", s), reason))
+    }
+
+    fn parse_terms(s: &str) -> Result<Self, String> {
+        let s: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+        if s.is_empty() {
+            return Err("empty polynomial".to_string());
+        }
+
+        let mut coefficients = [Complex::new(0.0, 0.0); MAX_DEGREE + 1];
+        for (sign, term) in split_terms(&s)? {
+            let (coefficient, power) = parse_term(term).ok_or_else(|| format!("invalid term '{}'", term))?;
+            if power > MAX_DEGREE {
+                return Err(format!("degree {} is above the maximum of {}", power, MAX_DEGREE));
+            }
+            coefficients[power] += coefficient * sign;
+        }
+        Self::from_coefficients(coefficients)
+    }
+
+    fn from_coefficients(coefficients: [Complex<f64>; MAX_DEGREE + 1]) -> Result<Self, String> {
+        let degree = (0..=MAX_DEGREE).rev().find(|&d| coefficients[d] != Complex::new(0.0, 0.0)).unwrap_or(0);
+        if degree == 0 {
+            return Err("it has no roots without a term in z".to_string());
+        }
+
+        let mut polynomial = Self {
+            coefficients,
+            degree,
+            roots: [Complex::new(0.0, 0.0); MAX_DEGREE],
+            root_count: 0,
+        };
+        polynomial.find_roots();
+        Ok(polynomial)
+    }
+
+    pub fn degree(&self) -> usize {
+        self.degree
+    }
+
+    /// The distinct roots, in a fixed order that `EscapeResult::root` indexes into.
+    pub fn roots(&self) -> &[Complex<f64>] {
+        &self.roots[..self.root_count]
+    }
+
+    /// p(z) and p'(z), by Horner's scheme.
+    pub fn eval(&self, z: Complex<f64>) -> (Complex<f64>, Complex<f64>) {
+        let mut p = self.coefficients[self.degree];
+        let mut dp = Complex::new(0.0, 0.0);
+        for &coefficient in self.coefficients[..self.degree].iter().rev() {
+            dp = dp * z + p;
+            p = p * z + coefficient;
+        }
+        (p, dp)
+    }
+
+    /// One step of Newton's method; a zero derivative leaves z where it is.
+    pub fn newton_step(&self, z: Complex<f64>) -> Complex<f64> {
+        let (p, dp) = self.eval(z);
+        let step = p / dp;
+        if step.is_finite() { z - step } else { z }
+    }
+
+    /// Runs Newton's method from `z0` until it lands on a root. Converged points report the
+    /// steps taken and which root; the rest count as `max_iters` with no root.
+    pub fn converge(&self, z0: Complex<f64>, max_iters: usize) -> EscapeResult {
+        let mut z = z0;
+        for i in 0..max_iters {
+            let (p, dp) = self.eval(z);
+            let step = p / dp;
+            if !step.is_finite() {
+                break;
+            }
+            z -= step;
+
+            if let Some(root) = self.roots().iter().position(|&root| (z - root).norm() < ROOT_TOLERANCE) {
+                return EscapeResult {
+                    iterations: i + 1,
+                    z_norm: z.norm(),
+                    distance: 0.0,
+                    interior: 0.0,
+                    stripe: 0.0,
+                    root: Some(root),
+                    z: Some(z),
+                    derivative: None,
+                };
+            }
+        }
+        EscapeResult {
+            iterations: max_iters,
+            z_norm: z.norm(),
+            distance: 0.0,
+            interior: 0.0,
+            stripe: 0.0,
+            root: None,
+            z: Some(z),
+            derivative: None,
+        }
+    }
+
+    // Durand–Kerner on the monic polynomial, then a few Newton steps to polish repeated roots,
+    // which Durand–Kerner only approaches slowly, before merging them
+    fn find_roots(&mut self) {
+        let leading = self.coefficients[self.degree];
+        let monic = |z: Complex<f64>| self.eval(z).0 / leading;
+
+        let seed = Complex::new(0.4, 0.9);
+        let mut guesses: Vec<Complex<f64>> = (0..self.degree).map(|k| seed.powu(k as u32)).collect();
+        for _ in 0..ROOT_SEARCH_ITERATIONS {
+            let mut change: f64 = 0.0;
+            for k in 0..guesses.len() {
+                let denominator = (0..guesses.len())
+                    .filter(|&j| j != k)
+                    .fold(Complex::new(1.0, 0.0), |product, j| product * (guesses[k] - guesses[j]));
+                let step = monic(guesses[k]) / denominator;
+                if step.is_finite() {
+                    guesses[k] -= step;
+                    change = change.max(step.norm());
+                }
+            }
+            if change < 1e-14 {
+                break;
+            }
+        }
+
+        for guess in guesses {
+            let polished = (0..50).fold(guess, |z, _| self.newton_step(z));
+            if self.roots().iter().all(|&root| (root - polished).norm() > DUPLICATE_TOLERANCE) {
+                self.roots[self.root_count] = polished;
+                self.root_count += 1;
+            }
+        }
+    }
+}
+
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut first = true;
+        for power in (0..=self.degree).rev() {
+            let coefficient = self.coefficients[power];
+            if coefficient == Complex::new(0.0, 0.0) {
+                continue;
+            }
+
+            // real coefficients get the sign pulled out in front, complex ones are bracketed
+            let (negative, magnitude) = if coefficient.im == 0.0 {
+                (coefficient.re < 0.0, format!("{}", coefficient.re.abs()))
+            } else {
+                (false, format!("({})", coefficient))
+            };
+            match (first, negative) {
+                (true, true) => write!(f, "-")?,
+                (true, false) => {}
+                (false, true) => write!(f, " - ")?,
+                (false, false) => write!(f, " + ")?,
+            }
+            first = false;
+
+            if power == 0 || magnitude != "1" {
+                write!(f, "{}", magnitude)?;
+            }
+            match power {
+                0 => {}
+                1 => write!(f, "z")?,
+                _ => write!(f, "z^{}", power)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// splits at the + and - between terms, keeping each term's sign; signs inside brackets or
+// of a float exponent (1e-3) belong to the term
+fn split_terms(s: &str) -> Result<Vec<(f64, &str)>, String> {
+    let mut terms = Vec::new();
+    let (mut depth, mut start, mut sign) = (0i32, 0, 1.0);
+    let bytes = s.as_bytes();
+    for (i, &byte) in bytes.iter().enumerate() {
+        match byte {
+            b'(' => depth += 1,
+            b')' => depth -= 1,
+            b'+' | b'-' if depth == 0 && !(i > 0 && matches!(bytes[i - 1], b'e' | b'E')) => {
+                if i > start {
+                    terms.push((sign, &s[start..i]));
+                } else if i > 0 {
+                    return Err(format!("missing term before '{}'", &s[i..]));
+                }
+                sign = if byte == b'-' { -1.0 } else { 1.0 };
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 {
+        return Err("unbalanced brackets".to_string());
+    }
+    if start >= s.len() {
+        return Err("missing term at the end".to_string());
+    }
+    terms.push((sign, &s[start..]));
+    Ok(terms)
+}
+
+// a coefficient, optionally followed by `*`, `z` and `^power`
+fn parse_term(term: &str) -> Option<(Complex<f64>, usize)> {
+    let Some((coefficient, rest)) = term.split_once('z') else {
+        return Some((parse_coefficient(term)?, 0));
+    };
+    let coefficient = coefficient.strip_suffix('*').unwrap_or(coefficient);
+    let coefficient = if coefficient.is_empty() { Complex::new(1.0, 0.0) } else { parse_coefficient(coefficient)? };
+    let power = match rest {
+        "" => 1,
+        _ => rest.strip_prefix('^')?.parse().ok()?,
+    };
+    Some((coefficient, power))
+}
+
+// `2.5`, `3i`, `i` or a bracketed complex number `(1+2i)`
+fn parse_coefficient(s: &str) -> Option<Complex<f64>> {
+    if let Some(inner) = s.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+        return inner.parse().ok();
+    }
+    match s.strip_suffix('i') {
+        Some("") => Some(Complex::new(0.0, 1.0)),
+        Some(imaginary) => Some(Complex::new(0.0, imaginary.parse().ok()?)),
+        None => Some(Complex::new(s.parse().ok()?, 0.0)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_and_finds_roots() {
+        let cubic = Polynomial::parse("z^3 - 1").unwrap();
+        assert_eq!(cubic.degree(), 3);
+        assert_eq!(cubic.roots().len(), 3);
+        for &root in cubic.roots() {
+            assert!((root.powu(3) - 1.0).norm() < 1e-9);
+        }
+        assert_eq!(cubic.to_string(), "z^3 - 1");
+
+        let mixed = Polynomial::parse("2z^2 + (1+2i)*z - 3i + z").unwrap();
+        let (p, dp) = mixed.eval(Complex::new(1.0, 1.0));
+        // 2(2i) + (2+2i)(1+i) - 3i = 4i + 4i - 3i, p' = 4z + 2 + 2i
+        assert!((p - Complex::new(0.0, 5.0)).norm() < 1e-12);
+        assert!((dp - Complex::new(6.0, 6.0)).norm() < 1e-12);
+
+        // a repeated root counts once
+        assert_eq!(Polynomial::parse("z^2-2z+1").unwrap().roots().len(), 1);
+
+        for invalid in ["", "7", "z^", "z^3 -", "2x+1", "(1+i*z", "z^13"] {
+            assert!(Polynomial::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_points_converge_to_nearest_root() {
+        let cubic = Polynomial::default();
+        let root_near = |z: Complex<f64>| {
+            let result = cubic.converge(z, 50);
+            assert!(result.iterations < 50);
+            cubic.roots()[result.root.unwrap()]
+        };
+        assert!((root_near(Complex::new(1.1, 0.1)) - Complex::new(1.0, 0.0)).norm() < 1e-6);
+        assert!((root_near(Complex::new(-0.6, -0.9)) - Complex::new(-0.5, -0.75f64.sqrt())).norm() < 1e-6);
+
+        // the derivative vanishes at the origin, so Newton's method has nowhere to go
+        let stuck = cubic.converge(Complex::new(0.0, 0.0), 50);
+        assert_eq!((stuck.iterations, stuck.root), (50, None));
+    }
+}
@@ -0,0 +1,435 @@
+// Annotations drawn over a render, such as the orbit of a point. An overlay is a layer the size
+// of the image: each pixel it covers takes the mark's color in images and colored terminal
+// output, and the mark's character in the text formats, which can't show colors.
+//
+// Labels are kept apart from the pixel marks, since text comes out so differently: images draw
+// them in a small bitmap font, while the terminal formats with a pixel per cell print their
+// characters one to a cell.
+
+use num::complex::Complex;
+
+use crate::colorscheme::Color;
+
+const ORBIT_LINE: Color = Color::new(255, 255, 255);
+const ORBIT_MARKER: Color = Color::new(255, 64, 64);
+const CROSS: Color = Color::new(255, 64, 64);
+const RECTANGLE: Color = Color::new(255, 255, 0);
+const AXES: Color = Color::new(230, 230, 230);
+const GRID: Color = Color::new(140, 140, 140);
+const RAY: Color = Color::new(255, 170, 0);
+const EQUIPOTENTIAL: Color = Color::new(80, 200, 255);
+const LABEL: Color = Color::new(255, 255, 255);
+const LABEL_BACKGROUND: Color = Color::new(0, 0, 0);
+// about how many grid lines cross the view, and how many scale bars fit across it
+const TICKS: f64 = 6.0;
+const SCALE_BARS: f64 = 5.0;
+
+// 3×5 pixel glyphs, one row per byte with the leftmost pixel in bit 2; enough for coordinates
+// and ray angles
+const FONT: [(char, [u8; 5]); 16] = [
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b001, 0b010, 0b010]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+    ('+', [0b000, 0b010, 0b111, 0b010, 0b000]),
+    ('e', [0b000, 0b111, 0b111, 0b100, 0b111]),
+    ('i', [0b010, 0b000, 0b010, 0b010, 0b010]),
+    ('/', [0b001, 0b001, 0b010, 0b100, 0b100]),
+];
+
+/// One covered pixel of an overlay.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mark {
+    pub color: Color,
+    /// what the pixel shows as in ascii and other text-only formats
+    pub glyph: char,
+}
+
+// text placed at a pixel position; `align` is the point of the text that goes there, from
+// (0, 0) at its top left to (1, 1) at its bottom right
+#[derive(Debug, Clone)]
+struct Label {
+    at: (f64, f64),
+    align: (f64, f64),
+    text: String,
+    color: Color,
+}
+
+/// Marks placed by complex coordinates onto a `width`×`height` render of `region`.
+#[derive(Debug, Clone)]
+pub struct Overlay {
+    width: usize,
+    height: usize,
+    region: (f64, f64, f64, f64),
+    marks: Vec<Option<Mark>>,
+    labels: Vec<Label>,
+}
+
+impl Overlay {
+    pub fn new(width: usize, height: usize, region: (f64, f64, f64, f64)) -> Self {
+        Self {
+            width,
+            height,
+            region,
+            marks: vec![None; width * height],
+            labels: Vec::new(),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn mark(&self, x: usize, y: usize) -> Option<Mark> {
+        if x < self.width && y < self.height { self.marks[y * self.width + x] } else { None }
+    }
+
+    /// The label character printed at (x, y) by the terminal formats with a pixel per cell.
+    pub fn text_at(&self, x: usize, y: usize) -> Option<Mark> {
+        self.labels.iter().rev().find_map(|label| {
+            let length = label.text.chars().count();
+            let (left, top) = self.origin(label, (length, 1));
+            let column = (x as i64).checked_sub(left).filter(|_| y as i64 == top)?;
+            let glyph = label.text.chars().nth(usize::try_from(column).ok()?)?;
+            Some(Mark { color: label.color, glyph })
+        })
+    }
+
+    /// Sets the pixel at (x, y), if it's inside the image.
+    pub fn set(&mut self, x: i64, y: i64, mark: Mark) {
+        if (0..self.width as i64).contains(&x) && (0..self.height as i64).contains(&y) {
+            self.marks[y as usize * self.width + x as usize] = Some(mark);
+        }
+    }
+
+    /// Where `point` falls in pixels, as `calculate` lays out the region: pixel (x, y) is
+    /// computed at (x, y) exactly, so the nearest pixel is the rounded position.
+    pub fn to_pixel(&self, point: Complex<f64>) -> (f64, f64) {
+        let (x_min, x_max, y_min, y_max) = self.region;
+        (
+            (point.re - x_min) / (x_max - x_min) * self.width as f64,
+            (point.im - y_min) / (y_max - y_min) * self.height as f64,
+        )
+    }
+
+    /// A straight line from `from` to `to`, drawn with '-', '|', '/' or '\' in text by its slope.
+    pub fn line(&mut self, from: Complex<f64>, to: Complex<f64>, color: Color) {
+        let (a, b) = (self.to_pixel(from), self.to_pixel(to));
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let glyph = if dy.abs() * 2.0 < dx.abs() {
+            '-'
+        } else if dx.abs() * 2.0 < dy.abs() {
+            '|'
+        } else if (dx > 0.0) == (dy > 0.0) {
+            '\\'
+        } else {
+            '/'
+        };
+
+        // only the visible part is stepped along, however far outside the ends are
+        let bounds = (-0.5, self.width as f64 - 0.5, -0.5, self.height as f64 - 0.5);
+        let Some((start, end)) = clip(a, b, bounds) else {
+            return;
+        };
+        let steps = (end.0 - start.0).abs().max((end.1 - start.1).abs()).ceil().max(1.0) as usize;
+        for step in 0..=steps {
+            let t = step as f64 / steps as f64;
+            let x = start.0 + (end.0 - start.0) * t;
+            let y = start.1 + (end.1 - start.1) * t;
+            self.set(x.round() as i64, y.round() as i64, Mark { color, glyph });
+        }
+    }
+
+    /// A square `2·radius + 1` pixels wide centered on `at`, shown as 'o' in text.
+    pub fn marker(&mut self, at: Complex<f64>, radius: usize, color: Color) {
+        let (x, y) = self.to_pixel(at);
+        if !x.is_finite() || !y.is_finite() {
+            return;
+        }
+        let (x, y, radius) = (x.round() as i64, y.round() as i64, radius as i64);
+        for my in y - radius..=y + radius {
+            for mx in x - radius..=x + radius {
+                self.set(mx, my, Mark { color, glyph: 'o' });
+            }
+        }
+    }
+
+    /// A plus sign with arms `radius` pixels long centered on `at`, marking a chosen point;
+    /// shown as '+' in text.
+    pub fn cross(&mut self, at: Complex<f64>, radius: usize) {
+        let (x, y) = self.to_pixel(at);
+        if !x.is_finite() || !y.is_finite() {
+            return;
+        }
+        let (x, y, radius) = (x.round() as i64, y.round() as i64, radius as i64);
+        let mark = Mark { color: CROSS, glyph: '+' };
+        for offset in -radius..=radius {
+            self.set(x + offset, y, mark);
+            self.set(x, y + offset, mark);
+        }
+    }
+
+    /// The outline of the rectangle with opposite corners `a` and `b`.
+    pub fn rectangle(&mut self, a: Complex<f64>, b: Complex<f64>) {
+        let (c, d) = (Complex::new(a.re, b.im), Complex::new(b.re, a.im));
+        for (from, to) in [(a, c), (c, b), (b, d), (d, a)] {
+            self.line(from, to, RECTANGLE);
+        }
+    }
+
+    /// `text` placed with its `align` point, from (0, 0) at its top left to (1, 1) at its
+    /// bottom right, at the pixel position `at`, and moved inside the image if it would stick out.
+    pub fn label(&mut self, at: (f64, f64), align: (f64, f64), text: &str, color: Color) {
+        self.labels.push(Label {
+            at,
+            align,
+            text: text.to_string(),
+            color,
+        });
+    }
+
+    /// The real and imaginary axes, where they cross the view.
+    pub fn axes(&mut self) {
+        let (x_min, x_max, y_min, y_max) = self.region;
+        if (y_min..=y_max).contains(&0.0) {
+            self.line(Complex::new(x_min, 0.0), Complex::new(x_max, 0.0), AXES);
+        }
+        if (x_min..=x_max).contains(&0.0) {
+            self.line(Complex::new(0.0, y_min), Complex::new(0.0, y_max), AXES);
+        }
+    }
+
+    /// Lines at round coordinates across the view, those `coordinate_labels` labels.
+    pub fn grid(&mut self) {
+        let (x_min, x_max, y_min, y_max) = self.region;
+        for re in ticks(x_min, x_max).0 {
+            self.line(Complex::new(re, y_min), Complex::new(re, y_max), GRID);
+        }
+        for im in ticks(y_min, y_max).0 {
+            self.line(Complex::new(x_min, im), Complex::new(x_max, im), GRID);
+        }
+    }
+
+    /// Round coordinates labeled along the axes, or along the bottom and left edges when an
+    /// axis is out of view.
+    pub fn coordinate_labels(&mut self) {
+        let (x_min, x_max, y_min, y_max) = self.region;
+        let (axis_x, axis_y) = self.to_pixel(Complex::new(0.0, 0.0));
+        let (res, decimals) = ticks(x_min, x_max);
+        for re in res {
+            let x = self.to_pixel(Complex::new(re, 0.0)).0;
+            let (at, align) = if (y_min..=y_max).contains(&0.0) { ((x, axis_y + 1.0), (0.5, 0.0)) } else { ((x, self.height as f64), (0.5, 1.0)) };
+            self.label(at, align, &format!("{:.*}", decimals, re), LABEL);
+        }
+        let (ims, decimals) = ticks(y_min, y_max);
+        // zero is on the real axis, which already has a 0 of its own
+        for im in ims.into_iter().filter(|&im| im != 0.0 || !(y_min..=y_max).contains(&0.0)) {
+            let y = self.to_pixel(Complex::new(0.0, im)).1;
+            let (at, align) = if (x_min..=x_max).contains(&0.0) { ((axis_x + 1.0, y), (0.0, 0.5)) } else { ((0.0, y), (0.0, 0.5)) };
+            self.label(at, align, &format!("{:.*}i", decimals, im), LABEL);
+        }
+    }
+
+    /// A bar of a round length in the bottom right corner, labeled with the length.
+    pub fn scale_bar(&mut self) {
+        let (x_min, x_max, _, _) = self.region;
+        let span = x_max - x_min;
+        let (length, exponent) = round_step(span / SCALE_BARS);
+        let text = if (-4..6).contains(&exponent) {
+            format!("{:.*}", (-exponent).max(0) as usize, length)
+        } else {
+            format!("{}e{}", (length / 10f64.powi(exponent)).round(), exponent)
+        };
+
+        let pixels = (length / span * self.width as f64).round().max(1.0) as i64;
+        let thickness = self.text_scale() as i64;
+        let right = self.width as i64 - 1 - (self.width as i64 / 40).max(1);
+        let bottom = self.height as i64 - 1 - (self.height as i64 / 40).max(1);
+        for y in bottom + 1 - thickness..=bottom {
+            for x in right + 1 - pixels..=right {
+                self.set(x, y, Mark { color: LABEL, glyph: '=' });
+            }
+        }
+        let at = ((right + 1) as f64 - pixels as f64 / 2.0, (bottom - thickness) as f64);
+        self.label(at, (0.5, 1.0), &text, LABEL);
+    }
+
+    /// Draws the labels onto `pixels`, a packed RGB image of the overlay's size.
+    pub fn draw_labels(&self, pixels: &mut [u8]) {
+        let scale = self.text_scale();
+        let mut paint = |x: usize, y: usize, color: Color| {
+            if x < self.width && y < self.height {
+                let i = (y * self.width + x) * 3;
+                pixels[i..i + 3].copy_from_slice(&[color.r, color.g, color.b]);
+            }
+        };
+        for label in &self.labels {
+            // a dark box a glyph pixel wider all round keeps the text readable on any colors
+            let length = label.text.chars().count();
+            let size = ((4 * length + 1) * scale, 7 * scale);
+            let (left, top) = self.origin(label, size);
+            let (left, top) = (left.max(0) as usize, top.max(0) as usize);
+            for y in top..top + size.1 {
+                for x in left..left + size.0 {
+                    paint(x, y, LABEL_BACKGROUND);
+                }
+            }
+            for (i, c) in label.text.chars().enumerate() {
+                let Some((_, rows)) = FONT.iter().find(|(glyph, _)| *glyph == c) else {
+                    continue;
+                };
+                for (row, bits) in rows.iter().enumerate() {
+                    for column in 0..3 {
+                        if bits & (0b100 >> column) == 0 {
+                            continue;
+                        }
+                        for dy in 0..scale {
+                            for dx in 0..scale {
+                                let x = left + (1 + 4 * i + column) * scale + dx;
+                                paint(x, top + (1 + row) * scale + dy, label.color);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // glyph pixels per font pixel, so labels stay legible in large images
+    fn text_scale(&self) -> usize {
+        (self.width / 400).max(1)
+    }
+
+    // top left pixel of a label `size` pixels across and down, kept inside the image; pixels
+    // are points, like those of `to_pixel`, so a label centered on a line lands on its row
+    fn origin(&self, label: &Label, size: (usize, usize)) -> (i64, i64) {
+        let place = |at: f64, align: f64, size: usize, room: usize| {
+            let start = (at - align * size.saturating_sub(1) as f64).round() as i64;
+            start.min(room as i64 - size as i64).max(0)
+        };
+        (
+            place(label.at.0, label.align.0, size.0, self.width),
+            place(label.at.1, label.align.1, size.1, self.height),
+        )
+    }
+
+    /// An external ray from `rays::external_ray`, labeled with its `angle` where it comes
+    /// into view.
+    pub fn external_ray(&mut self, points: &[Complex<f64>], angle: &str) {
+        for pair in points.windows(2) {
+            self.line(pair[0], pair[1], RAY);
+        }
+        let inside = |(x, y): (f64, f64)| (-0.5..self.width as f64 - 0.5).contains(&x) && (-0.5..self.height as f64 - 0.5).contains(&y);
+        if let Some(at) = points.iter().map(|&point| self.to_pixel(point)).find(|&at| inside(at)) {
+            self.label(at, (0.0, 1.0), angle, RAY);
+        }
+    }
+
+    /// An equipotential from `rays::equipotential`.
+    pub fn equipotential(&mut self, points: &[Complex<f64>]) {
+        for pair in points.windows(2) {
+            self.line(pair[0], pair[1], EQUIPOTENTIAL);
+        }
+    }
+
+    /// The points of an orbit as markers, joined in order by lines.
+    pub fn orbit(&mut self, orbit: &[Complex<f64>], marker_radius: usize) {
+        for pair in orbit.windows(2) {
+            self.line(pair[0], pair[1], ORBIT_LINE);
+        }
+        for &point in orbit {
+            self.marker(point, marker_radius, ORBIT_MARKER);
+        }
+    }
+}
+
+// a step of 1, 2 or 5 times a power of ten near `raw`, and that power
+fn round_step(raw: f64) -> (f64, i32) {
+    let exponent = raw.log10().floor() as i32;
+    let mantissa = raw / 10f64.powi(exponent);
+    match mantissa {
+        m if m < 1.5 => (10f64.powi(exponent), exponent),
+        m if m < 3.5 => (2.0 * 10f64.powi(exponent), exponent),
+        m if m < 7.5 => (5.0 * 10f64.powi(exponent), exponent),
+        _ => (10f64.powi(exponent + 1), exponent + 1),
+    }
+}
+
+// the round coordinates between `min` and `max`, and the decimals it takes to write them
+fn ticks(min: f64, max: f64) -> (Vec<f64>, usize) {
+    let (step, exponent) = round_step((max - min) / TICKS);
+    let first = (min / step).ceil() as i64;
+    let last = (max / step).floor() as i64;
+    // whole multiples of the step, so zero comes out as 0 rather than a rounding error
+    ((first..=last).map(|i| i as f64 * step).collect(), (-exponent).max(0) as usize)
+}
+
+// Liang–Barsky: the part of the segment from `a` to `b` inside (x_min, x_max, y_min, y_max)
+fn clip(a: (f64, f64), b: (f64, f64), (x_min, x_max, y_min, y_max): (f64, f64, f64, f64)) -> Option<((f64, f64), (f64, f64))> {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let (mut enter, mut leave) = (0.0f64, 1.0f64);
+    for (p, q) in [(-dx, a.0 - x_min), (dx, x_max - a.0), (-dy, a.1 - y_min), (dy, y_max - a.1)] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            enter = enter.max(q / p);
+        } else {
+            leave = leave.min(q / p);
+        }
+    }
+    (enter <= leave && enter.is_finite() && leave.is_finite())
+        .then_some(((a.0 + dx * enter, a.1 + dy * enter), (a.0 + dx * leave, a.1 + dy * leave)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orbit_lines_and_markers() {
+        // a 5×3 render of [0, 5) × [0, 3), so pixels sit on whole coordinates
+        let mut overlay = Overlay::new(5, 3, (0.0, 5.0, 0.0, 3.0));
+        overlay.orbit(&[Complex::new(0.0, 1.0), Complex::new(4.0, 1.0), Complex::new(1e9, -1e9)], 0);
+
+        let glyphs: String = (0..3)
+            .flat_map(|y| (0..5).map(move |x| (x, y)))
+            .map(|(x, y)| overlay.mark(x, y).map_or('.', |mark| mark.glyph))
+            .collect();
+        // the line out to the far off last point is clipped to the view, not stepped along in full
+        assert_eq!(glyphs, ".....o---o.....");
+        assert_eq!(overlay.mark(0, 1).unwrap().color, ORBIT_MARKER);
+        assert_eq!(overlay.mark(2, 1).unwrap().color, ORBIT_LINE);
+    }
+
+    #[test]
+    fn test_coordinate_labels() {
+        assert_eq!(ticks(-2.0, 1.0), (vec![-2.0, -1.5, -1.0, -0.5, 0.0, 0.5, 1.0], 1));
+        assert_eq!(round_step(0.0031), (0.002, -3));
+
+        // the real axis runs along row 1, with its labels below and pushed inside the edges
+        let mut overlay = Overlay::new(20, 4, (-2.0, 1.0, -1.0, 3.0));
+        overlay.axes();
+        overlay.label((0.0, 3.0), (0.5, 0.0), "-1.5", LABEL);
+        let row: String = (0..20).map(|x| overlay.text_at(x, 3).map_or(' ', |mark| mark.glyph)).collect();
+        assert_eq!(row, "-1.5                ");
+        assert_eq!(overlay.mark(5, 1).map(|mark| mark.glyph), Some('-'));
+
+        let mut pixels = vec![128; 20 * 4 * 3];
+        overlay.draw_labels(&mut pixels);
+        assert!(pixels.contains(&255) && pixels.contains(&0));
+    }
+}
@@ -0,0 +1,353 @@
+// Perturbation rendering for deep Mandelbrot zooms.
+//
+// One reference orbit Z_n is iterated in double-double precision at the center of the view,
+// every pixel then only iterates its offset δz_n from that orbit in plain f64:
+//     δz_{n+1} = 2·Z_n·δz_n + δz_n² + δc
+// A cubic series approximation of δz_n in terms of δc lets all pixels skip the first
+// iterations at once. Pixels whose orbit gets too close to the reference (Pauldelbrot's
+// criterion) are "glitched" and recomputed against a new reference picked among them.
+
+use std::ops::{Add, Mul, Sub};
+
+use num::complex::Complex;
+use rayon::prelude::*;
+
+use crate::fractal::{EscapeResult, Fractal};
+use crate::{ComputeOptions, Rotation};
+use crate::progress::Progress;
+
+// |Z_n + δz_n|² < GLITCH_TOLERANCE·|Z_n|² marks a pixel as glitched
+const GLITCH_TOLERANCE: f64 = 1e-6;
+// series terms must shrink by at least this factor for the approximation to be trusted
+const SERIES_TOLERANCE: f64 = 1e-3;
+const MAX_REFERENCES: usize = 16;
+
+/// An unevaluated sum hi + lo of two f64s, giving roughly 106 bits of mantissa.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DoubleDouble {
+    hi: f64,
+    lo: f64,
+}
+
+impl DoubleDouble {
+    fn new(x: f64) -> Self {
+        Self { hi: x, lo: 0.0 }
+    }
+
+    fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+
+    fn quick_two_sum(a: f64, b: f64) -> Self {
+        let hi = a + b;
+        Self { hi, lo: b - (hi - a) }
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        // Knuth's two-sum on the high parts, then fold in the low parts
+        let s = self.hi + other.hi;
+        let bb = s - self.hi;
+        let err = (self.hi - (s - bb)) + (other.hi - bb);
+        Self::quick_two_sum(s, err + self.lo + other.lo)
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self + DoubleDouble { hi: -other.hi, lo: -other.lo }
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let p = self.hi * other.hi;
+        let err = self.hi.mul_add(other.hi, -p);
+        Self::quick_two_sum(p, err + self.hi * other.lo + self.lo * other.hi)
+    }
+}
+
+struct ReferenceOrbit {
+    // Z_0 .. Z_k, ending at max_iters or at the first escaped value
+    orbit: Vec<Complex<f64>>,
+}
+
+impl ReferenceOrbit {
+    fn compute(c_re: DoubleDouble, c_im: DoubleDouble, max_iters: usize, bailout: f64) -> Self {
+        let mut orbit = Vec::with_capacity(max_iters + 1);
+        let mut z_re = DoubleDouble::new(0.0);
+        let mut z_im = DoubleDouble::new(0.0);
+
+        for _ in 0..=max_iters {
+            let z = Complex::new(z_re.to_f64(), z_im.to_f64());
+            orbit.push(z);
+            if z.norm_sqr() > bailout * bailout {
+                break;
+            }
+            let re = z_re * z_re - z_im * z_im + c_re;
+            let im = DoubleDouble::new(2.0) * z_re * z_im + c_im;
+            z_re = re;
+            z_im = im;
+        }
+
+        Self { orbit }
+    }
+
+    /// Number of iterations every pixel within `max_delta` of the reference can skip,
+    /// together with the series coefficients (A, B, C) at that iteration.
+    fn series_skip(&self, max_delta: f64) -> (usize, [Complex<f64>; 3]) {
+        let zero = Complex::new(0.0, 0.0);
+        let one = Complex::new(1.0, 0.0);
+        let (mut a, mut b, mut c) = (zero, zero, zero);
+        let mut skip = 0;
+
+        // never skip past the point where the reference itself escapes
+        for n in 0..self.orbit.len().saturating_sub(1) {
+            let z = self.orbit[n];
+            let next_a = z * a * 2.0 + one;
+            let next_b = z * b * 2.0 + a * a;
+            let next_c = z * c * 2.0 + a * b * 2.0;
+
+            let linear = next_a.norm() * max_delta;
+            let quadratic = next_b.norm() * max_delta * max_delta;
+            let cubic = next_c.norm() * max_delta * max_delta * max_delta;
+            if quadratic > SERIES_TOLERANCE * linear || cubic > SERIES_TOLERANCE * quadratic {
+                break;
+            }
+
+            a = next_a;
+            b = next_b;
+            c = next_c;
+            skip = n + 1;
+        }
+
+        (skip, [a, b, c])
+    }
+}
+
+enum PixelResult {
+    Done(EscapeResult),
+    Glitched,
+}
+
+// `derivative` is dz/dc at `start`, or None when no distance estimate is wanted
+fn iterate_delta(
+    reference: &ReferenceOrbit,
+    dc: Complex<f64>,
+    start: usize,
+    dz0: Complex<f64>,
+    derivative: Option<Complex<f64>>,
+    max_iters: usize,
+    bailout: f64,
+) -> PixelResult {
+    let orbit = &reference.orbit;
+    let mut dz = dz0;
+    let mut derivative = derivative;
+
+    for n in start..=max_iters {
+        // the reference escaped before this pixel did
+        let Some(&z_ref) = orbit.get(n) else {
+            return PixelResult::Glitched;
+        };
+
+        let z = z_ref + dz;
+        let norm_sqr = z.norm_sqr();
+        if n == max_iters {
+            return PixelResult::Done(EscapeResult {
+                iterations: max_iters,
+                z_norm: norm_sqr.sqrt(),
+                distance: 0.0,
+                interior: 0.0,
+                stripe: 0.0,
+                root: None,
+                z: Some(z),
+                derivative,
+            });
+        }
+        if norm_sqr > bailout * bailout {
+            // escaping before the series skip means the approximation was off
+            if n == start && start > 0 {
+                return PixelResult::Glitched;
+            }
+            let norm = norm_sqr.sqrt();
+            let distance = match derivative {
+                Some(d) if d.norm() > 0.0 => 0.5 * norm * norm.ln() / d.norm(),
+                _ => 0.0,
+            };
+            return PixelResult::Done(EscapeResult {
+                iterations: n,
+                z_norm: norm,
+                distance,
+                interior: 0.0,
+                stripe: 0.0,
+                root: None,
+                z: Some(z),
+                derivative,
+            });
+        }
+        if norm_sqr < GLITCH_TOLERANCE * z_ref.norm_sqr() {
+            return PixelResult::Glitched;
+        }
+
+        if let Some(d) = &mut derivative {
+            *d = z * *d * 2.0 + Complex::new(1.0, 0.0);
+        }
+        dz = z_ref * dz * 2.0 + dz * dz + dc;
+    }
+
+    PixelResult::Glitched
+}
+
+/// Perturbation equivalent of `Fractal::Mandelbrot.calculate`, for regions too small for plain
+/// f64. Of the options, only `distance`, `rotation` and `bailout` apply.
+pub fn calculate(
+    max_iters: usize,
+    (x_min, x_max, y_min, y_max): (f64, f64, f64, f64),
+    width: usize,
+    height: usize,
+    options: &ComputeOptions,
+    progress: Option<&Progress>,
+) -> Vec<EscapeResult> {
+    let (distance, rotation) = (options.distance, Rotation::degrees(options.rotation));
+    let x_span = x_max - x_min;
+    let y_span = y_max - y_min;
+
+    // pixel offsets from the reference at the center of the view, matching Fractal::calculate
+    let delta = |img_x: usize, img_y: usize| {
+        let (re, im) = rotation.to_plane((
+            (img_x as f64 / width as f64 - 0.5) * x_span,
+            (img_y as f64 / height as f64 - 0.5) * y_span,
+        ));
+        Complex::new(re, im)
+    };
+    let center_re = DoubleDouble::new(x_min) + DoubleDouble::new(x_span * 0.5);
+    let center_im = DoubleDouble::new(y_min) + DoubleDouble::new(y_span * 0.5);
+    // turned in double-double too, so deep views keep their precision
+    let (center_re, center_im) = if options.rotation == 0.0 {
+        (center_re, center_im)
+    } else {
+        let (cos, sin) = (DoubleDouble::new(rotation.cos), DoubleDouble::new(rotation.sin));
+        (cos * center_re - sin * center_im, sin * center_re + cos * center_im)
+    };
+
+    let mut results = vec![EscapeResult::default(); width * height];
+    let mut pending: Vec<(usize, usize)> = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .collect();
+    let mut reference_delta = Complex::new(0.0, 0.0);
+
+    for round in 0..MAX_REFERENCES {
+        if pending.is_empty() {
+            break;
+        }
+
+        let reference = ReferenceOrbit::compute(
+            center_re + DoubleDouble::new(reference_delta.re),
+            center_im + DoubleDouble::new(reference_delta.im),
+            max_iters,
+            options.bailout,
+        );
+
+        // the series approximation only pays off for the first, full-frame pass
+        let (skip, [a, b, c]) = if round == 0 {
+            reference.series_skip(x_span.hypot(y_span) / 2.0)
+        } else {
+            (0, [Complex::new(0.0, 0.0); 3])
+        };
+
+        let round_results: Vec<PixelResult> = pending
+            .par_iter()
+            .map(|&(x, y)| {
+                if progress.is_some_and(Progress::is_cancelled) {
+                    return PixelResult::Done(EscapeResult::skipped(max_iters));
+                }
+                let dc = delta(x, y) - reference_delta;
+                let dz0 = a * dc + b * dc * dc + c * dc * dc * dc;
+                // Z_n doesn't depend on δc, so dz/dc at the skip is the series' own derivative
+                let derivative = distance.then(|| a + b * dc * 2.0 + c * dc * dc * 3.0);
+                let result = iterate_delta(&reference, dc, skip, dz0, derivative, max_iters, options.bailout);
+
+                // re-referenced pixels were already counted in the first pass
+                if round == 0
+                    && let Some(progress) = progress
+                {
+                    progress.advance(1);
+                }
+                result
+            })
+            .collect();
+
+        let mut glitched = Vec::new();
+        for (&(x, y), result) in pending.iter().zip(round_results) {
+            match result {
+                PixelResult::Done(result) => results[y * width + x] = result,
+                PixelResult::Glitched => glitched.push((x, y)),
+            }
+        }
+
+        // re-reference on a glitched pixel; its own orbit is exact by construction
+        if let Some(&(x, y)) = glitched.get(glitched.len() / 2) {
+            reference_delta = delta(x, y);
+        }
+        pending = glitched;
+    }
+
+    // whatever is left after MAX_REFERENCES falls back to direct iteration
+    for (x, y) in pending {
+        let (cx, cy) = rotation.to_plane((x_min + x_span * (x as f64 / width as f64), y_min + y_span * (y as f64 / height as f64)));
+        let options = ComputeOptions {
+            early_bailout: true,
+            ..*options
+        };
+        results[y * width + x] = Fractal::Mandelbrot.at_point_with(cx, cy, max_iters, &options);
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_double_double_keeps_low_bits() {
+        let tiny = 1e-20;
+        let sum = DoubleDouble::new(1.0) + DoubleDouble::new(tiny);
+        assert_eq!(sum.hi, 1.0);
+        assert_eq!((sum - DoubleDouble::new(1.0)).to_f64(), tiny);
+    }
+
+    #[test]
+    fn test_matches_direct_iteration() {
+        let region = (-0.75, -0.735, 0.095, 0.11);
+        let options = crate::ComputeOptions {
+            distance: true,
+            ..crate::ComputeOptions::default()
+        };
+        let direct = Fractal::Mandelbrot.calculate(500, region, 40, 30, &options, None);
+        let perturbed = calculate(500, region, 40, 30, &options, None);
+
+        let mismatched = direct
+            .iter()
+            .zip(&perturbed)
+            .filter(|(a, b)| a.iterations.abs_diff(b.iterations) > 1)
+            .count();
+        assert!(mismatched <= 12, "{} of 1200 pixels differ", mismatched);
+
+        // distance estimates agree, up to the series approximation, wherever the counts do
+        let worst = direct
+            .iter()
+            .zip(&perturbed)
+            .filter(|(a, b)| a.iterations == b.iterations)
+            .map(|(a, b)| (a.distance - b.distance).abs() / a.distance.max(1e-12))
+            .fold(0.0, f64::max);
+        assert!(worst < 1e-3, "distances differ by {:e}", worst);
+    }
+}
@@ -0,0 +1,154 @@
+// Named views to start from: the built-in ones in presets.toml, compiled in, and any the user
+// adds in ~/.config/mandelbrot/presets.toml, written the same way:
+//
+//     [[preset]]
+//     name = "minibrot"
+//     description = "the period-3 mini-mandelbrot on the real axis"
+//     center = [-1.7685, 0.0]
+//     zoom = 60.0
+//     iterations = 500      # optional, like color and fractal
+//
+// Unlike bookmarks, presets are only ever written by hand.
+
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::error::MandelbrotError;
+
+const BUILT_IN: &str = include_str!("presets.toml");
+
+/// A named view, with what it shows and how it's best rendered.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub center: (f64, f64),
+    /// magnification relative to the default view, as for `region_around`
+    pub zoom: f64,
+    /// suggested max iterations, used unless others are asked for
+    pub iterations: Option<usize>,
+    /// suggested color scheme, likewise
+    pub color: Option<String>,
+    /// suggested fractal, by `--fractal` name, likewise
+    pub fractal: Option<String>,
+}
+
+impl Preset {
+    /// The region this preset shows in a `width`×`height` image, as `region_around` shapes it.
+    pub fn region(&self, width: usize, height: usize, pixel_aspect: f64) -> (f64, f64, f64, f64) {
+        crate::region_around(self.center, self.zoom, width, height, pixel_aspect)
+    }
+}
+
+#[derive(Deserialize)]
+struct PresetFile {
+    #[serde(default)]
+    preset: Vec<Preset>,
+}
+
+/// The presets in the order they're listed: the built-in ones, then the user's.
+#[derive(Debug, Clone)]
+pub struct Presets(Vec<Preset>);
+
+impl Presets {
+    pub fn built_in() -> Self {
+        Self(parse(BUILT_IN, "the built-in presets").expect("the built-in presets are valid"))
+    }
+
+    /// `$XDG_CONFIG_HOME/mandelbrot/presets.toml`, or under `~/.config` without it.
+    pub fn default_path() -> Option<PathBuf> {
+        let config = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(config.join("mandelbrot").join("presets.toml"))
+    }
+
+    /// The built-in presets with those in the file at `path`; a missing file adds none.
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        let mut presets = Self::built_in();
+        match std::fs::read_to_string(path) {
+            Ok(content) => presets.extend(parse(&content, &path.display().to_string())?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(MandelbrotError::file(path.display(), e)),
+        }
+        Ok(presets)
+    }
+
+    /// Adds presets, each replacing any other with the same name where that one was listed.
+    pub fn extend(&mut self, presets: impl IntoIterator<Item = Preset>) {
+        for preset in presets {
+            match self.0.iter_mut().find(|known| known.name.eq_ignore_ascii_case(&preset.name)) {
+                Some(known) => *known = preset,
+                None => self.0.push(preset),
+            }
+        }
+    }
+
+    /// Looks a preset up by name, ignoring case.
+    pub fn get(&self, name: &str) -> Option<&Preset> {
+        self.0.iter().find(|preset| preset.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Preset> {
+        self.0.iter()
+    }
+}
+
+// the presets in a file's TOML, checked for views that can be rendered; `source` names it in errors
+fn parse(content: &str, source: &str) -> crate::Result<Vec<Preset>> {
+    let file: PresetFile = toml::from_str(content).map_err(|e| MandelbrotError::invalid(source, e))?;
+    for preset in &file.preset {
+        let (re, im) = preset.center;
+        if !(re.is_finite() && im.is_finite() && preset.zoom > 0.0 && preset.zoom.is_finite()) {
+            let reason = "its center must be finite and its zoom a positive number";
+            return Err(MandelbrotError::invalid(format_args!("preset '{}' in {}", preset.name, source), reason));
+        }
+        if preset.iterations == Some(0) {
+            return Err(MandelbrotError::invalid(format_args!("preset '{}' in {}", preset.name, source), "iterations must be at least 1"));
+        }
+    }
+    Ok(file.preset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_presets_extend_the_built_in_ones() {
+        let presets = Presets::built_in();
+        let seahorse = presets.get("Seahorse").unwrap();
+        let (x_min, x_max, y_min, y_max) = seahorse.region(3, 3, 1.0);
+        assert!((x_min + 0.75).abs() < 1e-12 && (x_max + 0.735).abs() < 1e-12);
+        assert!((y_min - 0.095).abs() < 1e-12 && (y_max - 0.11).abs() < 1e-12);
+        assert!(presets.iter().all(|preset| !preset.description.is_empty()));
+
+        let mut presets = presets;
+        let content = r#"
+            [[preset]]
+            name = "elephant"
+            center = [0.3, 0.0]
+            zoom = 10.0
+
+            [[preset]]
+            name = "mine"
+            description = "somewhere of my own"
+            center = [-1.0, 0.25]
+            zoom = 8.0
+            color = "fire"
+        "#;
+        presets.extend(parse(content, "presets.toml").unwrap());
+        let names: Vec<&str> = presets.iter().map(|preset| preset.name.as_str()).collect();
+        assert_eq!(names.iter().position(|name| *name == "elephant"), Some(3));
+        assert_eq!(names.last(), Some(&"mine"));
+        assert_eq!(presets.get("elephant").map(|preset| preset.center), Some((0.3, 0.0)));
+        assert_eq!(presets.get("mine").and_then(|preset| preset.color.as_deref()), Some("fire"));
+
+        let content = "[[preset]]\nname = \"nowhere\"\ncenter = [0.0, 0.0]\nzoom = 0.0\n";
+        assert!(parse(content, "presets.toml").is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use std::io::{IsTerminal, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+const BAR_WIDTH: usize = 30;
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Thread-safe progress bar on stderr, advanced in pixels by the compute workers, which also
+/// ask it whether to stop.
+pub struct Progress {
+    total: usize,
+    row_width: usize,
+    done: AtomicUsize,
+    start: Instant,
+    last_draw: Mutex<Instant>,
+    visible: bool,
+    cancel: Option<&'static AtomicBool>,
+}
+
+impl Progress {
+    pub fn new(width: usize, height: usize, quiet: bool) -> Self {
+        let now = Instant::now();
+        Self {
+            total: width * height,
+            row_width: width.max(1),
+            done: AtomicUsize::new(0),
+            start: now,
+            last_draw: Mutex::new(now),
+            // a redrawn line only makes sense on a terminal
+            visible: !quiet && std::io::stderr().is_terminal(),
+            cancel: None,
+        }
+    }
+
+    /// Lets `flag`, set elsewhere (by a Ctrl-C handler, say), cancel the computations advancing
+    /// this: they stop starting on new rows and leave them `EscapeResult::skipped`.
+    pub fn with_cancel(mut self, flag: &'static AtomicBool) -> Self {
+        self.cancel = Some(flag);
+        self
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_some_and(|flag| flag.load(Ordering::Relaxed))
+    }
+
+    /// Fraction of the work done so far, 0.0 - 1.0.
+    pub fn fraction_done(&self) -> f64 {
+        match self.total {
+            0 => 1.0,
+            total => (self.done.load(Ordering::Relaxed) as f64 / total as f64).min(1.0),
+        }
+    }
+
+    pub fn advance(&self, pixels: usize) {
+        let done = self.done.fetch_add(pixels, Ordering::Relaxed) + pixels;
+        if !self.visible {
+            return;
+        }
+
+        // whoever holds the lock draws; everyone else just keeps computing
+        if let Ok(mut last_draw) = self.last_draw.try_lock()
+            && last_draw.elapsed() >= REDRAW_INTERVAL
+        {
+            *last_draw = Instant::now();
+            self.draw(done);
+        }
+    }
+
+    pub fn finish(&self) {
+        if self.visible {
+            // a cancelled computation stops short, and the bar with it
+            let done = if self.is_cancelled() { self.done.load(Ordering::Relaxed) } else { self.total };
+            self.draw(done);
+            eprintln!();
+        }
+    }
+
+    fn draw(&self, done: usize) {
+        let done = done.min(self.total);
+        let fraction = if self.total == 0 { 1.0 } else { done as f64 / self.total as f64 };
+        let elapsed = self.start.elapsed().as_secs_f64();
+        let rate = if elapsed > 0.0 { done as f64 / elapsed } else { 0.0 };
+        let eta = if rate > 0.0 { (self.total - done) as f64 / rate } else { 0.0 };
+
+        let filled = (fraction * BAR_WIDTH as f64) as usize;
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(
+            stderr,
+            "\r[{}{}] {:>3.0}%  {}/{} rows  {:.1}s  {}  ETA {:.1}s ",
+            "#".repeat(filled),
+            ".".repeat(BAR_WIDTH - filled),
+            fraction * 100.0,
+            done / self.row_width,
+            self.total / self.row_width,
+            elapsed,
+            format_rate(rate),
+            eta,
+        );
+        let _ = stderr.flush();
+    }
+}
+
+fn format_rate(pixels_per_sec: f64) -> String {
+    if pixels_per_sec >= 1e6 {
+        format!("{:.1}M px/s", pixels_per_sec / 1e6)
+    } else if pixels_per_sec >= 1e3 {
+        format!("{:.1}k px/s", pixels_per_sec / 1e3)
+    } else {
+        format!("{:.0} px/s", pixels_per_sec)
+    }
+}
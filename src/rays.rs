@@ -0,0 +1,247 @@
+// External rays and equipotentials of the Mandelbrot set and of quadratic Julia sets, for
+// illustrating how the plane outside the set is laid out.
+//
+// Both are curves of the Böttcher coordinate Φ, which maps the outside of the set onto the
+// outside of the unit disk: the ray of angle θ is where arg Φ = 2πθ and the equipotential of
+// potential G is where ln|Φ| = G. Far out, z_n ≈ Φ^(2^n) (counting z_1 = c for the Mandelbrot
+// set and z_0 = z for a Julia set), so a point with a given Φ is found by solving
+// z_n = Φ^(2^n) with Newton's method, for an n large enough that the approximation holds.
+//
+// Rays are traced inward from a large radius the way Kawahira describes: a few points per
+// level, each solved from the one before, with one more iteration and the angle doubled per
+// level. Equipotentials start where the ray of angle 0 crosses them and step around by angle.
+
+use num::complex::Complex;
+
+use crate::fractal::Fractal;
+
+// |Φ| where rays start; each level goes from it to its square root
+const ESCAPE_RADIUS: f64 = 65536.0;
+// points solved per level
+const SHARPNESS: usize = 8;
+// levels traced at most, after which doubles can't place the points apart anyway
+const MAX_LEVELS: usize = 60;
+const NEWTON_STEPS: usize = 64;
+// points of an equipotential at most, however much of it is out of view
+const MAX_POINTS: usize = 100_000;
+
+/// An angle as an exact fraction of a turn, so that doubling it, as tracing a ray does once
+/// per level, never loses precision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Angle {
+    numerator: u64,
+    denominator: u64,
+}
+
+impl Angle {
+    /// A fraction such as "1/3", or a decimal such as "0.25"; whole turns are dropped.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let (numerator, denominator) = match s.split_once('/') {
+            Some((numerator, denominator)) => (numerator.trim().parse::<u64>().ok()?, denominator.trim().parse::<u64>().ok()?),
+            None => {
+                let (whole, decimals) = s.split_once('.').unwrap_or((s, ""));
+                if decimals.len() > 18 || !decimals.chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                let denominator = 10u64.pow(decimals.len() as u32);
+                let whole = if whole.is_empty() { 0 } else { whole.parse::<u64>().ok()? };
+                let decimals = if decimals.is_empty() { 0 } else { decimals.parse::<u64>().ok()? };
+                (whole.checked_mul(denominator)?.checked_add(decimals)?, denominator)
+            }
+        };
+        // doubling has to stay within a u64
+        if denominator == 0 || denominator >= 1 << 62 {
+            return None;
+        }
+        Some(Self {
+            numerator: numerator % denominator,
+            denominator,
+        })
+    }
+
+    fn doubled(self) -> Self {
+        Self {
+            numerator: self.numerator * 2 % self.denominator,
+            ..self
+        }
+    }
+
+    fn radians(self) -> f64 {
+        self.numerator as f64 / self.denominator as f64 * std::f64::consts::TAU
+    }
+}
+
+// where curves are drawn: the parameter plane of the Mandelbrot set, or the plane of the
+// Julia set of c
+#[derive(Clone, Copy)]
+enum Plane {
+    Parameter,
+    Dynamic(Complex<f64>),
+}
+
+impl Plane {
+    fn of(fractal: &Fractal) -> Option<Self> {
+        match *fractal {
+            Fractal::Mandelbrot => Some(Plane::Parameter),
+            Fractal::Julia(c) => Some(Plane::Dynamic(c)),
+            _ => None,
+        }
+    }
+
+    // the point whose z_n, with n = `level` + 1 for the Mandelbrot set and `level` for a
+    // Julia set so that either is about Φ^(2^level), is `target`; None if Newton diverges
+    fn solve(self, level: usize, target: Complex<f64>, guess: Complex<f64>) -> Option<Complex<f64>> {
+        let (zero, one) = (Complex::new(0.0, 0.0), Complex::new(1.0, 0.0));
+        let mut w = guess;
+        for _ in 0..NEWTON_STEPS {
+            let (z, dz) = match self {
+                Plane::Parameter => (0..=level).fold((zero, zero), |(z, dz), _| (z * z + w, z * dz * 2.0 + one)),
+                Plane::Dynamic(c) => (0..level).fold((w, one), |(z, dz), _| (z * z + c, z * dz * 2.0)),
+            };
+            let step = (z - target) / dz;
+            if !step.is_finite() {
+                return None;
+            }
+            w -= step;
+            if step.norm() <= w.norm() * 1e-15 {
+                break;
+            }
+        }
+        w.is_finite().then_some(w)
+    }
+}
+
+/// Whether `external_ray` and `equipotential` can draw curves for `fractal`: the Mandelbrot
+/// set and Julia sets, whose formula is z² + c.
+pub fn supports(fractal: &Fractal) -> bool {
+    Plane::of(fractal).is_some()
+}
+
+/// Points along the external ray of `angle`, from far outside the view in to where it lands,
+/// which it's taken to have done once a whole level moves it less than `precision`.
+pub fn external_ray(fractal: &Fractal, angle: Angle, precision: f64) -> Vec<Complex<f64>> {
+    let Some(plane) = Plane::of(fractal) else {
+        return Vec::new();
+    };
+    let mut point = Complex::from_polar(ESCAPE_RADIUS, angle.radians());
+    let mut points = vec![point];
+    let mut angle = angle;
+    for level in 0..MAX_LEVELS {
+        let start = point;
+        for step in 0..SHARPNESS {
+            let radius = ESCAPE_RADIUS.powf(0.5f64.powf((step as f64 + 0.5) / SHARPNESS as f64));
+            match plane.solve(level, Complex::from_polar(radius, angle.radians()), point) {
+                Some(next) => point = next,
+                None => return points,
+            }
+            points.push(point);
+        }
+        if (point - start).norm() < precision {
+            break;
+        }
+        angle = angle.doubled();
+    }
+    points
+}
+
+/// Points around the equipotential of `potential` (ln|Φ|, so the one twice as far out in
+/// potential has half the escape time), no more than about `precision` apart; closed, ending
+/// where it starts.
+pub fn equipotential(fractal: &Fractal, potential: f64, precision: f64) -> Vec<Complex<f64>> {
+    let Some(plane) = Plane::of(fractal) else {
+        return Vec::new();
+    };
+    if !(potential > 0.0 && potential.is_finite() && precision > 0.0) {
+        return Vec::new();
+    }
+    // the level at which |Φ|^(2^level) is between the square root of the escape radius and it
+    let level = (ESCAPE_RADIUS.ln() / potential).log2().floor().max(0.0) as usize;
+    if level > MAX_LEVELS {
+        return Vec::new();
+    }
+    let radius = (potential * 2f64.powi(level as i32)).exp();
+    let target = |turn: f64| Complex::from_polar(radius, (turn * 2f64.powi(level as i32)).fract() * std::f64::consts::TAU);
+
+    // down the ray of angle 0, whose angle stays 0 however often it's doubled, to the level
+    let ray = Angle { numerator: 0, denominator: 1 };
+    let mut guess = Complex::new(ESCAPE_RADIUS, 0.0);
+    for ray_level in 0..=level {
+        for step in 0..SHARPNESS {
+            let ray_radius = ESCAPE_RADIUS.powf(0.5f64.powf((step as f64 + 0.5) / SHARPNESS as f64));
+            if ray_level == level && ray_radius < radius {
+                break;
+            }
+            match plane.solve(ray_level, Complex::from_polar(ray_radius, ray.radians()), guess) {
+                Some(next) => guess = next,
+                None => return Vec::new(),
+            }
+        }
+    }
+    let Some(first) = plane.solve(level, target(0.0), guess) else {
+        return Vec::new();
+    };
+
+    // around by angle, in steps short enough that Newton stays on the curve and the points
+    // stay within a few `precision` of each other
+    let mut points = vec![first];
+    let (mut turn, mut step) = (0.0, 1.0 / (64.0 * 2f64.powi(level as i32)));
+    while turn < 1.0 && points.len() < MAX_POINTS {
+        let last = *points.last().expect("it starts with a point");
+        let next_turn = (turn + step).min(1.0);
+        let next = plane.solve(level, target(next_turn), last).filter(|next| (next - last).norm() <= 4.0 * precision);
+        match next {
+            Some(next) => {
+                points.push(next);
+                turn = next_turn;
+                if (next - last).norm() < precision {
+                    step *= 2.0;
+                }
+            }
+            None if step > 1e-12 => step /= 2.0,
+            None => break,
+        }
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rays_land_where_they_should() {
+        assert_eq!(Angle::from_str("1/3"), Some(Angle { numerator: 1, denominator: 3 }));
+        assert_eq!(Angle::from_str("1.25"), Some(Angle { numerator: 25, denominator: 100 }));
+        assert!(Angle::from_str("1/0").is_none() && Angle::from_str("x").is_none());
+
+        // the ray of 1/6 lands on the Misiurewicz point i, and quickly
+        let ray = external_ray(&Fractal::Mandelbrot, Angle::from_str("1/6").unwrap(), 1e-6);
+        let end = *ray.last().unwrap();
+        assert!((end - Complex::new(0.0, 1.0)).norm() < 1e-4, "1/6 ends at {}", end);
+        // the rays of 1/3 and 2/3 head for the root of the period 2 bulb at -0.75 from either
+        // side, slowly as rays landing at a root do
+        for (angle, side) in [("1/3", 1.0), ("2/3", -1.0)] {
+            let end = *external_ray(&Fractal::Mandelbrot, Angle::from_str(angle).unwrap(), 1e-6).last().unwrap();
+            assert!((end - Complex::new(-0.75, 0.0)).norm() < 0.1 && end.im * side > 0.0, "{} ends at {}", angle, end);
+        }
+        // and the ray of 0 runs along the real axis toward the cusp at 0.25
+        let ray = external_ray(&Fractal::Mandelbrot, Angle::from_str("0").unwrap(), 1e-6);
+        assert!(ray.iter().all(|point| point.im.abs() < 1e-9 && point.re > 0.25));
+        assert!(ray.last().unwrap().re < 0.5);
+    }
+
+    #[test]
+    fn test_equipotentials_circle_the_set() {
+        // far out Φ(c) ≈ c, so a high potential is close to a circle
+        let far = equipotential(&Fractal::Mandelbrot, 100f64.ln(), 0.1);
+        assert!(far.iter().all(|point| (point.norm() - 100.0).abs() < 1.0));
+        assert!((far[0] - *far.last().unwrap()).norm() < 0.5);
+
+        // around the julia set of 0, the unit circle, each point is where Φ puts it
+        let near = equipotential(&Fractal::Julia(Complex::new(0.0, 0.0)), 0.01, 1e-3);
+        assert!(near.len() > 100);
+        assert!(near.iter().all(|point| (point.norm() - 0.01f64.exp()).abs() < 1e-9));
+    }
+}
@@ -0,0 +1,231 @@
+// Progressive rendering for interactive views: a rough picture right away, then sharper ones
+// for as long as the view stays put. Each pass computes a grid coarser than the view, at fewer
+// iterations, and blows its points up into blocks. Passes run coarsest first, and every pass
+// is computed a band of rows at a time, asking `cancel` between bands, so that a key press
+// waits on one band at most rather than on a full-quality render.
+//
+// The frame before is the other head start: a view panned by whole pixels only needs the
+// strips that came into view, and one zoomed into can be previewed by blowing the last up.
+
+use rayon::prelude::*;
+
+use crate::ComputeOptions;
+use crate::fractal::{EscapeResult, Fractal};
+use crate::renderer::RenderData;
+
+// coarse passes never go below this many iterations, or the set's outline melts away
+const MIN_PREVIEW_ITERATIONS: usize = 64;
+
+/// One pass of a progressive render: a point computed for every `scale`×`scale` block of
+/// pixels, at `iterations` max iterations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pass {
+    pub scale: usize,
+    pub iterations: usize,
+}
+
+impl Pass {
+    /// The final pass: every pixel, at full iterations.
+    pub fn full(max_iterations: usize) -> Self {
+        Self {
+            scale: 1,
+            iterations: max_iterations,
+        }
+    }
+}
+
+/// Passes from 1/8 resolution up to full, with iterations cut in proportion on the way.
+pub fn passes(max_iterations: usize) -> Vec<Pass> {
+    [8, 4, 2, 1]
+        .map(|scale| Pass {
+            scale,
+            iterations: (max_iterations / scale).max(max_iterations.min(MIN_PREVIEW_ITERATIONS)),
+        })
+        .to_vec()
+}
+
+/// Escape data of `pass` over `region` for a `width`×`height` view, or `None` if `cancel`
+/// returned true before it was done.
+pub fn calculate(
+    fractal: &Fractal,
+    pass: Pass,
+    region: (f64, f64, f64, f64),
+    width: usize,
+    height: usize,
+    cancel: &dyn Fn() -> bool,
+) -> Option<RenderData> {
+    let scale = pass.scale.max(1);
+    let (cols, rows) = (width.div_ceil(scale), height.div_ceil(scale));
+    let (x_min, x_max, y_min, y_max) = region;
+    let options = ComputeOptions::default();
+
+    // a couple of rows per thread keeps every thread busy between checks
+    let band = (rayon::current_num_threads() * 2).max(4) * cols;
+    let mut coarse: Vec<EscapeResult> = Vec::with_capacity(cols * rows);
+    while coarse.len() < cols * rows {
+        if cancel() {
+            return None;
+        }
+        let points = coarse.len()..(coarse.len() + band).min(cols * rows);
+        // each point is the top left pixel of its block, placed as `Fractal::calculate` does
+        let results: Vec<EscapeResult> = points
+            .into_par_iter()
+            .map(|i| {
+                let x_percent = ((i % cols) * scale) as f64 / width as f64;
+                let y_percent = ((i / cols) * scale) as f64 / height as f64;
+                let cx = x_min + (x_max - x_min) * x_percent;
+                let cy = y_min + (y_max - y_min) * y_percent;
+                fractal.at_point_with(cx, cy, pass.iterations, &options)
+            })
+            .collect();
+        coarse.extend(results);
+    }
+
+    let pixels = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| coarse[(y / scale) * cols + x / scale])
+        .collect();
+    Some(RenderData::new(width, height, pixels, pass.iterations))
+}
+
+/// Escape data for `region` made from `previous`, which was computed over `previous_region`
+/// at the same size and iterations, when one is the other moved by whole pixels: the pixels
+/// still in view are moved over, and only those that came into view are computed. `None` when
+/// the two don't line up on the pixel grid, or don't overlap.
+pub fn pan(
+    fractal: &Fractal,
+    previous: &RenderData,
+    previous_region: (f64, f64, f64, f64),
+    region: (f64, f64, f64, f64),
+) -> Option<RenderData> {
+    let (width, height) = (previous.width(), previous.height());
+    let (x_min, x_max, y_min, y_max) = region;
+    let (old_x_min, old_x_max, old_y_min, old_y_max) = previous_region;
+    let same = |a: f64, b: f64| (a - b).abs() <= b.abs() * 1e-9;
+    if width == 0 || height == 0 || !same(x_max - x_min, old_x_max - old_x_min) || !same(y_max - y_min, old_y_max - old_y_min) {
+        return None;
+    }
+
+    // how many pixels the view moved, which must be whole
+    let whole = |shift: f64| (shift.round() - shift).abs() < 1e-6 && shift.abs() < 1e15;
+    let dx = (x_min - old_x_min) / (x_max - x_min) * width as f64;
+    let dy = (y_min - old_y_min) / (y_max - y_min) * height as f64;
+    if !whole(dx) || !whole(dy) {
+        return None;
+    }
+    let (dx, dy) = (dx.round() as i64, dy.round() as i64);
+    if dx.unsigned_abs() as usize >= width || dy.unsigned_abs() as usize >= height {
+        return None;
+    }
+
+    // the new strip has to escape where the old pixels did to match them
+    let options = ComputeOptions {
+        bailout: previous.bailout,
+        ..ComputeOptions::default()
+    };
+    let pixels = (0..width * height)
+        .into_par_iter()
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            let (old_x, old_y) = (x as i64 + dx, y as i64 + dy);
+            if (0..width as i64).contains(&old_x) && (0..height as i64).contains(&old_y) {
+                return previous.sample(old_x as usize, old_y as usize);
+            }
+            let cx = x_min + (x_max - x_min) * (x as f64 / width as f64);
+            let cy = y_min + (y_max - y_min) * (y as f64 / height as f64);
+            fractal.at_point_with(cx, cy, previous.max_iterations, &options)
+        })
+        .collect();
+    let mut data = RenderData::new(width, height, pixels, previous.max_iterations);
+    (data.bailout, data.degree) = (previous.bailout, previous.degree);
+    Some(data)
+}
+
+/// `previous`, computed over `previous_region`, blown up to `width`×`height` pixels over a
+/// `region` inside it: a preview of a view zoomed into that costs no computation at all.
+/// `None` when `region` reaches outside `previous_region`.
+pub fn resample(
+    previous: &RenderData,
+    previous_region: (f64, f64, f64, f64),
+    region: (f64, f64, f64, f64),
+    width: usize,
+    height: usize,
+) -> Option<RenderData> {
+    let (x_min, x_max, y_min, y_max) = region;
+    let (old_x_min, old_x_max, old_y_min, old_y_max) = previous_region;
+    let (old_width, old_height) = (previous.width(), previous.height());
+    // where a point falls in the previous pixels, which may be off its edge by half a pixel
+    let old_x = |x: f64| (x - old_x_min) / (old_x_max - old_x_min) * old_width as f64;
+    let old_y = |y: f64| (y - old_y_min) / (old_y_max - old_y_min) * old_height as f64;
+    let inside = |position: f64, size: usize| position >= -0.5 && position <= size as f64 + 0.5;
+    let covered = inside(old_x(x_min), old_width)
+        && inside(old_x(x_max), old_width)
+        && inside(old_y(y_min), old_height)
+        && inside(old_y(y_max), old_height);
+    if !covered || old_width == 0 || old_height == 0 {
+        return None;
+    }
+
+    let nearest = |position: f64, size: usize| (position.floor().max(0.0) as usize).min(size - 1);
+    let pixels = (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let cx = x_min + (x_max - x_min) * (x as f64 / width as f64);
+            let cy = y_min + (y_max - y_min) * (y as f64 / height as f64);
+            previous.sample(nearest(old_x(cx), old_width), nearest(old_y(cy), old_height))
+        })
+        .collect();
+    let mut data = RenderData::new(width, height, pixels, previous.max_iterations);
+    (data.bailout, data.degree) = (previous.bailout, previous.degree);
+    Some(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passes_sharpen_toward_the_full_render() {
+        let passes = passes(1000);
+        assert_eq!(passes.first(), Some(&Pass { scale: 8, iterations: 125 }));
+        assert_eq!(passes.last(), Some(&Pass::full(1000)));
+        assert_eq!(super::passes(100)[0].iterations, 64);
+
+        // the full pass is the plain render, a coarse one repeats its points over blocks
+        let region = (-2.0, 1.0, -1.0, 1.0);
+        let never = || false;
+        let full = calculate(&Fractal::Mandelbrot, Pass::full(100), region, 30, 20, &never).unwrap();
+        let expected = Fractal::Mandelbrot.calculate(100, region, 30, 20, &ComputeOptions::default(), None);
+        assert_eq!(full.pixels(), RenderData::new(30, 20, expected, 100).pixels());
+
+        let coarse = calculate(&Fractal::Mandelbrot, Pass { scale: 4, iterations: 100 }, region, 30, 20, &never).unwrap();
+        assert_eq!((coarse.width(), coarse.height()), (30, 20));
+        assert_eq!(coarse.sample(7, 13), full.sample(4, 12));
+        assert_eq!(coarse.sample(29, 19), full.sample(28, 16));
+
+        assert!(calculate(&Fractal::Mandelbrot, Pass::full(100), region, 30, 20, &|| true).is_none());
+    }
+
+    #[test]
+    fn test_moved_views_reuse_the_last_frame() {
+        // pixels 0.1 wide, so moving 3 pixels left and 2 down is (-0.3, 0.2)
+        let fractal = Fractal::Mandelbrot;
+        let region = (-2.0, 1.0, -1.0, 1.0);
+        let render = |region| RenderData::new(30, 20, fractal.calculate(100, region, 30, 20, &ComputeOptions::default(), None), 100);
+        let before = render(region);
+        let moved = (-2.3, 0.7, -0.8, 1.2);
+        let panned = pan(&fractal, &before, region, moved).unwrap();
+        let expected = render(moved);
+        for (x, y) in [(0, 0), (3, 0), (29, 19), (10, 17), (10, 18)] {
+            assert_eq!(panned.sample(x, y).iterations, expected.sample(x, y).iterations, "at {}, {}", x, y);
+        }
+        assert!(pan(&fractal, &before, region, (-1.95, 1.05, -1.0, 1.0)).is_none());
+        assert!(pan(&fractal, &before, region, (5.0, 8.0, -1.0, 1.0)).is_none());
+
+        // zooming in twice about the middle blows each pixel up into 2×2
+        let preview = resample(&before, region, (-1.25, 0.25, -0.5, 0.5), 30, 20).unwrap();
+        assert_eq!(preview.sample(0, 0), before.sample(7, 5));
+        assert_eq!(preview.sample(2, 1), before.sample(8, 5));
+        assert!(resample(&before, region, (-3.0, 2.0, -1.0, 1.0), 30, 20).is_none());
+    }
+}
@@ -1,42 +1,698 @@
-use crate::colorscheme::{Color, ColorScheme};
+use std::fs::File;
+use std::fmt;
+use std::io::{BufWriter, Write};
+use std::ops::Range;
+use std::sync::OnceLock;
 
+use base64::Engine;
+use num::complex::Complex;
+use base64::prelude::BASE64_STANDARD;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::colorscheme::{self, Color, ColorScheme};
+use crate::fractal::{DEFAULT_BAILOUT, EscapeResult};
+use crate::interior::InteriorMode;
+use crate::layers::{Layers, Source};
+use crate::lighting::Lighting;
+use crate::metadata;
+use crate::overlay::{Mark, Overlay};
+#[cfg(feature = "script")]
+use crate::script::ColorScript;
+use crate::terminfo;
+use crate::timing;
+
+/// How `Renderer::render_to_terminal` draws pixels.
 #[derive(Debug, Clone, Copy)]
 pub enum OutputFormat {
     Ascii,
     AsciiExtended,
     Ansi256,
     AnsiTrueColor,
+    /// two pixels per cell: '▀' with the top pixel as foreground and the bottom one as background
+    HalfBlock { truecolor: bool },
+    /// colored cells shaded toward the next palette step with '░▒▓', by the fractional part of
+    /// the smooth iteration count
+    Shade { truecolor: bool },
+    /// 2×4 pixels per cell as monochrome braille dots
+    Braille,
+    /// a PNG at full resolution through the kitty graphics protocol
+    Kitty,
+    /// a PNG at full resolution through iTerm2's inline image escape sequence
+    Iterm2,
+}
+
+impl OutputFormat {
+    /// Height over width of one pixel as drawn in a terminal, whose cells are about twice as
+    /// tall as they are wide.
+    pub fn pixel_aspect(&self) -> f64 {
+        match self {
+            OutputFormat::HalfBlock { .. } | OutputFormat::Braille | OutputFormat::Kitty | OutputFormat::Iterm2 => 1.0,
+            _ => 2.0,
+        }
+    }
+
+    /// Pixels drawn by one terminal cell, across and down. For inline images this is only a
+    /// typical cell size, for terminals that don't report their size in pixels.
+    pub fn pixels_per_cell(&self) -> (usize, usize) {
+        match self {
+            OutputFormat::HalfBlock { .. } => (1, 2),
+            OutputFormat::Braille => (2, 4),
+            OutputFormat::Kitty | OutputFormat::Iterm2 => (8, 16),
+            _ => (1, 1),
+        }
+    }
+
+    /// Whether drawing this format needs the terminal to interpret escape sequences.
+    pub fn uses_escapes(&self) -> bool {
+        !matches!(self, OutputFormat::Ascii | OutputFormat::AsciiExtended | OutputFormat::Braille)
+    }
+
+    /// Whether the terminal shows a real image rather than colored text.
+    pub fn is_image(&self) -> bool {
+        matches!(self, OutputFormat::Kitty | OutputFormat::Iterm2)
+    }
+}
+
+/// File formats supported by `Renderer::save_image`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Ppm,
+    Png,
+    Gif,
+    /// bare packed RGB bytes without a header, for tools told the size some other way, like
+    /// ffmpeg's `-f rawvideo -pix_fmt rgb24 -s WxH`
+    Raw,
+}
+
+impl ImageFormat {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "ppm" => Some(ImageFormat::Ppm),
+            "png" => Some(ImageFormat::Png),
+            "gif" => Some(ImageFormat::Gif),
+            "raw" | "rgb" => Some(ImageFormat::Raw),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from a file extension, e.g. "out.png".
+    pub fn from_path(path: &str) -> Option<Self> {
+        std::path::Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_str)
+    }
+}
+
+/// How escape data is turned into a position on the palette.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColoringMode {
+    /// iterations / max_iterations
+    Linear,
+    /// fractional iteration count from the final |z|
+    Smooth,
+    /// cumulative distribution of iteration counts, so every palette color is used equally
+    Histogram,
+    /// estimated distance to the set, in pixels; `falloff` is how many pixels it takes to get
+    /// about two thirds of the way along the palette. Needs distances computed alongside the data
+    Distance { falloff: f64 },
+}
+
+/// Falloff used by `--coloring distance` unless another one is given.
+pub const DEFAULT_DISTANCE_FALLOFF: f64 = 4.0;
+
+impl ColoringMode {
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "linear" => Some(ColoringMode::Linear),
+            "smooth" => Some(ColoringMode::Smooth),
+            "histogram" => Some(ColoringMode::Histogram),
+            "distance" => Some(ColoringMode::Distance {
+                falloff: DEFAULT_DISTANCE_FALLOFF,
+            }),
+            _ => None,
+        }
+    }
 }
 
+/// Transfer function from an iteration count to a palette position, for linear and smooth
+/// coloring. Curves that rise quickly give low counts, near the boundary, more of the palette.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorMap {
+    /// iterations / max_iterations
+    Linear,
+    /// ln(1 + iterations) / ln(1 + max_iterations)
+    Log,
+    /// square root of the linear position
+    Sqrt,
+    /// linear position to the power `gamma`
+    Power(f64),
+}
+
+impl ColorMap {
+    /// Parses "linear", "log", "sqrt" or "power:<gamma>" with a positive gamma.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "linear" => Some(ColorMap::Linear),
+            "log" => Some(ColorMap::Log),
+            "sqrt" => Some(ColorMap::Sqrt),
+            s => {
+                let gamma: f64 = s.strip_prefix("power:")?.parse().ok()?;
+                (gamma > 0.0 && gamma.is_finite()).then_some(ColorMap::Power(gamma))
+            }
+        }
+    }
+
+    /// Palette position (0.0 - 1.0) of a possibly fractional iteration count.
+    pub fn position(self, iterations: f64, max_iterations: usize) -> f64 {
+        let (iterations, max) = (iterations.max(0.0), max_iterations.max(1) as f64);
+        match self {
+            ColorMap::Linear => iterations / max,
+            ColorMap::Log => (1.0 + iterations).ln() / (1.0 + max).ln(),
+            ColorMap::Sqrt => (iterations / max).sqrt(),
+            ColorMap::Power(gamma) => (iterations / max).powf(gamma),
+        }
+    }
+}
+
+/// What `RenderData` keeps of every pixel's `EscapeResult`: its escape count and final |z| in 8
+/// bytes, which is all plain escape-time coloring reads. Counts past `u32::MAX` saturate.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Pixel {
+    pub iterations: u32,
+    pub z_norm: f32,
+}
+
+impl From<&EscapeResult> for Pixel {
+    fn from(result: &EscapeResult) -> Self {
+        Self {
+            iterations: result.iterations.min(u32::MAX as usize) as u32,
+            z_norm: result.z_norm as f32,
+        }
+    }
+}
+
+impl From<Pixel> for EscapeResult {
+    fn from(pixel: Pixel) -> Self {
+        EscapeResult {
+            iterations: pixel.iterations as usize,
+            z_norm: pixel.z_norm as f64,
+            ..Default::default()
+        }
+    }
+}
+
+// a value of a side plane, and what samples read as when their plane wasn't kept
+trait PlaneValue: Copy {
+    const MISSING: Self;
+    fn is_missing(&self) -> bool;
+}
+
+impl PlaneValue for f32 {
+    const MISSING: f32 = 0.0;
+    fn is_missing(&self) -> bool {
+        *self == 0.0
+    }
+}
+
+// newton root indices
+impl PlaneValue for u16 {
+    const MISSING: u16 = u16::MAX;
+    fn is_missing(&self) -> bool {
+        *self == u16::MAX
+    }
+}
+
+// final z
+impl PlaneValue for [f32; 2] {
+    const MISSING: [f32; 2] = [f32::NAN; 2];
+    fn is_missing(&self) -> bool {
+        self[0].is_nan()
+    }
+}
+
+// one field of the escape data of every sample, or nothing while no sample has it
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(transparent)]
+struct Plane<T>(Vec<T>);
+
+impl<T: PlaneValue> Plane<T> {
+    fn collect(values: impl Iterator<Item = T>) -> Self {
+        let values: Vec<T> = values.collect();
+        Self(if values.iter().all(T::is_missing) { Vec::new() } else { values })
+    }
+
+    fn get(&self, i: usize) -> T {
+        self.0.get(i).copied().unwrap_or(T::MISSING)
+    }
+
+    // `range` of `from` after the `len` samples this has so far
+    fn extend(&mut self, len: usize, from: &Plane<T>, range: Range<usize>) {
+        if self.0.is_empty() && from.0.is_empty() {
+            return;
+        }
+        self.0.resize(len, T::MISSING);
+        self.0.extend(range.map(|i| from.get(i)));
+    }
+
+    fn set(&mut self, len: usize, i: usize, value: T) {
+        if self.0.is_empty() {
+            if value.is_missing() {
+                return;
+            }
+            self.0.resize(len, T::MISSING);
+        }
+        self.0[i] = value;
+    }
+
+    fn fits(&self, len: usize) -> bool {
+        self.0.is_empty() || self.0.len() == len
+    }
+}
+
+// escape data of a run of samples: a `Pixel` each, and the fields only some computations
+// fill in (distance estimates, interior measures, stripe layers, newton roots and the final z)
+// in planes of their own, kept only when a sample has them
+#[derive(Clone, Serialize, Deserialize)]
+struct Planes {
+    pixels: Vec<Pixel>,
+    distance: Plane<f32>,
+    interior: Plane<f32>,
+    stripe: Plane<f32>,
+    root: Plane<u16>,
+    z: Plane<[f32; 2]>,
+}
+
+impl Planes {
+    fn new<'a>(results: impl Iterator<Item = &'a EscapeResult> + Clone) -> Self {
+        Self {
+            pixels: results.clone().map(Pixel::from).collect(),
+            distance: Plane::collect(results.clone().map(|result| result.distance as f32)),
+            interior: Plane::collect(results.clone().map(|result| result.interior as f32)),
+            stripe: Plane::collect(results.clone().map(|result| result.stripe as f32)),
+            root: Plane::collect(results.clone().map(|result| result.root.map_or(u16::MAX, |root| root.min(u16::MAX as usize - 1) as u16))),
+            z: Plane::collect(results.map(|result| result.z.map_or([f32::NAN; 2], |z| [z.re as f32, z.im as f32]))),
+        }
+    }
+
+    fn empty() -> Self {
+        Self::new([].iter())
+    }
+
+    fn len(&self) -> usize {
+        self.pixels.len()
+    }
+
+    // sample `i` widened back, without the derivative, which isn't kept
+    fn get(&self, i: usize) -> EscapeResult {
+        let root = self.root.get(i);
+        let [re, im] = self.z.get(i);
+        EscapeResult {
+            distance: self.distance.get(i) as f64,
+            interior: self.interior.get(i) as f64,
+            stripe: self.stripe.get(i) as f64,
+            root: (root != u16::MAX).then_some(root as usize),
+            z: (!re.is_nan()).then(|| Complex::new(re as f64, im as f64)),
+            ..self.pixels[i].into()
+        }
+    }
+
+    fn extend(&mut self, from: &Planes, range: Range<usize>) {
+        let len = self.len();
+        self.pixels.extend_from_slice(&from.pixels[range.clone()]);
+        self.distance.extend(len, &from.distance, range.clone());
+        self.interior.extend(len, &from.interior, range.clone());
+        self.stripe.extend(len, &from.stripe, range.clone());
+        self.root.extend(len, &from.root, range.clone());
+        self.z.extend(len, &from.z, range);
+    }
+
+    fn set(&mut self, i: usize, result: &EscapeResult) {
+        let (len, single) = (self.len(), Planes::new(std::iter::once(result)));
+        self.pixels[i] = single.pixels[0];
+        self.distance.set(len, i, single.distance.get(0));
+        self.interior.set(len, i, single.interior.get(0));
+        self.stripe.set(len, i, single.stripe.get(0));
+        self.root.set(len, i, single.root.get(0));
+        self.z.set(len, i, single.z.get(0));
+    }
+
+    fn fits(&self) -> bool {
+        let len = self.len();
+        self.distance.fits(len) && self.interior.fits(len) && self.stripe.fits(len) && self.root.fits(len) && self.z.fits(len)
+    }
+
+    // sample `i` in a fixed little-endian layout
+    fn hash(&self, i: usize, hasher: &mut blake3::Hasher) {
+        let pixel = self.pixels[i];
+        hasher.update(&pixel.iterations.to_le_bytes());
+        for value in [pixel.z_norm, self.distance.get(i), self.interior.get(i), self.stripe.get(i)] {
+            hasher.update(&value.to_bits().to_le_bytes());
+        }
+        hasher.update(&self.root.get(i).to_le_bytes());
+        for part in self.z.get(i) {
+            hasher.update(&part.to_bits().to_le_bytes());
+        }
+    }
+}
+
+// supersamples, the same number for every pixel that has any, in `samples` one pixel's worth
+// after another: pixel i's are in slot `slots[i]`, or it has none when that's NO_SLOT
+#[derive(Clone, Serialize, Deserialize)]
+struct Subsamples {
+    per_pixel: usize,
+    slots: Vec<u32>,
+    samples: Planes,
+}
+
+const NO_SLOT: u32 = u32::MAX;
+
+impl Subsamples {
+    fn none() -> Self {
+        Self {
+            per_pixel: 0,
+            slots: Vec::new(),
+            samples: Planes::empty(),
+        }
+    }
+
+    // where pixel i's samples are
+    fn range(&self, i: usize) -> Option<Range<usize>> {
+        let slot = *self.slots.get(i).filter(|&&slot| slot != NO_SLOT)? as usize;
+        Some(slot * self.per_pixel..(slot + 1) * self.per_pixel)
+    }
+
+    // the samples of `from`'s pixels in `pixels`, after the `len` pixels this has so far
+    fn extend(&mut self, len: usize, from: &Subsamples, pixels: Range<usize>) {
+        if from.slots.is_empty() && self.slots.is_empty() {
+            return;
+        }
+        self.slots.resize(len, NO_SLOT);
+        for i in pixels {
+            match from.range(i) {
+                Some(range) => {
+                    self.claim(range.len());
+                    self.slots.push((self.samples.len() / self.per_pixel) as u32);
+                    self.samples.extend(&from.samples, range);
+                }
+                None => self.slots.push(NO_SLOT),
+            }
+        }
+    }
+
+    fn claim(&mut self, per_pixel: usize) {
+        assert!(
+            self.per_pixel == 0 || self.per_pixel == per_pixel,
+            "pixels can't have {} supersamples where others have {}",
+            per_pixel,
+            self.per_pixel
+        );
+        self.per_pixel = per_pixel;
+    }
+
+    fn fits(&self, pixels: usize) -> bool {
+        let slots = self.slots.iter().filter(|&&slot| slot != NO_SLOT);
+        (self.slots.is_empty() || self.slots.len() == pixels)
+            && self.samples.fits()
+            && slots.clone().all(|&slot| (slot as usize + 1) * self.per_pixel <= self.samples.len())
+            && self.samples.len() == slots.count() * self.per_pixel
+    }
+}
+
+/// Per-pixel escape data of a computed region, stored row-major in one flat buffer of
+/// `Pixel`s, with the fields only some computations fill in kept in planes of their own when
+/// they were, and read back widened into `EscapeResult`s.
+///
+/// Serializes with serde to everything that went into it, supersamples included, and checks
+/// the buffers match the size when deserialized.
+#[derive(Serialize, Deserialize)]
+#[serde(try_from = "Fields")]
 pub struct RenderData {
-    pub iterations: Vec<Vec<usize>>,
-    pub z_norms: Vec<Vec<f64>>, // For smooth coloring
+    width: usize,
+    height: usize,
     pub max_iterations: usize,
+    /// escape radius the data was computed with, which smooth coloring normalizes by
+    pub bailout: f64,
+    /// `Fractal::degree` of what was computed, the base of smooth coloring's logarithms
+    pub degree: f64,
+    planes: Planes,
+    subsamples: Subsamples,
+    #[serde(skip)]
+    histogram: OnceLock<Vec<f64>>,
+}
+
+// RenderData as it's serialized, before its sizes are checked
+#[derive(Deserialize)]
+struct Fields {
+    width: usize,
+    height: usize,
+    max_iterations: usize,
+    bailout: f64,
+    degree: f64,
+    planes: Planes,
+    subsamples: Subsamples,
+}
+
+impl TryFrom<Fields> for RenderData {
+    type Error = String;
+
+    fn try_from(fields: Fields) -> Result<Self, String> {
+        let size = fields.width.checked_mul(fields.height);
+        if size != Some(fields.planes.len()) || !fields.planes.fits() || !fields.subsamples.fits(fields.planes.len()) {
+            return Err(format!("render data doesn't match {}x{}", fields.width, fields.height));
+        }
+        let mut data = Self::from_planes(fields.width, fields.height, fields.planes, fields.max_iterations);
+        data.bailout = fields.bailout;
+        data.degree = fields.degree;
+        data.subsamples = fields.subsamples;
+        Ok(data)
+    }
 }
 
 impl RenderData {
-    pub fn new(iterations: Vec<Vec<usize>>, z_norms: Vec<Vec<f64>>, max_iterations: usize) -> Self {
+    /// Wraps `width`×`height` escape results, row by row, of a squaring formula computed with
+    /// the default bailout.
+    pub fn new(width: usize, height: usize, pixels: Vec<EscapeResult>, max_iterations: usize) -> Self {
+        Self::from_planes(width, height, Planes::new(pixels.iter()), max_iterations)
+    }
+
+    // `new` for pixels already narrowed
+    fn from_planes(width: usize, height: usize, planes: Planes, max_iterations: usize) -> Self {
+        assert_eq!(planes.len(), width * height, "pixel buffer doesn't match {}x{}", width, height);
         Self {
-            iterations,
-            z_norms,
+            width,
+            height,
             max_iterations,
+            bailout: DEFAULT_BAILOUT,
+            degree: 2.0,
+            planes,
+            subsamples: Subsamples::none(),
+            histogram: OnceLock::new(),
+        }
+    }
+
+    /// Groups a row-major grid `width` samples wide, computed at `factor` times the resolution,
+    /// into pixels of factor×factor samples. Each pixel's own sample is its top-left one,
+    /// which is what the pixel would have been without supersampling.
+    pub fn supersampled(results: &[EscapeResult], width: usize, max_iterations: usize, factor: usize) -> Self {
+        let grid_width = width;
+        let (width, height) = (grid_width / factor, results.len() / grid_width.max(1) / factor);
+
+        // the samples of pixel (x, y), row by row
+        let pixel_samples = |x: usize, y: usize| {
+            (y * factor..(y + 1) * factor).flat_map(move |sy| &results[sy * grid_width + x * factor..sy * grid_width + (x + 1) * factor])
+        };
+        let pixels = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)));
+        let own = pixels.clone().map(|(x, y)| &results[y * factor * grid_width + x * factor]);
+        let samples = pixels.flat_map(|(x, y)| pixel_samples(x, y));
+
+        let mut data = Self::from_planes(width, height, Planes::new(own), max_iterations);
+        data.subsamples = Subsamples {
+            per_pixel: factor * factor,
+            slots: (0..(width * height) as u32).collect(),
+            samples: Planes::new(samples),
+        };
+        data
+    }
+
+    /// Panels of the same height joined left to right into one image, for showing several
+    /// views at once. The image keeps the highest max iterations of the panels, and the bailout
+    /// and degree of the first.
+    pub fn side_by_side(panels: &[RenderData]) -> Self {
+        let height = panels.first().map_or(0, |panel| panel.height);
+        assert!(panels.iter().all(|panel| panel.height == height), "panels must be the same height");
+        let width = panels.iter().map(|panel| panel.width).sum();
+        let max_iterations = panels.iter().map(|panel| panel.max_iterations).max().unwrap_or(0);
+
+        let (mut planes, mut subsamples) = (Planes::empty(), Subsamples::none());
+        for y in 0..height {
+            for panel in panels {
+                let row = y * panel.width..(y + 1) * panel.width;
+                subsamples.extend(planes.len(), &panel.subsamples, row.clone());
+                planes.extend(&panel.planes, row);
+            }
+        }
+
+        let mut data = Self::from_planes(width, height, planes, max_iterations);
+        if let Some(first) = panels.first() {
+            (data.bailout, data.degree) = (first.bailout, first.degree);
+        }
+        data.subsamples = subsamples;
+        data
+    }
+
+    /// Bands of the same width joined top to bottom into one image, as computed in pieces by
+    /// tiles or workers. The image keeps the highest max iterations of the bands, and the
+    /// bailout and degree of the first.
+    pub fn stacked(bands: &[RenderData]) -> Self {
+        let width = bands.first().map_or(0, |band| band.width);
+        assert!(bands.iter().all(|band| band.width == width), "bands must be the same width");
+        let height = bands.iter().map(|band| band.height).sum();
+        let max_iterations = bands.iter().map(|band| band.max_iterations).max().unwrap_or(0);
+
+        let (mut planes, mut subsamples) = (Planes::empty(), Subsamples::none());
+        for band in bands {
+            subsamples.extend(planes.len(), &band.subsamples, 0..band.planes.len());
+            planes.extend(&band.planes, 0..band.planes.len());
+        }
+
+        let mut data = Self::from_planes(width, height, planes, max_iterations);
+        if let Some(first) = bands.first() {
+            (data.bailout, data.degree) = (first.bailout, first.degree);
         }
+        data.subsamples = subsamples;
+        data
+    }
+
+    /// Escape data of pixel (x, y), ignoring any supersamples.
+    pub fn sample(&self, x: usize, y: usize) -> EscapeResult {
+        self.planes.get(y * self.width + x)
+    }
+
+    /// Escape data of every pixel, row by row, ignoring any supersamples.
+    pub fn samples(&self) -> impl ExactSizeIterator<Item = EscapeResult> + '_ {
+        (0..self.planes.len()).map(|i| self.planes.get(i))
+    }
+
+    /// Every pixel's count and |z|, row by row.
+    pub fn pixels(&self) -> &[Pixel] {
+        &self.planes.pixels
+    }
+
+    /// Each row of pixels' counts and |z|, top to bottom, ignoring any supersamples.
+    pub fn rows(&self) -> std::slice::ChunksExact<'_, Pixel> {
+        self.planes.pixels.chunks_exact(self.width.max(1))
+    }
+
+    /// All supersamples of pixel (x, y), if it was supersampled.
+    pub fn subsamples(&self, x: usize, y: usize) -> Option<impl ExactSizeIterator<Item = EscapeResult> + '_> {
+        let range = self.subsamples.range(y * self.width + x)?;
+        Some(range.map(|i| self.subsamples.samples.get(i)))
+    }
+
+    /// Replaces the supersamples of pixel (x, y); the pixel is then colored by their average.
+    /// Every supersampled pixel has to have as many.
+    pub fn set_subsamples(&mut self, x: usize, y: usize, samples: Vec<EscapeResult>) {
+        let i = y * self.width + x;
+        let subsamples = &mut self.subsamples;
+        subsamples.claim(samples.len());
+        if subsamples.slots.is_empty() {
+            subsamples.slots = vec![NO_SLOT; self.planes.len()];
+        }
+        let range = subsamples.range(i).unwrap_or_else(|| {
+            subsamples.slots[i] = (subsamples.samples.len() / subsamples.per_pixel) as u32;
+            let start = subsamples.samples.len();
+            subsamples.samples.extend(&Planes::new(samples.iter()), 0..samples.len());
+            start..start
+        });
+        for (i, sample) in range.zip(&samples) {
+            subsamples.samples.set(i, sample);
+        }
+    }
+
+    /// Fraction of escaped pixels that escaped in fewer than `iterations` iterations, plus
+    /// `fraction` of those that escaped in exactly `iterations`.
+    pub fn histogram_position(&self, iterations: usize, fraction: f64) -> f64 {
+        // cdf[i] = share of escaped pixels with fewer than i iterations, built on first use
+        let cdf = self.histogram.get_or_init(|| {
+            let mut counts = vec![0usize; self.max_iterations + 1];
+            for pixel in &self.planes.pixels {
+                let iterations = pixel.iterations as usize;
+                if iterations < self.max_iterations {
+                    counts[iterations] += 1;
+                }
+            }
+
+            let escaped = counts.iter().sum::<usize>().max(1) as f64;
+            let mut cdf = Vec::with_capacity(counts.len());
+            let mut total = 0;
+            for count in counts {
+                cdf.push(total as f64 / escaped);
+                total += count;
+            }
+            cdf
+        });
+
+        let index = iterations.min(self.max_iterations.saturating_sub(1));
+        let start = cdf[index];
+        let end = cdf.get(index + 1).copied().unwrap_or(1.0);
+        start + (end - start) * fraction.clamp(0.0, 1.0)
     }
 
     pub fn width(&self) -> usize {
-        self.iterations.first().map(|row| row.len()).unwrap_or(0)
+        self.width
     }
 
     pub fn height(&self) -> usize {
-        self.iterations.len()
+        self.height
     }
-}
 
+    /// BLAKE3 hash, in hex, of the size, max iterations, bailout, degree and every pixel's
+    /// escape data and supersamples, in a fixed little-endian layout: equal checksums mean bit-identical
+    /// results, whatever the platform.
+    pub fn checksum(&self) -> String {
+        let mut hasher = blake3::Hasher::new();
+        for value in [self.width, self.height, self.max_iterations] {
+            hasher.update(&(value as u64).to_le_bytes());
+        }
+        for value in [self.bailout, self.degree] {
+            hasher.update(&value.to_bits().to_le_bytes());
+        }
+        for i in 0..self.planes.len() {
+            self.planes.hash(i, &mut hasher);
+        }
+        for i in 0..self.planes.len() {
+            for sample in self.subsamples.range(i).into_iter().flatten() {
+                self.subsamples.samples.hash(sample, &mut hasher);
+            }
+        }
+        hasher.finalize().to_hex().to_string()
+    }
+}
 
+/// Colors `RenderData` and draws it to the terminal or to image files.
+#[derive(Clone)]
 pub struct Renderer {
     color_scheme: ColorScheme,
     output_format: OutputFormat,
-    use_smooth_coloring: bool,
+    coloring: ColoringMode,
+    interior: InteriorMode,
+    color_map: ColorMap,
+    // (repeat, offset) of the palette over the exterior coloring range
+    cycle: (f64, f64),
+    // unset dithers colors and shades but not density characters, whose flat areas it would
+    // turn into a checkerboard of two characters
+    dither: Option<bool>,
+    overlay: Option<Overlay>,
+    lighting: Option<Lighting>,
+    layers: Option<Layers>,
+    metadata: Vec<(String, String)>,
+    #[cfg(feature = "script")]
+    script: Option<ColorScript>,
 }
 
 impl Renderer {
@@ -44,171 +700,1254 @@ impl Renderer {
         Self {
             color_scheme,
             output_format,
-            use_smooth_coloring: true,
+            coloring: ColoringMode::Smooth,
+            interior: InteriorMode::Black,
+            color_map: ColorMap::Linear,
+            cycle: (1.0, 0.0),
+            dither: None,
+            overlay: None,
+            lighting: None,
+            layers: None,
+            metadata: Vec::new(),
+            #[cfg(feature = "script")]
+            script: None,
         }
     }
 
     pub fn with_smooth_coloring(mut self, smooth: bool) -> Self {
-        self.use_smooth_coloring = smooth;
+        self.coloring = if smooth { ColoringMode::Smooth } else { ColoringMode::Linear };
+        self
+    }
+
+    pub fn with_coloring(mut self, coloring: ColoringMode) -> Self {
+        self.coloring = coloring;
+        self
+    }
+
+    pub fn with_interior(mut self, interior: InteriorMode) -> Self {
+        self.interior = interior;
         self
     }
 
+    /// Transfer function for linear and smooth coloring.
+    pub fn with_color_map(mut self, color_map: ColorMap) -> Self {
+        self.color_map = color_map;
+        self
+    }
+
+    /// Runs through the palette `repeat` times over the exterior coloring range, starting
+    /// `offset` (0.0 - 1.0) of the way in: position t becomes fract(t·repeat + offset).
+    pub fn with_color_cycle(mut self, repeat: f64, offset: f64) -> Self {
+        self.cycle = (repeat, offset);
+        self
+    }
+
+    /// Ordered dithering for the 256-color and ASCII formats, which hides the banding of their
+    /// few levels. By default it dithers colors and shades but not ASCII characters.
+    pub fn with_dither(mut self, dither: bool) -> Self {
+        self.dither = Some(dither);
+        self
+    }
+
+    /// Marks to draw over renders of the overlay's size; data of any other size is drawn
+    /// without them.
+    pub fn with_overlay(mut self, overlay: Overlay) -> Self {
+        self.overlay = Some(overlay);
+        self
+    }
+
+    /// Hillshading of the exterior, lit by `lighting` as if the smooth iteration count were
+    /// terrain.
+    pub fn with_lighting(mut self, lighting: Lighting) -> Self {
+        self.lighting = Some(lighting);
+        self
+    }
+
+    /// Colors made of `layers` composited from the bottom up instead of the coloring alone,
+    /// which becomes their palette source. The data has to carry what they draw from; see
+    /// `Layers::stripe_density` and `Layers::needs_distance`.
+    pub fn with_layers(mut self, layers: Layers) -> Self {
+        self.layers = Some(layers);
+        self
+    }
+
+    /// Text to store in the PNG files it saves, as (keyword, text) pairs; see `metadata`.
+    pub fn with_metadata(mut self, metadata: Vec<(String, String)>) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Colors from `script` instead of the color scheme: a palette script stands in for it
+    /// wherever the palette is used, and a script coloring points colors every point itself.
+    #[cfg(feature = "script")]
+    pub fn with_color_script(mut self, script: ColorScript) -> Self {
+        self.script = Some(script);
+        self
+    }
+
+    pub fn color_scheme(&self) -> &ColorScheme {
+        &self.color_scheme
+    }
+
+    pub fn output_format(&self) -> OutputFormat {
+        self.output_format
+    }
+
+    pub fn coloring(&self) -> ColoringMode {
+        self.coloring
+    }
+
+    pub fn interior(&self) -> InteriorMode {
+        self.interior
+    }
+
+    pub fn color_map(&self) -> ColorMap {
+        self.color_map
+    }
+
+    pub fn color_cycle(&self) -> (f64, f64) {
+        self.cycle
+    }
+
+    pub fn layers(&self) -> Option<&Layers> {
+        self.layers.as_ref()
+    }
+
+    /// Color of the pixel at (x, y), averaged over its supersamples if there are any.
+    pub fn pixel_color(&self, data: &RenderData, x: usize, y: usize) -> Color {
+        if let Some(mark) = self.mark_at(data, x, y) {
+            return mark.color;
+        }
+        let color = match data.subsamples(x, y) {
+            None => self.sample_color(data, &data.sample(x, y)),
+            Some(samples) => {
+                let (mut sum, count) = ([0u32; 3], samples.len() as u32);
+                for sample in samples {
+                    let color = self.sample_color(data, &sample);
+                    sum[0] += color.r as u32;
+                    sum[1] += color.g as u32;
+                    sum[2] += color.b as u32;
+                }
+                Color::new((sum[0] / count) as u8, (sum[1] / count) as u8, (sum[2] / count) as u8)
+            }
+        };
+        match &self.lighting {
+            Some(lighting) => lighting.shade(color, data, x, y),
+            None => color,
+        }
+    }
+
+    // the overlay, if it was made for renders of this size
+    fn overlay_for(&self, data: &RenderData) -> Option<&Overlay> {
+        self.overlay
+            .as_ref()
+            .filter(|overlay| (overlay.width(), overlay.height()) == (data.width(), data.height()))
+    }
+
+    fn mark_at(&self, data: &RenderData, x: usize, y: usize) -> Option<Mark> {
+        self.overlay_for(data)?.mark(x, y)
+    }
+
+    // label text, for the formats that print a character per pixel
+    fn text_at(&self, data: &RenderData, x: usize, y: usize) -> Option<Mark> {
+        self.overlay_for(data)?.text_at(x, y)
+    }
+
+    fn sample_color(&self, data: &RenderData, sample: &EscapeResult) -> Color {
+        let Some(layers) = &self.layers else {
+            return self.palette_color(data, sample);
+        };
+        layers.iter().fold(Color::new(0, 0, 0), |below, layer| match self.layer_color(data, sample, layer.source) {
+            Some(color) => layer.blend.apply(below, color, layer.opacity),
+            None => below,
+        })
+    }
+
+    // what a layer draws at `sample`; None leaves the layers below showing
+    fn layer_color(&self, data: &RenderData, sample: &EscapeResult, source: Source) -> Option<Color> {
+        let escaped = sample.iterations < data.max_iterations && sample.root.is_none();
+        let gray = |value: f64| {
+            let level = (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+            Color::new(level, level, level)
+        };
+        match source {
+            Source::Palette => Some(self.palette_color(data, sample)),
+            Source::Stripes { palette: true, .. } if escaped => Some(self.palette_at(self.cycled(sample.stripe))),
+            Source::Stripes { .. } if escaped => Some(gray(sample.stripe)),
+            Source::Edges { falloff } if escaped => Some(gray(distance_position(sample.distance, falloff))),
+            _ => None,
+        }
+    }
+
+    fn palette_color(&self, data: &RenderData, sample: &EscapeResult) -> Color {
+        let iters = sample.iterations;
+        #[cfg(feature = "script")]
+        if let Some(script) = self.script.as_ref().filter(|script| !script.is_palette()) {
+            let in_set = iters >= data.max_iterations && sample.root.is_none();
+            let t = if in_set || sample.root.is_some() { 0.0 } else { self.cycled(self.exterior_position(data, sample)) };
+            return script.point_color(t, sample, in_set);
+        }
+        if let Some(root) = sample.root {
+            // newton points take their root's color, darker the longer they took to get there
+            let color = self.palette_at(((root + 1) as f64 * 0.618_033_988_75).fract());
+            return color.lerp(Color::new(0, 0, 0), 0.8 * (1.0 - 0.9f64.powf(iters as f64)));
+        }
+        if iters >= data.max_iterations {
+            return self.interior_color(sample.interior);
+        }
+        self.palette_at(self.cycled(self.exterior_position(data, sample)))
+    }
+
+    /// Whether a color script colors every point itself, so it needs `ComputeOptions::final_z`.
+    pub fn script_colors_points(&self) -> bool {
+        #[cfg(feature = "script")]
+        return self.script.as_ref().is_some_and(|script| !script.is_palette());
+        #[cfg(not(feature = "script"))]
+        false
+    }
+
+    // the palette at position `t`, from a palette script in place of the scheme when there's
+    // one; scripts that color points leave the scheme as the palette of layers and the interior
+    fn palette_at(&self, t: f64) -> Color {
+        #[cfg(feature = "script")]
+        if let Some(script) = self.script.as_ref().filter(|script| script.is_palette()) {
+            return script.color_at(t);
+        }
+        self.color_scheme.color_at(t)
+    }
+
+    // where an escaping point falls on the palette, before any cycling
+    fn exterior_position(&self, data: &RenderData, sample: &EscapeResult) -> f64 {
+        let (iters, z_norm) = (sample.iterations, sample.z_norm);
+        match self.coloring {
+            ColoringMode::Linear => self.color_map.position(iters as f64, data.max_iterations),
+            ColoringMode::Smooth => {
+                let smooth = colorscheme::smooth_iteration(iters, z_norm, data.bailout, data.degree);
+                self.color_map.position(smooth, data.max_iterations)
+            }
+            ColoringMode::Histogram => {
+                // the smooth value picks a spot between neighbouring histogram buckets
+                let smooth = colorscheme::smooth_iteration(iters, z_norm, data.bailout, data.degree);
+                let fraction = smooth - smooth.floor();
+                let bucket = (smooth.floor().max(0.0) as usize).min(iters + 1);
+                data.histogram_position(bucket, fraction)
+            }
+            // the boundary takes the start of the palette whatever the iteration count
+            ColoringMode::Distance { falloff } => distance_position(sample.distance, falloff),
+        }
+    }
+
+    // the palette position of `position` once cycling is applied; without any, the end of the
+    // range stays at the end of the palette instead of wrapping to its start
+    fn cycled(&self, position: f64) -> f64 {
+        match self.cycle {
+            (1.0, 0.0) => position,
+            (repeat, offset) => (position.clamp(0.0, 1.0) * repeat + offset).rem_euclid(1.0),
+        }
+    }
+
+    fn interior_color(&self, measure: f64) -> Color {
+        match self.interior {
+            InteriorMode::Black => Color::new(0, 0, 0),
+            InteriorMode::Distance { falloff } => self.palette_at(distance_position(measure, falloff)),
+            InteriorMode::Angle => self.palette_at(measure / std::f64::consts::TAU + 0.5),
+            // 0 means the orbit never settled on a cycle
+            InteriorMode::Period if measure < 1.0 => Color::new(0, 0, 0),
+            // golden-ratio steps keep neighbouring periods far apart on the palette
+            InteriorMode::Period => self.palette_at((measure * 0.618_033_988_75).fract()),
+        }
+    }
+
+    /// Draws `data` to stdout through a buffer large enough that most frames go out in one write.
     pub fn render_to_terminal(&self, data: &RenderData) {
+        let mut stdout = BufWriter::with_capacity(TERMINAL_BUFFER_SIZE, std::io::stdout().lock());
+        // nothing sensible to do if stdout is gone, e.g. piped into `head`
+        let _ = self.render_to(data, &mut stdout).and_then(|_| stdout.flush());
+    }
+
+    /// Writes the text of `data` to `out`, which can be a file, a socket or a buffer as well
+    /// as a terminal: one line per row of cells, each ending in a newline. Colored formats
+    /// only emit an escape code where the color changes along a line.
+    pub fn render_to(&self, data: &RenderData, out: &mut impl Write) -> std::io::Result<()> {
+        let mut adapter = FmtAdapter { inner: out, error: Ok(()) };
+        match self.render_fmt(data, &mut adapter) {
+            Ok(()) => Ok(()),
+            Err(_) => adapter.error.and(Err(std::io::Error::other("formatting failed"))),
+        }
+    }
+
+    /// The text `render_to` writes, collected into a string.
+    pub fn render_to_string(&self, data: &RenderData) -> String {
+        let mut out = String::new();
+        self.render_fmt(data, &mut out).expect("writing to a String can't fail");
+        out
+    }
+
+    fn render_fmt(&self, data: &RenderData, out: &mut dyn fmt::Write) -> fmt::Result {
         match self.output_format {
-            OutputFormat::Ascii => self.render_ascii(data),
-            OutputFormat::AsciiExtended => self.render_ascii_extended(data),
-            OutputFormat::Ansi256 => self.render_ansi_256(data),
-            OutputFormat::AnsiTrueColor => self.render_ansi_truecolor(data),
+            OutputFormat::Ascii => self.render_ascii(data, out),
+            OutputFormat::AsciiExtended => self.render_ascii_extended(data, out),
+            OutputFormat::Ansi256 => self.render_ansi_256(data, out),
+            OutputFormat::AnsiTrueColor => self.render_ansi_truecolor(data, out),
+            OutputFormat::HalfBlock { truecolor } => self.render_half_block(data, truecolor, out),
+            OutputFormat::Shade { truecolor } => self.render_shade(data, truecolor, out),
+            OutputFormat::Braille => self.render_braille(data, out),
+            OutputFormat::Kitty | OutputFormat::Iterm2 => self.render_inline_image(data, out),
+        }
+    }
+
+    // the escape sequences carry a base64 PNG; kitty takes it in chunks of at most 4096 bytes,
+    // each but the last marked m=1, with the format and action only on the first
+    fn render_inline_image(&self, data: &RenderData, out: &mut dyn fmt::Write) -> fmt::Result {
+        let mut png = Vec::new();
+        write_png(&mut png, data.width(), data.height(), &self.to_rgb_buffer(data), &[]).expect("writing to a Vec can't fail");
+        let encoded = BASE64_STANDARD.encode(&png);
+
+        if matches!(self.output_format, OutputFormat::Iterm2) {
+            write!(out, "\x1b]1337;File=inline=1;size={};preserveAspectRatio=1:{}\x07", png.len(), encoded)?;
+        } else {
+            let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+            for (i, chunk) in chunks.iter().enumerate() {
+                let more = (i + 1 < chunks.len()) as u8;
+                let control = if i == 0 { format!("a=T,f=100,m={}", more) } else { format!("m={}", more) };
+                // base64 is plain ascii
+                write!(out, "\x1b_G{};{}\x1b\\", control, std::str::from_utf8(chunk).unwrap())?;
+            }
         }
+        out.write_char('\n')?;
+        Ok(())
     }
 
-    fn render_ascii(&self, data: &RenderData) {
+    fn render_ascii(&self, data: &RenderData, out: &mut dyn fmt::Write) -> fmt::Result {
         let chars = [' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
         
         for y in 0..data.height() {
-            let mut line = String::with_capacity(data.width());
             for x in 0..data.width() {
-                let iters = data.iterations[y][x];
-                
-                if iters >= data.max_iterations {
-                    line.push(' ');
-                } else {
-                    let idx = ((iters as f64 / data.max_iterations as f64) * (chars.len() - 1) as f64) as usize;
-                    line.push(chars[idx.min(chars.len() - 1)]);
-                }
+                out.write_char(self.density_char(data, x, y, &chars))?;
             }
-            println!("{}", line);
+            out.write_char('\n')?;
         }
+        Ok(())
     }
 
-    fn render_ascii_extended(&self, data: &RenderData) {
+    fn render_ascii_extended(&self, data: &RenderData, out: &mut dyn fmt::Write) -> fmt::Result {
         let chars = [
             ' ', '·', '∙', '•', '○', '◦', '⋅', '⋆', '∗', '⊕',
             '⊗', '⊛', '⊚', '◉', '●', '◐', '◑', '◒', '◓', '█'
         ];
         
         for y in 0..data.height() {
-            let mut line = String::with_capacity(data.width() * 3); // Unicode chars
             for x in 0..data.width() {
-                let iters = data.iterations[y][x];
-                
-                if iters >= data.max_iterations {
-                    line.push(' ');
-                } else {
-                    let idx = ((iters as f64 / data.max_iterations as f64) * (chars.len() - 1) as f64) as usize;
-                    line.push(chars[idx.min(chars.len() - 1)]);
+                out.write_char(self.density_char(data, x, y, &chars))?;
+            }
+            out.write_char('\n')?;
+        }
+        Ok(())
+    }
+
+    // `chars` from sparse to dense along the same coloring and color map as the palette;
+    // points in the set are blank
+    fn density_char(&self, data: &RenderData, x: usize, y: usize, chars: &[char]) -> char {
+        if let Some(mark) = self.text_at(data, x, y).or_else(|| self.mark_at(data, x, y)) {
+            return mark.glyph;
+        }
+        let sample = data.sample(x, y);
+        if sample.iterations >= data.max_iterations {
+            return ' ';
+        }
+        let position = self.exterior_position(data, &sample).clamp(0.0, 1.0);
+        let level = position * (chars.len() - 1) as f64;
+        let idx = if self.dither == Some(true) { (level + bayer_threshold(x, y)) as usize } else { level as usize };
+        chars[idx.min(chars.len() - 1)]
+    }
+
+    // the 256-color palette entry for the pixel at (x, y)
+    fn ansi256_color(&self, color: Color, x: usize, y: usize) -> u8 {
+        if self.dither != Some(false) {
+            rgb_to_ansi256_dithered(color, bayer_threshold(x, y))
+        } else {
+            rgb_to_ansi256(color.r, color.g, color.b)
+        }
+    }
+
+    // a label character in its own color over the cell's background, or a blank
+    fn write_text(&self, data: &RenderData, x: usize, y: usize, truecolor: bool, out: &mut dyn fmt::Write) -> fmt::Result {
+        match self.text_at(data, x, y) {
+            Some(mark) => {
+                self.write_color(out, 38, mark.color, truecolor, (x, y))?;
+                out.write_char(mark.glyph)
+            }
+            None => out.write_char(' '),
+        }
+    }
+
+    fn render_ansi_256(&self, data: &RenderData, out: &mut dyn fmt::Write) -> fmt::Result {
+        for y in 0..data.height() {
+            let mut current = None;
+            for x in 0..data.width() {
+                let color = self.pixel_color(data, x, y);
+                let ansi_color = self.ansi256_color(color, x, y);
+                if current != Some(ansi_color) {
+                    write!(out, "\x1b[48;5;{}m", ansi_color)?;
+                    current = Some(ansi_color);
                 }
+                self.write_text(data, x, y, false, out)?;
             }
-            println!("{}", line);
+            out.write_str("\x1b[0m\n")?;
         }
+        Ok(())
     }
 
-    fn render_ansi_256(&self, data: &RenderData) {
+    fn render_ansi_truecolor(&self, data: &RenderData, out: &mut dyn fmt::Write) -> fmt::Result {
         for y in 0..data.height() {
+            let mut current = None;
             for x in 0..data.width() {
-                let iters = data.iterations[y][x];
-                let z_norm = data.z_norms[y][x];
-                
-                let color = if self.use_smooth_coloring {
-                    self.color_scheme.get_smooth_color(iters, data.max_iterations, z_norm)
-                } else {
-                    self.color_scheme.get_color(iters, data.max_iterations)
-                };
+                let color = self.pixel_color(data, x, y);
+                if current != Some(color) {
+                    write!(out, "\x1b[48;2;{};{};{}m", color.r, color.g, color.b)?;
+                    current = Some(color);
+                }
+                self.write_text(data, x, y, true, out)?;
+            }
+            out.write_str("\x1b[0m\n")?;
+        }
+        Ok(())
+    }
 
-                let ansi_color = rgb_to_ansi256(color.r, color.g, color.b);
-                print!("\x1b[48;5;{}m \x1b[0m", ansi_color);
+    // sets the foreground (layer 38) or background (48) color for the cell at (x, y)
+    fn write_color(&self, out: &mut dyn fmt::Write, layer: u8, color: Color, truecolor: bool, (x, y): (usize, usize)) -> fmt::Result {
+        if truecolor {
+            write!(out, "\x1b[{};2;{};{};{}m", layer, color.r, color.g, color.b)
+        } else {
+            write!(out, "\x1b[{};5;{}m", layer, self.ansi256_color(color, x, y))
+        }
+    }
+
+    fn render_half_block(&self, data: &RenderData, truecolor: bool, out: &mut dyn fmt::Write) -> fmt::Result {
+        let escape = |out: &mut dyn fmt::Write, layer: u8, color: Color, at: (usize, usize)| self.write_color(out, layer, color, truecolor, at);
+
+        for y in (0..data.height()).step_by(2) {
+            let (mut top_color, mut bottom_color) = (None, None);
+            for x in 0..data.width() {
+                let top = self.pixel_color(data, x, y);
+                if top_color != Some(top) {
+                    escape(out, 38, top, (x, y))?;
+                    top_color = Some(top);
+                }
+                // an odd last row only has a top half
+                if y + 1 < data.height() {
+                    let bottom = self.pixel_color(data, x, y + 1);
+                    if bottom_color != Some(bottom) {
+                        escape(out, 48, bottom, (x, y + 1))?;
+                        bottom_color = Some(bottom);
+                    }
+                }
+                out.write_char('▀')?;
             }
-            println!();
+            out.write_str("\x1b[0m\n")?;
         }
+        Ok(())
     }
 
-    fn render_ansi_truecolor(&self, data: &RenderData) {
+    fn render_shade(&self, data: &RenderData, truecolor: bool, out: &mut dyn fmt::Write) -> fmt::Result {
+        const SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+
         for y in 0..data.height() {
+            let (mut background, mut foreground) = (None, None);
             for x in 0..data.width() {
-                let iters = data.iterations[y][x];
-                let z_norm = data.z_norms[y][x];
-                
-                let color = if self.use_smooth_coloring {
-                    self.color_scheme.get_smooth_color(iters, data.max_iterations, z_norm)
-                } else {
-                    self.color_scheme.get_color(iters, data.max_iterations)
-                };
-
-                print!("\x1b[48;2;{};{};{}m \x1b[0m", color.r, color.g, color.b);
+                let (low, high, fraction) = self.shade_colors(data, x, y);
+                if let Some(mark) = self.text_at(data, x, y) {
+                    if background != Some(low) {
+                        self.write_color(out, 48, low, truecolor, (x, y))?;
+                        background = Some(low);
+                    }
+                    if foreground != Some(mark.color) {
+                        self.write_color(out, 38, mark.color, truecolor, (x, y))?;
+                        foreground = Some(mark.color);
+                    }
+                    out.write_char(mark.glyph)?;
+                    continue;
+                }
+                let level = fraction * (SHADES.len() - 1) as f64;
+                let idx = if self.dither != Some(false) { level + bayer_threshold(x, y) } else { level + 0.5 } as usize;
+                let shade = SHADES[idx.min(SHADES.len() - 1)];
+                // a blank cell shows only its background and a full block only its foreground
+                if shade != '█' && background != Some(low) {
+                    self.write_color(out, 48, low, truecolor, (x, y))?;
+                    background = Some(low);
+                }
+                if shade != ' ' && foreground != Some(high) {
+                    self.write_color(out, 38, high, truecolor, (x, y))?;
+                    foreground = Some(high);
+                }
+                out.write_char(shade)?;
             }
-            println!();
+            out.write_str("\x1b[0m\n")?;
         }
+        Ok(())
     }
 
-    pub fn save_as_ppm(&self, data: &RenderData, filename: &str) -> std::io::Result<()> {
-        use std::fs::File;
-        use std::io::Write;
+    // the palette colors at the whole iteration counts either side of the smooth one, and how far
+    // between them it is; points that don't escape, or that are colored by distance or by a
+    // script, stay flat
+    fn shade_colors(&self, data: &RenderData, x: usize, y: usize) -> (Color, Color, f64) {
+        let color = self.pixel_color(data, x, y);
+        let sample = data.sample(x, y);
+        let smooth = colorscheme::smooth_iteration(sample.iterations, sample.z_norm, data.bailout, data.degree);
+        if self.mark_at(data, x, y).is_some() || sample.root.is_some() || sample.iterations >= data.max_iterations || !smooth.is_finite() || self.script_colors_points() {
+            return (color, color, 0.0);
+        }
 
-        let mut file = File::create(filename)?;
-        
-        // PPM header
-        writeln!(file, "P6")?;
-        writeln!(file, "{} {}", data.width(), data.height())?;
-        writeln!(file, "255")?;
+        let step = smooth.floor();
+        let position = |iterations: f64| match self.coloring {
+            ColoringMode::Histogram => Some(data.histogram_position((iterations.max(0.0) as usize).min(data.max_iterations), 0.0)),
+            ColoringMode::Distance { .. } => None,
+            _ => Some(self.color_map.position(iterations, data.max_iterations)),
+        };
+        match (position(step), position(step + 1.0)) {
+            (Some(low), Some(high)) => (
+                self.palette_at(self.cycled(low)),
+                self.palette_at(self.cycled(high)),
+                smooth - step,
+            ),
+            _ => (color, color, 0.0),
+        }
+    }
+
+    fn render_braille(&self, data: &RenderData, out: &mut dyn fmt::Write) -> fmt::Result {
+        // light up pixels that took longer than average to escape (and the set itself), with
+        // overlay marks flipping the dots they cover
+        let pixels = (data.width() * data.height()).max(1) as f64;
+        let threshold = data.pixels().iter().map(|p| p.iterations as f64).sum::<f64>() / pixels;
+
+        for cell_y in (0..data.height()).step_by(4) {
+            for cell_x in (0..data.width()).step_by(2) {
+                let mut dots = [[false; 2]; 4];
+                for (dy, row) in dots.iter_mut().enumerate() {
+                    for (dx, dot) in row.iter_mut().enumerate() {
+                        let (x, y) = (cell_x + dx, cell_y + dy);
+                        *dot = x < data.width()
+                            && y < data.height()
+                            && (data.sample(x, y).iterations as f64 >= threshold) != self.mark_at(data, x, y).is_some();
+                    }
+                }
+                out.write_char(braille_char(dots))?;
+            }
+            out.write_char('\n')?;
+        }
+        Ok(())
+    }
+
+    /// BLAKE3 hash, in hex, of the RGB buffer `data` colors into, as `RenderData::checksum`
+    /// is of the escape data.
+    pub fn rgb_checksum(&self, data: &RenderData) -> String {
+        blake3::hash(&self.to_rgb_buffer(data)).to_hex().to_string()
+    }
 
-        // Pixel data
+    /// Colors every pixel into a packed RGB buffer, row by row.
+    pub fn to_rgb_buffer(&self, data: &RenderData) -> Vec<u8> {
+        let _span = timing::span("color", || format!("{}×{}", data.width(), data.height()));
+        let mut buffer = Vec::with_capacity(data.width() * data.height() * 3);
         for y in 0..data.height() {
             for x in 0..data.width() {
-                let iters = data.iterations[y][x];
-                let z_norm = data.z_norms[y][x];
-                
-                let color = if self.use_smooth_coloring {
-                    self.color_scheme.get_smooth_color(iters, data.max_iterations, z_norm)
-                } else {
-                    self.color_scheme.get_color(iters, data.max_iterations)
-                };
-
-                file.write_all(&[color.r, color.g, color.b])?;
+                let color = self.pixel_color(data, x, y);
+                buffer.extend_from_slice(&[color.r, color.g, color.b]);
             }
         }
+        if let Some(overlay) = self.overlay_for(data) {
+            overlay.draw_labels(&mut buffer);
+        }
+        buffer
+    }
+
+    /// Colors `data` and writes it to `filename` in the given format.
+    pub fn save_image(&self, data: &RenderData, filename: &str, format: ImageFormat) -> std::io::Result<()> {
+        self.write_image(data, BufWriter::new(File::create(filename)?), format)
+    }
+
+    /// Colors `data` and writes it to `out` in the given format, e.g. to stdout.
+    pub fn write_image(&self, data: &RenderData, out: impl Write, format: ImageFormat) -> std::io::Result<()> {
+        let pixels = self.to_rgb_buffer(data);
+        encode(out, format, data.width(), data.height(), &pixels, &self.metadata)
+    }
+
+    pub fn save_as_ppm(&self, data: &RenderData, filename: &str) -> std::io::Result<()> {
+        self.save_image(data, filename, ImageFormat::Ppm)
+    }
+
+    pub fn save_as_png(&self, data: &RenderData, filename: &str) -> std::io::Result<()> {
+        self.save_image(data, filename, ImageFormat::Png)
+    }
+}
+
+// palette position for a distance in pixels, approaching 1.0 a few `falloff`s away
+fn distance_position(distance: f64, falloff: f64) -> f64 {
+    1.0 - (-distance / falloff.max(f64::MIN_POSITIVE)).exp()
+}
+
+/// Encodes packed RGB pixels as a single image.
+pub fn write_image(
+    out: impl Write,
+    format: ImageFormat,
+    width: usize,
+    height: usize,
+    pixels: &[u8],
+) -> std::io::Result<()> {
+    encode(out, format, width, height, pixels, &[])
+}
+
+// `write_image`, with `text` chunks in a PNG
+fn encode(out: impl Write, format: ImageFormat, width: usize, height: usize, pixels: &[u8], text: &[(String, String)]) -> std::io::Result<()> {
+    let _span = timing::span("encode", || format!("{:?} {}×{}", format, width, height));
+    match format {
+        ImageFormat::Ppm => write_ppm(out, width, height, pixels),
+        ImageFormat::Png => write_png(out, width, height, pixels, text),
+        ImageFormat::Gif => {
+            let mut encoder = GifEncoder::new(out, width, height, 0, 0, GIF_DEFAULT_SPEED)?;
+            encoder.write_frame(0, width, height, pixels)?;
+            encoder.finish()
+        }
+        ImageFormat::Raw => {
+            let mut out = out;
+            out.write_all(pixels)?;
+            out.flush()
+        }
+    }
+}
+
+fn write_ppm(mut out: impl Write, width: usize, height: usize, pixels: &[u8]) -> std::io::Result<()> {
+    // PPM header
+    writeln!(out, "P6")?;
+    writeln!(out, "{} {}", width, height)?;
+    writeln!(out, "255")?;
+
+    // Pixel data
+    out.write_all(pixels)?;
+    out.flush()
+}
+
+fn write_png(out: impl Write, width: usize, height: usize, pixels: &[u8], text: &[(String, String)]) -> std::io::Result<()> {
+    let mut encoder = png::Encoder::new(out, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    metadata::add_text(&mut encoder, text)?;
+
+    let mut writer = encoder.write_header().map_err(std::io::Error::other)?;
+    writer.write_image_data(pixels).map_err(std::io::Error::other)?;
+    writer.finish().map_err(std::io::Error::other)
+}
+
+pub const GIF_DEFAULT_SPEED: i32 = 10;
+
+const KITTY_CHUNK_SIZE: usize = 4096;
+const TERMINAL_BUFFER_SIZE: usize = 1 << 20;
+
+// lets the text renderers, which write through `fmt::Write`, feed an `io::Write`, keeping the
+// I/O error that `fmt::Error` can't carry
+struct FmtAdapter<'a, W: Write> {
+    inner: &'a mut W,
+    error: std::io::Result<()>,
+}
+
+impl<W: Write> fmt::Write for FmtAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Err(e);
+            fmt::Error
+        })
+    }
+}
+
+/// Destination for the frames of an animation.
+pub trait FrameWriter {
+    /// Writes one frame of packed RGB pixels and returns where it ended up.
+    fn write_frame(&mut self, index: usize, width: usize, height: usize, pixels: &[u8]) -> std::io::Result<String>;
 
+    fn finish(&mut self) -> std::io::Result<()> {
         Ok(())
     }
 }
 
-fn rgb_to_ansi256(r: u8, g: u8, b: u8) -> u8 {
-    // Use the 216-color cube (16-231)
-    // Each component is divided into 6 levels (0-5)
-    let r = (r as u16 * 5 / 255) as u8;
-    let g = (g as u16 * 5 / 255) as u8;
-    let b = (b as u16 * 5 / 255) as u8;
-    
-    16 + 36 * r + 6 * g + b
+/// Animated GIF encoder; each frame gets its own NeuQuant-quantized palette.
+pub struct GifEncoder<W: Write> {
+    encoder: Option<gif::Encoder<W>>,
+    delay: u16,
+    speed: i32,
 }
 
-// helper
-pub fn detect_terminal_capabilities() -> OutputFormat {
-    // Check COLORTERM environment variable for truecolor support
-    if let Ok(colorterm) = std::env::var("COLORTERM") {
-        if colorterm == "truecolor" || colorterm == "24bit" {
-            return OutputFormat::AnsiTrueColor;
+impl GifEncoder<BufWriter<File>> {
+    pub fn create(
+        filename: &str,
+        width: usize,
+        height: usize,
+        delay_ms: u32,
+        loop_count: u16,
+        speed: i32,
+    ) -> std::io::Result<Self> {
+        GifEncoder::new(BufWriter::new(File::create(filename)?), width, height, delay_ms, loop_count, speed)
+    }
+}
+
+impl<W: Write> GifEncoder<W> {
+    /// `loop_count` 0 loops forever; `speed` trades palette quality (1) for encoding speed (30).
+    pub fn new(
+        out: W,
+        width: usize,
+        height: usize,
+        delay_ms: u32,
+        loop_count: u16,
+        speed: i32,
+    ) -> std::io::Result<Self> {
+        let (width, height) = gif_dimensions(width, height)?;
+        let mut encoder = gif::Encoder::new(out, width, height, &[]).map_err(std::io::Error::other)?;
+        let repeat = match loop_count {
+            0 => gif::Repeat::Infinite,
+            n => gif::Repeat::Finite(n),
+        };
+        encoder.set_repeat(repeat).map_err(std::io::Error::other)?;
+
+        Ok(Self {
+            encoder: Some(encoder),
+            // GIF delays are in hundredths of a second
+            delay: (delay_ms / 10).min(u16::MAX as u32) as u16,
+            speed: speed.clamp(1, 30),
+        })
+    }
+}
+
+impl<W: Write> FrameWriter for GifEncoder<W> {
+    fn write_frame(&mut self, index: usize, width: usize, height: usize, pixels: &[u8]) -> std::io::Result<String> {
+        let (width, height) = gif_dimensions(width, height)?;
+        let mut frame = gif::Frame::from_rgb_speed(width, height, pixels, self.speed);
+        frame.delay = self.delay;
+
+        let encoder = self.encoder.as_mut().ok_or_else(|| std::io::Error::other("gif already finished"))?;
+        encoder.write_frame(&frame).map_err(std::io::Error::other)?;
+        Ok(format!("gif frame {}", index))
+    }
+
+    fn finish(&mut self) -> std::io::Result<()> {
+        if let Some(encoder) = self.encoder.take() {
+            encoder.into_inner().map_err(std::io::Error::other)?.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn gif_dimensions(width: usize, height: usize) -> std::io::Result<(u16, u16)> {
+    match (u16::try_from(width), u16::try_from(height)) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(std::io::Error::other("gif images are limited to 65535×65535 pixels")),
+    }
+}
+
+// xterm's 256-color palette past the 16 system colors, which terminals theme: a 6×6×6 cube
+// over these channel levels, then a ramp of 24 grays from 8 to 238
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+// slots of the cache of matched colors, a power of two
+const ANSI256_CACHE_SLOTS: usize = 4096;
+
+/// The 256-color palette entry perceptually nearest to (r, g, b), out of the color cube and
+/// the grayscale ramp.
+pub fn rgb_to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    // frames have few distinct colors, so matches are kept in a direct-mapped cache of
+    // (1 << 32 | rgb << 8 | index) entries, where a colliding color simply replaces the old one
+    static CACHE: [AtomicU64; ANSI256_CACHE_SLOTS] = [const { AtomicU64::new(0) }; ANSI256_CACHE_SLOTS];
+
+    let rgb = (r as u64) << 16 | (g as u64) << 8 | b as u64;
+    let slot = &CACHE[(rgb.wrapping_mul(0x9e37_79b9) >> 12) as usize % ANSI256_CACHE_SLOTS];
+    let entry = slot.load(Ordering::Relaxed);
+    if entry >> 8 == 1 << 24 | rgb {
+        return entry as u8;
+    }
+
+    let index = nearest_ansi256(Color::new(r, g, b));
+    slot.store(1 << 32 | rgb << 8 | index as u64, Ordering::Relaxed);
+    index
+}
+
+/// The color of 256-color palette entry `index`; the 16 system colors, which terminals theme,
+/// are taken to be the standard VGA ones.
+pub fn ansi256_to_rgb(index: u8) -> Color {
+    const SYSTEM: [u32; 16] = [
+        0x000000, 0x800000, 0x008000, 0x808000, 0x000080, 0x800080, 0x008080, 0xc0c0c0,
+        0x808080, 0xff0000, 0x00ff00, 0xffff00, 0x0000ff, 0xff00ff, 0x00ffff, 0xffffff,
+    ];
+    match index {
+        0..=15 => {
+            let rgb = SYSTEM[index as usize];
+            Color::new((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+        }
+        16..=231 => {
+            let i = (index - 16) as usize;
+            Color::new(CUBE_LEVELS[i / 36], CUBE_LEVELS[i / 6 % 6], CUBE_LEVELS[i % 6])
+        }
+        _ => {
+            let gray = 8 + 10 * (index - 232);
+            Color::new(gray, gray, gray)
         }
     }
+}
+
+// the palette entry closest to `color` in Oklab, searching all 240 of them
+fn nearest_ansi256(color: Color) -> u8 {
+    static PALETTE: OnceLock<Vec<(u8, [f64; 3])>> = OnceLock::new();
+    let palette = PALETTE.get_or_init(|| (16..=255).map(|index| (index, ansi256_to_rgb(index).to_oklab())).collect());
+
+    let target = color.to_oklab();
+    let distance = |lab: &[f64; 3]| (0..3).map(|i| (lab[i] - target[i]).powi(2)).sum::<f64>();
+    palette
+        .iter()
+        .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+        .map(|&(index, _)| index)
+        .unwrap()
+}
+
+// ordered dithering between the nearest entry and the one on the far side of `color`: the far
+// one is used where the Bayer threshold falls below how far over to it the color lies, so
+// in-between shades come out as a fine mix and exact palette colors stay solid
+fn rgb_to_ansi256_dithered(color: Color, threshold: f64) -> u8 {
+    let near = rgb_to_ansi256(color.r, color.g, color.b);
+    let near_color = ansi256_to_rgb(near);
+    let past = |c: u8, n: u8| (2 * c as i32 - n as i32).clamp(0, 255) as u8;
+    let far = rgb_to_ansi256(past(color.r, near_color.r), past(color.g, near_color.g), past(color.b, near_color.b));
+    if far == near {
+        return near;
+    }
+
+    let far_color = ansi256_to_rgb(far);
+    let distance = |a: Color, b: Color| {
+        let d = [a.r as f64 - b.r as f64, a.g as f64 - b.g as f64, a.b as f64 - b.b as f64];
+        d.iter().map(|d| d * d).sum::<f64>().sqrt()
+    };
+    let ratio = distance(color, near_color) / distance(near_color, far_color);
+    if threshold < ratio { far } else { near }
+}
 
-    // Check TERM for 256 color support
-    if let Ok(term) = std::env::var("TERM") {
-        if term.contains("256color") {
-            return OutputFormat::Ansi256;
+// threshold in 0.0 - 1.0 for the pixel at (x, y) from a 4×4 Bayer matrix
+fn bayer_threshold(x: usize, y: usize) -> f64 {
+    const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+    (BAYER[y % 4][x % 4] as f64 + 0.5) / 16.0
+}
+
+// dots[row][column] of a 2×4 cell, following the Unicode braille dot numbering
+fn braille_char(dots: [[bool; 2]; 4]) -> char {
+    const BITS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+    let mut code = 0x2800;
+    for (row, bits) in dots.iter().zip(BITS) {
+        for (&dot, bit) in row.iter().zip(bits) {
+            if dot {
+                code |= bit;
+            }
         }
     }
+    char::from_u32(code).unwrap_or(' ')
+}
+
+/// Best output format the current terminal advertises, from the environment and its terminfo
+/// entry. NO_COLOR (https://no-color.org) and TERM=dumb keep it to plain ASCII.
+pub fn detect_terminal_capabilities() -> OutputFormat {
+    let var = |name| std::env::var(name).unwrap_or_default();
+    let term = var("TERM");
+    if !var("NO_COLOR").is_empty() || term == "dumb" || !enable_ansi() {
+        return OutputFormat::Ascii;
+    }
+
+    if matches!(var("COLORTERM").as_str(), "truecolor" | "24bit") {
+        return OutputFormat::AnsiTrueColor;
+    }
+    // terminals that draw 24-bit color whether or not they set COLORTERM; WT_SESSION is
+    // Windows Terminal
+    if detect_image_protocol().is_some()
+        || matches!(var("TERM_PROGRAM").as_str(), "vscode" | "Hyper" | "rio")
+        || std::env::var_os("WT_SESSION").is_some()
+        || term.ends_with("-direct")
+        || term.contains("truecolor")
+    {
+        return OutputFormat::AnsiTrueColor;
+    }
+    if var("TERM_PROGRAM") == "Apple_Terminal" || var("ConEmuANSI") == "ON" {
+        return OutputFormat::Ansi256;
+    }
 
-    // Default to ASCII
-    OutputFormat::Ascii
+    // the Windows 10 console draws 24-bit color once escape sequences are on, and sets no TERM
+    if cfg!(windows) && term.is_empty() {
+        return OutputFormat::AnsiTrueColor;
+    }
+
+    // what the terminal answers beats what its entry says, when it answers
+    if terminfo::query_truecolor() == Some(true) {
+        return OutputFormat::AnsiTrueColor;
+    }
+    match terminfo::lookup(&term) {
+        Some(support) if support.truecolor => OutputFormat::AnsiTrueColor,
+        Some(support) if support.max_colors >= 256 => OutputFormat::Ansi256,
+        _ if term.contains("256color") => OutputFormat::Ansi256,
+        _ => OutputFormat::Ascii,
+    }
+}
+
+/// Makes sure escape sequences reach the terminal as colors rather than literal text. The
+/// Windows console needs virtual terminal processing switched on, which older versions
+/// don't have; everywhere else, and without the terminal feature, this is a no-op.
+#[cfg(all(windows, feature = "terminal"))]
+pub fn enable_ansi() -> bool {
+    crossterm::ansi_support::supports_ansi()
+}
+
+#[cfg(not(all(windows, feature = "terminal")))]
+pub fn enable_ansi() -> bool {
+    true
+}
+
+/// The inline image format the terminal understands, if any: kitty (and terminals such as
+/// WezTerm and Ghostty that speak its graphics protocol) or iTerm2.
+pub fn detect_image_protocol() -> Option<OutputFormat> {
+    let var = |name| std::env::var(name).unwrap_or_default();
+    let term = var("TERM");
+    if std::env::var_os("KITTY_WINDOW_ID").is_some() || term.contains("kitty") || term.contains("ghostty") {
+        return Some(OutputFormat::Kitty);
+    }
+    match var("TERM_PROGRAM").as_str() {
+        "iTerm.app" | "WezTerm" => return Some(OutputFormat::Iterm2),
+        "ghostty" => return Some(OutputFormat::Kitty),
+        _ => {}
+    }
+    (var("LC_TERMINAL") == "iTerm2").then_some(OutputFormat::Iterm2)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::colorscheme::Gradient;
+    use crate::layers::{Blend, Layer};
 
     #[test]
     fn test_rgb_to_ansi256() {
         assert_eq!(rgb_to_ansi256(0, 0, 0), 16);
         assert_eq!(rgb_to_ansi256(255, 255, 255), 231);
         assert_eq!(rgb_to_ansi256(255, 0, 0), 196);
+        // the real cube levels rather than even fifths, and the gray ramp in between them
+        assert_eq!(rgb_to_ansi256(0, 0, 95), 17);
+        assert_eq!(rgb_to_ansi256(128, 128, 128), 244);
+        assert_eq!(rgb_to_ansi256(20, 20, 20), 233);
+        for index in 16..=255 {
+            let color = ansi256_to_rgb(index);
+            assert_eq!(rgb_to_ansi256(color.r, color.g, color.b), index);
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_dithering_mixes_neighbours() {
+        // a blue between two cube levels comes out as both across a Bayer tile
+        let color = Color::new(0, 0, 115);
+        let shades: Vec<u8> = (0..16).map(|i| rgb_to_ansi256_dithered(color, bayer_threshold(i % 4, i / 4))).collect();
+        assert!(shades.iter().all(|&shade| shade == 17 || shade == 18), "{:?}", shades);
+        assert_eq!(shades.iter().filter(|&&shade| shade == 17).count(), 8);
+        for threshold in [0.0, 0.999] {
+            assert_eq!(rgb_to_ansi256_dithered(Color::new(255, 255, 255), threshold), 231);
+            assert_eq!(rgb_to_ansi256_dithered(Color::new(128, 128, 128), threshold), 244);
+        }
+    }
+
+    #[test]
+    fn test_ascii_keeps_flat_areas_on_one_character() {
+        let pixels = vec![EscapeResult { iterations: 25, ..Default::default() }; 16];
+        let data = RenderData::new(4, 4, pixels, 100);
+        let renderer = Renderer::new(ColorScheme::Classic, OutputFormat::Ascii).with_smooth_coloring(false);
+
+        assert_eq!(renderer.render_to_string(&data), "::::\n".repeat(4));
+        // asked for, dithering mixes in the next character
+        let mut dithered: Vec<char> = renderer.with_dither(true).render_to_string(&data).replace('\n', "").chars().collect();
+        dithered.sort_unstable();
+        dithered.dedup();
+        assert_eq!(dithered.len(), 2, "{:?}", dithered);
+    }
+
+    #[test]
+    fn test_truecolor_merges_repeated_colors() {
+        // the set is black, the escaping pixel isn't
+        let pixels = [10, 10, 10, 2].map(|iterations| EscapeResult { iterations, ..Default::default() });
+        let data = RenderData::new(4, 1, pixels.to_vec(), 10);
+        let renderer = Renderer::new(ColorScheme::Ocean, OutputFormat::AnsiTrueColor);
+
+        let frame = renderer.render_to_string(&data);
+        assert!(frame.starts_with("\x1b[48;2;0;0;0m   \x1b[48;2;"), "{:?}", frame);
+        assert!(frame.ends_with(" \x1b[0m\n"));
+        assert_eq!(frame.matches("\x1b[48;2;").count(), 2);
+    }
+
+    #[test]
+    fn test_shade_blends_toward_the_next_step() {
+        // z_norm = 2^(2^k) puts the smooth iteration count 1 - k past the whole one
+        let pixels = [(10, 0.0), (3, 2f64.powf(2f64.sqrt())), (2, 4.0), (2, 2f64.powf(2f64.powf(0.25)))]
+            .map(|(iterations, z_norm)| EscapeResult { iterations, z_norm, ..Default::default() });
+        let data = RenderData::new(4, 1, pixels.to_vec(), 10);
+        let renderer = Renderer::new(ColorScheme::Fire, OutputFormat::Shade { truecolor: true }).with_dither(false);
+
+        let frame = renderer.render_to_string(&data);
+        let text: String = frame.split('\x1b').map(|part| part.split_once('m').map_or(part, |(_, rest)| rest)).collect();
+        assert_eq!(text, " ▒ ▓\n");
+        assert_eq!(frame.matches("\x1b[38;2;").count(), 2, "{:?}", frame);
+    }
+
+    #[test]
+    fn test_render_to_writer() {
+        let pixels = [10, 3, 2, 1].map(|iterations| EscapeResult { iterations, ..Default::default() });
+        let data = RenderData::new(2, 2, pixels.to_vec(), 10);
+        let renderer = Renderer::new(ColorScheme::Fire, OutputFormat::HalfBlock { truecolor: false });
+
+        let mut out = Vec::new();
+        renderer.render_to(&data, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), renderer.render_to_string(&data));
+
+        // the writer's own error comes back rather than a generic formatting one
+        let mut full = [0u8; 4];
+        let error = renderer.render_to(&data, &mut &mut full[..]).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::WriteZero);
+    }
+
+    #[test]
+    fn test_braille_char() {
+        assert_eq!(braille_char([[false; 2]; 4]), '⠀');
+        assert_eq!(braille_char([[true; 2]; 4]), '⣿');
+        assert_eq!(braille_char([[true, false], [false, false], [false, false], [false, true]]), '⢁');
+    }
+
+    #[test]
+    fn test_image_format_from_path() {
+        assert_eq!(ImageFormat::from_path("out.PNG"), Some(ImageFormat::Png));
+        assert_eq!(ImageFormat::from_path("dir/out.ppm"), Some(ImageFormat::Ppm));
+        assert_eq!(ImageFormat::from_path("zoom.gif"), Some(ImageFormat::Gif));
+        assert_eq!(ImageFormat::from_path("out"), None);
+        assert_eq!(ImageFormat::from_path("frame.rgb"), Some(ImageFormat::Raw));
+    }
+
+    #[test]
+    fn test_histogram_spreads_palette() {
+        // three quarters of the pixels escape at 1, so they take three quarters of the palette
+        let pixels = [1, 1, 1, 5, 10].map(|iterations| EscapeResult { iterations, ..Default::default() });
+        let data = RenderData::new(5, 1, pixels.to_vec(), 10);
+        assert_eq!(data.histogram_position(1, 0.0), 0.0);
+        assert_eq!(data.histogram_position(1, 1.0), 0.75);
+        assert_eq!(data.histogram_position(5, 0.0), 0.75);
+        assert_eq!(data.histogram_position(5, 1.0), 1.0);
+    }
+
+    #[test]
+    fn test_supersampled_averages_colors() {
+        // one 2×1 image at 2×2 samples: the left pixel is half in the set, the right one fully
+        let results = [0, 10, 10, 10, 10, 0, 10, 10].map(|iterations| EscapeResult {
+            iterations,
+            z_norm: 4.0,
+            ..Default::default()
+        });
+        let data = RenderData::supersampled(&results, 4, 10, 2);
+        assert_eq!((data.width(), data.height()), (2, 1));
+        assert_eq!((data.sample(0, 0).iterations, data.sample(1, 0).iterations), (0, 10));
+
+        let white = Color::new(255, 255, 255);
+        let renderer = Renderer::new(ColorScheme::Custom(Gradient::new(vec![(0.0, white), (1.0, white)])), OutputFormat::Ascii)
+            .with_smooth_coloring(false);
+        assert_eq!(renderer.pixel_color(&data, 0, 0).r, 127);
+        assert_eq!(renderer.pixel_color(&data, 1, 0).r, 0);
+    }
+
+    #[test]
+    fn test_hillshade_lights_slopes_facing_the_light() {
+        // counts climb to the right, so the surface faces left, toward the west
+        let pixels = [1, 4, 8, 10].map(|iterations| EscapeResult { iterations, z_norm: 4.0, ..Default::default() });
+        let data = RenderData::new(4, 1, pixels.to_vec(), 10);
+        let gray = Color::new(128, 128, 128);
+        let renderer = Renderer::new(ColorScheme::Custom(Gradient::new(vec![(0.0, gray), (1.0, gray)])), OutputFormat::Ascii);
+        let lit_from = |spec| renderer.clone().with_lighting(Lighting::from_str(spec).unwrap()).pixel_color(&data, 1, 0).r;
+
+        assert!(lit_from("270,30") > lit_from("90,30"), "{} {}", lit_from("270,30"), lit_from("90,30"));
+        // the point that never escapes has no slope and keeps its color
+        let black = renderer.pixel_color(&data, 3, 0);
+        assert_eq!(renderer.clone().with_lighting(Lighting::default()).pixel_color(&data, 3, 0), black);
+    }
+
+    #[test]
+    fn test_layers_composite_from_the_bottom_up() {
+        // one point on the boundary, one far from it and one in the set
+        let pixels = [(5, 0.0), (5, 100.0), (10, 0.0)].map(|(iterations, distance)| EscapeResult {
+            iterations,
+            z_norm: 4.0,
+            distance,
+            ..Default::default()
+        });
+        let data = RenderData::new(3, 1, pixels.to_vec(), 10);
+        let white = Color::new(255, 255, 255);
+        let palette = Layer { source: Source::Palette, blend: Blend::Normal, opacity: 1.0 };
+        let edges = Layer { source: Source::Edges { falloff: 3.0 }, blend: Blend::Multiply, opacity: 1.0 };
+        let renderer = Renderer::new(ColorScheme::Custom(Gradient::new(vec![(0.0, white), (1.0, white)])), OutputFormat::Ascii)
+            .with_interior(InteriorMode::Angle)
+            .with_layers(Layers::new(vec![palette, edges]).unwrap());
+
+        assert_eq!(renderer.pixel_color(&data, 0, 0), Color::new(0, 0, 0));
+        assert_eq!(renderer.pixel_color(&data, 1, 0), white);
+        // edges leave the interior to the palette below
+        assert_eq!(renderer.pixel_color(&data, 2, 0), white);
+    }
+
+    #[test]
+    fn test_pixels_keep_escape_data_in_side_planes() {
+        assert_eq!(std::mem::size_of::<Pixel>(), 8);
+
+        let escaped = EscapeResult {
+            iterations: 37,
+            z_norm: 3.25,
+            distance: 0.5,
+            stripe: 0.75,
+            z: Some(Complex::new(-2.5, 2.0)),
+            derivative: Some(Complex::new(1e300, 0.0)),
+            ..Default::default()
+        };
+        let newton = EscapeResult { iterations: 6, root: Some(2), interior: 0.125, ..Default::default() };
+        let data = RenderData::new(2, 1, vec![escaped, newton], 100);
+        // everything but the derivative comes back, these values being exact in f32
+        assert_eq!(data.sample(0, 0), EscapeResult { derivative: None, ..escaped });
+        assert_eq!(data.sample(1, 0), newton);
+        assert_eq!(data.samples().map(|sample| sample.root).collect::<Vec<_>>(), vec![None, Some(2)]);
+
+        // plain escape-time data has no planes past its pixels
+        let plain = RenderData::new(2, 1, vec![EscapeResult { iterations: 3, z_norm: 4.0, ..Default::default() }; 2], 100);
+        let planes = &plain.planes;
+        assert!(planes.distance.0.is_empty() && planes.interior.0.is_empty() && planes.stripe.0.is_empty());
+        assert!(planes.root.0.is_empty() && planes.z.0.is_empty());
+    }
+
+    #[test]
+    fn test_supersamples_are_stored_in_one_buffer() {
+        let sample = |iterations| EscapeResult { iterations, ..Default::default() };
+        let mut data = RenderData::new(3, 1, vec![sample(1); 3], 10);
+        data.set_subsamples(2, 0, vec![sample(2); 4]);
+        data.set_subsamples(0, 0, vec![sample(3), EscapeResult { distance: 0.5, ..sample(3) }, sample(3), sample(3)]);
+        // set again in place
+        data.set_subsamples(2, 0, vec![sample(4); 4]);
+
+        let subsamples = &data.subsamples;
+        assert_eq!((subsamples.per_pixel, subsamples.slots.as_slice(), subsamples.samples.len()), (4, &[1, NO_SLOT, 0][..], 8));
+        assert!(data.subsamples(1, 0).is_none());
+        let iterations = |x| data.subsamples(x, 0).unwrap().map(|sample| sample.iterations).collect::<Vec<_>>();
+        assert_eq!((iterations(0), iterations(2)), (vec![3; 4], vec![4; 4]));
+        assert_eq!(data.subsamples(0, 0).unwrap().nth(1).unwrap().distance, 0.5);
+
+        let stacked = RenderData::stacked(&[RenderData::new(3, 1, vec![sample(1); 3], 10), data]);
+        assert!(stacked.subsamples(0, 0).is_none());
+        assert_eq!(stacked.subsamples(0, 1).unwrap().nth(1).unwrap().distance, 0.5);
+        assert_eq!(stacked.subsamples.samples.len(), 8);
+        assert!(stacked.subsamples.fits(6));
+    }
+
+    #[test]
+    fn test_side_by_side_joins_rows() {
+        let panel = |width, iterations: &[usize]| {
+            let pixels = iterations.iter().map(|&iterations| EscapeResult { iterations, ..Default::default() }).collect();
+            RenderData::new(width, iterations.len() / width, pixels, 10)
+        };
+        let joined = RenderData::side_by_side(&[panel(2, &[1, 2, 3, 4]), panel(1, &[5, 6])]);
+        let rows: Vec<Vec<u32>> = joined.rows().map(|row| row.iter().map(|pixel| pixel.iterations).collect()).collect();
+        assert_eq!((joined.width(), joined.height(), rows), (3, 2, vec![vec![1, 2, 5], vec![3, 4, 6]]));
+
+        // only the supersampled panel's pixels keep supersamples
+        let supersampled = RenderData::supersampled(&[EscapeResult::default(); 8], 4, 10, 2);
+        let joined = RenderData::side_by_side(&[panel(1, &[7]), supersampled]);
+        assert!(joined.subsamples(0, 0).is_none());
+        assert_eq!(joined.subsamples(2, 0).map(|samples| samples.len()), Some(4));
+    }
+
+    #[test]
+    fn test_color_maps() {
+        assert_eq!(ColorMap::from_str("power:0.5"), Some(ColorMap::Power(0.5)));
+        assert_eq!(ColorMap::from_str("power:-1"), None);
+        for map in [ColorMap::Linear, ColorMap::Log, ColorMap::Sqrt, ColorMap::Power(2.5)] {
+            assert_eq!(map.position(0.0, 1000), 0.0);
+            assert!((map.position(1000.0, 1000) - 1.0).abs() < 1e-12);
+        }
+        // low counts get more of the palette under log and sqrt, less under gamma > 1
+        let linear = ColorMap::Linear.position(10.0, 1000);
+        assert!(ColorMap::Log.position(10.0, 1000) > ColorMap::Sqrt.position(10.0, 1000));
+        assert!(ColorMap::Sqrt.position(10.0, 1000) > linear);
+        assert!(ColorMap::Power(2.0).position(10.0, 1000) < linear);
+    }
+
+    #[test]
+    fn test_ascii_follows_color_map() {
+        let pixels = vec![EscapeResult { iterations: 25, ..Default::default() }];
+        let data = RenderData::new(1, 1, pixels, 100);
+        let shade = |color_map| {
+            Renderer::new(ColorScheme::Classic, OutputFormat::Ascii)
+                .with_smooth_coloring(false)
+                .with_color_map(color_map)
+                .with_dither(false)
+                .render_to_string(&data)
+        };
+
+        assert_eq!(shade(ColorMap::Linear), ":\n");
+        // a quarter of the way in iterations is halfway along the square root map
+        assert_eq!(shade(ColorMap::Sqrt), "=\n");
+    }
+
+    #[test]
+    fn test_color_cycle_wraps_palette() {
+        // linear coloring puts iteration 2 of 10 at 0.2 of a black to white palette
+        let pixels = vec![EscapeResult { iterations: 2, ..Default::default() }];
+        let data = RenderData::new(1, 1, pixels, 10);
+        let shade = |repeat, offset| {
+            let gradient = Gradient::new(vec![(0.0, Color::new(0, 0, 0)), (1.0, Color::new(250, 250, 250))]);
+            Renderer::new(ColorScheme::Custom(gradient), OutputFormat::Ascii)
+                .with_smooth_coloring(false)
+                .with_color_cycle(repeat, offset)
+                .pixel_color(&data, 0, 0)
+                .r
+        };
+
+        assert_eq!(shade(1.0, 0.0), 50);
+        assert_eq!(shade(3.0, 0.0), 150);
+        assert_eq!(shade(4.0, 0.5), 75);
+        assert_eq!(shade(1.0, -0.4), 200);
+    }
+
+    #[test]
+    fn test_gif_encoder_writes_all_frames() {
+        let mut out = Vec::new();
+        let mut encoder = GifEncoder::new(&mut out, 2, 2, 50, 0, GIF_DEFAULT_SPEED).unwrap();
+        encoder.write_frame(0, 2, 2, &[0; 12]).unwrap();
+        encoder.write_frame(1, 2, 2, &[255; 12]).unwrap();
+        encoder.finish().unwrap();
+        drop(encoder);
+
+        assert!(out.starts_with(b"GIF89a"));
+        assert_eq!(out.last(), Some(&0x3b)); // trailer
+    }
+
+    #[test]
+    fn test_inline_images_carry_the_png() {
+        // noisy pixels so the PNG doesn't compress into a single kitty chunk
+        let mut state = 1u64;
+        let pixels = (0..128 * 128)
+            .map(|_| {
+                state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+                EscapeResult { iterations: (state >> 58) as usize, ..Default::default() }
+            })
+            .collect();
+        let data = RenderData::new(128, 128, pixels, 100);
+
+        let kitty = Renderer::new(ColorScheme::Classic, OutputFormat::Kitty).with_smooth_coloring(false).render_to_string(&data);
+        let chunks: Vec<&str> = kitty.trim_end().split("\x1b\\").filter(|c| !c.is_empty()).collect();
+        assert!(chunks.len() > 1);
+        assert!(chunks[0].starts_with("\x1b_Ga=T,f=100,m=1;"));
+        assert!(chunks[chunks.len() - 1].starts_with("\x1b_Gm=0;"));
+        let payload: String = chunks.iter().map(|c| c.split_once(';').unwrap().1).collect();
+        assert!(chunks.iter().all(|c| c.split_once(';').unwrap().1.len() <= KITTY_CHUNK_SIZE));
+
+        let iterm = Renderer::new(ColorScheme::Classic, OutputFormat::Iterm2).with_smooth_coloring(false).render_to_string(&data);
+        let (header, rest) = iterm.split_once(':').unwrap();
+        assert!(header.starts_with("\x1b]1337;File=inline=1;"));
+        assert_eq!(rest.trim_end().strip_suffix('\x07'), Some(payload.as_str()));
+        assert!(BASE64_STANDARD.decode(&payload).unwrap().starts_with(b"\x89PNG"));
+    }
+}
@@ -0,0 +1,170 @@
+// Dives into one spot near the boundary after another until a key is pressed, cycling the
+// palette as it goes. The terminal is in raw mode throughout, so Ctrl-C arrives as a key press
+// rather than a signal, and the screen is always put back before returning.
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant, SystemTime};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyEventKind},
+    execute, queue,
+    style::{self, Print},
+    terminal,
+};
+
+use crate::animation::{self, ZoomAnimation};
+use crate::fractal::Fractal;
+use crate::renderer::Renderer;
+use crate::{ComputeOptions, DEFAULT_VIEW_WIDTH};
+
+// shortest time between frames, so cheap views don't spin a core redrawing
+const FRAME_TIME: Duration = Duration::from_millis(40);
+// palette turns per second
+const CYCLE_SPEED: f64 = 0.05;
+// random points tried for one that escapes slowly before settling for a known one
+const SEED_TRIES: usize = 10_000;
+const FALLBACK_TARGET: (f64, f64) = (-0.743_643_887, 0.131_825_904);
+
+pub struct Screensaver {
+    fractal: Fractal,
+    renderer: Renderer,
+    options: ComputeOptions,
+    max_iterations: usize,
+    // center and width of the view every dive starts from
+    home: ((f64, f64), f64),
+    dive: Duration,
+    final_zoom: f64,
+    rng: u64,
+}
+
+impl Screensaver {
+    /// Dives start from `region` and last 20 seconds, ending 10⁴ times deeper.
+    pub fn new(
+        fractal: Fractal,
+        renderer: Renderer,
+        options: ComputeOptions,
+        region: (f64, f64, f64, f64),
+        max_iterations: usize,
+    ) -> Self {
+        let (x_min, x_max, y_min, y_max) = region;
+        let seed = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        Self {
+            fractal,
+            renderer,
+            options,
+            max_iterations: max_iterations.max(1),
+            home: (((x_min + x_max) / 2.0, (y_min + y_max) / 2.0), x_max - x_min),
+            dive: Duration::from_secs(20),
+            final_zoom: 1e4,
+            // xorshift gets stuck on zero
+            rng: seed | 1,
+        }
+    }
+
+    /// How long each dive takes and how far it zooms in.
+    pub fn with_dive(mut self, seconds: f64, final_zoom: f64) -> Self {
+        self.dive = Duration::from_secs_f64(seconds);
+        self.final_zoom = final_zoom;
+        self
+    }
+
+    pub fn run(&mut self) -> io::Result<()> {
+        let mut stdout = io::stdout();
+
+        terminal::enable_raw_mode()?;
+        execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+        let result = self.dive_loop(&mut stdout);
+
+        // always restore the terminal, even if drawing failed
+        execute!(stdout, style::ResetColor, cursor::Show, terminal::LeaveAlternateScreen)?;
+        terminal::disable_raw_mode()?;
+        result
+    }
+
+    fn dive_loop(&mut self, stdout: &mut io::Stdout) -> io::Result<()> {
+        let started = Instant::now();
+        loop {
+            let target = self.pick_target();
+            let dive_started = Instant::now();
+            while dive_started.elapsed() < self.dive {
+                let frame_started = Instant::now();
+                let t = dive_started.elapsed().as_secs_f64() / self.dive.as_secs_f64();
+                self.draw(stdout, target, t, started.elapsed().as_secs_f64())?;
+
+                // wait out the rest of the frame, leaving on any key
+                if event::poll(FRAME_TIME.saturating_sub(frame_started.elapsed()))?
+                    && let Event::Key(key) = event::read()?
+                    && key.kind == KeyEventKind::Press
+                {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    fn next_random(&mut self) -> f64 {
+        // xorshift64
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // a random point of the home view that takes a while to escape, which puts it close to the
+    // boundary, where zooming in keeps finding detail
+    fn pick_target(&mut self) -> (f64, f64) {
+        let ((re, im), width) = self.home;
+        let slow = (self.max_iterations / 10).max(1);
+        for _ in 0..SEED_TRIES {
+            let x = re + (self.next_random() - 0.5) * width;
+            let y = im + (self.next_random() - 0.5) * width;
+            let iterations = self.fractal.at_point(x, y, self.max_iterations).iterations;
+            if (slow..self.max_iterations).contains(&iterations) {
+                return (x, y);
+            }
+        }
+        FALLBACK_TARGET
+    }
+
+    fn draw(&self, stdout: &mut io::Stdout, target: (f64, f64), t: f64, seconds: f64) -> io::Result<()> {
+        let (cols, rows) = terminal::size()?;
+        let output_format = self.renderer.output_format();
+        let (across, down) = output_format.pixels_per_cell();
+        let (width, height) = (cols as usize * across, rows as usize * down);
+
+        let (center, view_width) = self.home;
+        let start = crate::region_around(center, DEFAULT_VIEW_WIDTH / view_width, width, height, output_format.pixel_aspect());
+        let dive = ZoomAnimation::new(start, target, self.final_zoom, 1, self.max_iterations);
+        let max_iters = animation::iterations_at(self.max_iterations, dive.zoom_at(t));
+        let data = crate::calculate(&self.fractal, max_iters, dive.region_at(t), width, height, &self.options, None);
+
+        let (repeat, offset) = self.renderer.color_cycle();
+        let renderer = self.renderer.clone().with_color_cycle(repeat, offset + seconds * CYCLE_SPEED);
+        // raw mode doesn't return the cursor on a newline, so each line is placed by hand
+        for (y, line) in renderer.render_to_string(&data).lines().enumerate() {
+            queue!(stdout, cursor::MoveTo(0, y as u16), Print(line))?;
+        }
+        stdout.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorScheme, OutputFormat};
+
+    #[test]
+    fn test_targets_are_near_the_boundary() {
+        let renderer = Renderer::new(ColorScheme::Classic, OutputFormat::Ascii);
+        let mut saver = Screensaver::new(Fractal::Mandelbrot, renderer, ComputeOptions::default(), (-2.0, 1.0, -1.5, 1.5), 200);
+        for _ in 0..5 {
+            let (x, y) = saver.pick_target();
+            let iterations = Fractal::Mandelbrot.at_point(x, y, 200).iterations;
+            assert!((20..200).contains(&iterations), "{} at {}, {}", iterations, x, y);
+        }
+    }
+}
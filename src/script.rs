@@ -0,0 +1,184 @@
+// Color schemes written as Rhai scripts, so new colorings don't need a new build. A script
+// defines a function `color`, which returns a color as an array of three numbers, integers
+// 0 - 255 or floats 0.0 - 1.0, or as a "#rrggbb" string. It takes either
+//     fn color(t)
+// and is then a palette: t is the position 0.0 - 1.0 a built-in scheme would be looked up at,
+// and everything that colors with the palette (interior modes, newton roots, stripe layers)
+// colors with it, or
+//     fn color(t, iterations, in_set, orbit)
+// and then colors each point itself from its palette position (0.0 in the set), escape count,
+// whether it's in the set, and a map of what the orbit left: z_norm, re and im of the final z,
+// distance, interior, stripe and root (-1 when there's none).
+//
+// Calling into the script costs microseconds, so a palette is looked up once per step of
+// LUT_SIZE when the script is loaded, and only scripts that color points themselves run per
+// point. Scripts run with an operation limit, so a runaway loop fails instead of hanging.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use rhai::{AST, CallFnOptions, Dynamic, Engine, Map, Scope};
+
+use crate::colorscheme::Color;
+use crate::error::MandelbrotError;
+use crate::fractal::EscapeResult;
+
+// steps of the lookup table a palette script is evaluated into
+const LUT_SIZE: usize = 4096;
+// operations one call may take before it's stopped
+const MAX_OPERATIONS: u64 = 100_000;
+const FUNCTION: &str = "color";
+
+/// A color scheme from a Rhai script; see `Renderer::with_color_script`.
+#[derive(Clone)]
+pub struct ColorScript {
+    kind: Kind,
+}
+
+#[derive(Clone)]
+enum Kind {
+    // fn color(t), evaluated into a table
+    Palette(Arc<[Color]>),
+    // fn color(t, iterations, in_set, orbit), called per point; errors at that point are
+    // reported once and leave it black
+    Points {
+        engine: Arc<Engine>,
+        ast: Arc<AST>,
+        failed: Arc<AtomicBool>,
+    },
+}
+
+impl ColorScript {
+    /// Compiles `source`, named `name` in errors, and checks that its `color` runs on a point.
+    pub fn new(name: &str, source: &str) -> crate::Result<Self> {
+        let invalid = |reason: String| MandelbrotError::invalid(format_args!("color script {}", name), reason);
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let ast = engine.compile(source).map_err(|e| invalid(e.to_string()))?;
+        let params = ast.iter_functions().find(|function| function.name == FUNCTION).map(|function| function.params.len());
+
+        let kind = match params {
+            Some(1) => {
+                let lut = (0..LUT_SIZE)
+                    .map(|i| call(&engine, &ast, (i as f64 / (LUT_SIZE - 1) as f64,)))
+                    .collect::<Result<Vec<Color>, String>>()
+                    .map_err(invalid)?;
+                Kind::Palette(lut.into())
+            }
+            Some(4) => {
+                call(&engine, &ast, point_args(0.5, &EscapeResult::default(), false)).map_err(invalid)?;
+                Kind::Points {
+                    engine: Arc::new(engine),
+                    ast: Arc::new(ast),
+                    failed: Arc::new(AtomicBool::new(false)),
+                }
+            }
+            _ => return Err(invalid("it must define fn color(t) or fn color(t, iterations, in_set, orbit)".to_string())),
+        };
+        Ok(Self { kind })
+    }
+
+    pub fn from_file(path: &str) -> crate::Result<Self> {
+        let source = std::fs::read_to_string(path).map_err(|e| MandelbrotError::file(path, e))?;
+        Self::new(path, &source)
+    }
+
+    /// Whether the script is a palette, looked up by `color_at`, rather than coloring points.
+    pub fn is_palette(&self) -> bool {
+        matches!(self.kind, Kind::Palette(_))
+    }
+
+    /// Color at palette position `t` (0.0 - 1.0); black for scripts that color points.
+    pub fn color_at(&self, t: f64) -> Color {
+        match &self.kind {
+            Kind::Palette(lut) => lut[(t.clamp(0.0, 1.0) * (lut.len() - 1) as f64).round() as usize],
+            Kind::Points { .. } => Color::new(0, 0, 0),
+        }
+    }
+
+    /// Color of a point at palette position `t` with escape data `sample`; palettes look `t`
+    /// up, while scripts that color points run on it.
+    pub fn point_color(&self, t: f64, sample: &EscapeResult, in_set: bool) -> Color {
+        let Kind::Points { engine, ast, failed } = &self.kind else {
+            return self.color_at(t);
+        };
+        call(engine, ast, point_args(t, sample, in_set)).unwrap_or_else(|e| {
+            if !failed.swap(true, Ordering::Relaxed) {
+                log::warn!("color script failed, leaving points black: {}", e);
+            }
+            Color::new(0, 0, 0)
+        })
+    }
+}
+
+fn point_args(t: f64, sample: &EscapeResult, in_set: bool) -> (f64, i64, bool, Map) {
+    let z = sample.z.unwrap_or_default();
+    let mut orbit = Map::new();
+    for (key, value) in [
+        ("z_norm", sample.z_norm),
+        ("re", z.re),
+        ("im", z.im),
+        ("distance", sample.distance),
+        ("interior", sample.interior),
+        ("stripe", sample.stripe),
+    ] {
+        orbit.insert(key.into(), Dynamic::from_float(value));
+    }
+    orbit.insert("root".into(), Dynamic::from_int(sample.root.map_or(-1, |root| root as i64)));
+    (t, sample.iterations as i64, in_set, orbit)
+}
+
+fn call(engine: &Engine, ast: &AST, args: impl rhai::FuncArgs) -> Result<Color, String> {
+    // the script's top level already ran when it compiled, if at all; functions can't see it
+    let options = CallFnOptions::new().eval_ast(false);
+    let result: Dynamic = engine.call_fn_with_options(options, &mut Scope::new(), ast, FUNCTION, args).map_err(|e| e.to_string())?;
+    to_color(&result).ok_or_else(|| format!("color returned {}, not [r, g, b] or \"#rrggbb\"", result))
+}
+
+fn to_color(value: &Dynamic) -> Option<Color> {
+    if let Some(hex) = value.read_lock::<rhai::ImmutableString>() {
+        return Color::from_hex(&hex);
+    }
+    let channels = value.read_lock::<rhai::Array>()?;
+    let channel = |value: &Dynamic| match (value.as_int(), value.as_float()) {
+        (Ok(int), _) => Some(int.clamp(0, 255) as u8),
+        (_, Ok(float)) if float.is_finite() => Some((float.clamp(0.0, 1.0) * 255.0).round() as u8),
+        _ => None,
+    };
+    match channels.as_slice() {
+        [r, g, b] => Some(Color::new(channel(r)?, channel(g)?, channel(b)?)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_and_point_scripts() {
+        let palette = ColorScript::new("gray", "fn color(t) { [t, t, t] }").unwrap();
+        assert!(palette.is_palette());
+        assert_eq!(palette.color_at(0.0), Color::new(0, 0, 0));
+        assert_eq!(palette.color_at(1.0), Color::new(255, 255, 255));
+        assert_eq!(palette.color_at(0.5).r, 128);
+
+        let points = ColorScript::new(
+            "parity",
+            r##"fn color(t, iterations, in_set, orbit) {
+                if in_set { return "#000080"; }
+                if iterations % 2 == 0 { [1.0, 0.0, 0.0] } else { [0, 255, orbit.root + 1] }
+            }"##,
+        )
+        .unwrap();
+        assert!(!points.is_palette());
+        let sample = |iterations| EscapeResult { iterations, ..EscapeResult::default() };
+        assert_eq!(points.point_color(0.0, &sample(100), true), Color::new(0, 0, 128));
+        assert_eq!(points.point_color(0.2, &sample(4), false), Color::new(255, 0, 0));
+        assert_eq!(points.point_color(0.2, &sample(5), false), Color::new(0, 255, 0));
+
+        assert!(ColorScript::new("none", "fn paint(t) { [0, 0, 0] }").is_err());
+        assert!(ColorScript::new("broken", "fn color(t) { [0, 0] }").is_err());
+        assert!(ColorScript::new("forever", "fn color(t) { loop {} }").is_err());
+    }
+}
@@ -0,0 +1,288 @@
+// Map tiles over HTTP, for browsing the set in Leaflet, OpenLayers or anything else that reads
+// slippy-map tiles: GET /{z}/{x}/{y}.png is the 256×256 tile in column x, row y of the 2^z×2^z
+// grid over the whole set at zoom level z, computed the first time it's asked for and kept in
+// memory after that. GET / is a page with a Leaflet map of them.
+//
+// This is a plain HTTP/1.1 server on std's sockets, one thread per connection, closing every
+// connection after its response; tiles are small and browsers open several connections. At most
+// MAX_CONNECTIONS are served at once, the rest waiting to be accepted, and a client gets TIMEOUT
+// for each read and write, so idle or trickling clients can't hold on to threads for good.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Width and height of a tile, in pixels.
+pub const TILE_SIZE: usize = 256;
+
+/// Deepest zoom level served, about where f64 runs out of precision for the pixels.
+pub const MAX_ZOOM: u32 = 40;
+
+// zoom level 0 is one tile this wide, around this center
+const WORLD_WIDTH: f64 = 4.0;
+const WORLD_CENTER: (f64, f64) = (-0.75, 0.0);
+// longest request head read before giving up on a client
+const MAX_REQUEST: u64 = 8192;
+const MAX_CONNECTIONS: usize = 64;
+const TIMEOUT: Duration = Duration::from_secs(10);
+// pause after a failed accept, which tends to fail again at once when out of file descriptors
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+const MAP_PAGE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>mandelbrot</title>
+<link rel="stylesheet" href="https://unpkg.com/leaflet@1.9.4/dist/leaflet.css">
+<script src="https://unpkg.com/leaflet@1.9.4/dist/leaflet.js"></script>
+<style>html, body, #map { height: 100%; margin: 0; background: #000; }</style>
+</head>
+<body>
+<div id="map"></div>
+<script>
+const map = L.map("map", { crs: L.CRS.Simple, minZoom: 0, maxZoom: MAX_ZOOM });
+L.tileLayer("/{z}/{x}/{y}.png", { tileSize: 256, noWrap: true, maxZoom: MAX_ZOOM }).addTo(map);
+map.setView([-128, 128], 1);
+</script>
+</body>
+</html>
+"#;
+
+/// Region (x_min, x_max, y_min, y_max) of tile (x, y) at zoom level `z`, or `None` past the
+/// edge of the grid or `MAX_ZOOM`.
+pub fn tile_region(z: u32, x: u64, y: u64) -> Option<(f64, f64, f64, f64)> {
+    let tiles = 1u64 << z.min(MAX_ZOOM);
+    if z > MAX_ZOOM || x >= tiles || y >= tiles {
+        return None;
+    }
+    let size = WORLD_WIDTH / tiles as f64;
+    let x_min = WORLD_CENTER.0 - WORLD_WIDTH / 2.0 + x as f64 * size;
+    let y_min = WORLD_CENTER.1 - WORLD_WIDTH / 2.0 + y as f64 * size;
+    Some((x_min, x_min + size, y_min, y_min + size))
+}
+
+/// The tile a request path such as "/3/2/5.png" names.
+pub fn parse_tile_path(path: &str) -> Option<(u32, u64, u64)> {
+    let mut parts = path.strip_prefix('/')?.strip_suffix(".png")?.split('/');
+    let tile = (parts.next()?.parse().ok()?, parts.next()?.parse().ok()?, parts.next()?.parse().ok()?);
+    parts.next().is_none().then_some(tile)
+}
+
+/// Serves tiles made by `render`, which gets a tile's region and returns its PNG, holding on
+/// to the last `capacity` tiles. Runs for good; connections that fail to be accepted are
+/// logged and skipped.
+pub fn serve(listener: &TcpListener, capacity: usize, render: impl Fn((f64, f64, f64, f64)) -> Vec<u8> + Sync) {
+    let tiles = TileCache::new(capacity);
+    let slots = Slots::new(MAX_CONNECTIONS);
+    thread::scope(|scope| {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log::warn!("couldn't accept a connection: {}", e);
+                    thread::sleep(ACCEPT_BACKOFF);
+                    continue;
+                }
+            };
+            let slot = slots.take();
+            let (tiles, render) = (&tiles, &render);
+            // a client that goes away mid-request, or is too slow, only loses its own response
+            scope.spawn(move || {
+                let _slot = slot;
+                let _ = stream
+                    .set_read_timeout(Some(TIMEOUT))
+                    .and_then(|_| stream.set_write_timeout(Some(TIMEOUT)))
+                    .and_then(|_| respond(stream, tiles, render));
+            });
+        }
+    })
+}
+
+// a count of connections that may still be served, waited on when there are none
+struct Slots {
+    free: Mutex<usize>,
+    freed: Condvar,
+}
+
+// one connection's place, given back when it's dropped
+struct Slot<'a>(&'a Slots);
+
+impl Slots {
+    fn new(count: usize) -> Self {
+        Self {
+            free: Mutex::new(count),
+            freed: Condvar::new(),
+        }
+    }
+
+    fn take(&self) -> Slot<'_> {
+        let mut free = self.freed.wait_while(self.free.lock().unwrap(), |free| *free == 0).unwrap();
+        *free -= 1;
+        Slot(self)
+    }
+}
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) {
+        *self.0.free.lock().unwrap() += 1;
+        self.0.freed.notify_one();
+    }
+}
+
+fn respond(mut stream: TcpStream, tiles: &TileCache, render: &(impl Fn((f64, f64, f64, f64)) -> Vec<u8> + Sync)) -> io::Result<()> {
+    // the request line is all that matters, but the headers are read too: closing a socket
+    // with a request still unread can reset it before the client has the response
+    let mut head = BufReader::new((&stream).take(MAX_REQUEST));
+    let mut request = String::new();
+    head.read_line(&mut request)?;
+    let mut header = String::new();
+    while head.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+        header.clear();
+    }
+    let mut words = request.split_whitespace();
+    let (method, path) = (words.next().unwrap_or(""), words.next().unwrap_or(""));
+
+    if method != "GET" {
+        return write_response(&mut stream, "405 Method Not Allowed", "text/plain", b"only GET is supported\n");
+    }
+    if path == "/" {
+        let page = MAP_PAGE.replace("MAX_ZOOM", &MAX_ZOOM.to_string());
+        return write_response(&mut stream, "200 OK", "text/html; charset=utf-8", page.as_bytes());
+    }
+    let Some((z, x, y)) = parse_tile_path(path) else {
+        return write_response(&mut stream, "404 Not Found", "text/plain", b"tiles are at /{z}/{x}/{y}.png\n");
+    };
+    let Some(region) = tile_region(z, x, y) else {
+        return write_response(&mut stream, "404 Not Found", "text/plain", b"no such tile\n");
+    };
+    let png = tiles.get_or_insert_with((z, x, y), || render(region));
+    write_response(&mut stream, "200 OK", "image/png", &png)
+}
+
+fn write_response(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: max-age=86400\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+// zoom level, column and row
+type TileKey = (u32, u64, u64);
+
+// the most recently made tiles, dropping the oldest past `capacity`
+struct TileCache {
+    capacity: usize,
+    tiles: Mutex<Tiles>,
+}
+
+#[derive(Default)]
+struct Tiles {
+    made: HashMap<TileKey, Arc<Vec<u8>>>,
+    // oldest first
+    order: VecDeque<TileKey>,
+}
+
+impl TileCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tiles: Mutex::default(),
+        }
+    }
+
+    // two requests for a tile that isn't made yet may both make it, which beats holding the
+    // lock while one does
+    fn get_or_insert_with(&self, key: TileKey, make: impl FnOnce() -> Vec<u8>) -> Arc<Vec<u8>> {
+        if let Some(tile) = self.tiles.lock().unwrap().made.get(&key) {
+            return tile.clone();
+        }
+        let tile = Arc::new(make());
+        let Tiles { made, order } = &mut *self.tiles.lock().unwrap();
+        if self.capacity > 0 && made.insert(key, tile.clone()).is_none() {
+            order.push_back(key);
+            while order.len() > self.capacity {
+                if let Some(oldest) = order.pop_front() {
+                    made.remove(&oldest);
+                }
+            }
+        }
+        tile
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn test_tiles_cover_the_set() {
+        assert_eq!(tile_region(0, 0, 0), Some((-2.75, 1.25, -2.0, 2.0)));
+        assert_eq!(tile_region(1, 1, 0), Some((-0.75, 1.25, -2.0, 0.0)));
+        assert_eq!(tile_region(1, 2, 0), None);
+        assert_eq!(tile_region(MAX_ZOOM + 1, 0, 0), None);
+
+        assert_eq!(parse_tile_path("/3/2/5.png"), Some((3, 2, 5)));
+        assert_eq!(parse_tile_path("/3/2/5.jpg"), None);
+        assert_eq!(parse_tile_path("/3/2.png"), None);
+        assert_eq!(parse_tile_path("/3/2/5/1.png"), None);
+    }
+
+    #[test]
+    fn test_server_answers_and_keeps_tiles() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        static RENDERED: AtomicUsize = AtomicUsize::new(0);
+        thread::spawn(move || {
+            serve(&listener, 16, |(x_min, x_max, _, _)| {
+                RENDERED.fetch_add(1, Ordering::SeqCst);
+                format!("tile {}", x_max - x_min).into_bytes()
+            })
+        });
+
+        let get = |path: &str| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        let tile = get("/2/1/3.png");
+        assert!(tile.starts_with("HTTP/1.1 200 OK\r\n"), "{}", tile);
+        assert!(tile.contains("Content-Type: image/png") && tile.ends_with("\r\n\r\ntile 1"), "{}", tile);
+        get("/2/1/3.png");
+        assert_eq!(RENDERED.load(Ordering::SeqCst), 1);
+
+        assert!(get("/").contains("L.tileLayer"));
+        assert!(get("/2/4/0.png").starts_with("HTTP/1.1 404"));
+        assert!(get("/favicon.ico").starts_with("HTTP/1.1 404"));
+
+        // clients that connect and never send hold a slot each, not the server
+        let idle = (0..4).map(|_| TcpStream::connect(address).unwrap()).collect::<Vec<_>>();
+        assert!(get("/2/1/3.png").starts_with("HTTP/1.1 200 OK\r\n"));
+        drop(idle);
+    }
+
+    #[test]
+    fn test_slots_wait_for_one_to_be_given_back() {
+        let slots = Slots::new(2);
+        let (first, _second) = (slots.take(), slots.take());
+        thread::scope(|scope| {
+            let waiting = scope.spawn(|| drop(slots.take()));
+            thread::sleep(Duration::from_millis(50));
+            assert!(!waiting.is_finished());
+            drop(first);
+            waiting.join().unwrap();
+        });
+        assert_eq!(*slots.free.lock().unwrap(), 1);
+    }
+}
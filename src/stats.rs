@@ -0,0 +1,128 @@
+// Summary numbers of a render, for tuning max iterations and comparing optimizations: how much
+// of the view is in the set, how fast the rest escaped, and how many iterations it all took.
+
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::renderer::RenderData;
+
+// widest bar of the histogram, in characters
+const BAR_WIDTH: usize = 40;
+
+/// Escape statistics of a `RenderData`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Stats {
+    pub pixels: usize,
+    /// pixels that reached max iterations without escaping
+    pub in_set: usize,
+    /// fewest, most and mean iterations of the escaped pixels, `None` when none escaped
+    pub escape: Option<(usize, usize, f64)>,
+    /// escaped pixels by iterations in powers of two: 0-1, 2-3, 4-7, 8-15, ...
+    pub histogram: Vec<usize>,
+    /// iterations summed over every computed point, supersamples included. Points found in the
+    /// set early (the cardioid check, periodicity, filled or mirrored pixels) count in full, so
+    /// this is the work a plain render does
+    pub work: u64,
+    pub max_iterations: usize,
+}
+
+impl Stats {
+    /// Statistics of every pixel of `data`.
+    pub fn of(data: &RenderData) -> Self {
+        let max_iterations = data.max_iterations;
+        let mut stats = Stats {
+            pixels: data.pixels().len(),
+            in_set: 0,
+            escape: None,
+            histogram: vec![0; bucket(max_iterations) + 1],
+            work: 0,
+            max_iterations,
+        };
+        let mut total = 0;
+        for (i, pixel) in data.samples().enumerate() {
+            stats.work += match data.subsamples(i % data.width(), i / data.width()) {
+                Some(samples) => samples.map(|sample| sample.iterations as u64).sum(),
+                None => pixel.iterations as u64,
+            };
+            if pixel.iterations >= max_iterations {
+                stats.in_set += 1;
+                continue;
+            }
+            stats.histogram[bucket(pixel.iterations)] += 1;
+            total += pixel.iterations;
+            let (min, max, _) = stats.escape.get_or_insert((pixel.iterations, pixel.iterations, 0.0));
+            (*min, *max) = ((*min).min(pixel.iterations), (*max).max(pixel.iterations));
+        }
+        if let Some((_, _, mean)) = &mut stats.escape {
+            *mean = total as f64 / (stats.pixels - stats.in_set) as f64;
+        }
+        stats
+    }
+
+    /// Iterations the histogram bucket `index` takes in.
+    pub fn bucket_range(index: usize) -> (usize, usize) {
+        match index {
+            0 => (0, 1),
+            _ => (1 << index, (1 << (index + 1)) - 1),
+        }
+    }
+}
+
+// histogram bucket of an escape count: 0 and 1 share the first, then one per power of two
+fn bucket(iterations: usize) -> usize {
+    iterations.max(1).ilog2() as usize
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let share = self.in_set as f64 / self.pixels.max(1) as f64 * 100.0;
+        writeln!(f, " pixels: {}, {:.1}% in the set", self.pixels, share)?;
+        match self.escape {
+            Some((min, max, mean)) => writeln!(f, " escape iterations: min {}, max {}, mean {:.1}", min, max, mean)?,
+            None => writeln!(f, " escape iterations: none escaped")?,
+        }
+        let per_pixel = self.work as f64 / self.pixels.max(1) as f64;
+        writeln!(f, " iteration work: {} ({:.1} per pixel, max {})", self.work, per_pixel, self.max_iterations)?;
+
+        // only the buckets from the first to the last one used
+        let first = self.histogram.iter().position(|&count| count > 0).unwrap_or(0);
+        let last = self.histogram.iter().rposition(|&count| count > 0).unwrap_or(0);
+        let largest = self.histogram.iter().copied().max().unwrap_or(0).max(1);
+        for (index, &count) in self.histogram.iter().enumerate().take(last + 1).skip(first) {
+            let (low, high) = Self::bucket_range(index);
+            let range = format!("{}-{}", low, high.min(self.max_iterations.saturating_sub(1)));
+            let bar = "█".repeat((count * BAR_WIDTH).div_ceil(largest));
+            writeln!(f, " {:>13} {} {}", range, bar, count)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fractal::EscapeResult;
+
+    #[test]
+    fn test_stats_summarize_escape_counts() {
+        let pixels = [1, 5, 6, 100, 100, 3]
+            .map(|iterations| EscapeResult {
+                iterations,
+                ..Default::default()
+            })
+            .to_vec();
+        let stats = Stats::of(&RenderData::new(3, 2, pixels, 100));
+        assert_eq!(stats.pixels, 6);
+        assert_eq!(stats.in_set, 2);
+        assert_eq!(stats.escape, Some((1, 6, 3.75)));
+        assert_eq!(stats.work, 215);
+        assert_eq!(stats.histogram, vec![1, 1, 2, 0, 0, 0, 0]);
+        assert_eq!(Stats::bucket_range(2), (4, 7));
+
+        let text = stats.to_string();
+        assert!(text.contains("33.3% in the set"), "{}", text);
+        assert!(text.contains("4-7 ████████████████████████████████████████ 2"), "{}", text);
+        assert!(!text.contains("8-15"), "{}", text);
+    }
+}
@@ -0,0 +1,94 @@
+// The Mandelbrot set is its own mirror image across the real axis, so in a view the axis runs
+// through, the rows on one side are the rows on the other upside down. When the axis falls on
+// the pixel grid, the rows it mirrors are copied instead of computed, which halves the work of
+// the default view and of anything centered on the axis.
+
+use rayon::prelude::*;
+
+use crate::ComputeOptions;
+use crate::fractal::{EscapeResult, Fractal};
+use crate::progress::Progress;
+
+/// The row sum `k` with row `y` at the mirror image of row `k - y`, for a view of `height`
+/// rows over `y_min..y_max`, or `None` when the real axis isn't on the pixel grid or no row's
+/// image is in view.
+pub fn mirror(fractal: &Fractal, (_, _, y_min, y_max): (f64, f64, f64, f64), height: usize) -> Option<usize> {
+    if !matches!(fractal, Fractal::Mandelbrot) || height < 2 {
+        return None;
+    }
+    // the axis sits at row k / 2, which must be on a row or halfway between two
+    let sum = -2.0 * y_min * height as f64 / (y_max - y_min);
+    if !sum.is_finite() || sum < 1.0 || (sum - sum.round()).abs() > 1e-6 {
+        return None;
+    }
+    let sum = sum.round() as usize;
+    (sum / 2 + 1 < height).then_some(sum)
+}
+
+/// Escape results of `fractal` over `region` at `width`×`height` pixels, row by row, as
+/// `Fractal::calculate` gives them, with the rows mirrored across the real axis copied rather
+/// than computed. `None` when `mirror` finds no rows to copy.
+pub fn calculate(
+    fractal: &Fractal,
+    max_iters: usize,
+    region: (f64, f64, f64, f64),
+    width: usize,
+    height: usize,
+    options: &ComputeOptions,
+    progress: Option<&Progress>,
+) -> Option<Vec<EscapeResult>> {
+    let sum = mirror(fractal, region, height)?;
+    // rows past the axis whose image is in view, which is every row up to `sum`
+    let mirrored = sum / 2 + 1..(sum + 1).min(height);
+    let (x_min, x_max, y_min, y_max) = region;
+
+    let mut results = vec![EscapeResult::default(); width * height];
+    results
+        .par_chunks_mut(width.max(1))
+        .enumerate()
+        .filter(|(y, _)| !mirrored.contains(y))
+        .for_each(|(img_y, row)| {
+            if progress.is_some_and(Progress::is_cancelled) {
+                row.fill(EscapeResult::skipped(max_iters));
+                return;
+            }
+            for (img_x, result) in row.iter_mut().enumerate() {
+                let cx = x_min + (x_max - x_min) * (img_x as f64 / width as f64);
+                let cy = y_min + (y_max - y_min) * (img_y as f64 / height as f64);
+                *result = fractal.at_point_with(cx, cy, max_iters, options);
+            }
+            if let Some(progress) = progress {
+                progress.advance(width);
+            }
+        });
+
+    for y in mirrored.clone() {
+        results.copy_within((sum - y) * width..(sum - y + 1) * width, y * width);
+    }
+    if let Some(progress) = progress {
+        progress.advance(mirrored.len() * width);
+    }
+    Some(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mirrored_rows_match_the_full_computation() {
+        assert_eq!(mirror(&Fractal::Mandelbrot, (-2.0, 1.0, -1.0, 1.0), 40), Some(40));
+        assert_eq!(mirror(&Fractal::Mandelbrot, (-2.0, 1.0, -0.5, 1.5), 40), Some(20));
+        assert_eq!(mirror(&Fractal::Mandelbrot, (-2.0, 1.0, -0.51, 1.49), 40), None);
+        assert_eq!(mirror(&Fractal::Mandelbrot, (-2.0, 1.0, 0.1, 1.1), 40), None);
+        assert_eq!(mirror(&Fractal::BurningShip, (-2.0, 1.0, -1.0, 1.0), 40), None);
+
+        let options = ComputeOptions::default();
+        for (region, height) in [((-2.0, 1.0, -1.0, 1.0), 40), ((-2.0, 1.0, -1.0, 1.0), 41), ((-2.0, 1.0, -0.5, 1.5), 40)] {
+            let mirrored = calculate(&Fractal::Mandelbrot, 200, region, 60, height, &options, None).unwrap();
+            let full = Fractal::Mandelbrot.calculate(200, region, 60, height, &options, None);
+            let iterations = |grid: &[EscapeResult]| grid.iter().map(|r| r.iterations).collect::<Vec<_>>();
+            assert_eq!(iterations(&mirrored), iterations(&full), "{:?} at {} rows", region, height);
+        }
+    }
+}
@@ -0,0 +1,268 @@
+// Reads the color support a terminal declares in its compiled terminfo entry, as written by
+// ncurses' tic: a header of six little-endian i16s, the terminal names, the boolean, numeric
+// and string capabilities, then optionally the same again for extended capabilities, which is
+// where the truecolor flags (RGB, Tc) live.
+//
+// Terminals that aren't in the database, or whose entry undersells them, can still be asked:
+// setting a 24-bit background and reading it back with DECRQSS shows whether the terminal kept
+// it, and the DA1 request after it, which every terminal answers, marks the end of the reply.
+
+use std::path::PathBuf;
+#[cfg(all(unix, feature = "terminal"))]
+use std::sync::OnceLock;
+#[cfg(all(unix, feature = "terminal"))]
+use std::time::{Duration, Instant};
+
+const MAGIC_16BIT: i16 = 0o432;
+const MAGIC_32BIT: i16 = 0o1036;
+// position of max_colors among the standard numeric capabilities
+const MAX_COLORS: usize = 13;
+// set an unlikely 24-bit background, ask for the current SGR (DECRQSS), reset, then ask DA1
+#[cfg(all(unix, feature = "terminal"))]
+const QUERY: &[u8] = b"\x1b[48:2:1:2:3m\x1bP$qm\x1b\\\x1b[m\x1b[c";
+// how long a terminal gets to answer; local ones take a few milliseconds, ssh a round trip
+#[cfg(all(unix, feature = "terminal"))]
+const QUERY_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// What a terminfo entry says about colors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorSupport {
+    pub max_colors: u32,
+    /// the entry sets the RGB or Tc extension, e.g. xterm-direct
+    pub truecolor: bool,
+}
+
+/// Looks up `term` in the usual terminfo directories.
+pub fn lookup(term: &str) -> Option<ColorSupport> {
+    let first = term.chars().next()?;
+    if term.contains(['/', '\\']) || term.starts_with('.') {
+        return None;
+    }
+    search_dirs().into_iter().find_map(|dir| {
+        // Linux files entries by their first letter, macOS by its hex code
+        [first.to_string(), format!("{:x}", first as u32)]
+            .iter()
+            .find_map(|sub| std::fs::read(dir.join(sub).join(term)).ok())
+            .and_then(|bytes| parse(&bytes))
+    })
+}
+
+/// Asks the terminal on stdin and stdout whether it draws 24-bit color, once per run; `None`
+/// when they aren't a terminal or it doesn't answer within QUERY_TIMEOUT.
+#[cfg(all(unix, feature = "terminal"))]
+pub fn query_truecolor() -> Option<bool> {
+    static ANSWER: OnceLock<Option<bool>> = OnceLock::new();
+    *ANSWER.get_or_init(|| {
+        use std::io::IsTerminal;
+        use crossterm::terminal;
+
+        if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+            return None;
+        }
+        // raw mode, so the answer isn't echoed and arrives without a newline
+        let raw = terminal::is_raw_mode_enabled().ok()?;
+        if !raw {
+            terminal::enable_raw_mode().ok()?;
+        }
+        let answer = ask();
+        if !raw {
+            let _ = terminal::disable_raw_mode();
+        }
+        answer.map(|answer| kept_truecolor(&answer))
+    })
+}
+
+#[cfg(not(all(unix, feature = "terminal")))]
+pub fn query_truecolor() -> Option<bool> {
+    None
+}
+
+// writes QUERY and reads stdin up to the end of the DA1 answer
+#[cfg(all(unix, feature = "terminal"))]
+fn ask() -> Option<Vec<u8>> {
+    use std::io::Write;
+
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(QUERY).ok()?;
+    stdout.flush().ok()?;
+    let deadline = Instant::now() + QUERY_TIMEOUT;
+    let mut answer = Vec::new();
+    while !answered(&answer) {
+        let left = deadline.saturating_duration_since(Instant::now());
+        let mut stdin = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+        // SAFETY: one pollfd, which outlives the call
+        if left.is_zero() || unsafe { libc::poll(&mut stdin, 1, left.as_millis().max(1) as libc::c_int) } <= 0 {
+            return None;
+        }
+        // read straight from the descriptor: std's buffered stdin would keep what comes after
+        let mut buffer = [0u8; 256];
+        // SAFETY: reads at most the buffer's length into it
+        let read = unsafe { libc::read(libc::STDIN_FILENO, buffer.as_mut_ptr().cast(), buffer.len()) };
+        if read <= 0 {
+            return None;
+        }
+        answer.extend_from_slice(&buffer[..read as usize]);
+    }
+    Some(answer)
+}
+
+// whether `answer` holds a complete DA1 reply, ESC [ ? <params> c
+#[cfg(any(test, all(unix, feature = "terminal")))]
+fn answered(answer: &[u8]) -> bool {
+    answer.windows(3).enumerate().any(|(start, window)| {
+        window == b"\x1b[?" && answer[start + 3..].iter().find(|byte| !byte.is_ascii_digit() && **byte != b';') == Some(&b'c')
+    })
+}
+
+// whether the DECRQSS part of `answer`, ESC P 1 $ r <sgr> m ESC \, still has the background QUERY set
+#[cfg(any(test, all(unix, feature = "terminal")))]
+fn kept_truecolor(answer: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(answer);
+    let Some((_, rest)) = text.split_once("\x1bP1$r") else {
+        return false;
+    };
+    let sgr = rest.split('\x1b').next().unwrap_or_default();
+    // the color space id xterm puts before the channels is optional
+    sgr.trim_end_matches('m').split(';').any(|part| part == "48:2:1:2:3" || part == "48:2::1:2:3")
+}
+
+fn search_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(dir) = std::env::var_os("TERMINFO") {
+        dirs.push(PathBuf::from(dir));
+    }
+    if let Some(home) = std::env::var_os("HOME") {
+        dirs.push(PathBuf::from(home).join(".terminfo"));
+    }
+    if let Ok(list) = std::env::var("TERMINFO_DIRS") {
+        // an empty entry stands for the system default, which the list below covers
+        dirs.extend(list.split(':').filter(|dir| !dir.is_empty()).map(PathBuf::from));
+    }
+    for dir in ["/etc/terminfo", "/lib/terminfo", "/usr/share/terminfo", "/usr/lib/terminfo", "/usr/local/share/terminfo"] {
+        dirs.push(PathBuf::from(dir));
+    }
+    dirs
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Option<&[u8]> {
+        let slice = self.bytes.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(slice)
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        self.take(2).map(|b| i16::from_le_bytes([b[0], b[1]]))
+    }
+
+    // counts and sizes in the headers; negative ones mean a corrupt file
+    fn count(&mut self) -> Option<usize> {
+        usize::try_from(self.i16()?).ok()
+    }
+
+    fn number(&mut self, wide: bool) -> Option<i32> {
+        if wide { self.take(4).map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]])) } else { self.i16().map(i32::from) }
+    }
+
+    // sections start on an even offset
+    fn align(&mut self) {
+        self.pos += self.pos % 2;
+    }
+}
+
+fn parse(bytes: &[u8]) -> Option<ColorSupport> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let wide = match reader.i16()? {
+        MAGIC_16BIT => false,
+        MAGIC_32BIT => true,
+        _ => return None,
+    };
+    let [names_size, bool_count, num_count, str_count, table_size] = [(); 5].map(|_| reader.count());
+    reader.take(names_size?)?;
+    reader.take(bool_count?)?;
+    reader.align();
+    let numbers: Vec<i32> = (0..num_count?).map(|_| reader.number(wide)).collect::<Option<_>>()?;
+    reader.take(str_count? * 2)?;
+    reader.take(table_size?)?;
+
+    let max_colors = numbers.get(MAX_COLORS).copied().filter(|&n| n > 0).unwrap_or(0) as u32;
+    reader.align();
+    let truecolor = extended_truecolor(&mut reader, wide).unwrap_or(false);
+    Some(ColorSupport { max_colors, truecolor })
+}
+
+// the extended section lists its values first and the capability names at the end of its
+// string table, booleans then numbers then strings
+fn extended_truecolor(reader: &mut Reader, wide: bool) -> Option<bool> {
+    let [bool_count, num_count, str_count, _offset_count, table_size] = [(); 5].map(|_| reader.count());
+    let (bool_count, num_count, str_count) = (bool_count?, num_count?, str_count?);
+    let bools = reader.take(bool_count)?.to_vec();
+    reader.align();
+    let numbers: Vec<i32> = (0..num_count).map(|_| reader.number(wide)).collect::<Option<_>>()?;
+    reader.take(str_count * 2)?;
+    reader.take((bool_count + num_count + str_count) * 2)?;
+    let table = reader.take(table_size?)?;
+
+    let mut entries: Vec<&[u8]> = table.split(|&b| b == 0).collect();
+    // the table ends in a NUL, leaving an empty piece after it
+    entries.pop();
+    let names = &entries[entries.len().checked_sub(bool_count + num_count + str_count)?..];
+    let is_truecolor = |name: &[u8]| name == b"RGB" || name == b"Tc";
+    let flagged = names[..bool_count].iter().zip(&bools).any(|(name, &set)| set == 1 && is_truecolor(name));
+    let numbered = names[bool_count..bool_count + num_count]
+        .iter()
+        .zip(&numbers)
+        .any(|(name, &value)| value > 0 && is_truecolor(name));
+    Some(flagged || numbered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a hand-assembled entry: 16 colors, plus an extended Tc flag
+    fn entry(tc: bool) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut push = |values: &[i16]| values.iter().for_each(|v| bytes.extend_from_slice(&v.to_le_bytes()));
+        push(&[MAGIC_16BIT, 5, 1, 14, 0, 0]);
+        bytes.extend_from_slice(b"test\0\x01");
+        let mut numbers = [-1i16; 14];
+        numbers[MAX_COLORS] = 16;
+        numbers.iter().for_each(|v| bytes.extend_from_slice(&v.to_le_bytes()));
+        if tc {
+            // one boolean, no numbers or strings, one name offset, a 3-byte table
+            [1i16, 0, 0, 1, 3].iter().for_each(|v| bytes.extend_from_slice(&v.to_le_bytes()));
+            bytes.extend_from_slice(&[1, 0]);
+            bytes.extend_from_slice(&0i16.to_le_bytes());
+            bytes.extend_from_slice(b"Tc\0");
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_parses_colors_and_truecolor_flag() {
+        assert_eq!(parse(&entry(false)), Some(ColorSupport { max_colors: 16, truecolor: false }));
+        assert_eq!(parse(&entry(true)), Some(ColorSupport { max_colors: 16, truecolor: true }));
+        assert_eq!(parse(b"not terminfo"), None);
+        assert_eq!(parse(&entry(true)[..20]), None);
+    }
+
+    #[test]
+    fn test_reads_terminal_answers() {
+        // xterm keeps the color, with an empty color space; a terminal without DECRQSS
+        // answers only DA1, and one without 24-bit color rejects the request
+        let xterm = b"\x1bP1$r0;48:2::1:2:3m\x1b\\\x1b[?64;1;2;6;9;15;18;21;22c";
+        let kitty = b"\x1bP1$r48:2:1:2:3m\x1b\\\x1b[?62;c";
+        let plain = b"\x1b[?1;2c";
+        let rejected = b"\x1bP0$r\x1b\\\x1b[?62;22c";
+        assert!(kept_truecolor(xterm) && kept_truecolor(kitty));
+        assert!(!kept_truecolor(plain) && !kept_truecolor(rejected));
+        assert!([&xterm[..], kitty, plain, rejected].iter().all(|answer| answered(answer)));
+        assert!(!answered(b"\x1bP1$r48:2:1:2:3m\x1b\\\x1b[?62;"));
+    }
+}
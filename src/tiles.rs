@@ -0,0 +1,399 @@
+// Tiled rendering for images too big to hold in memory at once.
+//
+// The image is cut into tiles of whole rows. Each tile is computed, colored and appended to a
+// streaming PPM/PNG encoder before the next one starts, so memory stays at about one tile of
+// escape data plus the encoder's buffer, whatever the size of the image.
+//
+// Long renders can also keep a checkpoint: a file that starts with one line describing the
+// render, followed by the RGB rows of every tile finished so far. Resuming copies those rows
+// into the new output and only computes the tiles that are missing.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use rayon::prelude::*;
+
+use crate::farm::{Farm, Job};
+use crate::fractal::Fractal;
+use crate::metadata;
+use crate::progress::Progress;
+use crate::renderer::{ImageFormat, RenderData, Renderer};
+use crate::{ComputeOptions, PlaneMap, calculate, refine_edges};
+
+pub const DEFAULT_TILE_ROWS: usize = 256;
+const CHECKPOINT_MAGIC: &str = "mandelbrot checkpoint 1";
+
+/// A full-size render split into tiles of `tile_rows` rows.
+pub struct TiledRender<'a> {
+    pub fractal: &'a Fractal,
+    pub max_iters: usize,
+    pub region: (f64, f64, f64, f64),
+    pub width: usize,
+    pub height: usize,
+    pub tile_rows: usize,
+    pub options: ComputeOptions,
+    /// adaptive anti-aliasing as (samples, threshold), applied to each tile on its own
+    pub refine: Option<(usize, f64)>,
+    /// workers to compute each tile on, in bands, instead of only this machine
+    pub farm: Option<&'a Farm>,
+}
+
+impl TiledRender<'_> {
+    pub fn tiles(&self) -> usize {
+        self.height.div_ceil(self.tile_rows.max(1))
+    }
+
+    /// First row and number of rows of tile `index`.
+    pub fn tile_span(&self, index: usize) -> (usize, usize) {
+        let first_row = index * self.tile_rows.max(1);
+        (first_row, self.tile_rows.max(1).min(self.height - first_row))
+    }
+
+    /// The part of the region covered by `rows` rows from `first_row`; its pixels land on the
+    /// same points as the matching rows of an untiled render, up to float rounding.
+    pub fn tile_region(&self, first_row: usize, rows: usize) -> (f64, f64, f64, f64) {
+        let (x_min, x_max, y_min, y_max) = self.region;
+        let row_y = |row: usize| y_min + (y_max - y_min) * (row as f64 / self.height as f64);
+        (x_min, x_max, row_y(first_row), row_y(first_row + rows))
+    }
+
+    /// Computes tile `index`; `renderer` decides which pixels `refine` anti-aliases.
+    pub fn compute_tile(&self, index: usize, renderer: &Renderer, progress: Option<&Progress>) -> RenderData {
+        let (first_row, rows) = self.tile_span(index);
+        let region = self.tile_region(first_row, rows);
+
+        let mut data = match self.farm {
+            Some(farm) => {
+                let job = Job {
+                    fractal: *self.fractal,
+                    max_iters: self.max_iters,
+                    region,
+                    width: self.width,
+                    height: rows,
+                    options: self.options,
+                };
+                farm.calculate(&job, progress)
+            }
+            None => calculate(self.fractal, self.max_iters, region, self.width, rows, &self.options, progress),
+        };
+        if let Some((samples, threshold)) = self.refine {
+            refine_edges(self.fractal, &mut data, region, PlaneMap::new(&self.options), renderer, samples, threshold);
+        }
+        data
+    }
+
+    /// Computes and colors tile `index` into packed RGB rows.
+    pub fn render_tile(&self, index: usize, renderer: &Renderer, progress: Option<&Progress>) -> Vec<u8> {
+        renderer.to_rgb_buffer(&self.compute_tile(index, renderer, progress))
+    }
+
+    /// Computes tiles on every worker thread and hands each one to `on_tile` in order, as soon as
+    /// it and all the tiles above it are done. Workers take the next tile as they free up, so
+    /// tiles finish roughly top to bottom and little has to wait for its turn.
+    pub fn stream(&self, renderer: &Renderer, progress: Option<&Progress>, mut on_tile: impl FnMut(RenderData)) {
+        let next_tile = AtomicUsize::new(0);
+        let (sender, receiver) = mpsc::channel();
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                (0..rayon::current_num_threads()).into_par_iter().for_each_with(sender, |sender, _| {
+                    loop {
+                        let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                        if index >= self.tiles() {
+                            break;
+                        }
+                        // the receiver only goes away if `on_tile` panicked
+                        if sender.send((index, self.compute_tile(index, renderer, progress))).is_err() {
+                            break;
+                        }
+                    }
+                });
+            });
+
+            let mut waiting = BTreeMap::new();
+            let mut next = 0;
+            for (index, data) in receiver {
+                waiting.insert(index, data);
+                while let Some(data) = waiting.remove(&next) {
+                    on_tile(data);
+                    next += 1;
+                }
+            }
+        });
+    }
+
+    /// Renders every tile in order into `out`, then finishes the image. With a `checkpoint`
+    /// file, the tiles it already holds are copied instead of computed and every new tile is
+    /// appended to it; the file is removed once the image is complete. If `progress` is
+    /// cancelled, the image is finished with the rows still to come black and the checkpoint
+    /// kept to resume from; returns whether the image is complete.
+    pub fn render<W: Write + 'static>(
+        &self,
+        renderer: &Renderer,
+        mut out: RowWriter<W>,
+        checkpoint: Option<&Path>,
+        progress: Option<&Progress>,
+    ) -> io::Result<bool> {
+        let mut saved = None;
+        let mut first = 0;
+        if let Some(path) = checkpoint {
+            let (file, done) = self.open_checkpoint(path, renderer, &mut out)?;
+            if let Some(progress) = progress {
+                let samples = self.options.samples.max(1);
+                progress.advance(self.rows_before(done) * self.width * samples * samples);
+            }
+            saved = Some(file);
+            first = done;
+            log::debug!("resuming after {} tiles from {}", done, path.display());
+        }
+
+        let cancelled = || progress.is_some_and(Progress::is_cancelled);
+        let mut rows = self.rows_before(first);
+        for index in first..self.tiles() {
+            if cancelled() {
+                break;
+            }
+            log::trace!("tile {} of {}", index + 1, self.tiles());
+            let pixels = self.render_tile(index, renderer, progress);
+            out.write_rows(&pixels)?;
+            rows = self.rows_before(index + 1);
+            // a tile cancelled partway has skipped rows, which mustn't be resumed from
+            if let Some(file) = &mut saved
+                && !cancelled()
+            {
+                file.write_all(&pixels)?;
+                file.sync_data()?;
+            }
+        }
+        out.write_rows(&vec![0; (self.height - rows) * self.width * 3])?;
+        out.finish()?;
+
+        let complete = !cancelled();
+        if complete && let Some(path) = checkpoint {
+            fs::remove_file(path)?;
+        }
+        Ok(complete)
+    }
+
+    // rows covered by the first `tiles` tiles
+    fn rows_before(&self, tiles: usize) -> usize {
+        (tiles * self.tile_rows.max(1)).min(self.height)
+    }
+
+    // one line naming everything that affects the pixels, so a checkpoint is only ever resumed
+    // into the render it was made for
+    fn checkpoint_header(&self, renderer: &Renderer) -> String {
+        let params = (
+            self.fractal,
+            self.max_iters,
+            self.region,
+            (self.width, self.height, self.tile_rows.max(1)),
+            self.options,
+            self.refine,
+            renderer.color_scheme(),
+            renderer.coloring(),
+            renderer.interior(),
+            renderer.color_map(),
+            renderer.color_cycle(),
+            renderer.layers(),
+        );
+        format!("{} {:?}\n", CHECKPOINT_MAGIC, params)
+    }
+
+    // opens the checkpoint at `path`, or starts one, and copies the finished tiles it holds into
+    // `out`; returns the file ready for appending and the number of tiles copied
+    fn open_checkpoint<W: Write + 'static>(
+        &self,
+        path: &Path,
+        renderer: &Renderer,
+        out: &mut RowWriter<W>,
+    ) -> io::Result<(File, usize)> {
+        let header = self.checkpoint_header(renderer);
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+
+        let mut reader = BufReader::new(&mut file);
+        let mut found = String::new();
+        reader.read_line(&mut found)?;
+        if found.is_empty() {
+            file.write_all(header.as_bytes())?;
+            return Ok((file, 0));
+        }
+        if found != header {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} was made for a different render", path.display()),
+            ));
+        }
+
+        let mut done = 0;
+        let mut pixels = Vec::new();
+        while done < self.tiles() {
+            pixels.resize(self.tile_span(done).1 * self.width * 3, 0);
+            match reader.read_exact(&mut pixels) {
+                Ok(()) => out.write_rows(&pixels)?,
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e),
+            }
+            done += 1;
+        }
+        drop(reader);
+
+        // drop whatever the interruption left of the next tile
+        let end = (header.len() + self.rows_before(done) * self.width * 3) as u64;
+        file.set_len(end)?;
+        file.seek(SeekFrom::Start(end))?;
+        Ok((file, done))
+    }
+}
+
+/// Image encoder that takes packed RGB rows as they are produced.
+pub enum RowWriter<W: Write + 'static> {
+    Ppm(W),
+    Png(Box<png::StreamWriter<'static, W>>),
+    Raw(W),
+}
+
+impl<W: Write + 'static> RowWriter<W> {
+    /// Writes the header of a `width`×`height` image, with `text` chunks in a PNG. GIF needs the
+    /// whole image at once to pick its palette, so it isn't supported.
+    pub fn new(mut out: W, format: ImageFormat, width: usize, height: usize, text: &[(String, String)]) -> io::Result<Self> {
+        match format {
+            ImageFormat::Ppm => {
+                write!(out, "P6\n{} {}\n255\n", width, height)?;
+                Ok(RowWriter::Ppm(out))
+            }
+            ImageFormat::Png => {
+                let mut encoder = png::Encoder::new(out, width as u32, height as u32);
+                encoder.set_color(png::ColorType::Rgb);
+                encoder.set_depth(png::BitDepth::Eight);
+                metadata::add_text(&mut encoder, text)?;
+                let writer = encoder.write_header().map_err(io::Error::other)?;
+                Ok(RowWriter::Png(Box::new(writer.into_stream_writer().map_err(io::Error::other)?)))
+            }
+            ImageFormat::Raw => Ok(RowWriter::Raw(out)),
+            ImageFormat::Gif => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "GIF can't be written tile by tile, use PNG or PPM",
+            )),
+        }
+    }
+
+    pub fn write_rows(&mut self, pixels: &[u8]) -> io::Result<()> {
+        match self {
+            RowWriter::Ppm(out) | RowWriter::Raw(out) => out.write_all(pixels),
+            RowWriter::Png(out) => out.write_all(pixels),
+        }
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            RowWriter::Ppm(mut out) | RowWriter::Raw(mut out) => out.flush(),
+            RowWriter::Png(out) => out.finish().map_err(io::Error::other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::atomic::AtomicBool;
+
+    use super::*;
+    use crate::{ColorScheme, OutputFormat};
+
+    // files a test writes, named for the process and the test so parallel runs don't share
+    // them, and removed when the test ends, failed or not
+    struct TempFiles(Vec<PathBuf>);
+
+    impl TempFiles {
+        fn new(test: &str, extensions: &[&str]) -> Self {
+            let dir = std::env::temp_dir();
+            Self(extensions.iter().map(|extension| dir.join(format!("mandelbrot-{}-{}.{}", std::process::id(), test, extension))).collect())
+        }
+    }
+
+    impl Drop for TempFiles {
+        fn drop(&mut self) {
+            for path in &self.0 {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tiles_match_untiled_render() {
+        let region = (-2.0, 1.0, -1.0, 1.0);
+        let options = ComputeOptions::default();
+        let renderer = Renderer::new(ColorScheme::Ocean, OutputFormat::AnsiTrueColor);
+        let tiled = TiledRender {
+            fractal: &Fractal::Mandelbrot,
+            max_iters: 100,
+            region,
+            width: 48,
+            height: 30,
+            tile_rows: 7,
+            options,
+            refine: None,
+            farm: None,
+        };
+        assert_eq!(tiled.tiles(), 5);
+        assert_eq!(tiled.tile_span(4), (28, 2));
+
+        let tiles: Vec<u8> = (0..tiled.tiles()).flat_map(|i| tiled.render_tile(i, &renderer, None)).collect();
+        let data = calculate(&Fractal::Mandelbrot, 100, region, 48, 30, &options, None);
+        assert_eq!(tiles, renderer.to_rgb_buffer(&data));
+
+        let mut streamed = Vec::new();
+        tiled.stream(&renderer, None, |tile| streamed.extend(renderer.to_rgb_buffer(&tile)));
+        assert_eq!(streamed, tiles);
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let renderer = Renderer::new(ColorScheme::Fire, OutputFormat::AnsiTrueColor);
+        let tiled = TiledRender {
+            fractal: &Fractal::Mandelbrot,
+            max_iters: 100,
+            region: (-2.0, 1.0, -1.0, 1.0),
+            width: 40,
+            height: 24,
+            tile_rows: 5,
+            options: ComputeOptions::default(),
+            refine: None,
+            farm: None,
+        };
+        let files = TempFiles::new("test_resume_from_checkpoint", &["checkpoint", "ppm"]);
+        let (checkpoint, image) = (&files.0[0], &files.0[1]);
+
+        // two finished tiles and part of a third, as an interrupted render leaves it
+        let mut saved = tiled.checkpoint_header(&renderer).into_bytes();
+        saved.extend(tiled.render_tile(0, &renderer, None));
+        saved.extend(tiled.render_tile(1, &renderer, None));
+        saved.extend(&tiled.render_tile(2, &renderer, None)[..100]);
+        fs::write(checkpoint, saved).unwrap();
+
+        let out = RowWriter::new(File::create(image).unwrap(), ImageFormat::Ppm, 40, 24, &[]).unwrap();
+        tiled.render(&renderer, out, Some(checkpoint), None).unwrap();
+        assert!(!checkpoint.exists());
+
+        let mut expected = b"P6\n40 24\n255\n".to_vec();
+        expected.extend((0..tiled.tiles()).flat_map(|i| tiled.render_tile(i, &renderer, None)));
+        assert_eq!(fs::read(image).unwrap(), expected);
+
+        // cancelled before a tile is done: a whole black image, and the checkpoint stays
+        static CANCELLED: AtomicBool = AtomicBool::new(true);
+        let progress = Progress::new(40, 24, true).with_cancel(&CANCELLED);
+        fs::write(checkpoint, tiled.checkpoint_header(&renderer)).unwrap();
+        let out = RowWriter::new(File::create(image).unwrap(), ImageFormat::Ppm, 40, 24, &[]).unwrap();
+        assert!(!tiled.render(&renderer, out, Some(checkpoint), Some(&progress)).unwrap());
+        assert!(checkpoint.exists());
+        let mut black = b"P6\n40 24\n255\n".to_vec();
+        black.resize(black.len() + 40 * 24 * 3, 0);
+        assert_eq!(fs::read(image).unwrap(), black);
+    }
+}
@@ -0,0 +1,32 @@
+// Timing of the phases of a render, logged at debug level as each one ends, so a `-v` run shows
+// where the time goes: computing the escape data, coloring it and encoding the image. Nothing
+// is measured while debug logging is off, which also keeps the clock out of wasm builds, where
+// there is none.
+
+use std::time::Instant;
+
+/// A phase being timed; logs how long it took when dropped.
+pub struct Span {
+    phase: &'static str,
+    detail: String,
+    started: Option<Instant>,
+}
+
+/// Starts timing `phase`, described by `detail`, which is only called when the time will be
+/// logged.
+pub fn span(phase: &'static str, detail: impl FnOnce() -> String) -> Span {
+    let enabled = log::log_enabled!(log::Level::Debug);
+    Span {
+        phase,
+        detail: if enabled { detail() } else { String::new() },
+        started: enabled.then(Instant::now),
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(started) = self.started {
+            log::debug!("{} {}: {:.1?}", self.phase, self.detail, started.elapsed());
+        }
+    }
+}
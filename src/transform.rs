@@ -0,0 +1,172 @@
+// Where the points of a computed region land in the plane. A region is a rectangle of the view,
+// which `ComputeOptions::rotation` turns about the origin and `ComputeOptions::transform` then
+// bends through a conformal map:
+//
+//     inversion     c = 1/w, the plane turned inside out: infinity at the origin, and the set
+//                   around it with its cusp out at 4
+//     exponential   c = center + e^w, so a region's x is the log of the distance from the center
+//                   and its y the angle around it; a strip 2π high shows every scale between
+//                   two radii at once, and zooming in is moving left
+//
+// Both maps are conformal, so shapes stay undistorted up close, only their size changes across
+// the picture: `PlaneMap::stretch` is how much.
+
+use num::Complex;
+use serde::{Deserialize, Serialize};
+
+use crate::ComputeOptions;
+
+// where the origin goes under inversion: as far out as points get while their squares stay finite
+const INFINITY: f64 = 1e150;
+
+/// A conformal map applied to the points of a region after `Rotation`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Transform {
+    None,
+    /// z ↦ 1/z
+    Inversion,
+    /// z ↦ center + e^z; the region's x is the log of the radius and y the angle, in radians
+    Exponential { center: (f64, f64) },
+}
+
+impl Transform {
+    /// `center` is where the exponential map is centered; the others ignore it.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str, center: (f64, f64)) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "none" => Some(Transform::None),
+            "inversion" | "invert" => Some(Transform::Inversion),
+            "exponential" | "exp" | "log-polar" => Some(Transform::Exponential { center }),
+            _ => None,
+        }
+    }
+
+    pub fn list_transforms() -> Vec<&'static str> {
+        vec!["none", "inversion", "exponential"]
+    }
+
+    /// The point of the plane at `point`.
+    pub fn apply(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        match *self {
+            Transform::None => (x, y),
+            Transform::Inversion if x == 0.0 && y == 0.0 => (INFINITY, 0.0),
+            Transform::Inversion => {
+                let c = Complex::new(x, y).inv();
+                (c.re, c.im)
+            }
+            Transform::Exponential { center: (re, im) } => {
+                let c = Complex::new(x, y).exp();
+                (re + c.re, im + c.im)
+            }
+        }
+    }
+
+    /// How many times bigger things are in the plane than at `point`: |f'(point)|.
+    pub fn stretch(&self, (x, y): (f64, f64)) -> f64 {
+        match self {
+            Transform::None => 1.0,
+            Transform::Inversion => 1.0 / (x * x + y * y),
+            Transform::Exponential { .. } => x.exp(),
+        }
+    }
+
+    /// The region on which the exponential map shows the circle of `radius` about its center
+    /// and everything inside it, down to where an image `width` by `height` pixels, each
+    /// `pixel_aspect` times taller than wide, runs out: the angle goes down the image and the
+    /// log of the radius across it, at the same scale so nothing is stretched.
+    pub fn log_polar_region(radius: f64, width: usize, height: usize, pixel_aspect: f64) -> (f64, f64, f64, f64) {
+        use std::f64::consts::PI;
+        let span = 2.0 * PI * width as f64 / (height.max(1) as f64 * pixel_aspect);
+        (radius.ln() - span, radius.ln(), -PI, PI)
+    }
+}
+
+/// A turn about the origin, from the frame computed regions are given in to the plane.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rotation {
+    pub(crate) cos: f64,
+    pub(crate) sin: f64,
+}
+
+impl Rotation {
+    /// Counterclockwise by `degrees`. Quarter turns are exact, so they move points of the
+    /// pixel grid onto each other, and 0 leaves every point where it is.
+    pub fn degrees(degrees: f64) -> Self {
+        let turn = degrees.rem_euclid(360.0);
+        let (sin, cos) = if turn % 90.0 == 0.0 {
+            [(0.0, 1.0), (1.0, 0.0), (0.0, -1.0), (-1.0, 0.0)][(turn / 90.0) as usize % 4]
+        } else {
+            turn.to_radians().sin_cos()
+        };
+        Self { cos, sin }
+    }
+
+    /// The point of the plane at (x, y) of a region.
+    pub fn to_plane(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (x * self.cos - y * self.sin, x * self.sin + y * self.cos)
+    }
+
+    /// The point of a region at (re, im) of the plane.
+    pub fn to_view(&self, (re, im): (f64, f64)) -> (f64, f64) {
+        (re * self.cos + im * self.sin, im * self.cos - re * self.sin)
+    }
+}
+
+/// The whole way from a point of a computed region to the plane, as `options` set it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaneMap {
+    rotation: Rotation,
+    transform: Transform,
+}
+
+impl PlaneMap {
+    pub fn new(options: &ComputeOptions) -> Self {
+        Self {
+            rotation: Rotation::degrees(options.rotation),
+            transform: options.transform,
+        }
+    }
+
+    /// Whether every point stays where it is, as the GPU and mirrored rows need.
+    pub fn is_identity(&self) -> bool {
+        self.rotation == Rotation::degrees(0.0) && self.transform == Transform::None
+    }
+
+    pub fn to_plane(&self, point: (f64, f64)) -> (f64, f64) {
+        self.transform.apply(self.rotation.to_plane(point))
+    }
+
+    /// `Transform::stretch` at the point of a region; turning stretches nothing.
+    pub fn stretch(&self, point: (f64, f64)) -> f64 {
+        self.transform.stretch(self.rotation.to_plane(point))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transforms_map_conformally() {
+        assert_eq!(Transform::Inversion.apply((2.0, 0.0)), (0.5, 0.0));
+        assert_eq!(Transform::Inversion.apply((0.0, 0.0)), (INFINITY, 0.0));
+        let (re, im) = Transform::Inversion.apply((0.0, 0.5));
+        assert!(re.abs() < 1e-15 && (im + 2.0).abs() < 1e-15);
+
+        // a quarter turn up the strip is a quarter turn about the center
+        let exponential = Transform::from_str("log-polar", (-0.75, 0.1)).unwrap();
+        let (re, im) = exponential.apply((2f64.ln(), std::f64::consts::FRAC_PI_2));
+        assert!((re + 0.75).abs() < 1e-12 && (im - 2.1).abs() < 1e-12);
+
+        // the stretch is how far a small step goes
+        for transform in [Transform::None, Transform::Inversion, exponential] {
+            let (point, step) = ((0.3, -0.4), 1e-7);
+            let (a, b) = (transform.apply(point), transform.apply((point.0 + step, point.1)));
+            let moved = ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt() / step;
+            assert!((moved / transform.stretch(point) - 1.0).abs() < 1e-5, "{:?}", transform);
+        }
+
+        let (x_min, x_max, y_min, y_max) = Transform::log_polar_region(1.0, 200, 100, 1.0);
+        assert!((x_max - x_min - 2.0 * (y_max - y_min)).abs() < 1e-12 && x_max == 0.0);
+    }
+}
@@ -0,0 +1,126 @@
+// JavaScript bindings, for driving a web page with the same computation and colors as the
+// command line. Build the library for the browser and generate the glue with:
+//
+//     cargo rustc --lib --release --target wasm32-unknown-unknown --no-default-features \
+//         --features wasm --crate-type cdylib
+//     wasm-bindgen --target web --out-dir web target/wasm32-unknown-unknown/release/mandelbrot.wasm
+//
+// and from JavaScript:
+//
+//     const view = new View("mandelbrot", "ocean");
+//     view.iterations = 500;
+//     const pixels = view.render(-2.0, 1.0, -1.0, 1.0, canvas.width, canvas.height);
+//     context.putImageData(new ImageData(new Uint8ClampedArray(pixels), canvas.width), 0, 0);
+//
+// There are no threads in the browser without extra setup, so rayon runs everything on the
+// calling thread; render from a web worker to keep the page responsive.
+
+use num::Complex;
+use wasm_bindgen::prelude::*;
+
+use crate::ComputeOptions;
+use crate::colorscheme::ColorScheme;
+use crate::error::MandelbrotError;
+use crate::formula::Formula;
+use crate::fractal::Fractal;
+use crate::renderer::{OutputFormat, Renderer};
+
+const ITERATIONS: usize = 1000;
+
+/// A fractal in a color scheme, rendered region by region.
+#[wasm_bindgen]
+pub struct View {
+    fractal: Fractal,
+    renderer: Renderer,
+    max_iterations: usize,
+    options: ComputeOptions,
+}
+
+#[wasm_bindgen]
+impl View {
+    /// `fractal` is a name from `Fractal::list_fractals` and `colors` one from
+    /// `ColorScheme::list_schemes`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(fractal: &str, colors: &str) -> Result<View, JsError> {
+        Ok(Self::named(fractal, colors)?)
+    }
+
+    /// A view of `z = formula(z, c)`, as `--formula` takes it.
+    pub fn formula(formula: &str, colors: &str) -> Result<View, JsError> {
+        Ok(Self::with(Fractal::Formula(Formula::parse(formula)?), colors)?)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn iterations(&self) -> usize {
+        self.max_iterations
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_iterations(&mut self, max_iterations: usize) {
+        self.max_iterations = max_iterations.max(1);
+    }
+
+    /// Moves the constant of a julia view; other fractals ignore it.
+    #[wasm_bindgen(js_name = setJuliaC)]
+    pub fn set_julia_c(&mut self, re: f64, im: f64) {
+        if let Fractal::Julia(c) = &mut self.fractal {
+            *c = Complex::new(re, im);
+        }
+    }
+
+    /// Smooths edges by computing `samples`×`samples` points per pixel.
+    #[wasm_bindgen(setter)]
+    pub fn set_samples(&mut self, samples: usize) {
+        self.options.samples = samples.max(1);
+    }
+
+    /// RGBA pixels of the region, row by row from `y_min`, as `ImageData` takes them.
+    pub fn render(&self, x_min: f64, x_max: f64, y_min: f64, y_max: f64, width: usize, height: usize) -> Vec<u8> {
+        let region = (x_min, x_max, y_min, y_max);
+        let data = crate::calculate(&self.fractal, self.max_iterations, region, width, height, &self.options, None);
+        let rgb = self.renderer.to_rgb_buffer(&data);
+        rgb.chunks_exact(3).flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255]).collect()
+    }
+}
+
+// errors only become a `JsError` on the way out, since making one calls into JavaScript
+impl View {
+    fn named(fractal: &str, colors: &str) -> crate::Result<View> {
+        let fractal = Fractal::from_name_with_defaults(fractal).ok_or_else(|| MandelbrotError::unknown("fractal", fractal))?;
+        Self::with(fractal, colors)
+    }
+
+    fn with(fractal: Fractal, colors: &str) -> crate::Result<View> {
+        let scheme = ColorScheme::from_str(colors).ok_or_else(|| MandelbrotError::unknown("color scheme", colors))?;
+        Ok(View {
+            fractal,
+            // the output format only matters for text, which this never draws
+            renderer: Renderer::new(scheme, OutputFormat::AnsiTrueColor),
+            max_iterations: ITERATIONS,
+            options: ComputeOptions::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renders_rgba_rows_and_rejects_unknown_names() {
+        let mut view = View::named("mandelbrot", "ocean").unwrap();
+        view.set_iterations(50);
+        let pixels = view.render(-2.0, 1.0, -1.0, 1.0, 12, 8);
+        assert_eq!(pixels.len(), 12 * 8 * 4);
+        let data = crate::calculate(&Fractal::Mandelbrot, 50, (-2.0, 1.0, -1.0, 1.0), 12, 8, &ComputeOptions::default(), None);
+        let rgb = Renderer::new(ColorScheme::Ocean, OutputFormat::AnsiTrueColor).to_rgb_buffer(&data);
+        for (rgba, rgb) in pixels.chunks_exact(4).zip(rgb.chunks_exact(3)) {
+            assert_eq!(&rgba[..3], rgb);
+            assert_eq!(rgba[3], 255);
+        }
+
+        assert!(matches!(View::named("mandelbread", "ocean"), Err(MandelbrotError::Unknown { kind: "fractal", .. })));
+        assert!(matches!(View::named("julia", "no-such"), Err(MandelbrotError::Unknown { kind: "color scheme", .. })));
+        assert!(View::with(Fractal::Julia(crate::DEFAULT_JULIA_C), "viridis-r").is_ok());
+    }
+}
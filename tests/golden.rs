@@ -0,0 +1,157 @@
+// Bit-for-bit checks of the whole pipeline, from escape data to colored pixels, against the
+// checksums of renders known to be right. Any change to the math or the coloring that moves a
+// pixel fails here; when the change is meant, copy the new checksums the failure prints into
+// the table.
+
+use mandelbrot::newton::Polynomial;
+use mandelbrot::{ColorMap, ColorScheme, ColoringMode, ComputeOptions, Fractal, InteriorMode, Job, OutputFormat, Renderer};
+use num::complex::Complex;
+
+struct Golden {
+    name: &'static str,
+    fractal: Fractal,
+    region: (f64, f64, f64, f64),
+    iterations: usize,
+    options: ComputeOptions,
+    renderer: Renderer,
+    data: &'static str,
+    rgb: &'static str,
+}
+
+fn renderer(scheme: ColorScheme) -> Renderer {
+    Renderer::new(scheme, OutputFormat::AnsiTrueColor)
+}
+
+fn cases() -> Vec<Golden> {
+    let deep = mandelbrot::region_around((-0.743_643_887_037_151, 0.131_825_904_205_330), 1e10, 32, 16, 1.0);
+    vec![
+        Golden {
+            name: "mandelbrot smooth",
+            fractal: Fractal::Mandelbrot,
+            region: (-2.0, 1.0, -1.0, 1.0),
+            iterations: 200,
+            options: ComputeOptions::default(),
+            renderer: renderer(ColorScheme::Classic),
+            data: "8383842e625969756e518b9e7b75fea67eadd3da92f2fd13afa010388e443134",
+            rgb: "dd1cafcc69adae45814ea83e61ff6310723c650ff71efc718f5bec14a73ca85b",
+        },
+        Golden {
+            name: "julia histogram",
+            fractal: Fractal::Julia(Complex::new(-0.8, 0.156)),
+            region: (-1.5, 1.5, -1.0, 1.0),
+            iterations: 300,
+            options: ComputeOptions::default(),
+            renderer: renderer(ColorScheme::Fire).with_coloring(ColoringMode::Histogram),
+            data: "4c18693e211c1482b4a52a6d3d74ab43f84c2ac7dc8aa5afe926fae7a5f7bafa",
+            rgb: "4dae11ee3eb00bc2ae34a72eead17499600d0af88995b35ddba62b1143f254a6",
+        },
+        Golden {
+            name: "burning ship distance",
+            fractal: Fractal::BurningShip,
+            region: (-2.2, 1.3, -2.0, 0.8),
+            iterations: 100,
+            options: ComputeOptions {
+                distance: true,
+                ..ComputeOptions::default()
+            },
+            renderer: renderer(ColorScheme::Ocean).with_coloring(ColoringMode::Distance { falloff: 4.0 }),
+            data: "162a1acb8ba57c49cdef6133f465b736b879d3cca1fcc0c99ee2ead1d0a3adfe",
+            rgb: "18a901e42c93d4f058f3280bedc272e1b5b3c1f2a0d13d8c17e00e674b2e4180",
+        },
+        Golden {
+            name: "newton",
+            fractal: Fractal::Newton(Polynomial::default()),
+            region: (-2.0, 2.0, -2.0, 2.0),
+            iterations: 50,
+            options: ComputeOptions::default(),
+            renderer: renderer(ColorScheme::Classic),
+            data: "d9295480e2cfbae7b2148074e0711240d129de3ee65cc1978ac867693fe60f7b",
+            rgb: "3844ea1cfc0d64a56d302fb2a2a49a45880815dd39d5f6dab1850e0b9995a5f9",
+        },
+        Golden {
+            name: "seahorse supersampled",
+            fractal: Fractal::Mandelbrot,
+            region: (-0.75, -0.73, 0.09, 0.11),
+            iterations: 500,
+            options: ComputeOptions {
+                samples: 2,
+                interior: InteriorMode::Period,
+                ..ComputeOptions::default()
+            },
+            renderer: renderer(ColorScheme::Viridis)
+                .with_interior(InteriorMode::Period)
+                .with_color_map(ColorMap::Sqrt)
+                .with_color_cycle(3.0, 0.25),
+            data: "d4c9ca7df3cfbaf918797b1c45c30129e6f729dff6b0776d56c98eb74d9ebe10",
+            rgb: "f0417b617c18f0baf647d2c582e8f11e4274f03844518e7ba0ac700331144591",
+        },
+        Golden {
+            name: "perturbation deep zoom",
+            fractal: Fractal::Mandelbrot,
+            region: deep,
+            iterations: 1000,
+            options: ComputeOptions {
+                perturbation: true,
+                ..ComputeOptions::default()
+            },
+            renderer: renderer(ColorScheme::Magma),
+            data: "1254317a240d4589f1d0299783b42a853644ba206b02aad993a9e1e776e7768c",
+            rgb: "80b25f8b27755c340c912b38a9810f886bb991b66f42f939e82cd2ece3cfe3b0",
+        },
+    ]
+}
+
+#[test]
+fn test_renders_match_their_checksums() {
+    let mut changed = Vec::new();
+    for case in cases() {
+        let job = Job {
+            fractal: case.fractal,
+            max_iters: case.iterations,
+            region: case.region,
+            width: 32,
+            height: 16,
+            options: case.options,
+        };
+        let data = mandelbrot::compute(&job);
+        let (data_checksum, rgb_checksum) = (data.checksum(), case.renderer.rgb_checksum(&data));
+        if data_checksum != case.data || rgb_checksum != case.rgb {
+            changed.push(format!("{}:\n    data: \"{}\",\n    rgb: \"{}\",", case.name, data_checksum, rgb_checksum));
+        }
+    }
+    assert!(changed.is_empty(), "renders changed:\n{}", changed.join("\n"));
+}
+
+// small enough to read: far out is light, the set is blank, and a change shows where it is
+const MANDELBROT_ASCII: &str = "\
+.:::::::::::::::--------=+=-----::::::::
+::::::::::::::---------=+ +*-----:::::::
+:::::::::::--------=====   ===-----:::::
+::::::::----------=*          % =---::::
+::::------=----===             *=---::::
+::-------==*+*+==               +----:::
+:------=+=+                     #----:::
+                               ==----:::
+:------=+=+                     #----:::
+::-------==*+*+==               +----:::
+::::------=----===             *=---::::
+::::::::----------=*          % =---::::
+:::::::::::--------=====   ===-----:::::
+::::::::::::::---------=+ +*-----:::::::
+";
+
+#[test]
+fn test_small_render_matches_its_picture() {
+    let job = Job {
+        fractal: Fractal::Mandelbrot,
+        max_iters: 100,
+        region: (-2.0, 1.0, -1.0, 1.0),
+        width: 40,
+        height: 14,
+        options: ComputeOptions::default(),
+    };
+    let renderer = Renderer::new(ColorScheme::from_str("grayscale-r").unwrap(), OutputFormat::Ascii)
+        .with_color_map(ColorMap::Log);
+    let picture = renderer.render_to_string(&mandelbrot::compute(&job));
+    assert_eq!(picture, MANDELBROT_ASCII, "the picture changed to:\n{}", picture);
+}
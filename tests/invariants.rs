@@ -0,0 +1,110 @@
+// Properties the escape data of any view has to keep, checked over views and points picked at
+// random from a fixed seed: the Mandelbrot set mirrors itself in the real axis, points known to
+// be in it never escape, and more iterations only ever let more points escape, later.
+
+use mandelbrot::{ComputeOptions, Fractal, Job, RenderData};
+use num::complex::Complex;
+
+// xorshift64*, so every run checks the same cases without a crate for it
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn between(&mut self, low: f64, high: f64) -> f64 {
+        low + (high - low) * self.next()
+    }
+}
+
+// a random view of the mandelbrot set, `width`×`height` pixels of `max_iters`, somewhere
+// around it and down to 1000 times closer
+fn random_job(random: &mut Random, width: usize, height: usize, max_iters: usize, options: ComputeOptions) -> Job {
+    let center = (random.between(-2.0, 0.5), random.between(-1.0, 1.0));
+    let zoom = 10f64.powf(random.between(0.0, 3.0));
+    Job {
+        fractal: Fractal::Mandelbrot,
+        max_iters,
+        region: mandelbrot::region_around(center, zoom, width, height, 1.0),
+        width,
+        height,
+        options,
+    }
+}
+
+fn iterations(data: &RenderData) -> Vec<usize> {
+    data.samples().map(|result| result.iterations).collect()
+}
+
+#[test]
+fn test_compute_is_deterministic() {
+    let mut random = Random(1);
+    for _ in 0..10 {
+        let job = random_job(&mut random, 24, 16, 300, ComputeOptions::default());
+        assert_eq!(mandelbrot::compute(&job).checksum(), mandelbrot::compute(&job).checksum(), "{:?}", job.region);
+    }
+}
+
+#[test]
+fn test_mandelbrot_set_mirrors_in_the_real_axis() {
+    let mut random = Random(2);
+    for _ in 0..1000 {
+        let (x, y) = (random.between(-2.0, 0.5), random.between(0.0, 1.2));
+        let (above, below) = (Fractal::Mandelbrot.at_point(x, y, 500), Fractal::Mandelbrot.at_point(x, -y, 500));
+        assert_eq!((above.iterations, above.z_norm), (below.iterations, below.z_norm), "{}, {}", x, y);
+    }
+
+    // row y of a view centered on the axis shows the conjugates of row height - y, with or
+    // without the computation mirroring rows itself
+    for symmetry in [false, true] {
+        let job = Job {
+            fractal: Fractal::Mandelbrot,
+            max_iters: 200,
+            region: (-2.0, 1.0, -1.0, 1.0),
+            width: 30,
+            height: 20,
+            options: ComputeOptions { symmetry, ..ComputeOptions::default() },
+        };
+        let data = mandelbrot::compute(&job);
+        for y in 1..job.height {
+            for x in 0..job.width {
+                assert_eq!(data.sample(x, y).iterations, data.sample(x, job.height - y).iterations, "{}, {}", x, y);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_known_interior_points_never_escape() {
+    let mut random = Random(3);
+    for _ in 0..500 {
+        // inside the main cardioid, c = w/2 - w²/4 for |w| < 1, and the period 2 disk around -1
+        let w = Complex::from_polar(random.between(0.0, 0.95), random.between(0.0, std::f64::consts::TAU));
+        let cardioid = w / 2.0 - w * w / 4.0;
+        let disk = Complex::new(-1.0, 0.0) + w / 4.0;
+        for c in [cardioid, disk] {
+            for options in [ComputeOptions::default(), ComputeOptions { early_bailout: false, ..ComputeOptions::default() }] {
+                let result = Fractal::Mandelbrot.at_point_with(c.re, c.im, 5000, &options);
+                assert_eq!(result.iterations, 5000, "{} escaped", c);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_more_iterations_only_let_more_points_escape() {
+    let mut random = Random(4);
+    for _ in 0..10 {
+        let (fewer, more) = (random.between(20.0, 200.0) as usize, random.between(200.0, 2000.0) as usize);
+        let job = random_job(&mut random, 24, 16, fewer, ComputeOptions::default());
+        let (short, long) = (iterations(&mandelbrot::compute(&job)), iterations(&mandelbrot::compute(&Job { max_iters: more, ..job.clone() })));
+        // points that escaped still escape when they did, and those that didn't escape later or not at all
+        for (i, (&short, &long)) in short.iter().zip(&long).enumerate() {
+            assert_eq!(long.min(fewer), short, "pixel {} of {:?}, {} then {} iterations", i, job.region, fewer, more);
+        }
+    }
+}